@@ -0,0 +1,353 @@
+// Fluent construction of a single request on a Client
+use std::fmt;
+use std::io::Read;
+
+use crate::chunked;
+use crate::client::{Client, StreamedBody, TrailerValues};
+use crate::cookie;
+use crate::error::HttpError;
+use crate::method::Method;
+use crate::multipart::Multipart;
+use crate::reader::ResponseReader;
+use crate::redact;
+use crate::request::{self, Request, SentRequest};
+use crate::response::HttpResponse;
+use crate::scratch;
+use crate::upgrade::{self, Upgraded};
+use crate::url;
+
+// Created by Client::request(). Invalid input is remembered and reported by send(),
+// so calls can be chained without handling errors at every step.
+pub struct RequestBuilder<'a> {
+    client: &'a Client,
+    method: Method,
+    host: String,
+    path: String,
+    // Written in the order they were added
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    // Sent with chunked encoding instead of `body`
+    stream: Option<Box<dyn Read + 'a>>,
+    // Sent instead of the length of `body`, or to send `stream` without chunked encoding
+    content_length: Option<u64>,
+    trailer_names: Vec<String>,
+    trailers: Option<TrailerValues<'a>>,
+    error: Option<HttpError>,
+}
+
+impl<'a> RequestBuilder<'a> {
+    // Continue from a built request, e.g. to add a header to a copy of it
+    pub(crate) fn from_request(client: &'a Client, request: Request) -> RequestBuilder<'a> {
+        let Request { method, host, path, headers, body } = request;
+        RequestBuilder { headers, body, ..RequestBuilder::new(client, method, &host, &path) }
+    }
+
+    pub(crate) fn new(client: &'a Client, method: Method, host: &str, path: &str) -> RequestBuilder<'a> {
+        RequestBuilder {
+            client,
+            method,
+            host: host.to_string(),
+            path: path.to_string(),
+            headers: Vec::new(),
+            body: None,
+            stream: None,
+            content_length: None,
+            trailer_names: Vec::new(),
+            trailers: None,
+            error: None,
+        }
+    }
+
+    // The value must be visible ASCII; see header_latin1() and ext_param() for other text
+    pub fn header(mut self, name: &str, value: &str) -> RequestBuilder<'a> {
+        if let Err(error) = request::require_ascii(name, value) {
+            return self.fail(error);
+        }
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Several headers at once, in order, each checked as by header()
+    pub fn headers<K: Into<String>, V: Into<String>>(self, headers: impl IntoIterator<Item = (K, V)>) -> RequestBuilder<'a> {
+        headers.into_iter().fold(self, |builder, (name, value)| builder.header(&name.into(), &value.into()))
+    }
+
+    // Send the value as ISO-8859-1, one byte per character, for a server that expects
+    // that; characters beyond U+00FF make send() fail
+    pub fn header_latin1(mut self, name: &str, value: &str) -> RequestBuilder<'a> {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Headers from a block of "Name: value" lines as captured, e.g. from a browser or a
+    // curl -v trace, added after those given so far and sent as written (one space after the
+    // colon). Lines may end in CRLF or LF, and blank ones are skipped. Like header_latin1()
+    // values they only have to be free of line breaks; a line without a name makes send() fail.
+    pub fn raw_headers(mut self, block: &str) -> RequestBuilder<'a> {
+        for line in block.lines().filter(|line| !line.trim().is_empty()) {
+            let parsed = line.split_once(':').filter(|(name, _)| !name.is_empty() && !line.starts_with([' ', '\t']));
+            let Some((name, value)) = parsed else {
+                return self.fail(HttpError::InvalidHeader(format!("Not a \"Name: value\" header line: {:?}", line)));
+            };
+            self.headers.push((name.to_string(), value.strip_prefix(' ').unwrap_or(value).to_string()));
+        }
+        self
+    }
+
+    // A cookie added to the request's single Cookie header ("a=1; b=2"), created if needed,
+    // rather than sent in a header of its own. A Cookie default header of the Client is
+    // merged in when the request is sent, with these winning for the same name. A name that
+    // isn't a token, or a value with spaces, quotes inside, commas, semicolons or
+    // backslashes, makes send() fail.
+    pub fn cookie(mut self, name: &str, value: &str) -> RequestBuilder<'a> {
+        if let Err(error) = cookie::validate_pair(name, value) {
+            return self.fail(error);
+        }
+        let pair = format!("{}={}", name, value);
+        match self.headers.iter_mut().find(|(existing, _)| existing.eq_ignore_ascii_case("Cookie")) {
+            Some((_, existing)) if !existing.trim().is_empty() => {
+                existing.push_str("; ");
+                existing.push_str(&pair);
+            }
+            Some((_, existing)) => *existing = pair,
+            None => self.headers.push(("Cookie".to_string(), pair)),
+        }
+        self
+    }
+
+    // Several cookies at once, in order, each added as by cookie()
+    pub fn cookies<K: Into<String>, V: Into<String>>(self, cookies: impl IntoIterator<Item = (K, V)>) -> RequestBuilder<'a> {
+        cookies.into_iter().fold(self, |builder, (name, value)| builder.cookie(&name.into(), &value.into()))
+    }
+
+    // Accept header listing media types with their weights (q-values), e.g.
+    // [("application/json", 0.9), ("text/plain", 0.5)]. Weights are clamped to 0..=1 and
+    // written with at most three decimals; a weight of 1, the default, is left out.
+    pub fn accept_with_quality(self, types: &[(&str, f32)]) -> RequestBuilder<'a> {
+        let value: Vec<String> = types.iter().map(|&(mime, quality)| match qvalue(quality) {
+            Some(q) => format!("{};q={}", mime.trim(), q),
+            None => mime.trim().to_string(),
+        }).collect();
+        self.header("Accept", &value.join(", "))
+    }
+
+    // Sent with a Content-Length
+    pub fn body(mut self, body: &str) -> RequestBuilder<'a> {
+        self.body = Some(body.to_string());
+        self.stream = None;
+        self
+    }
+
+    // Send this Content-Length whatever the body's actual length, e.g. to see how a server
+    // handles a wrong one. Nothing warns about a mismatch; send_debug shows the header next
+    // to the body bytes actually written. A streamed body is then sent as exactly `length`
+    // bytes of the reader instead of with chunked encoding, and can't have trailers.
+    pub fn content_length(mut self, length: u64) -> RequestBuilder<'a> {
+        self.content_length = Some(length);
+        self
+    }
+
+    // Read the body from `reader` while sending it, with chunked encoding, so its length
+    // needn't be known. Such a request is sent once: no retries, redirects returned as they are.
+    pub fn body_stream(mut self, reader: impl Read + 'a) -> RequestBuilder<'a> {
+        self.stream = Some(Box::new(reader));
+        self.body = None;
+        self
+    }
+
+    // Send a multipart/form-data body, streamed part by part (see body_stream) with the
+    // form's Content-Type in place of any set before
+    pub fn multipart(mut self, mut form: Multipart<'a>) -> RequestBuilder<'a> {
+        if let Some(error) = form.take_error() {
+            return self.fail(error);
+        }
+        self.headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Content-Type"));
+        self.headers.push(("Content-Type".to_string(), form.content_type()));
+        self.body_stream(form)
+    }
+
+    // Trailer fields sent after a streamed body, e.g. a checksum of what was streamed.
+    // `names` go in the Trailer header up front; `values` is called once the body is sent
+    // and may only return fields among them. Adds to the trailers of trailer() and earlier
+    // calls. Fields recipients need before the body, like Content-Length, Host, Authorization
+    // or Content-Type (RFC 9110 section 6.5.1), make send() fail.
+    pub fn trailers(mut self, names: &[&str], values: impl FnOnce() -> Vec<(String, String)> + 'a) -> RequestBuilder<'a> {
+        self.trailer_names.extend(names.iter().map(|name| name.to_string()));
+        let earlier = self.trailers.take();
+        self.trailers = Some(Box::new(move || {
+            let mut fields = earlier.map_or_else(Vec::new, |earlier| earlier());
+            fields.extend(values());
+            fields
+        }));
+        self
+    }
+
+    // A trailer field whose value is known up front, announced in the Trailer header and
+    // sent after the last chunk of a streamed body; see trailers()
+    pub fn trailer(self, name: &str, value: &str) -> RequestBuilder<'a> {
+        if let Err(error) = request::validate_header(name, value).and_then(|_| request::require_ascii(name, value)) {
+            return self.fail(error);
+        }
+        if chunked::forbidden_trailer(name) {
+            return self.fail(HttpError::InvalidHeader(format!("{} can't be sent as a trailer", name)));
+        }
+        let field = (name.to_string(), value.to_string());
+        self.trailers(&[name], move || vec![field])
+    }
+
+    // Referer header from an absolute URL; anything else makes send() fail.
+    // A fragment is dropped, as RFC 9110 doesn't allow one in a Referer.
+    pub fn referer(self, referer: &str) -> RequestBuilder<'a> {
+        if !url::is_absolute_url(referer) {
+            return self.fail(HttpError::InvalidRequest(format!("Invalid Referer URL: {:?}", referer)));
+        }
+        let referer = referer.split('#').next().unwrap_or(referer);
+        self.header("Referer", referer)
+    }
+
+    // The request as a value to execute later, possibly more than once. A streamed body
+    // can only be sent once, so a request with one can't be built.
+    pub fn build(mut self) -> Result<Request, HttpError> {
+        self.check()?;
+        if self.stream.is_some() {
+            return Err(HttpError::InvalidRequest("A request with a streamed body can't be built; send it instead".to_string()));
+        }
+        Ok(Request { method: self.method, host: self.host, path: self.path, headers: self.headers, body: self.body })
+    }
+
+    pub fn send(mut self) -> Result<HttpResponse, HttpError> {
+        self.check()?;
+        if let Some(mut stream) = self.stream {
+            let body = StreamedBody {
+                reader: &mut stream,
+                length: self.content_length,
+                trailer_names: &self.trailer_names,
+                trailers: self.trailers,
+            };
+            return self.client.send_streamed(self.method.as_str(), &self.host, &self.path, &self.headers, body)
+                .map(|(request, response)| {
+                    scratch::give(request);
+                    response
+                });
+        }
+        let request = Request { method: self.method, host: self.host, path: self.path, headers: self.headers, body: self.body };
+        self.client.execute(&request)
+    }
+
+    // Send once (no retries or redirects) and return the request exactly as it was
+    // written to the socket along with the response, for debugging
+    pub fn send_debug(mut self) -> Result<(SentRequest, HttpResponse), HttpError> {
+        self.check()?;
+        let mut stream = self.stream.take();
+        let streamed = stream.as_mut()
+            .map(|reader| StreamedBody { reader, length: self.content_length, trailer_names: &self.trailer_names, trailers: self.trailers });
+        self.client.send_debug(self.method.as_str(), &self.host, &self.path, self.body.as_deref(), &self.headers, streamed)
+    }
+
+    // Send once (no retries or redirects) and return when the response head arrives;
+    // the body is then read from the connection as the ResponseReader is consumed
+    pub fn send_streaming(mut self) -> Result<ResponseReader, HttpError> {
+        self.check()?;
+        let mut stream = self.stream.take();
+        let streamed = stream.as_mut()
+            .map(|reader| StreamedBody { reader, length: self.content_length, trailer_names: &self.trailer_names, trailers: self.trailers });
+        self.client.open(self.method.as_str(), &self.host, &self.path, self.body.as_deref(), &self.headers, streamed)
+    }
+
+    // Send a WebSocket opening handshake (a GET with Upgrade: websocket and a fresh
+    // Sec-WebSocket-Key) and, once the server answers 101 with the matching
+    // Sec-WebSocket-Accept, hand over the connection for WebSocket framing. Headers such
+    // as Sec-WebSocket-Protocol can be added with header() beforehand.
+    pub fn upgrade_websocket(mut self) -> Result<Upgraded, HttpError> {
+        self.check()?;
+        if self.method != Method::Get || self.body.is_some() || self.stream.is_some() {
+            return Err(HttpError::InvalidRequest("A WebSocket handshake is a GET without a body".to_string()));
+        }
+        let key = upgrade::websocket_key();
+        for (name, value) in [("Upgrade", "websocket"), ("Connection", "Upgrade"), ("Sec-WebSocket-Key", &key), ("Sec-WebSocket-Version", "13")] {
+            self.headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+            self.headers.push((name.to_string(), value.to_string()));
+        }
+        let reader = self.client.open(self.method.as_str(), &self.host, &self.path, None, &self.headers, None)?;
+        upgrade::check_websocket_response(reader.head(), &key)?;
+        reader.into_upgraded()
+    }
+
+    // The first error recorded while building, or one for a combination that can't be sent.
+    // An explicit Content-Length for a body that isn't streamed goes in with the headers here.
+    fn check(&mut self) -> Result<(), HttpError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if self.method == Method::Trace && (self.body.is_some() || self.stream.is_some()) {
+            return Err(HttpError::InvalidRequest("A TRACE request can't have a body".to_string()));
+        }
+        if self.trailers.is_some() && (self.stream.is_none() || self.content_length.is_some()) {
+            return Err(HttpError::InvalidRequest(
+                "Trailers need a streamed body; a body with a Content-Length can't carry them".to_string(),
+            ));
+        }
+        if let Some(length) = self.content_length.filter(|_| self.stream.is_none()) {
+            self.headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Content-Length"));
+            self.headers.push(("Content-Length".to_string(), length.to_string()));
+        }
+        Ok(())
+    }
+
+    // Keep the first error only
+    fn fail(mut self, error: HttpError) -> RequestBuilder<'a> {
+        self.error.get_or_insert(error);
+        self
+    }
+}
+
+// A weight as RFC 9110 section 12.4.2 writes it ("0.5", "0.125", "0"); None for 1.
+// A NaN weight counts as 0.
+fn qvalue(quality: f32) -> Option<String> {
+    let quality = if quality.is_nan() { 0.0 } else { quality.clamp(0.0, 1.0) };
+    let text = format!("{:.3}", quality);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    (text != "1").then(|| text.to_string())
+}
+
+impl fmt::Debug for RequestBuilder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RequestBuilder")
+            .field("method", &self.method)
+            .field("host", &self.host)
+            .field("path", &self.path)
+            .field("headers", &redact::Headers(&self.headers))
+            .field("body", &self.body)
+            .field("stream", &self.stream.as_ref().map(|_| "<reader>"))
+            .field("content_length", &self.content_length)
+            .field("trailer_names", &self.trailer_names)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qvalues_are_clamped_and_trimmed() {
+        let cases = [(0.5, Some("0.5")), (0.9, Some("0.9")), (0.1234, Some("0.123")), (0.01, Some("0.01")), (0.0, Some("0")), (-1.0, Some("0")), (f32::NAN, Some("0")), (1.0, None), (2.0, None), (0.9999, None)];
+        for (quality, expected) in cases {
+            assert_eq!(qvalue(quality).as_deref(), expected, "{}", quality);
+        }
+    }
+
+    #[test]
+    fn accept_with_quality_lists_types_in_order() {
+        let client = Client::new();
+        let request = client.request(Method::Get, "example.com", "/")
+            .accept_with_quality(&[("application/json", 1.0), (" text/plain ", 0.5), ("*/*", 0.1)])
+            .build()
+            .unwrap();
+        assert_eq!(request.header("Accept"), Some("application/json, text/plain;q=0.5, */*;q=0.1"));
+
+        let injected = client.request(Method::Get, "example.com", "/").accept_with_quality(&[("a/b\r\nX-Evil: 1", 0.5)]).build();
+        assert!(matches!(injected, Err(HttpError::InvalidHeader(_))), "{:?}", injected);
+    }
+}
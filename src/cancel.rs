@@ -0,0 +1,82 @@
+// Stopping a request that is already in flight, from another thread
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Longest a blocking read waits before looking at the token again
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Cheap to clone; every clone cancels the same requests
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    // Requests using this token fail with HttpError::Cancelled at their next check.
+    // Has no effect on requests that already finished.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// A token on a flag the caller already shares, e.g. with threads that don't use this crate;
+// setting the flag cancels like cancel() does
+impl From<Arc<AtomicBool>> for CancelToken {
+    fn from(flag: Arc<AtomicBool>) -> CancelToken {
+        CancelToken(flag)
+    }
+}
+
+// Carried inside an io::Error so a cancellation survives the Read/Write plumbing
+#[derive(Debug)]
+struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "request cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+pub(crate) fn cancelled_io() -> io::Error {
+    io::Error::other(Cancelled)
+}
+
+pub(crate) fn is_cancelled_io(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<Cancelled>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_and_shared_flags_cancel_together() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+
+        let flag = Arc::new(AtomicBool::new(false));
+        let token = CancelToken::from(flag.clone());
+        flag.store(true, Ordering::SeqCst);
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn cancellation_is_recognised_through_io_errors() {
+        assert!(is_cancelled_io(&cancelled_io()));
+        assert!(!is_cancelled_io(&io::Error::other("request cancelled")));
+        assert!(!is_cancelled_io(&io::Error::from(io::ErrorKind::TimedOut)));
+    }
+}
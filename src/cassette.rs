@@ -0,0 +1,352 @@
+// VCR-style cassettes: record exchanges to a JSON file, then replay them without the network
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::base64;
+use crate::error::HttpError;
+use crate::json::Json;
+use crate::redact::is_sensitive_header;
+use crate::request::ParsedRequest;
+use crate::response::HttpResponse;
+
+// Stored in place of the values of filtered headers
+const FILTERED: &str = "<filtered>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    // Send requests for real and keep each exchange
+    Record,
+    // Answer requests from the recorded exchanges; nothing is sent
+    Replay,
+}
+
+// What besides method and URL a request must match during replay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Matching {
+    // Same headers (names case-insensitive, order ignored), except filtered ones
+    pub headers: bool,
+    pub body: bool,
+}
+
+struct Interaction {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    response: HttpResponse,
+    played: bool,
+}
+
+// Shared handle like HarRecorder: clones use the same interactions, so keep one to save()
+#[derive(Clone)]
+pub struct Cassette {
+    interactions: Arc<Mutex<Vec<Interaction>>>,
+    mode: CassetteMode,
+    matching: Matching,
+    // Headers whose values never reach the cassette, on top of the credential headers
+    filtered: Vec<String>,
+}
+
+impl Cassette {
+    // An empty cassette that records
+    pub fn recording() -> Cassette {
+        Cassette {
+            interactions: Arc::new(Mutex::new(Vec::new())),
+            mode: CassetteMode::Record,
+            matching: Matching::default(),
+            filtered: Vec::new(),
+        }
+    }
+
+    // A saved cassette, for replay
+    pub fn load(path: impl AsRef<Path>) -> Result<Cassette, HttpError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| HttpError::InvalidRequest(format!("Failed to read cassette {}: {}", path.display(), e)))?;
+        Cassette::from_json(&Json::parse(&text)?)
+    }
+
+    pub fn from_json(json: &Json) -> Result<Cassette, HttpError> {
+        let invalid = |what: &str| HttpError::InvalidResponse(format!("Invalid cassette: {}", what));
+
+        let mut interactions = Vec::new();
+        for entry in json.get("interactions").and_then(Json::as_array).ok_or_else(|| invalid("missing interactions"))? {
+            let request = entry.get("request").ok_or_else(|| invalid("interaction without a request"))?;
+            let response = entry.get("response").ok_or_else(|| invalid("interaction without a response"))?;
+            let text = |key: &str| request.get(key).and_then(Json::as_str).map(str::to_string)
+                .ok_or_else(|| invalid(&format!("request without a {}", key)));
+
+            let mut headers = Vec::new();
+            for pair in request.get("headers").and_then(Json::as_array).ok_or_else(|| invalid("request without headers"))? {
+                match pair.as_array() {
+                    Some([Json::String(name), Json::String(value)]) => headers.push((name.clone(), value.clone())),
+                    _ => return Err(invalid("headers must be [name, value] pairs")),
+                }
+            }
+            let body = match (request.get("body"), request.get("body_base64")) {
+                (Some(Json::String(text)), None) => text.clone().into_bytes(),
+                (None, Some(Json::String(encoded))) => base64::decode(encoded).ok_or_else(|| invalid("bad body_base64"))?,
+                (None, None) => Vec::new(),
+                _ => return Err(invalid("expected one of body or body_base64")),
+            };
+
+            interactions.push(Interaction {
+                method: text("method")?,
+                url: text("url")?,
+                headers,
+                body,
+                response: HttpResponse::from_json(response)?,
+                played: false,
+            });
+        }
+
+        Ok(Cassette {
+            interactions: Arc::new(Mutex::new(interactions)),
+            mode: CassetteMode::Replay,
+            matching: Matching::default(),
+            filtered: Vec::new(),
+        })
+    }
+
+    pub fn with_matching(mut self, matching: Matching) -> Cassette {
+        self.matching = matching;
+        self
+    }
+
+    // Keep this header's value (in requests and responses) out of the cassette
+    pub fn filter_header(mut self, name: &str) -> Cassette {
+        self.filtered.push(name.to_string());
+        self
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_json(&self) -> Json {
+        let interactions = self.lock().iter()
+            .map(|interaction| {
+                let headers = interaction.headers.iter()
+                    .map(|(name, value)| Json::Array(vec![name.as_str().into(), value.as_str().into()]))
+                    .collect();
+                let mut request = vec![
+                    ("method".to_string(), interaction.method.as_str().into()),
+                    ("url".to_string(), interaction.url.as_str().into()),
+                    ("headers".to_string(), Json::Array(headers)),
+                ];
+                if !interaction.body.is_empty() {
+                    request.push(match std::str::from_utf8(&interaction.body) {
+                        Ok(text) => ("body".to_string(), text.into()),
+                        Err(_) => ("body_base64".to_string(), base64::encode(&interaction.body).into()),
+                    });
+                }
+                Json::Object(vec![
+                    ("request".to_string(), Json::Object(request)),
+                    ("response".to_string(), interaction.response.to_json()),
+                ])
+            })
+            .collect();
+        Json::Object(vec![("interactions".to_string(), Json::Array(interactions))])
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_json().to_string())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Interaction>> {
+        self.interactions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_filtered(&self, name: &str) -> bool {
+        is_sensitive_header(name) || self.filtered.iter().any(|filtered| filtered.eq_ignore_ascii_case(name))
+    }
+
+    // Add one exchange, given the request bytes exactly as they were sent
+    pub(crate) fn record(&self, request: &[u8], response: &HttpResponse) {
+        let sent = ParsedRequest::parse(request);
+        let headers = sent.headers.iter()
+            .map(|(name, value)| {
+                let value = if self.is_filtered(name) { FILTERED } else { value };
+                (name.clone(), value.to_string())
+            })
+            .collect();
+
+        let mut response = response.clone();
+        response.connection = None;
+        response.timings = None;
+        for line in response.headers.lines_mut() {
+            if let Some((name, _)) = line.split_once(':')
+                && self.is_filtered(name.trim())
+            {
+                *line = format!("{}: {}", name.trim(), FILTERED);
+            }
+        }
+
+        self.lock().push(Interaction {
+            method: sent.method.clone(),
+            url: sent.url(),
+            headers,
+            body: sent.body.to_vec(),
+            response,
+            played: false,
+        });
+    }
+
+    // The recorded response for a request. Each recording is used once, in order; when
+    // all the matching ones have been used the last of them keeps answering.
+    pub(crate) fn replay(&self, request: &[u8]) -> Result<HttpResponse, HttpError> {
+        let sent = ParsedRequest::parse(request);
+        let url = sent.url();
+        let mut interactions = self.lock();
+
+        let matches: Vec<usize> = interactions.iter()
+            .enumerate()
+            .filter(|(_, interaction)| self.matches(interaction, &sent, &url))
+            .map(|(i, _)| i)
+            .collect();
+        let chosen = matches.iter().copied()
+            .find(|&i| !interactions[i].played)
+            .or(matches.last().copied())
+            .ok_or_else(|| HttpError::InvalidRequest(format!("No cassette interaction matches {} {}", sent.method, url)))?;
+
+        interactions[chosen].played = true;
+        Ok(interactions[chosen].response.clone())
+    }
+
+    fn matches(&self, interaction: &Interaction, sent: &ParsedRequest, url: &str) -> bool {
+        if interaction.method != sent.method || interaction.url != url {
+            return false;
+        }
+        if self.matching.body && interaction.body != sent.body {
+            return false;
+        }
+        if self.matching.headers {
+            // Headers filtered when recording can't be compared, whatever this cassette filters now
+            let skipped: Vec<&str> = interaction.headers.iter()
+                .filter(|(_, value)| value == FILTERED)
+                .map(|(name, _)| name.as_str())
+                .collect();
+            if self.comparable(&interaction.headers, &skipped) != self.comparable(&sent.headers, &skipped) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn comparable<'a>(&self, headers: &'a [(String, String)], skipped: &[&str]) -> Vec<(String, &'a str)> {
+        let mut headers: Vec<(String, &str)> = headers.iter()
+            .filter(|(name, _)| !self.is_filtered(name) && !skipped.iter().any(|s| s.eq_ignore_ascii_case(name)))
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.as_str()))
+            .collect();
+        headers.sort();
+        headers
+    }
+}
+
+impl fmt::Debug for Cassette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cassette")
+            .field("interactions", &self.len())
+            .field("mode", &self.mode)
+            .field("matching", &self.matching)
+            .field("filtered", &self.filtered)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::try_parse_response;
+
+    const GET: &[u8] = b"GET /a?b=1 HTTP/1.1\r\nHost: example.com\r\nAuthorization: Bearer secret\r\nX-Trace: 7\r\n\r\n";
+
+    fn response(body: &str) -> HttpResponse {
+        let raw = format!("HTTP/1.1 200 OK\r\nSet-Cookie: id=1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        try_parse_response(raw.as_bytes()).unwrap()
+    }
+
+    fn replaying(cassette: &Cassette) -> Cassette {
+        Cassette::from_json(&Json::parse(&cassette.to_json().to_string()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let cassette = Cassette::recording();
+        cassette.record(GET, &response("one"));
+        cassette.record(b"POST /upload HTTP/1.1\r\nHost: example.com\r\n\r\n\xff\x00", &response("two"));
+
+        let replay = replaying(&cassette);
+        assert_eq!(replay.mode(), CassetteMode::Replay);
+        assert_eq!(replay.len(), 2);
+        assert_eq!(replay.replay(GET).unwrap().body, b"one");
+        let json = cassette.to_json().to_string();
+        assert!(json.contains("\"body_base64\":\"/wA=\""), "{}", json);
+    }
+
+    #[test]
+    fn credentials_and_filtered_headers_are_not_stored() {
+        let cassette = Cassette::recording().filter_header("x-trace");
+        cassette.record(GET, &response("one"));
+        let json = cassette.to_json().to_string();
+        assert!(!json.contains("secret"), "{}", json);
+        assert!(!json.contains("id=1"), "{}", json);
+        assert!(!json.contains("\"7\""), "{}", json);
+        assert!(json.contains(FILTERED));
+    }
+
+    #[test]
+    fn recordings_are_used_in_order_then_the_last_repeats() {
+        let cassette = Cassette::recording();
+        cassette.record(GET, &response("one"));
+        cassette.record(GET, &response("two"));
+        let replay = replaying(&cassette);
+        let bodies: Vec<Vec<u8>> = (0..3).map(|_| replay.replay(GET).unwrap().body).collect();
+        assert_eq!(bodies, [b"one".to_vec(), b"two".to_vec(), b"two".to_vec()]);
+    }
+
+    #[test]
+    fn unmatched_requests_are_errors() {
+        let cassette = Cassette::recording();
+        cassette.record(GET, &response("one"));
+        let replay = replaying(&cassette);
+        let error = replay.replay(b"GET /other HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap_err();
+        assert!(matches!(&error, HttpError::InvalidRequest(message) if message.contains("/other")), "{:?}", error);
+        assert!(replay.replay(b"DELETE /a?b=1 HTTP/1.1\r\nHost: example.com\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn matching_can_include_headers_and_body() {
+        let cassette = Cassette::recording();
+        cassette.record(b"POST /p HTTP/1.1\r\nHost: h\r\nX-A: 1\r\nAuthorization: old\r\n\r\nbody", &response("ok"));
+        let loose = replaying(&cassette);
+        assert!(loose.replay(b"POST /p HTTP/1.1\r\nHost: h\r\n\r\nother").is_ok());
+
+        let strict = replaying(&cassette).with_matching(Matching { headers: true, body: true });
+        // Header order, name case and the filtered Authorization value don't matter
+        assert!(strict.replay(b"POST /p HTTP/1.1\r\nauthorization: new\r\nx-a: 1\r\nHost: h\r\n\r\nbody").is_ok());
+        assert!(strict.replay(b"POST /p HTTP/1.1\r\nHost: h\r\nX-A: 2\r\nAuthorization: old\r\n\r\nbody").is_err());
+        assert!(strict.replay(b"POST /p HTTP/1.1\r\nHost: h\r\nX-A: 1\r\nAuthorization: old\r\n\r\nelse").is_err());
+    }
+
+    #[test]
+    fn malformed_cassettes_are_rejected() {
+        for text in [
+            "{}",
+            "{\"interactions\": [{\"request\": {\"method\": \"GET\"}}]}",
+            "{\"interactions\": [{\"request\": {\"method\": \"GET\", \"url\": \"u\", \"headers\": [[\"a\"]]}, \"response\": {}}]}",
+        ] {
+            assert!(Cassette::from_json(&Json::parse(text).unwrap()).is_err(), "{}", text);
+        }
+    }
+}
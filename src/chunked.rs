@@ -0,0 +1,277 @@
+// Chunked transfer coding (RFC 9112 section 7.1)
+use std::io::{self, BufRead, Read, Write};
+
+use crate::error::HttpError;
+use crate::response::decode_field;
+
+// Decode a whole chunked body, returning the data and any trailer field lines
+#[cfg(feature = "test-util")]
+pub(crate) fn decode<R: BufRead>(reader: &mut R) -> Result<(Vec<u8>, Vec<String>), HttpError> {
+    let mut chunked = ChunkedReader::new(reader);
+    let mut body = Vec::new();
+    chunked.read_to_end(&mut body)
+        .map_err(|e| HttpError::from_io("Failed to read response", e))?;
+    Ok((body, chunked.trailers))
+}
+
+// Yields the data of a chunked body as it arrives, reading exactly up to the end of
+// the trailer section. Framing errors are io::ErrorKind::InvalidData.
+pub(crate) struct ChunkedReader<R> {
+    inner: R,
+    // Data bytes left in the current chunk
+    left: usize,
+    // Whether the CRLF after the current chunk's data is still to be read
+    in_chunk: bool,
+    done: bool,
+    // Filled in once the last chunk has been read
+    pub(crate) trailers: Vec<String>,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    pub(crate) fn new(inner: R) -> ChunkedReader<R> {
+        ChunkedReader { inner, left: 0, in_chunk: false, done: false, trailers: Vec::new() }
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub(crate) fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Whether the last chunk and the trailers have been read
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // Move on to the next chunk with data, or to the end
+    fn next_chunk(&mut self) -> io::Result<()> {
+        if self.in_chunk {
+            if read_line(&mut self.inner)?.as_deref() != Some("") {
+                return Err(invalid("Missing CRLF after chunk data"));
+            }
+            self.in_chunk = false;
+        }
+
+        let line = read_line(&mut self.inner)?
+            .ok_or_else(|| invalid("Chunked body ended before the last chunk"))?;
+        // Chunk extensions after ';' carry nothing we use
+        let size_text = line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_text, 16)
+            .map_err(|_| invalid(&format!("Invalid chunk size: {:?}", size_text)))?;
+        if size > 0 {
+            self.left = size;
+            self.in_chunk = true;
+            return Ok(());
+        }
+
+        // Trailer fields up to the blank line; a connection closed right after the last chunk is tolerated
+        while let Some(line) = read_line(&mut self.inner)? {
+            if line.is_empty() {
+                break;
+            }
+            self.trailers.push(line);
+        }
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for ChunkedReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.left == 0 && !self.done {
+            self.next_chunk()?;
+        }
+        if self.done {
+            return Ok(&[]);
+        }
+        let left = self.left;
+        let available = self.inner.fill_buf()?;
+        if available.is_empty() {
+            return Err(invalid("Chunked body ended inside a chunk"));
+        }
+        Ok(&available[..available.len().min(left)])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.left -= amt;
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// One line without its CRLF (or bare LF), or None at the end of input
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = reader.read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+    }
+    Ok(Some(decode_field(&line).into_owned()))
+}
+
+// Send everything `body` yields as chunks of at most `chunk_size` bytes, without the last
+// chunk (see finish). A read that returns less makes a smaller chunk; only the end of
+// `body` ends the chunks.
+pub(crate) fn encode<R: Read + ?Sized, W: Write>(body: &mut R, out: &mut W, chunk_size: usize) -> Result<(), HttpError> {
+    let mut buf = vec![0; chunk_size.max(1)];
+    loop {
+        let n = match body.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(HttpError::InvalidRequest(format!("Failed to read the request body: {}", e))),
+        };
+        write!(out, "{:x}\r\n", n)
+            .and_then(|_| out.write_all(&buf[..n]))
+            .and_then(|_| out.write_all(b"\r\n"))
+            .map_err(|e| HttpError::from_io("Failed to send request", e))?;
+    }
+}
+
+// Fields a trailer can't carry (RFC 9110 section 6.5.1): framing, routing, request
+// modifiers, authentication and what describes the content, which recipients need before
+// the body rather than after it
+pub(crate) fn forbidden_trailer(name: &str) -> bool {
+    const FORBIDDEN: [&str; 21] = [
+        "Transfer-Encoding", "Content-Length", "Host", "Trailer", "TE", "Cache-Control", "Expect", "Max-Forwards",
+        "Pragma", "Range", "If-Match", "If-None-Match", "If-Modified-Since", "If-Unmodified-Since", "If-Range",
+        "Authorization", "Proxy-Authorization", "Cookie", "Content-Encoding", "Content-Type", "Content-Range",
+    ];
+    FORBIDDEN.iter().any(|forbidden| forbidden.eq_ignore_ascii_case(name))
+}
+
+// The last chunk and the trailer section
+pub(crate) fn finish<W: Write>(out: &mut W, trailers: &[(String, String)]) -> Result<(), HttpError> {
+    let mut end = String::from("0\r\n");
+    for (name, value) in trailers {
+        end.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    end.push_str("\r\n");
+    out.write_all(end.as_bytes())
+        .and_then(|_| out.flush())
+        .map_err(|e| HttpError::from_io("Failed to send request", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(body: &str) -> io::Result<(Vec<u8>, Vec<String>)> {
+        let mut reader = ChunkedReader::new(body.as_bytes());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok((data, reader.trailers))
+    }
+
+    #[test]
+    fn chunks_with_and_without_extensions_decode() {
+        assert_eq!(read_all("5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n").unwrap().0, b"hello world");
+        assert_eq!(read_all("5;name=value\r\nhello\r\n6 ; a=\"q;x\"\r\n world\r\n0;last\r\n\r\n").unwrap().0, b"hello world");
+    }
+
+    #[test]
+    fn sizes_that_are_not_hex_are_invalid() {
+        for body in ["zz\r\nhello\r\n0\r\n\r\n", "5x;a\r\nhello\r\n0\r\n\r\n", ";a\r\n0\r\n\r\n"] {
+            let error = read_all(body).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{:?}", body);
+            assert!(error.to_string().contains("Invalid chunk size"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn trailers_are_kept_and_reading_stops_after_them() {
+        let mut input = &b"2\r\nok\r\n0\r\nX-Sum: 1\r\n\r\nNEXT"[..];
+        let mut reader = ChunkedReader::new(&mut input);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert!(reader.is_done());
+        assert_eq!(reader.trailers, ["X-Sum: 1"]);
+        assert_eq!(input, b"NEXT");
+    }
+
+    #[test]
+    fn encoded_bodies_decode_back() {
+        let mut out = Vec::new();
+        encode(&mut &b"a body longer than one chunk"[..], &mut out, 8).unwrap();
+        finish(&mut out, &[("X-Sum".to_string(), "1".to_string())]).unwrap();
+        let (data, trailers) = read_all(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(data, b"a body longer than one chunk");
+        assert_eq!(trailers, ["X-Sum: 1"]);
+    }
+
+    #[test]
+    fn framing_and_routing_fields_are_not_trailers() {
+        for name in ["content-length", "Transfer-Encoding", "HOST", "Authorization", "Trailer"] {
+            assert!(forbidden_trailer(name), "{}", name);
+        }
+        for name in ["X-Content-Sha256", "grpc-status", "Server-Timing"] {
+            assert!(!forbidden_trailer(name), "{}", name);
+        }
+    }
+
+    // Chunk sizes in what encode() wrote
+    fn chunk_sizes(mut encoded: &[u8]) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        while let Some(end) = encoded.windows(2).position(|w| w == b"\r\n") {
+            let size = usize::from_str_radix(std::str::from_utf8(&encoded[..end]).unwrap(), 16).unwrap();
+            sizes.push(size);
+            encoded = &encoded[end + 2 + size + 2..];
+        }
+        sizes
+    }
+
+    // Hands out at most 1000 bytes per read
+    struct Trickle(io::Take<io::Repeat>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(1000);
+            self.0.read(&mut buf[..n])
+        }
+    }
+
+    #[test]
+    fn encode_splits_at_the_chunk_size_and_round_trips() {
+        let body: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let mut out = Vec::new();
+        encode(&mut &body[..], &mut out, 4096).unwrap();
+        assert_eq!(chunk_sizes(&out), [4096, 4096, 1808]);
+        finish(&mut out, &[]).unwrap();
+        let mut decoded = Vec::new();
+        ChunkedReader::new(&out[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn short_reads_make_short_chunks_and_an_empty_body_is_just_the_end() {
+        let mut out = Vec::new();
+        encode(&mut Trickle(io::repeat(b'x').take(2500)), &mut out, 16 * 1024).unwrap();
+        assert_eq!(chunk_sizes(&out), [1000, 1000, 500]);
+
+        let mut empty = Vec::new();
+        encode(&mut io::empty(), &mut empty, 16 * 1024).unwrap();
+        finish(&mut empty, &[]).unwrap();
+        assert_eq!(empty, b"0\r\n\r\n");
+    }
+}
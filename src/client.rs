@@ -0,0 +1,1498 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(feature = "json")]
+use crate::cassette::{Cassette, CassetteMode};
+use crate::auth::{self, Challenge, CredentialCallback, Credentials};
+use crate::builder::RequestBuilder;
+use crate::cancel::CancelToken;
+use crate::cookie;
+use crate::error::{HttpError, TimeoutPhase};
+use crate::gzip;
+use crate::headers::HeaderList;
+use crate::intercept::{OutgoingRequest, RequestInterceptor};
+use crate::method::Method;
+use crate::mime;
+use crate::netrc::Netrc;
+#[cfg(feature = "json")]
+use crate::har::HarRecorder;
+#[cfg(feature = "json")]
+use crate::json::{Json, PatchFormat};
+use crate::chunked;
+use crate::pool::{KeepAlive, Pool, PoolStats, Recycle};
+use crate::proxy::{self, Proxy};
+use crate::ratelimit::RateLimiter;
+use crate::reader::{is_interim, read_final_head, read_head_bytes, ResponseReader};
+use crate::redact;
+use crate::request::{build_request, build_request_from, downgrade_to_http10, end_head_lines_with_lf, expects_continue, remove_host_header, require_ascii, validate_header, Request, SentRequest};
+use crate::resolver::Resolver;
+use crate::response::{check_status, parse_response_bytes, require_crlf, ConnectionInfo, HttpResponse, Timings};
+use crate::retry::{self, default_retry_policy, RetryContext, RetryPredicate};
+use crate::scratch;
+use crate::trace::TRACE_MAX_FORWARDS;
+use crate::transport::{self, apply_socket_config, connect, connection_info, TimedStream};
+use crate::url;
+
+// Per-request settings
+#[derive(Clone)]
+pub struct RequestConfig {
+    // Bind the socket to this local address (port 0) before connecting
+    pub local_address: Option<IpAddr>,
+    // Limit on each individual read
+    pub read_timeout: Option<Duration>,
+    // Longest wait, once the request has been written, for the whole response head; the body
+    // then only has read_timeout (and timeout) to keep to. Fails with HttpError::Timeout.
+    pub header_timeout: Option<Duration>,
+    // Budget for the whole request, shared by every hop of a redirect chain
+    pub timeout: Option<Duration>,
+    // How many redirects to follow (0 returns 3xx responses as they are)
+    pub max_redirects: usize,
+    // Fail with an InvalidResponse instead of returning the 3xx when a redirect points to a
+    // scheme other than http or https (file:, gopher: ...), which is never followed
+    pub error_on_non_http_redirect: bool,
+    // Extra attempts after a failure the retry policy accepts
+    pub max_retries: u32,
+    // Pause between attempts
+    pub retry_delay: Duration,
+    // Also retry (within max_retries, whatever the method) when a connection closes without
+    // any response, HttpError::EmptyResponse; a reused connection found closed is always
+    // retried on a new one
+    pub retry_empty_response: bool,
+    // Custom retry policy (default_retry_policy when unset)
+    pub retry_if: Option<RetryPredicate>,
+    // Send POST, PATCH and other non-idempotent requests with a random key under this header,
+    // e.g. "Idempotency-Key", the same one for every retry, so the server can tell a retry from
+    // a new request. The default policy then retries them too. A key set by the caller is used
+    // as it is. The key ends up in HttpResponse::idempotency_key and in error messages.
+    pub idempotency_key_header: Option<String>,
+    // Options applied to every new connection
+    pub socket: SocketConfig,
+    // Extra connect attempts when the connection is refused, e.g. by a server that is
+    // still starting. Separate from max_retries, which covers whole requests.
+    pub connect_retries: u32,
+    // Pause before each of those attempts
+    pub connect_retry_delay: Duration,
+    // When a host has both IPv6 and IPv4 addresses, how long a connect attempt gets before
+    // the next address is tried alongside it (happy eyeballs, RFC 8305)
+    pub happy_eyeballs_delay: Duration,
+    // Connect to these addresses for the given "host[:port]" authorities (port 80 when left
+    // out, punycode for internationalized names) instead of looking them up, like curl's
+    // --connect-to. Nothing else about the request changes: it keeps its Host header, and
+    // redirects to other hosts are unaffected.
+    pub connect_to: HashMap<String, SocketAddr>,
+    // Look host names up by asking this DNS server directly over UDP instead of the system
+    // resolver (A and AAAA records only; /etc/hosts is not consulted)
+    pub dns_server: Option<SocketAddr>,
+    // Look host names up with this instead of the system resolver or dns_server (connect_to
+    // still comes first); see Client::with_resolver
+    pub resolver: Option<Arc<dyn Resolver>>,
+    // Copy announced chunked trailers into the headers (they are always in `trailers`)
+    pub merge_trailers: bool,
+    // Return a body cut short of its Content-Length as it is, instead of HttpError::TruncatedBody
+    pub allow_truncated_body: bool,
+    // Hash the body as it is read and fail with HttpError::DigestMismatch unless it matches the
+    // response's Digest (sha-256, sha-512 or md5) or Content-MD5 header; other algorithms are
+    // ignored, and so is a response without either header
+    pub verify_digest: bool,
+    // Read and throw away response bodies instead of keeping them, e.g. for health checks that
+    // only look at the status and headers; HttpResponse::body is then empty
+    pub ignore_body: bool,
+    // Longest body ResponseReader::discard_body() and ignore_body read through to keep a
+    // keep-alive connection; at a longer one the connection is closed instead
+    pub discard_limit: u64,
+    // Take a reply that doesn't start with "HTTP/" as an HTTP/0.9 response (see parse_response_lenient)
+    pub lenient: bool,
+    // Size of the buffer responses are read from the connection through: a larger one takes
+    // fewer reads (system calls) for a large body. Must not be 0.
+    pub read_buffer_size: usize,
+    // Largest chunk a streamed request body (RequestBuilder::body_stream) is sent in
+    pub request_chunk_size: usize,
+    // Send request bodies gzip-compressed with Content-Encoding: gzip, replacing a Content-Length
+    // and an identity Content-Encoding of the caller's. A body whose caller set another
+    // Content-Encoding is taken as encoded already and sent as it is; streamed bodies are too.
+    pub gzip_request_body: bool,
+    // Ask for gzip with Accept-Encoding (unless the request sets its own) and decode gzip
+    // bodies, see HttpResponse::decompress. Bodies read from a ResponseReader (open()) are
+    // left as they arrive, and such requests don't ask for gzip.
+    pub decompress: bool,
+    // Shut down the sending side of the connection once the request is written, for servers
+    // that wait for EOF before answering. Not possible together with keep_alive.
+    pub shutdown_after_write: bool,
+    // For a request with an Expect: 100-continue header: how long to wait for the server's
+    // 100 Continue before sending the body anyway, as servers that ignore Expect never answer.
+    // A final response that comes instead (e.g. 417 or 401) is returned without the body being sent.
+    pub continue_timeout: Duration,
+    // Send a request once more as HTTP/1.0 when the server answers the HTTP/1.1 one with 400 or
+    // 505, or with something that can't be parsed, for old servers that choke on HTTP/1.1.
+    // Streamed bodies, sent chunked, have no HTTP/1.0 form and aren't sent again.
+    pub http10_fallback: bool,
+    // Leave the Host header out of requests, which HTTP/1.1 requires (RFC 9112 section 3.2),
+    // to see how a server handles its absence. CONNECT requests to a proxy still have one.
+    pub omit_host_header: bool,
+    // End the request line and header lines with a bare LF instead of CRLF, for servers that
+    // only take that; RFC 9112 requires CRLF. The body, chunked framing included, is sent as usual.
+    pub bare_lf: bool,
+    // Reject a response head with lines ending in a bare LF instead of CRLF, rather than
+    // accepting both as hand-written servers and fixtures need
+    pub strict_line_endings: bool,
+    // Limit on the status line plus headers of a response
+    pub max_header_bytes: usize,
+    // Lets another thread abort the request; reads then wake up at least every 50ms to check it
+    pub cancel: Option<CancelToken>,
+    // Send requests through this HTTP proxy
+    pub proxy: Option<Proxy>,
+    // Hosts reached directly rather than through `proxy`: names (with their subdomains), IP
+    // addresses and CIDR ranges, each optionally with a port, or "*" for all (see
+    // Client::with_proxy_from_env for NO_PROXY). Loopback is in it by default; leave it out
+    // to proxy local servers too.
+    pub no_proxy: Vec<String>,
+    // Keep connections open after a response and reuse them for later requests to the
+    // same host (otherwise every request sends Connection: close)
+    pub keep_alive: bool,
+    // Idle connections older than this are not reused
+    pub pool_idle_timeout: Duration,
+    // Idle connections kept per host; more are closed
+    pub pool_max_idle: usize,
+    // Connections a Client (with its clones) may have open at once, pooled or not; at the
+    // limit the longest idle one is closed to make room, or else a request waits for one
+    pub max_connections: Option<usize>,
+    // Fail with a NetworkError instead of waiting (up to timeout) when max_connections are busy
+    pub fail_at_connection_limit: bool,
+    // Requests per second a Client (with its clones) may send, retries and redirects
+    // included; each one waits its turn, evenly spaced 1/rate_limit seconds apart
+    pub rate_limit: Option<f64>,
+    // Fail with a NetworkError instead of waiting (up to timeout) for the rate limit
+    pub fail_at_rate_limit: bool,
+}
+
+// TCP options set right after connecting. One that can't be set doesn't fail the request,
+// which goes ahead without it; ConnectionInfo::socket_config_applied tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketConfig {
+    // Disable Nagle's algorithm so small requests go out at once
+    pub nodelay: bool,
+    // Enable TCP keepalive, probing after this much idle time and at this interval
+    pub keepalive: Option<Duration>,
+    // SO_RCVBUF / SO_SNDBUF in bytes (the OS may round or double them)
+    pub recv_buffer_size: Option<usize>,
+    pub send_buffer_size: Option<usize>,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        SocketConfig { nodelay: true, keepalive: None, recv_buffer_size: None, send_buffer_size: None }
+    }
+}
+
+impl std::fmt::Debug for RequestConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RequestConfig")
+            .field("local_address", &self.local_address)
+            .field("read_timeout", &self.read_timeout)
+            .field("header_timeout", &self.header_timeout)
+            .field("timeout", &self.timeout)
+            .field("max_redirects", &self.max_redirects)
+            .field("error_on_non_http_redirect", &self.error_on_non_http_redirect)
+            .field("max_retries", &self.max_retries)
+            .field("retry_delay", &self.retry_delay)
+            .field("retry_empty_response", &self.retry_empty_response)
+            .field("retry_if", &self.retry_if.as_ref().map(|_| "<fn>"))
+            .field("idempotency_key_header", &self.idempotency_key_header)
+            .field("socket", &self.socket)
+            .field("connect_retries", &self.connect_retries)
+            .field("connect_retry_delay", &self.connect_retry_delay)
+            .field("happy_eyeballs_delay", &self.happy_eyeballs_delay)
+            .field("connect_to", &self.connect_to)
+            .field("dns_server", &self.dns_server)
+            .field("resolver", &self.resolver.as_ref().map(|_| "<resolver>"))
+            .field("merge_trailers", &self.merge_trailers)
+            .field("allow_truncated_body", &self.allow_truncated_body)
+            .field("verify_digest", &self.verify_digest)
+            .field("ignore_body", &self.ignore_body)
+            .field("discard_limit", &self.discard_limit)
+            .field("lenient", &self.lenient)
+            .field("read_buffer_size", &self.read_buffer_size)
+            .field("request_chunk_size", &self.request_chunk_size)
+            .field("gzip_request_body", &self.gzip_request_body)
+            .field("decompress", &self.decompress)
+            .field("shutdown_after_write", &self.shutdown_after_write)
+            .field("continue_timeout", &self.continue_timeout)
+            .field("http10_fallback", &self.http10_fallback)
+            .field("omit_host_header", &self.omit_host_header)
+            .field("bare_lf", &self.bare_lf)
+            .field("strict_line_endings", &self.strict_line_endings)
+            .field("max_header_bytes", &self.max_header_bytes)
+            .field("cancel", &self.cancel)
+            .field("proxy", &self.proxy.as_ref().map(|p| &p.authority))
+            .field("no_proxy", &self.no_proxy)
+            .field("keep_alive", &self.keep_alive)
+            .field("pool_idle_timeout", &self.pool_idle_timeout)
+            .field("pool_max_idle", &self.pool_max_idle)
+            .field("max_connections", &self.max_connections)
+            .field("fail_at_connection_limit", &self.fail_at_connection_limit)
+            .field("rate_limit", &self.rate_limit)
+            .field("fail_at_rate_limit", &self.fail_at_rate_limit)
+            .finish()
+    }
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        RequestConfig {
+            local_address: None,
+            read_timeout: Some(Duration::from_secs(10)),
+            header_timeout: None,
+            timeout: None,
+            max_redirects: 0,
+            error_on_non_http_redirect: false,
+            max_retries: 0,
+            retry_delay: Duration::from_millis(100),
+            retry_empty_response: false,
+            retry_if: None,
+            idempotency_key_header: None,
+            socket: SocketConfig::default(),
+            connect_retries: 0,
+            connect_retry_delay: Duration::from_millis(50),
+            happy_eyeballs_delay: Duration::from_millis(250),
+            connect_to: HashMap::new(),
+            dns_server: None,
+            resolver: None,
+            merge_trailers: false,
+            allow_truncated_body: false,
+            verify_digest: false,
+            ignore_body: false,
+            discard_limit: 1024 * 1024,
+            lenient: false,
+            read_buffer_size: 8 * 1024,
+            request_chunk_size: 16 * 1024,
+            gzip_request_body: false,
+            decompress: false,
+            shutdown_after_write: false,
+            continue_timeout: Duration::from_secs(1),
+            http10_fallback: false,
+            omit_host_header: false,
+            bare_lf: false,
+            strict_line_endings: false,
+            max_header_bytes: 256 * 1024,
+            cancel: None,
+            proxy: None,
+            no_proxy: ["localhost", "127.0.0.0/8", "::1"].map(str::to_string).to_vec(),
+            keep_alive: false,
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle: 8,
+            max_connections: None,
+            fail_at_connection_limit: false,
+            rate_limit: None,
+            fail_at_rate_limit: false,
+        }
+    }
+}
+
+// Reusable client: a configuration shared by its requests, plus optional traffic recording
+// It can be shared between threads, in an Arc or as clones. Requests running at the same
+// time to the same host each get a connection of their own, an idle pooled one or a new
+// one; locks are only held around pool bookkeeping, never across network I/O.
+#[derive(Clone, Default)]
+pub struct Client {
+    config: RequestConfig,
+    // Sent with every request unless the request sets the same header itself
+    default_headers: Vec<(String, String)>,
+    // Sent, in place of default headers of the same name, with every request to a matching
+    // host (see with_host_header) unless the request sets the same header itself
+    host_headers: Vec<(String, Vec<(String, String)>)>,
+    // Idle keep-alive connections, shared with clones of this client
+    pool: Pool,
+    // When the next request may go out under rate_limit, shared with clones like the pool
+    rate_limiter: RateLimiter,
+    #[cfg(feature = "json")]
+    har: Option<HarRecorder>,
+    #[cfg(feature = "json")]
+    cassette: Option<Cassette>,
+    // Run in order on every request just before it is serialized
+    interceptors: Vec<Arc<dyn RequestInterceptor>>,
+    // Asked for credentials when a request without an Authorization header gets a 401
+    credentials: Option<CredentialCallback>,
+    // Basic credentials for the hosts it has entries for, sent when a request has no Authorization
+    netrc: Option<Netrc>,
+}
+
+// A Client is shared between threads (in an Arc, or as clones sharing one pool), so every
+// field has to stay Send + Sync: hooks are Send + Sync trait objects and shared state sits
+// behind a Mutex. This stops compiling if that ever changes.
+const _: fn() = || {
+    fn shareable<T: Send + Sync>() {}
+    shareable::<Client>();
+};
+
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut debug = f.debug_struct("Client");
+        debug.field("config", &self.config)
+            .field("default_headers", &redact::Headers(&self.default_headers))
+            .field("host_headers", &self.host_headers.iter().map(|(host, headers)| (host, redact::Headers(headers))).collect::<Vec<_>>())
+            .field("pool", &self.pool)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("interceptors", &self.interceptors.len())
+            .field("credential_callback", &self.credentials.is_some())
+            .field("netrc", &self.netrc);
+        #[cfg(feature = "json")]
+        debug.field("har", &self.har).field("cassette", &self.cassette);
+        debug.finish()
+    }
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client::default()
+    }
+
+    pub fn with_config(config: RequestConfig) -> Client {
+        Client {
+            config,
+            default_headers: Vec::new(),
+            host_headers: Vec::new(),
+            pool: Pool::default(),
+            rate_limiter: RateLimiter::default(),
+            #[cfg(feature = "json")]
+            har: None,
+            #[cfg(feature = "json")]
+            cassette: None,
+            interceptors: Vec::new(),
+            credentials: None,
+            netrc: None,
+        }
+    }
+
+    pub fn config(&self) -> &RequestConfig {
+        &self.config
+    }
+
+    // What has happened to the connections of this client and its clones so far
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    // Start the totals in pool_stats() from zero again, e.g. between tests
+    pub fn reset_pool_stats(&self) {
+        self.pool.reset_stats();
+    }
+
+    // Open a connection to host:port ahead of the first request and leave it idle in the
+    // pool, so that request doesn't wait for DNS and the TCP handshake. Needs keep_alive.
+    // Nothing is opened when the pool already has a usable connection there; its details are
+    // returned with reused set. The connection is subject to pool_max_idle and
+    // pool_idle_timeout like any other.
+    //
+    //     client.preconnect("api.example.com", 80)?;
+    pub fn preconnect(&self, host: &str, port: u16) -> Result<ConnectionInfo, HttpError> {
+        if !self.config.keep_alive {
+            return Err(HttpError::InvalidRequest("preconnect needs RequestConfig::keep_alive".to_string()));
+        }
+        let host = url::join_authority(host.trim_start_matches('[').trim_end_matches(']'), port);
+        let key = self.pool_key(&host);
+        if let Some(connection) = self.pool.peek(&key, self.config.pool_idle_timeout) {
+            return Ok(ConnectionInfo { reused: true, ..connection });
+        }
+        let deadline = self.config.timeout.map(|timeout| Instant::now() + timeout);
+        let proxy_auth = self.preemptive_proxy_auth(&host);
+        let (stream, connection) = self.connection(&host, Some(&key), deadline, proxy_auth.as_deref(), true)?;
+        self.pool.put(&key, stream, connection, KeepAlive::default(), self.config.pool_max_idle);
+        Ok(connection)
+    }
+
+    // e.g. with_default_header("User-Agent", "my-app/1.0")
+    pub fn with_default_header(mut self, name: &str, value: &str) -> Client {
+        self.default_headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        self.default_headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // A default header for one host only, e.g. with_host_header("api.example.com",
+    // "X-Api-Key", key). `host` is a name compared case-insensitively, whatever the port (one
+    // given, as in "127.0.0.1:8080", is left out), or "*.example.com" for every subdomain of
+    // example.com (but not example.com itself). For a host several patterns match, the one
+    // added last wins.
+    pub fn with_host_header(mut self, host: &str, name: &str, value: &str) -> Client {
+        let (host, _) = url::split_authority(host.trim());
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let i = match self.host_headers.iter().position(|(existing, _)| *existing == host) {
+            Some(i) => i,
+            None => {
+                self.host_headers.push((host, Vec::new()));
+                self.host_headers.len() - 1
+            }
+        };
+        let headers = &mut self.host_headers[i].1;
+        headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Record every exchange (each redirect hop separately) into `recorder`
+    #[cfg(feature = "json")]
+    pub fn with_har_recorder(mut self, recorder: HarRecorder) -> Client {
+        self.har = Some(recorder);
+        self
+    }
+
+    // Record exchanges into `cassette`, or answer from it without the network when it replays
+    #[cfg(feature = "json")]
+    pub fn with_cassette(mut self, cassette: Cassette) -> Client {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    // Resolve host names for every connection, proxies included, through `resolver`
+    pub fn with_resolver(mut self, resolver: impl Resolver + 'static) -> Client {
+        self.config.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    // Use the proxy in http_proxy (see Proxy::from_env), if set, and the hosts in no_proxy
+    // (comma-separated, in RequestConfig::no_proxy form) in place of the no_proxy list.
+    // NO_PROXY is read too, but never HTTP_PROXY.
+    pub fn with_proxy_from_env(mut self) -> Client {
+        if let Some(proxy) = Proxy::from_env() {
+            self.config.proxy = Some(proxy);
+        }
+        if let Some(no_proxy) = proxy::no_proxy_from_env() {
+            self.config.no_proxy = no_proxy;
+        }
+        self
+    }
+
+    // Add a hook that may change each request before it is sent, after those added before it
+    pub fn with_interceptor(mut self, interceptor: impl RequestInterceptor + 'static) -> Client {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    // Authenticate only where a server asks to: when a request without an Authorization
+    // header gets a 401 with a Basic or Bearer challenge, `callback` is asked for
+    // credentials and the request is sent once more with them. A None from the callback,
+    // or a second 401, leaves the 401 as the outcome. Streamed bodies aren't sent twice.
+    pub fn with_credential_callback<F>(mut self, callback: F) -> Client
+    where
+        F: Fn(&Challenge) -> Option<Credentials> + Send + Sync + 'static,
+    {
+        self.credentials = Some(Arc::new(callback));
+        self
+    }
+
+    // Send Basic credentials from `netrc` to the hosts it has a machine (or default) entry
+    // for, as curl --netrc does, on every request without an Authorization header of its own
+    // or from with_default_header. Each redirect hop gets the credentials for its own host.
+    pub fn with_netrc(mut self, netrc: Netrc) -> Client {
+        self.netrc = Some(netrc);
+        self
+    }
+
+    // with_netrc with the file Netrc::from_env finds, if there is one
+    pub fn with_netrc_from_env(mut self) -> Client {
+        if let Some(netrc) = Netrc::from_env() {
+            self.netrc = Some(netrc);
+        }
+        self
+    }
+
+    // Start a request to fill in step by step, e.g. client.request("GET", host, "/").header(..).send()
+    pub fn request(&self, method: impl Into<Method>, host: &str, path: &str) -> RequestBuilder<'_> {
+        RequestBuilder::new(self, method.into(), host, path)
+    }
+
+    // A builder starting from a built request, to send a variation of it
+    pub fn request_from(&self, request: Request) -> RequestBuilder<'_> {
+        RequestBuilder::from_request(self, request)
+    }
+
+    pub fn get(&self, host: &str, path: &str, headers: impl Into<HeaderList>) -> Result<HttpResponse, HttpError> {
+        self.send(Method::Get, host, path, None, headers)
+    }
+
+    pub fn post(&self, host: &str, path: &str, body: &str, headers: impl Into<HeaderList>) -> Result<HttpResponse, HttpError> {
+        self.send(Method::Post, host, path, Some(body), headers)
+    }
+
+    // PATCH with `patch` serialized as the body and the Content-Type of `format`. A JSON
+    // Patch has to be an array of operations; anything else fails before it is sent.
+    #[cfg(feature = "json")]
+    pub fn patch_json(&self, host: &str, path: &str, patch: &Json, format: PatchFormat) -> Result<HttpResponse, HttpError> {
+        if format == PatchFormat::JsonPatch && patch.as_array().is_none() {
+            return Err(HttpError::InvalidRequest("A JSON Patch document must be an array of operations".to_string()));
+        }
+        self.request(Method::Patch, host, path).header("Content-Type", format.content_type()).body(&patch.to_string()).send()
+    }
+
+    // See trace()
+    pub fn trace(&self, host: &str, path: &str, headers: impl Into<HeaderList>) -> Result<HttpResponse, HttpError> {
+        self.send(Method::Trace, host, path, None, headers)
+    }
+
+    // Whether `url` (http://host[:port]/path) answers within `timeout`, for health checks
+    // and monitoring: one HEAD request on a new connection, without retries or redirects,
+    // whose timings come back whatever the status, as any answer means the server is up.
+    // One that can't be reached in time fails with HttpError::Timeout or NetworkError.
+    pub fn check(&self, url: &str, timeout: Duration) -> Result<Timings, HttpError> {
+        let (host, path) = url::split_url(url)
+            .ok_or_else(|| HttpError::InvalidRequest(format!("Not an http:// URL: {:?}", url)))?;
+        let mut probe = self.clone();
+        probe.config = RequestConfig { timeout: Some(timeout), keep_alive: false, ..self.config.clone() };
+        #[cfg(feature = "json")]
+        {
+            probe.har = None;
+            probe.cassette = None;
+        }
+        let method = Method::Head.as_str();
+        let proxy_auth = probe.preemptive_proxy_auth(&host);
+        let (target, headers) = probe.route(method, &host, &path, &[], proxy_auth.as_deref(), false)?;
+        let request = probe.adjust_head(build_request_from(method, &host, &target, None, &headers)?);
+        let deadline = Some(Instant::now() + timeout);
+        let response = probe.exchange(method, &host, &request, None, deadline, proxy_auth.as_deref());
+        scratch::give(request);
+        Ok(response?.timings.unwrap_or_default())
+    }
+
+    pub fn send(
+        &self,
+        method: impl Into<Method>,
+        host: &str,
+        path: &str,
+        body: Option<&str>,
+        custom_headers: impl Into<HeaderList>
+    ) -> Result<HttpResponse, HttpError> {
+        let headers = custom_headers.into();
+        for (name, value) in headers.iter() {
+            require_ascii(name, value)?;
+        }
+        let request = Request {
+            method: method.into(),
+            host: host.to_string(),
+            path: path.to_string(),
+            headers: headers.as_slice().to_vec(),
+            body: body.map(str::to_string),
+        };
+        self.execute(&request)
+    }
+
+    // Send a request from RequestBuilder::build(); the same request can be executed again
+    pub fn execute(&self, request: &Request) -> Result<HttpResponse, HttpError> {
+        self.send_with_headers(&request.method, &request.host, &request.path, request.body.as_deref(), &request.headers)
+    }
+
+    // send() with the custom headers written in the given order
+    pub(crate) fn send_with_headers(
+        &self,
+        method: &Method,
+        host: &str,
+        path: &str,
+        body: Option<&str>,
+        custom_headers: &[(String, String)]
+    ) -> Result<HttpResponse, HttpError> {
+        let config = &self.config;
+        let deadline = config.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut method = method.clone();
+        // Owned only once a redirect has been followed
+        let mut host = Cow::Borrowed(host);
+        let mut path = Cow::Borrowed(path);
+        let mut body = body;
+        let mut redirects = 0;
+        // Without the credentials once a redirect leaves the origin
+        let mut custom_headers = Cow::Borrowed(custom_headers);
+
+        // Generated once, so redirects carry the same key along with retries
+        let mut idempotency = None;
+
+        loop {
+            let keyed = match &config.idempotency_key_header {
+                Some(header) if !method.is_idempotent() => {
+                    let (key, headers) = idempotency.get_or_insert_with(|| with_idempotency_key(header, &custom_headers));
+                    Some((header.as_str(), key.as_str(), headers.as_slice()))
+                }
+                _ => None,
+            };
+            let headers = keyed.map_or(&custom_headers[..], |(_, _, headers)| headers);
+            let outcome = self.send_with_retries(method.as_str(), &host, &path, body, headers, deadline);
+            let response = match (outcome, keyed) {
+                (Ok(response), Some((_, key, _))) => HttpResponse { idempotency_key: Some(key.to_string()), ..response },
+                (Ok(response), None) => response,
+                (Err(e), Some((header, key, _))) => return Err(e.with_idempotency_key(header, key)),
+                (Err(e), None) => return Err(e),
+            };
+            let response = HttpResponse { redirect_count: redirects, ..response };
+            if !response.status().is_redirect() || redirects == config.max_redirects {
+                return Ok(response);
+            }
+            let Some(location) = response.header("Location") else {
+                return Ok(response);
+            };
+            if let Some(scheme) = url::scheme(location).filter(|scheme| !["http", "https"].iter().any(|web| scheme.eq_ignore_ascii_case(web))) {
+                if config.error_on_non_http_redirect {
+                    return Err(HttpError::InvalidResponse(format!("Refusing to follow a redirect to a {}: URL: {}", scheme, location)));
+                }
+                return Ok(response);
+            }
+
+            let (next_host, next_path) = url::resolve_location(&host, &path, location)
+                .ok_or_else(|| HttpError::InvalidResponse(format!("Unsupported redirect location: {}", location)))?;
+
+            // 303, and 301/302 after a POST, continue as a GET without the body
+            let code = response.status_code;
+            if code == 303 || ((code == 301 || code == 302) && method == Method::Post) {
+                method = Method::Get;
+                body = None;
+            }
+            // As curl and browsers do, credentials aren't sent on to another host or port
+            if !url::same_origin(&host, &next_host) {
+                let withhold = |headers: &mut Vec<(String, String)>| {
+                    headers.retain(|(name, _)| !CROSS_ORIGIN_WITHHELD.iter().any(|withheld| withheld.eq_ignore_ascii_case(name)));
+                };
+                withhold(custom_headers.to_mut());
+                if let Some((_, headers)) = idempotency.as_mut() {
+                    withhold(headers);
+                }
+            }
+            host = Cow::Owned(next_host);
+            path = Cow::Owned(next_path);
+            redirects += 1;
+        }
+    }
+
+    fn send_with_retries(
+        &self,
+        method: &str,
+        host: &str,
+        path: &str,
+        body: Option<&str>,
+        custom_headers: &[(String, String)],
+        deadline: Option<Instant>
+    ) -> Result<HttpResponse, HttpError> {
+        let config = &self.config;
+        let started = Instant::now();
+        let idempotency_key = config.idempotency_key_header.as_ref()
+            .and_then(|header| custom_headers.iter().find(|(name, _)| name.eq_ignore_ascii_case(header)))
+            .map(|(_, key)| key.as_str());
+        let mut attempt = 1;
+
+        loop {
+            let outcome = self.send_once(method, host, path, body, custom_headers, deadline).map(|(request, response)| {
+                scratch::give(request);
+                response
+            });
+            if attempt > config.max_retries {
+                return outcome;
+            }
+
+            let ctx = RetryContext::new(attempt, method, &outcome, started.elapsed(), idempotency_key);
+            let retry = match &config.retry_if {
+                Some(predicate) => predicate(&ctx),
+                None => default_retry_policy(&ctx),
+            } || (config.retry_empty_response && matches!(ctx.error, Some(HttpError::EmptyResponse)));
+            // Don't start a pause the deadline won't let us finish
+            let out_of_time = deadline.is_some_and(|d| Instant::now() + config.retry_delay >= d);
+            let cancelled = config.cancel.as_ref().is_some_and(CancelToken::is_cancelled);
+            if !retry || out_of_time || cancelled {
+                return outcome;
+            }
+
+            std::thread::sleep(config.retry_delay);
+            attempt += 1;
+        }
+    }
+
+    // The response along with the request bytes it answers
+    fn send_once(
+        &self,
+        method: &str,
+        host: &str,
+        path: &str,
+        body: Option<&str>,
+        custom_headers: &[(String, String)],
+        deadline: Option<Instant>
+    ) -> Result<(Vec<u8>, HttpResponse), HttpError> {
+        let proxy = self.proxy_for(host);
+        let mut proxy_auth = self.preemptive_proxy_auth(host);
+        // From the credential callback, once the server has asked for it
+        let mut authorization: Option<String> = None;
+        loop {
+            // Build first so an invalid request fails before anything is connected
+            let (target, mut headers) = self.route(method, host, path, custom_headers, proxy_auth.as_deref(), self.config.keep_alive)?;
+            if let Some(authorization) = &authorization {
+                headers.push(("Authorization".to_string(), authorization.clone()));
+            }
+            self.accept_gzip(&mut headers);
+            let body = self.encode_body(body, &mut headers);
+            let mut request = self.adjust_head(build_request_from(method, host, &target, body.as_deref(), &headers)?);
+            let mut outcome = self.exchange(method, host, &request, None, deadline, proxy_auth.as_deref());
+            let rejected = match &outcome {
+                Ok(response) => matches!(response.status_code, 400 | 505),
+                Err(e) => matches!(e, HttpError::InvalidResponse(_)),
+            };
+            if self.config.http10_fallback && rejected {
+                downgrade_to_http10(&mut request);
+                outcome = self.exchange(method, host, &request, None, deadline, proxy_auth.as_deref());
+            }
+            if let Ok(response) = &outcome
+                && authorization.is_none()
+                && let Some(answer) = self.answer_challenge(method, response, &headers)
+            {
+                authorization = Some(answer);
+                scratch::give(request);
+                continue;
+            }
+            match outcome {
+                // Credentials held back until the proxy asks for them
+                Err(HttpError::ProxyAuthRequired { .. }) if proxy_auth.is_none() && proxy.is_some_and(|p| p.credentials.is_some()) => {
+                    proxy_auth = proxy.and_then(Proxy::authorization);
+                    scratch::give(request);
+                }
+                outcome => {
+                    let response = HttpResponse { url: request_url(host, path), ..check_status(outcome?)? };
+                    return Ok((request, response));
+                }
+            }
+        }
+    }
+
+    // One request with a body streamed from `body.reader` using chunked encoding.
+    // The body can't be sent twice, so there are no retries and redirects are returned as they are.
+    pub(crate) fn send_streamed(
+        &self,
+        method: &str,
+        host: &str,
+        path: &str,
+        custom_headers: &[(String, String)],
+        body: StreamedBody<'_>
+    ) -> Result<(Vec<u8>, HttpResponse), HttpError> {
+        let proxy_auth = self.preemptive_proxy_auth(host);
+        let (target, mut headers) = self.route(method, host, path, custom_headers, proxy_auth.as_deref(), self.config.keep_alive)?;
+        self.accept_gzip(&mut headers);
+        let request = self.adjust_head(streamed_head(method, host, &target, &headers, &body)?);
+        let deadline = self.config.timeout.map(|timeout| Instant::now() + timeout);
+        let response = self.exchange(method, host, &request, Some(body), deadline, proxy_auth.as_deref())?;
+        Ok((request, HttpResponse { url: request_url(host, path), ..check_status(response)? }))
+    }
+
+    // Send one request (no retries or redirects) and return the bytes written along with the response
+    pub(crate) fn send_debug(
+        &self,
+        method: &str,
+        host: &str,
+        path: &str,
+        body: Option<&str>,
+        custom_headers: &[(String, String)],
+        streamed: Option<StreamedBody<'_>>
+    ) -> Result<(SentRequest, HttpResponse), HttpError> {
+        let (bytes, response) = match streamed {
+            Some(streamed) => self.send_streamed(method, host, path, custom_headers, streamed)?,
+            None => {
+                let deadline = self.config.timeout.map(|timeout| Instant::now() + timeout);
+                self.send_once(method, host, path, body, custom_headers, deadline)?
+            }
+        };
+        let address = response.connection.map(|connection| connection.peer_addr);
+        Ok((SentRequest { bytes, address }, response))
+    }
+
+    // Send one request (no retries or redirects) and return as soon as the response head
+    // has arrived, leaving the body to be read from the connection. With keep_alive the
+    // connection goes back to the pool once the body has been read or the reader is dropped.
+    pub(crate) fn open(
+        &self,
+        method: &str,
+        host: &str,
+        path: &str,
+        body: Option<&str>,
+        custom_headers: &[(String, String)],
+        streamed: Option<StreamedBody<'_>>
+    ) -> Result<ResponseReader, HttpError> {
+        let proxy_auth = self.preemptive_proxy_auth(host);
+        let (target, mut headers) = self.route(method, host, path, custom_headers, proxy_auth.as_deref(), self.config.keep_alive)?;
+        let request = match &streamed {
+            Some(streamed) => streamed_head(method, host, &target, &headers, streamed)?,
+            None => {
+                let body = self.encode_body(body, &mut headers);
+                build_request_from(method, host, &target, body.as_deref(), &headers)?
+            }
+        };
+        let request = self.adjust_head(request);
+
+        #[cfg(feature = "json")]
+        if let Some(cassette) = self.cassette.as_ref().filter(|c| c.mode() == CassetteMode::Replay) {
+            let response = check_status(cassette.replay(&request)?)?;
+            return Ok(ResponseReader::from_response(HttpResponse { url: request_url(host, path), ..response }));
+        }
+
+        let deadline = self.config.timeout.map(|timeout| Instant::now() + timeout);
+        let started = self.start(method, host, &request, streamed, deadline, proxy_auth.as_deref());
+        scratch::give(request);
+        let reader = started?.reader.with_url(request_url(host, path));
+        self.check_proxy_status(host, reader.head())?;
+        check_status(reader.head().clone())?;
+        Ok(reader)
+    }
+
+    // Write `request` (then the streamed body, if any) and read the whole response, leaving
+    // error statuses other than a proxy's 407 to the caller. HAR and cassette recordings of
+    // a streamed request contain its head only.
+    fn exchange(
+        &self,
+        method: &str,
+        host: &str,
+        request: &[u8],
+        streamed: Option<StreamedBody<'_>>,
+        deadline: Option<Instant>,
+        proxy_auth: Option<&str>
+    ) -> Result<HttpResponse, HttpError> {
+        #[cfg(feature = "json")]
+        if let Some(cassette) = self.cassette.as_ref().filter(|c| c.mode() == CassetteMode::Replay) {
+            return cassette.replay(request);
+        }
+
+        let started_at = SystemTime::now();
+        let Started { reader, connection, started, resolved, connected, sent, first_byte } =
+            self.start(method, host, request, streamed, deadline, proxy_auth)?;
+        let mut reader = reader;
+        let body = if self.config.ignore_body { reader.skip_body().map(|_| Vec::new()) } else { reader.read_body() };
+        let body = body.map_err(|e| e.with_connection(&connection))?;
+        let (bytes_sent, bytes_received) = reader.transferred();
+        let mut response = reader.finish(body);
+        if self.config.decompress {
+            response.decompress().map_err(|e| e.with_connection(&connection))?;
+        }
+        let done = Instant::now();
+        let first_byte = first_byte.unwrap_or(done);
+
+        if self.config.merge_trailers {
+            response.merge_trailers();
+        }
+        response.timings = Some(Timings {
+            dns: resolved.saturating_duration_since(started),
+            connect: connected.saturating_duration_since(resolved),
+            send: sent - connected,
+            wait: first_byte.saturating_duration_since(sent),
+            receive: done.saturating_duration_since(first_byte),
+            bytes_sent,
+            bytes_received,
+        });
+
+        #[cfg(feature = "json")]
+        if let Some(har) = &self.har {
+            har.record(request, &response, started_at);
+        }
+        #[cfg(feature = "json")]
+        if let Some(cassette) = &self.cassette {
+            cassette.record(request, &response);
+        }
+        #[cfg(not(feature = "json"))]
+        let _ = started_at;
+
+        self.check_proxy_status(host, &response)?;
+        Ok(response)
+    }
+
+    // The Authorization value to retry a 401 with, if the callback gives credentials for it
+    // (never for TRACE, which would echo them back)
+    fn answer_challenge(&self, method: &str, response: &HttpResponse, headers: &[(String, String)]) -> Option<String> {
+        let callback = self.credentials.as_ref().filter(|_| response.status_code == 401 && method != Method::Trace.as_str())?;
+        if headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Authorization")) {
+            return None;
+        }
+        auth::answer(response, callback)
+    }
+
+    // Send the request and read the response head. With keep_alive a pooled connection is
+    // tried first; if the server turns out to have closed it (it fails before any response
+    // byte arrives) the request is sent once more on a new connection, unless its body was
+    // streamed and can't be sent again.
+    fn start(
+        &self,
+        method: &str,
+        host: &str,
+        request: &[u8],
+        streamed: Option<StreamedBody<'_>>,
+        deadline: Option<Instant>,
+        proxy_auth: Option<&str>
+    ) -> Result<Started, HttpError> {
+        if self.config.shutdown_after_write && self.config.keep_alive {
+            return Err(HttpError::InvalidRequest(
+                "shutdown_after_write can't be used with keep_alive: a half-closed connection can't be reused".to_string(),
+            ));
+        }
+        if self.config.read_buffer_size == 0 {
+            return Err(HttpError::InvalidRequest("read_buffer_size must be at least 1".to_string()));
+        }
+        if let Some(rate) = self.config.rate_limit {
+            self.rate_limiter.acquire(rate, self.config.fail_at_rate_limit, deadline, self.config.timeout, self.config.cancel.as_ref())?;
+        }
+        let replayable = streamed.is_none();
+        let mut streamed = streamed;
+        let mut stale_retry = false;
+        let key = self.config.keep_alive.then(|| self.pool_key(host));
+        loop {
+            let started = Instant::now();
+            let (mut stream, mut connection) = self.connection(host, key.as_deref(), deadline, proxy_auth, stale_retry)?;
+            connection.stale_retry = stale_retry;
+            let timed = stream.get_mut();
+            timed.requests += 1;
+            timed.bytes_written = 0;
+            timed.bytes_read = 0;
+
+            // With Expect: 100-continue the body, if there is one, waits for the go-ahead
+            let body_start = expects_continue(request).filter(|&start| start < request.len() || streamed.is_some());
+            let (head_part, body_part) = request.split_at(body_start.unwrap_or(request.len()));
+
+            // Whether the connection was already closed when the request went out
+            let mut stale = false;
+            let mut written = timed.write_all(head_part).and_then(|_| timed.flush()).map_err(|e| {
+                stale = is_stale(&e);
+                HttpError::from_io("Failed to send request", e)
+            });
+            // The final response a server sent instead of 100 Continue, the body left unsent
+            let mut early = None;
+            if written.is_ok() && body_start.is_some() {
+                match await_continue(&mut stream, self.config.continue_timeout, self.config.lenient, self.config.max_header_bytes) {
+                    Ok(head) => early = head,
+                    Err(e) => {
+                        stale = is_stale(&e) && stream.get_ref().first_byte.is_none();
+                        written = Err(HttpError::from_io("Failed to read response", e));
+                    }
+                }
+            }
+            let body_unsent = early.is_some();
+            if written.is_ok() && !body_unsent {
+                let timed = stream.get_mut();
+                written = timed.write_all(body_part).and_then(|_| timed.flush())
+                    .map_err(|e| HttpError::from_io("Failed to send request", e))
+                    .and_then(|_| match streamed.take() {
+                        Some(body) => write_streamed_body(timed, body, self.config.request_chunk_size),
+                        None => Ok(()),
+                    })
+                    .and_then(|_| if self.config.shutdown_after_write {
+                        timed.shutdown_write().map_err(|e| HttpError::from_io("Failed to shut down sending", e))
+                    } else {
+                        Ok(())
+                    });
+            }
+            // A body this side failed to produce is no reason to wait for the server's answer
+            if let Err(error @ (HttpError::InvalidRequest(_) | HttpError::InvalidHeader(_))) = written {
+                return Err(error);
+            }
+            let sent = Instant::now();
+            stream.get_mut().await_head();
+            let read = match early {
+                Some(head) => Ok(head),
+                None => read_final_head(&mut stream, self.config.lenient, self.config.max_header_bytes),
+            };
+            let head = written.and_then(|_| match read {
+                Ok(head) => {
+                    stale = head.is_empty();
+                    Ok(head)
+                }
+                Err(e) => {
+                    stale = is_stale(&e) && stream.get_ref().first_byte.is_none();
+                    Err(HttpError::from_io("Failed to read response", e))
+                }
+            });
+            stream.get_mut().head_done();
+            if stale && connection.reused {
+                self.pool.closed_stale();
+            }
+            if stale && connection.reused && replayable {
+                stale_retry = true;
+                continue;
+            }
+            if connection.reused && head.is_ok() {
+                self.pool.reused();
+            }
+
+            let head = head
+                .and_then(|head| if self.config.strict_line_endings { require_crlf(&head).map(|_| head) } else { Ok(head) })
+                .map_err(|e| e.with_connection(&connection))?;
+            // A server that answered before the body was sent may still be waiting for it
+            let recycle = key.filter(|_| !body_unsent).map(|key| Recycle { pool: self.pool.clone(), key, max_idle: self.config.pool_max_idle });
+            let resolved = stream.get_ref().resolved;
+            let connected = stream.get_ref().connected;
+            let first_byte = stream.get_ref().first_byte;
+            let reader = ResponseReader::from_head(stream, &head, method, connection);
+            scratch::give(head);
+            let reader = reader
+                .map_err(|e| e.with_connection(&connection))?
+                .allow_truncated(self.config.allow_truncated_body)
+                .verify_digest(self.config.verify_digest)
+                .discard_limit(self.config.discard_limit)
+                .recycle_into(recycle);
+            return Ok(Started { reader, connection, started, resolved, connected, sent, first_byte });
+        }
+    }
+
+    // A connection pooled under `key` (given when keep_alive is on) unless `fresh` is asked
+    // for, otherwise a new one (to the proxy, through a tunnel if configured). A pooled one the
+    // server has closed while it was idle is dropped before anything is written to it.
+    fn connection(
+        &self,
+        host: &str,
+        key: Option<&str>,
+        deadline: Option<Instant>,
+        proxy_auth: Option<&str>,
+        fresh: bool
+    ) -> Result<(BufReader<TimedStream>, ConnectionInfo), HttpError> {
+        // At max_connections, whichever comes first: a connection pooled for `key` or room
+        // for a new one
+        let slot = loop {
+            if let Some(key) = key.filter(|_| !fresh)
+                && let Some((mut stream, connection)) = self.pool.take(key, self.config.pool_idle_timeout)
+            {
+                if !stream.buffer().is_empty() || !stream.get_ref().is_open() {
+                    self.pool.closed_stale();
+                    continue;
+                }
+                stream.get_mut().reset(&self.config, deadline);
+                return Ok((stream, connection));
+            }
+            if let Some(slot) = self.pool.slot(self.config.max_connections) {
+                break slot;
+            }
+            if self.config.fail_at_connection_limit {
+                return Err(HttpError::NetworkError(format!(
+                    "Connection limit reached: {} connections are open", self.config.max_connections.unwrap_or_default()
+                )));
+            }
+            self.pool.wait_for_slot(self.config.max_connections, deadline, self.config.timeout, self.config.cancel.as_ref())?;
+        };
+        let proxy = self.proxy_for(host);
+        let (stream, resolved) = connect(proxy.map_or(host, |p| p.authority.as_str()), &self.config, deadline)?;
+        let connection = ConnectionInfo { socket_config_applied: apply_socket_config(&stream, &self.config.socket), ..connection_info(&stream)? };
+        let mut timed = TimedStream::new(stream, &self.config, deadline);
+        timed.resolved = resolved;
+        timed.in_use = Some(self.pool.opened());
+        timed.slot = Some(slot);
+        if proxy.is_some_and(|p| p.tunnel) {
+            proxy::open_tunnel(&mut timed, host, proxy_auth).map_err(|e| e.with_connection(&connection))?;
+        }
+        Ok((BufReader::with_capacity(self.config.read_buffer_size, timed), connection))
+    }
+
+    // Requests share a pooled connection only if they would have opened the same one;
+    // "example.com" and "EXAMPLE.com:80" are the same host
+    fn pool_key(&self, host: &str) -> String {
+        let (name, port) = url::split_authority(host);
+        let mut key = String::with_capacity(host.len() + 64);
+        let _ = write!(key, "{}", url::Authority(name, port));
+        key.make_ascii_lowercase();
+        match self.proxy_for(&key) {
+            Some(proxy) if proxy.tunnel => key.extend([" via ", &proxy.authority]),
+            Some(proxy) => key.clone_from(&proxy.authority),
+            None => {}
+        }
+        key.push_str(" from ");
+        if let Some(local) = self.config.local_address {
+            let _ = write!(key, "{}", local);
+        }
+        key
+    }
+
+    // The default headers for a request to `host`: with_default_header's, with those of the
+    // with_host_header patterns matching it taking the place of any of the same name
+    fn defaults_for(&self, host: &str) -> Cow<'_, [(String, String)]> {
+        if self.host_headers.is_empty() {
+            return Cow::Borrowed(&self.default_headers);
+        }
+        let (name, _) = url::split_authority(host);
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let matches = |pattern: &str| match pattern.strip_prefix("*.") {
+            Some(domain) => name.strip_suffix(domain).is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+            None => name == pattern,
+        };
+        let mut matching = self.host_headers.iter().filter(|(pattern, _)| matches(pattern)).peekable();
+        if matching.peek().is_none() {
+            return Cow::Borrowed(&self.default_headers);
+        }
+        let mut defaults = self.default_headers.clone();
+        for (_, headers) in matching {
+            for (name, value) in headers {
+                defaults.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+                defaults.push((name.clone(), value.clone()));
+            }
+        }
+        Cow::Owned(defaults)
+    }
+
+    // Request target and headers for the configured route: absolute-form with
+    // Proxy-Authorization when going through a proxy without a tunnel. Default headers come
+    // first, and Connection: keep-alive is added for a pooled connection. A TRACE request,
+    // whose headers come back in the response, gets no sensitive default headers and a
+    // Max-Forwards limit. The interceptors get the last word.
+    fn route<'p>(
+        &self,
+        method: &str,
+        host: &str,
+        path: &'p str,
+        custom_headers: &[(String, String)],
+        proxy_auth: Option<&str>,
+        keep_alive: bool
+    ) -> Result<Route<'p>, HttpError> {
+        let defaults = self.defaults_for(host);
+        for (name, value) in defaults.iter() {
+            require_ascii(name, value)?;
+        }
+        let overridden = |name: &str| custom_headers.iter().any(|(custom, _)| custom.eq_ignore_ascii_case(name));
+        let trace = method == Method::Trace.as_str();
+        let withheld = |name: &str| trace && redact::is_sensitive_header(name);
+        let mut headers: Vec<(String, String)> = defaults.iter()
+            .filter(|(name, _)| !overridden(name) && !withheld(name))
+            .cloned()
+            .collect();
+        headers.extend_from_slice(custom_headers);
+        // Cookies add up rather than replace each other, so a default Cookie header is merged
+        // into the request's
+        if let Some((_, defaults)) = defaults.iter().find(|(name, _)| name.eq_ignore_ascii_case("Cookie"))
+            && !withheld("Cookie")
+            && let Some((_, value)) = headers.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case("Cookie"))
+        {
+            *value = cookie::merge(defaults, value);
+        }
+        if let Some(netrc) = &self.netrc
+            && !withheld("Authorization")
+            && !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+            && let Some(credentials) = netrc.credentials(host)
+        {
+            headers.push(("Authorization".to_string(), credentials.authorization()));
+        }
+        if trace && !overridden("Max-Forwards") {
+            headers.push(("Max-Forwards".to_string(), TRACE_MAX_FORWARDS.to_string()));
+        }
+        if keep_alive && !overridden("Connection") {
+            headers.push(("Connection".to_string(), "keep-alive".to_string()));
+        }
+        let target = match self.proxy_for(host) {
+            Some(proxy) if !proxy.tunnel => {
+                if let Some(authorization) = proxy_auth {
+                    headers.push(("Proxy-Authorization".to_string(), authorization.to_string()));
+                }
+                Cow::Owned(proxy::absolute_target(host, path)?)
+            }
+            _ => Cow::Borrowed(path),
+        };
+        if !self.interceptors.is_empty() {
+            let mut request = OutgoingRequest { method, host, target: &target, headers: &mut headers };
+            for interceptor in &self.interceptors {
+                interceptor.intercept(&mut request)?;
+            }
+            for (name, value) in &headers {
+                require_ascii(name, value)?;
+            }
+        }
+        Ok((target, headers))
+    }
+
+    // For RequestConfig::omit_host_header and bare_lf
+    fn adjust_head(&self, mut request: Vec<u8>) -> Vec<u8> {
+        if self.config.omit_host_header {
+            remove_host_header(&mut request);
+        }
+        if self.config.bare_lf {
+            end_head_lines_with_lf(&mut request);
+        }
+        request
+    }
+
+    // For RequestConfig::decompress, which only reaches responses read in full
+    fn accept_gzip(&self, headers: &mut Vec<(String, String)>) {
+        if self.config.decompress && !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Accept-Encoding")) {
+            headers.push(("Accept-Encoding".to_string(), "gzip".to_string()));
+        }
+    }
+
+    // The body as it is sent, compressed for RequestConfig::gzip_request_body with the
+    // headers made to match, so neither a stale Content-Length nor a second
+    // Content-Encoding goes out. An empty body is sent as it is, still with Content-Length: 0,
+    // rather than as 20 bytes of gzip framing.
+    fn encode_body<'b>(&self, body: Option<&'b str>, headers: &mut Vec<(String, String)>) -> Option<Cow<'b, [u8]>> {
+        let body = body?.as_bytes();
+        let encoded = headers.iter()
+            .any(|(name, value)| name.eq_ignore_ascii_case("Content-Encoding") && !value.trim().eq_ignore_ascii_case("identity"));
+        if !self.config.gzip_request_body || encoded || body.is_empty() {
+            return Some(Cow::Borrowed(body));
+        }
+        headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Content-Encoding") && !name.eq_ignore_ascii_case("Content-Length"));
+        // Guessed from the body as it is, not as compressed
+        if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Content-Type")) {
+            headers.push(("Content-Type".to_string(), mime::sniff(body).to_string()));
+        }
+        headers.push(("Content-Encoding".to_string(), "gzip".to_string()));
+        Some(Cow::Owned(gzip::compress(body)))
+    }
+
+    // The proxy requests to `host` go through, unless no_proxy has it reached directly
+    fn proxy_for(&self, host: &str) -> Option<&Proxy> {
+        self.config.proxy.as_ref().filter(|_| !proxy::bypasses(&self.config.no_proxy, host))
+    }
+
+    fn preemptive_proxy_auth(&self, host: &str) -> Option<String> {
+        self.proxy_for(host).filter(|p| p.preemptive).and_then(Proxy::authorization)
+    }
+
+    // A 407 comes from the proxy, not the origin
+    fn check_proxy_status(&self, host: &str, response: &HttpResponse) -> Result<(), HttpError> {
+        let proxied = self.proxy_for(host).is_some_and(|p| !p.tunnel);
+        if proxied && response.status_code == 407 {
+            return Err(proxy::auth_required(response.header("Proxy-Authenticate")));
+        }
+        Ok(())
+    }
+}
+
+// Request target and headers from Client::route()
+type Route<'p> = (Cow<'p, str>, Vec<(String, String)>);
+
+// A response whose head has arrived, with the times the Timings are worked out from
+struct Started {
+    reader: ResponseReader,
+    connection: ConnectionInfo,
+    started: Instant,
+    resolved: Instant,
+    connected: Instant,
+    sent: Instant,
+    first_byte: Option<Instant>,
+}
+
+// A request body read from a stream, with trailers whose values are known once it is sent
+pub(crate) struct StreamedBody<'s> {
+    pub(crate) reader: &'s mut dyn Read,
+    // Send exactly this many bytes of `reader` with a Content-Length instead of chunked encoding
+    pub(crate) length: Option<u64>,
+    // Announced in the Trailer header before the body
+    pub(crate) trailer_names: &'s [String],
+    // Called after the last body byte has been sent
+    pub(crate) trailers: Option<TrailerValues<'s>>,
+}
+
+pub(crate) type TrailerValues<'s> = Box<dyn FnOnce() -> Vec<(String, String)> + 's>;
+
+// Head of a request whose body follows with chunked encoding, or as exactly `body.length` bytes
+pub(crate) fn streamed_head(
+    method: &str,
+    host: &str,
+    path: &str,
+    custom_headers: &[(String, String)],
+    body: &StreamedBody<'_>
+) -> Result<Vec<u8>, HttpError> {
+    if custom_headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Content-Length")) {
+        return Err(HttpError::InvalidRequest(
+            "A streamed body is sent chunked; give its length with content_length() rather than a header".to_string(),
+        ));
+    }
+    let mut headers = custom_headers.to_vec();
+    if let Some(length) = body.length {
+        headers.push(("Content-Length".to_string(), length.to_string()));
+        return build_request_from(method, host, path, None, &headers);
+    }
+    headers.push(("Transfer-Encoding".to_string(), "chunked".to_string()));
+    if !body.trailer_names.is_empty() {
+        for name in body.trailer_names {
+            validate_header(name, "")?;
+            if chunked::forbidden_trailer(name) {
+                return Err(HttpError::InvalidHeader(format!("{} can't be sent as a trailer", name)));
+            }
+        }
+        headers.push(("Trailer".to_string(), body.trailer_names.join(", ")));
+    }
+    build_request_from(method, host, path, None, &headers)
+}
+
+fn write_streamed_body<W: Write>(out: &mut W, body: StreamedBody<'_>, chunk_size: usize) -> Result<(), HttpError> {
+    if let Some(length) = body.length {
+        let sent = io::copy(&mut body.reader.take(length), out)
+            .and_then(|sent| out.flush().map(|_| sent))
+            .map_err(|e| HttpError::from_io("Failed to send request", e))?;
+        if sent < length {
+            return Err(HttpError::InvalidRequest(format!(
+                "Body stream ended after {} of the {} bytes given as its Content-Length", sent, length
+            )));
+        }
+        return Ok(());
+    }
+    chunked::encode(body.reader, out, chunk_size)?;
+    let trailers = body.trailers.map(|values| values()).unwrap_or_default();
+    for (name, value) in &trailers {
+        if !body.trailer_names.iter().any(|announced| announced.eq_ignore_ascii_case(name)) {
+            return Err(HttpError::InvalidRequest(format!("Trailer {} was not announced before the body", name)));
+        }
+        validate_header(name, value)?;
+        require_ascii(name, value)?;
+    }
+    chunked::finish(out, &trailers)
+}
+
+// Used by send_request() and the get/post/options shortcuts; set once at startup
+static DEFAULT_CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+
+// Replace the process-wide client behind the free functions. Clients created
+// elsewhere are not affected.
+pub fn set_default_client(client: Client) {
+    *DEFAULT_CLIENT.write().unwrap_or_else(|e| e.into_inner()) = Some(client);
+}
+
+// A copy of the process-wide client (Client::default() until one is set)
+pub fn default_client() -> Client {
+    DEFAULT_CLIENT.read().unwrap_or_else(|e| e.into_inner()).clone().unwrap_or_default()
+}
+
+pub fn send_request(
+    method: impl Into<Method>,
+    host: &str,
+    path: &str,
+    body: Option<&str>,
+    custom_headers: impl Into<HeaderList>
+) -> Result<HttpResponse, HttpError> {
+    default_client().send(method, host, path, body, custom_headers)
+}
+
+pub fn send_request_with(
+    method: impl Into<Method>,
+    host: &str,
+    path: &str,
+    body: Option<&str>,
+    custom_headers: impl Into<HeaderList>,
+    config: &RequestConfig
+) -> Result<HttpResponse, HttpError> {
+    Client::with_config(config.clone()).send(method, host, path, body, custom_headers)
+}
+
+// Core request/response exchange over an already established stream (no connecting or timeouts)
+pub fn send_request_on<S: Read + Write>(
+    stream: &mut S,
+    method: impl Into<Method>,
+    host: &str,
+    path: &str,
+    body: Option<&str>,
+    custom_headers: impl Into<HeaderList>
+) -> Result<HttpResponse, HttpError> {
+    let request = build_request(method, host, path, body, custom_headers)?;
+    write_request(stream, &request)?;
+    check_status(read_response(stream)?)
+}
+
+// Escape hatch: write `request` verbatim and return the raw response bytes, unparsed
+pub fn send_bytes(host: &str, port: u16, request: &[u8], config: &RequestConfig) -> Result<Vec<u8>, HttpError> {
+    let authority = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let deadline = config.timeout.map(|timeout| Instant::now() + timeout);
+
+    let (stream, _) = connect(&authority, config, deadline)?;
+    let connection = ConnectionInfo { socket_config_applied: apply_socket_config(&stream, &config.socket), ..connection_info(&stream)? };
+    let mut timed = TimedStream::new(stream, config, deadline);
+    write_request(&mut timed, request).map_err(|e| e.with_connection(&connection))?;
+
+    let mut response = Vec::new();
+    timed.read_to_end(&mut response)
+        .map_err(|e| HttpError::from_io("Failed to read response", e).with_connection(&connection))?;
+    Ok(response)
+}
+
+// For HttpResponse::url; the host in ASCII, as the request went out
+fn request_url(host: &str, path: &str) -> Option<String> {
+    url::ascii_authority(host).ok().map(|authority| ["http://", &authority, path].concat())
+}
+
+// Request headers dropped when a redirect goes to another origin
+const CROSS_ORIGIN_WITHHELD: [&str; 3] = ["Authorization", "Cookie", "Proxy-Authorization"];
+
+// The custom headers with an idempotency key under `header`, generated unless the caller
+// set one
+fn with_idempotency_key(header: &str, custom_headers: &[(String, String)]) -> (String, Vec<(String, String)>) {
+    let mut headers = custom_headers.to_vec();
+    match custom_headers.iter().find(|(name, _)| name.eq_ignore_ascii_case(header)) {
+        Some((_, key)) => (key.clone(), headers),
+        None => {
+            let key = retry::idempotency_key();
+            headers.push((header.to_string(), key.clone()));
+            (key, headers)
+        }
+    }
+}
+
+fn write_request<S: Write>(stream: &mut S, request: &[u8]) -> Result<(), HttpError> {
+    stream.write_all(request)
+        .and_then(|_| stream.flush())
+        .map_err(|e| HttpError::from_io("Failed to send request", e))
+}
+
+// Wait up to `timeout` for the answer to Expect: 100-continue: None once the server says
+// 100 Continue, or says nothing in time, for the body to follow; otherwise the head of the
+// final response it sent instead, empty if it closed the connection
+fn await_continue(stream: &mut BufReader<TimedStream>, timeout: Duration, lenient: bool, max_bytes: usize) -> io::Result<Option<Vec<u8>>> {
+    loop {
+        // Bounded by the request's read timeout too, if that is shorter
+        let limit = stream.get_mut().replace_read_timeout(None);
+        stream.get_mut().replace_read_timeout(Some(limit.map_or(timeout, |limit| limit.min(timeout))));
+        let arrived = stream.fill_buf().map(|buf| !buf.is_empty());
+        stream.get_mut().replace_read_timeout(limit);
+        match arrived {
+            Err(e) if transport::timed_out(&e).is_some_and(|(phase, _)| phase == TimeoutPhase::Read) => return Ok(None),
+            Err(e) => return Err(e),
+            Ok(false) => return Ok(Some(Vec::new())),
+            Ok(true) => {}
+        }
+        let head = read_head_bytes(stream, lenient, max_bytes)?;
+        if !is_interim(&head) {
+            return Ok(Some(head));
+        }
+        // Timings::wait is for the final response
+        stream.get_mut().first_byte = None;
+        if head.get(9..12) == Some(b"100") {
+            return Ok(None);
+        }
+    }
+}
+
+// Errors that mean the server had closed the connection, rather than rejected the request
+fn is_stale(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+// Read until the server closes the connection
+fn read_response<S: Read>(stream: &mut S) -> Result<HttpResponse, HttpError> {
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)
+        .map_err(|e| HttpError::from_io("Failed to read response", e))?;
+
+    parse_response_bytes(&response, false)
+}
+
+// Update convenience functions to use new error type
+pub fn get(host: &str, path: &str, headers: impl Into<HeaderList>) -> Result<HttpResponse, HttpError> {
+    send_request(Method::Get, host, path, None, headers)
+}
+
+pub fn post(host: &str, path: &str, body: &str, headers: impl Into<HeaderList>) -> Result<HttpResponse, HttpError> {
+    send_request(Method::Post, host, path, Some(body), headers)
+}
+
+// Pass "*" as the path for a server-wide OPTIONS request
+pub fn options(host: &str, path: &str, headers: impl Into<HeaderList>) -> Result<HttpResponse, HttpError> {
+    send_request(Method::Options, host, path, None, headers)
+}
+
+// Client::check with the process-wide client
+pub fn check(url: &str, timeout: Duration) -> Result<Timings, HttpError> {
+    default_client().check(url, timeout)
+}
+
+// Ask the server (or the last proxy Max-Forwards lets it reach) to echo the request back, to
+// see what the proxies on the way changed; read it with echoed_request() on the response
+pub fn trace(host: &str, path: &str, headers: impl Into<HeaderList>) -> Result<HttpResponse, HttpError> {
+    send_request(Method::Trace, host, path, None, headers)
+}
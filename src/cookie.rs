@@ -0,0 +1,249 @@
+// Cookies a server sets, parsed from Set-Cookie (RFC 6265 section 5.2). There's no jar:
+// the client doesn't store cookies, and only sends those given with RequestBuilder::cookie()
+// or a Cookie header.
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use crate::date;
+use crate::error::HttpError;
+use crate::redact;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    // From the Expires attribute; None if absent or not a date
+    pub expires: Option<SystemTime>,
+    // From Max-Age, which wins over Expires; zero (also for a negative Max-Age) means expire now
+    pub max_age: Option<Duration>,
+    // Lowercase, without a leading dot; None means host-only
+    pub domain: Option<String>,
+    // None when absent or not starting with '/', leaving the default path to the caller
+    pub path: Option<String>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl Cookie {
+    // Parse one Set-Cookie value, e.g. "id=a3fWa; Max-Age=2592000; Secure; SameSite=Lax".
+    // Attribute names are case-insensitive; unknown attributes and attributes with
+    // unusable values are ignored. Fails only without a "name=value" pair.
+    pub fn parse(set_cookie: &str) -> Result<Cookie, HttpError> {
+        let mut parts = set_cookie.split(';');
+        let pair = parts.next().unwrap_or("");
+        let Some((name, value)) = pair.split_once('=') else {
+            return Err(HttpError::InvalidHeader(format!("Set-Cookie without name=value: {}", pair.trim())));
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(HttpError::InvalidHeader(format!("Set-Cookie without a cookie name: {}", pair.trim())));
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+            expires: None,
+            max_age: None,
+            domain: None,
+            path: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        };
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let (key, value) = (key.trim(), value.trim());
+            if key.eq_ignore_ascii_case("Expires") {
+                if let Some(expires) = date::parse_http_date(value) {
+                    cookie.expires = Some(expires);
+                }
+            } else if key.eq_ignore_ascii_case("Max-Age") {
+                if let Some(max_age) = parse_max_age(value) {
+                    cookie.max_age = Some(max_age);
+                }
+            } else if key.eq_ignore_ascii_case("Domain") {
+                let domain = value.strip_prefix('.').unwrap_or(value);
+                if !domain.is_empty() {
+                    cookie.domain = Some(domain.to_ascii_lowercase());
+                }
+            } else if key.eq_ignore_ascii_case("Path") {
+                if value.starts_with('/') {
+                    cookie.path = Some(value.to_string());
+                }
+            } else if key.eq_ignore_ascii_case("Secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("HttpOnly") {
+                cookie.http_only = true;
+            } else if key.eq_ignore_ascii_case("SameSite") {
+                cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                    "strict" => Some(SameSite::Strict),
+                    "lax" => Some(SameSite::Lax),
+                    "none" => Some(SameSite::None),
+                    _ => cookie.same_site,
+                };
+            }
+        }
+        Ok(cookie)
+    }
+
+    // When the cookie expires, for one received at `received`; None for a session cookie
+    pub fn expires_at(&self, received: SystemTime) -> Option<SystemTime> {
+        match self.max_age {
+            Some(max_age) => Some(received.checked_add(max_age).unwrap_or(received)),
+            None => self.expires,
+        }
+    }
+
+    // Whether the cookie has expired by `now`, for one received at `received`
+    pub fn is_expired(&self, received: SystemTime, now: SystemTime) -> bool {
+        self.expires_at(received).is_some_and(|expires| expires <= now)
+    }
+}
+
+// A pair for a Cookie header: the name a token, the value cookie-octets, optionally in
+// double quotes (RFC 6265 section 4.1.1), so neither can break the "a=1; b=2" list
+pub(crate) fn validate_pair(name: &str, value: &str) -> Result<(), HttpError> {
+    let token = |b: u8| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+    if name.is_empty() || !name.bytes().all(token) {
+        return Err(HttpError::InvalidHeader(format!("Invalid cookie name: {:?}", name)));
+    }
+    let octets = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+    if !octets.bytes().all(|b| matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)) {
+        return Err(HttpError::InvalidHeader(format!("Invalid value for cookie {}", name)));
+    }
+    Ok(())
+}
+
+// One Cookie header value from a Client's default one and a request's: the request's pairs
+// come last and replace default ones of the same name
+pub(crate) fn merge(defaults: &str, request: &str) -> String {
+    let name = |pair: &str| pair.split('=').next().unwrap_or("").trim().to_string();
+    let names: Vec<String> = request.split(';').map(name).collect();
+    let mut pairs: Vec<&str> = defaults.split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty() && !names.contains(&name(pair)))
+        .collect();
+    pairs.extend(request.split(';').map(str::trim).filter(|pair| !pair.is_empty()));
+    pairs.join("; ")
+}
+
+// Digits with an optional leading '-'; anything not positive means expire now
+fn parse_max_age(value: &str) -> Option<Duration> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if value.starts_with('-') {
+        return Some(Duration::ZERO);
+    }
+    // More seconds than fit in a u64 is as good as forever
+    Some(Duration::from_secs(digits.parse().unwrap_or(u64::MAX)))
+}
+
+// The value is a credential like any other Set-Cookie content, so it's masked
+impl fmt::Debug for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cookie")
+            .field("name", &self.name)
+            .field("value", &redact::MASK)
+            .field("expires", &self.expires)
+            .field("max_age", &self.max_age)
+            .field("domain", &self.domain)
+            .field("path", &self.path)
+            .field("secure", &self.secure)
+            .field("http_only", &self.http_only)
+            .field("same_site", &self.same_site)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn real_world_set_cookie_headers_parse() {
+        let cases = [
+            ("id=a3fWa; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure; HttpOnly", ("id", "a3fWa"), None, None, None, true, true, None),
+            ("__Host-sid=x=y==; path=/; SECURE; samesite=strict", ("__Host-sid", "x=y=="), None, None, Some("/"), true, false, Some(SameSite::Strict)),
+            ("NID=511=abc; expires=Mon, 07-Apr-2025 10:00:00 GMT; path=/; domain=.google.com; HttpOnly", ("NID", "511=abc"), None, Some("google.com"), Some("/"), false, true, None),
+            ("lang=en-US; Max-Age=3600; Domain=Example.COM; SameSite=Lax", ("lang", "en-US"), Some(3600), Some("example.com"), None, false, false, Some(SameSite::Lax)),
+            ("gone=; Max-Age=-1; SameSite=None; Secure", ("gone", ""), Some(0), None, None, true, false, Some(SameSite::None)),
+            ("a=b; Priority=High; Partitioned; Path=relative; SameSite=Sometimes", ("a", "b"), None, None, None, false, false, None),
+            (" spaced = value ;  max-age = 10 ", ("spaced", "value"), Some(10), None, None, false, false, None),
+        ];
+        for (header, (name, value), max_age, domain, path, secure, http_only, same_site) in cases {
+            let cookie = Cookie::parse(header).unwrap();
+            assert_eq!((cookie.name.as_str(), cookie.value.as_str()), (name, value), "{}", header);
+            assert_eq!(cookie.max_age, max_age.map(Duration::from_secs), "{}", header);
+            assert_eq!(cookie.domain.as_deref(), domain, "{}", header);
+            assert_eq!(cookie.path.as_deref(), path, "{}", header);
+            assert_eq!((cookie.secure, cookie.http_only, cookie.same_site), (secure, http_only, same_site), "{}", header);
+        }
+    }
+
+    #[test]
+    fn expiry_comes_from_max_age_before_expires() {
+        let received = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let dated = Cookie::parse("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(dated.expires_at(received), Some(UNIX_EPOCH + Duration::from_secs(1_445_412_480)));
+
+        let both = Cookie::parse("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=60").unwrap();
+        assert_eq!(both.expires_at(received), Some(received + Duration::from_secs(60)));
+        assert!(!both.is_expired(received, received + Duration::from_secs(59)));
+        assert!(both.is_expired(received, received + Duration::from_secs(60)));
+
+        assert!(Cookie::parse("a=1; Max-Age=-5").unwrap().is_expired(received, received));
+        assert_eq!(Cookie::parse("a=1; Expires=someday").unwrap().expires_at(received), None);
+        assert_eq!(Cookie::parse("a=1; Max-Age=99999999999999999999999").unwrap().max_age, Some(Duration::from_secs(u64::MAX)));
+    }
+
+    #[test]
+    fn a_set_cookie_needs_a_named_pair() {
+        for header in ["", "novalue", "=value", " =x; Secure"] {
+            assert!(matches!(Cookie::parse(header), Err(HttpError::InvalidHeader(_))), "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn cookie_values_stay_out_of_debug() {
+        let shown = format!("{:?}", Cookie::parse("session=s3cr3t; Path=/").unwrap());
+        assert!(!shown.contains("s3cr3t") && shown.contains("session"), "{}", shown);
+    }
+
+    #[test]
+    fn request_cookies_merge_over_default_ones() {
+        assert_eq!(merge("a=1; b=2", "b=3; c=4"), "a=1; b=3; c=4");
+        assert_eq!(merge("", "x=1"), "x=1");
+        assert!(validate_pair("ok", "\"quoted\"").is_ok());
+        for (name, value) in [("", "v"), ("bad name", "v"), ("n", "a;b"), ("n", "a b")] {
+            assert!(validate_pair(name, value).is_err(), "{:?}={:?}", name, value);
+        }
+    }
+
+    #[test]
+    fn cookie_pairs_must_be_tokens_and_octets() {
+        for (name, value) in [("a", "1"), ("sid", "\"quoted\""), ("x!", "a-b.c_d~"), ("e", "")] {
+            assert!(validate_pair(name, value).is_ok(), "{}={}", name, value);
+        }
+        for (name, value) in [("", "1"), ("a b", "1"), ("a=", "1"), ("a", "x;y"), ("a", "x y"), ("a", "x,y"), ("a", "x\\y"), ("a", "\"x\"y\""), ("a", "caf\u{e9}")] {
+            assert!(matches!(validate_pair(name, value), Err(HttpError::InvalidHeader(_))), "{}={}", name, value);
+        }
+    }
+
+    #[test]
+    fn merge_puts_request_pairs_last_and_drops_replaced_defaults() {
+        assert_eq!(merge("session=s; a=old", "a=1"), "session=s; a=1");
+        assert_eq!(merge("", "a=1; b=2"), "a=1; b=2");
+        assert_eq!(merge("x=1;; y=2 ", ""), "x=1; y=2");
+    }
+}
@@ -0,0 +1,155 @@
+// Calendar conversions for timestamps, without any time zone support (everything is UTC)
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// (year, month, day) for a count of days since 1970-01-01 (Howard Hinnant's civil_from_days)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// e.g. 2024-03-05T14:07:09.250Z
+#[cfg(feature = "json")]
+pub(crate) fn format_iso8601(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let of_day = secs.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day % 3600 / 60,
+        of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+// Days since 1970-01-01 for a date (Howard Hinnant's days_from_civil)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// An HTTP date in any of the three formats of RFC 9110 section 5.6.7, e.g.
+// "Sun, 06 Nov 1994 08:49:37 GMT", "Sunday, 06-Nov-94 08:49:37 GMT" or
+// "Sun Nov  6 08:49:37 1994". Parsed the lenient way RFC 6265 section 5.1.1 describes for
+// cookie dates: the fields are picked out of the tokens in whatever order they come.
+pub fn parse_http_date(text: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+
+    for token in text.split(delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() && let Some(parsed) = parse_time(token) {
+            time = Some(parsed);
+        } else if day.is_none() && let Some(parsed) = leading_number(token, 1, 2) {
+            day = Some(parsed);
+        } else if month.is_none() && let Some(index) = token.get(..3)
+            .and_then(|prefix| MONTHS.iter().position(|m| prefix.eq_ignore_ascii_case(m)))
+        {
+            month = Some(index as u32 + 1);
+        } else if year.is_none() && let Some(parsed) = leading_number(token, 2, 4) {
+            year = Some(parsed);
+        }
+    }
+
+    let ((hour, minute, second), day, month, year) = (time?, day?, month?, year?);
+    let year = match year {
+        70..=99 => year + 1900,
+        0..=69 => year + 2000,
+        _ => year,
+    };
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year as i64, month, day);
+    // Catches e.g. 31 Feb, which would roll over into March
+    if civil_from_days(days).2 != day {
+        return None;
+    }
+    let secs = days * 86_400 + (hour * 3600 + minute * 60 + second) as i64;
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    }
+}
+
+// hh:mm:ss with one or two digits each, optionally followed by non-digits
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = whole_number(parts.next()?, 1, 2)?;
+    let minute = whole_number(parts.next()?, 1, 2)?;
+    let second = leading_number(parts.next()?, 1, 2)?;
+    Some((hour, minute, second))
+}
+
+// A token of min..=max digits, optionally followed by non-digits (e.g. "1994" or "06th")
+fn leading_number(token: &str, min: usize, max: usize) -> Option<u32> {
+    let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+    whole_number(&token[..digits], min, max)
+}
+
+fn whole_number(digits: &str, min: usize, max: usize) -> Option<u32> {
+    if !(min..=max).contains(&digits.len()) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> Option<SystemTime> {
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn the_three_http_date_formats_parse() {
+        for text in ["Sun, 06 Nov 1994 08:49:37 GMT", "Sunday, 06-Nov-94 08:49:37 GMT", "Sun Nov  6 08:49:37 1994"] {
+            assert_eq!(parse_http_date(text), at(784_111_777), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn cookie_dates_are_read_leniently() {
+        assert_eq!(parse_http_date("Wed, 21-Oct-2015 07:28:00 GMT"), at(1_445_412_480));
+        assert_eq!(parse_http_date("wed 21 OCTOBER 15 7:28:0"), at(1_445_412_480));
+        // Two-digit years: 70-99 are 19xx, 00-69 are 20xx
+        assert_eq!(parse_http_date("01 Jan 70 00:00:00 GMT"), at(0));
+        assert_eq!(parse_http_date("01 Jan 69 00:00:00 GMT"), at(3_124_224_000));
+        assert!(parse_http_date("Mon, 01 Jan 1601 00:00:00 GMT").unwrap() < UNIX_EPOCH);
+    }
+
+    #[test]
+    fn impossible_or_incomplete_dates_are_none() {
+        for text in ["", "tomorrow", "Sun, 31 Feb 2021 00:00:00 GMT", "Sun, 06 Nov 1994 24:00:00 GMT", "06 Nov 1994", "Sun, 06 Nov 1600 08:49:37 GMT", "32 Jan 2020 00:00:00"] {
+            assert_eq!(parse_http_date(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        for days in [-719_468, -1, 0, 59, 10_957, 19_782] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+}
@@ -0,0 +1,136 @@
+// Checking a body against the digest its response announces: Digest (RFC 3230) with
+// sha-256 or sha-512, or the older Content-MD5 (RFC 1864), for RequestConfig::verify_digest
+use std::fmt;
+use std::io;
+
+use crate::base64;
+use crate::md5::Md5;
+use crate::response::HttpResponse;
+use crate::sha2::{Sha256, Sha512};
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Md5(Md5),
+}
+
+// Hashes body bytes as they are read and compares the result at the end
+pub(crate) struct Verifier {
+    hasher: Hasher,
+    // As the header gave it, e.g. "sha-256=X48E9q..."
+    expected: String,
+    expected_bytes: Vec<u8>,
+}
+
+impl Verifier {
+    // For the strongest supported digest the response carries; None when it has none.
+    // Unsupported algorithms (e.g. sha, unixsum) and undecodable values are ignored.
+    pub(crate) fn for_response(head: &HttpResponse) -> Option<Verifier> {
+        let mut best: Option<(u8, Hasher, String, Vec<u8>)> = None;
+        let digests = head.header_all("Digest").into_iter().flat_map(|value| value.split(','));
+        for digest in digests {
+            let Some((algorithm, value)) = digest.trim().split_once('=') else { continue };
+            let (rank, hasher, length) = match algorithm.trim().to_ascii_lowercase().as_str() {
+                "sha-512" => (3, Hasher::Sha512(Sha512::new()), 64),
+                "sha-256" => (2, Hasher::Sha256(Sha256::new()), 32),
+                "md5" => (1, Hasher::Md5(Md5::new()), 16),
+                _ => continue,
+            };
+            let Some(bytes) = base64::decode(value.trim()).filter(|bytes| bytes.len() == length) else { continue };
+            if best.as_ref().is_none_or(|(best_rank, ..)| rank > *best_rank) {
+                best = Some((rank, hasher, format!("{}={}", algorithm.trim().to_ascii_lowercase(), value.trim()), bytes));
+            }
+        }
+        if best.is_none()
+            && let Some(value) = head.header("Content-MD5")
+            && let Some(bytes) = base64::decode(value).filter(|bytes| bytes.len() == 16)
+        {
+            best = Some((0, Hasher::Md5(Md5::new()), format!("md5={}", value), bytes));
+        }
+        best.map(|(_, hasher, expected, expected_bytes)| Verifier { hasher, expected, expected_bytes })
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match &mut self.hasher {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+            Hasher::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    // The whole body has been hashed: an error carrying DigestMismatch unless it matches
+    pub(crate) fn finish(self) -> io::Result<()> {
+        let (algorithm, computed) = match self.hasher {
+            Hasher::Sha256(hasher) => ("sha-256", hasher.finish().to_vec()),
+            Hasher::Sha512(hasher) => ("sha-512", hasher.finish().to_vec()),
+            Hasher::Md5(hasher) => ("md5", hasher.finish().to_vec()),
+        };
+        if computed == self.expected_bytes {
+            return Ok(());
+        }
+        let computed = format!("{}={}", algorithm, base64::encode(&computed));
+        Err(io::Error::new(io::ErrorKind::InvalidData, Mismatch { expected: self.expected, computed }))
+    }
+}
+
+// Carried inside an io::Error from a body that doesn't match its digest
+#[derive(Debug)]
+struct Mismatch {
+    expected: String,
+    computed: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "body digest is {}, the response announced {}", self.computed, self.expected)
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+// Expected and computed digests if `e` reports a mismatch
+pub(crate) fn mismatch(e: &io::Error) -> Option<(String, String)> {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<Mismatch>())
+        .map(|m| (m.expected.clone(), m.computed.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256: &str = "uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=";
+    const SHA512: &str = "MJ7MSJwS1utMxA9QyQLytNDtd+5RGnx6m808qG1M2G+YndNbxf9JlnDaNCVbRbDP2DDoH2Bdz33FVC6TrpzXbw==";
+    const MD5: &str = "XrY7u+Ae7tCTyyK7j1rNww==";
+
+    fn check(headers: &[(&str, &str)], body: &[u8]) -> Option<Result<(), (String, String)>> {
+        let mut verifier = Verifier::for_response(&HttpResponse::new(200, headers, ""))?;
+        verifier.update(body);
+        Some(verifier.finish().map_err(|e| mismatch(&e).unwrap()))
+    }
+
+    #[test]
+    fn the_strongest_supported_digest_is_checked() {
+        let both = format!("unixsum=30637, SHA-256={}, md5={}", SHA256, MD5);
+        assert_eq!(check(&[("Digest", &both)], b"hello world"), Some(Ok(())));
+        let corrupted = check(&[("Digest", &both)], b"hello worle").unwrap().unwrap_err();
+        assert_eq!(corrupted.0, format!("sha-256={}", SHA256));
+        assert!(corrupted.1.starts_with("sha-256="));
+        assert_eq!(check(&[("Digest", &format!("sha-512={}", SHA512))], b"hello world"), Some(Ok(())));
+        // A weaker digest that doesn't match is never looked at
+        assert_eq!(check(&[("Digest", &format!("md5=AAAAAAAAAAAAAAAAAAAAAA==,sha-512={}", SHA512))], b"hello world"), Some(Ok(())));
+    }
+
+    #[test]
+    fn content_md5_is_used_without_a_digest_header() {
+        assert_eq!(check(&[("Content-MD5", MD5)], b"hello world"), Some(Ok(())));
+        assert!(check(&[("Content-MD5", MD5)], b"goodbye").unwrap().is_err());
+    }
+
+    #[test]
+    fn unsupported_or_undecodable_digests_are_ignored() {
+        assert!(check(&[], b"").is_none());
+        assert!(check(&[("Digest", "sha=abc, unixsum=1")], b"").is_none());
+        assert!(check(&[("Digest", "sha-256=not base64!"), ("Content-MD5", "short")], b"").is_none());
+    }
+}
@@ -0,0 +1,229 @@
+// A small stub resolver that asks one DNS server over UDP for A and AAAA records (RFC 1035,
+// RFC 3596), for RequestConfig::dns_server when the system resolver can't be relied on
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+// Largest reply a plain (non-EDNS) query gets over UDP
+const MAX_MESSAGE: usize = 512;
+
+// Addresses for `name` from `server`, IPv6 first, waiting at most `timeout` for the answers.
+// Fails if the name doesn't exist or the server answers neither query.
+pub(crate) fn resolve(name: &str, port: u16, server: SocketAddr, timeout: Duration) -> io::Result<Vec<SocketAddr>> {
+    let bind: SocketAddr = if server.is_ipv4() { (Ipv4Addr::UNSPECIFIED, 0).into() } else { (Ipv6Addr::UNSPECIFIED, 0).into() };
+    let socket = UdpSocket::bind(bind)?;
+    socket.connect(server)?;
+
+    let first_id = query_id();
+    let queries = [(first_id, TYPE_AAAA), (first_id.wrapping_add(1), TYPE_A)];
+    for (id, record_type) in queries {
+        socket.send(&query(id, name, record_type)?)?;
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut answered = [false; 2];
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    let mut buf = [0u8; MAX_MESSAGE];
+    while answered.contains(&false) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(left))?;
+        let n = match socket.recv(&mut buf) {
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+            Err(e) => return Err(e),
+        };
+        // Stray datagrams and replies to other queries are ignored
+        let Some(slot) = queries.iter().position(|&(id, _)| n >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id) else {
+            continue;
+        };
+        if answered[slot] {
+            continue;
+        }
+        answered[slot] = true;
+        match parse_response(&buf[..n]) {
+            Ok(found) => addresses.extend(found),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} not found by DNS server {}", name, server)));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if !answered.contains(&true) {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, format!("No answer from DNS server {}", server)));
+    }
+    if addresses.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("No A or AAAA records for {} from DNS server {}", name, server)));
+    }
+    addresses.sort_by_key(IpAddr::is_ipv4);
+    Ok(addresses.into_iter().map(|ip| SocketAddr::new(ip, port)).collect())
+}
+
+// Query IDs only have to be hard to guess for an off-path attacker; std's randomly
+// seeded hasher does for that
+fn query_id() -> u16 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(Instant::now().elapsed().as_nanos());
+    hasher.finish() as u16
+}
+
+// A recursive query for one record type
+fn query(id: u16, name: &str, record_type: u16) -> io::Result<Vec<u8>> {
+    let mut message = Vec::with_capacity(18 + name.len());
+    message.extend_from_slice(&id.to_be_bytes());
+    // Flags: recursion desired; one question, no other records
+    message.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Can't look up {:?} in DNS", name)));
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&record_type.to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(message)
+}
+
+// The A and AAAA addresses in the answer section. NotFound for a name that doesn't exist.
+fn parse_response(message: &[u8]) -> io::Result<Vec<IpAddr>> {
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "Malformed DNS response");
+    if message.len() < 12 {
+        return Err(malformed());
+    }
+    let is_response = message[2] & 0x80 != 0;
+    let rcode = message[3] & 0x0F;
+    match rcode {
+        _ if !is_response => return Err(malformed()),
+        0 => {}
+        3 => return Err(io::Error::new(io::ErrorKind::NotFound, "name does not exist")),
+        code => return Err(io::Error::other(format!("DNS server failed the query (rcode {})", code))),
+    }
+    let questions = u16::from_be_bytes([message[4], message[5]]);
+    let answers = u16::from_be_bytes([message[6], message[7]]);
+
+    let mut at = 12;
+    for _ in 0..questions {
+        at = skip_name(message, at).ok_or_else(malformed)? + 4;
+    }
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        at = skip_name(message, at).ok_or_else(malformed)?;
+        let fixed = message.get(at..at + 10).ok_or_else(malformed)?;
+        let record_type = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let class = u16::from_be_bytes([fixed[2], fixed[3]]);
+        let length = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let data = message.get(at + 10..at + 10 + length).ok_or_else(malformed)?;
+        at += 10 + length;
+        // CNAMEs along the way are skipped; the addresses they lead to follow them
+        match (record_type, class, data.len()) {
+            (TYPE_A, CLASS_IN, 4) => addresses.push(IpAddr::from(<[u8; 4]>::try_from(data).map_err(|_| malformed())?)),
+            (TYPE_AAAA, CLASS_IN, 16) => addresses.push(IpAddr::from(<[u8; 16]>::try_from(data).map_err(|_| malformed())?)),
+            _ => {}
+        }
+    }
+    Ok(addresses)
+}
+
+// Position just past the (possibly compressed) name starting at `at`
+fn skip_name(message: &[u8], mut at: usize) -> Option<usize> {
+    loop {
+        let length = *message.get(at)?;
+        match length {
+            0 => return Some(at + 1),
+            // A pointer ends the name
+            _ if length & 0xC0 == 0xC0 => return Some(at + 2).filter(|&end| end <= message.len()),
+            _ => at += 1 + length as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // A reply to `query` with `rcode` and the given (type, data) answers, each named by a
+    // pointer back to the question
+    fn reply(query: &[u8], rcode: u8, answers: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut message = query[..2].to_vec();
+        message.extend_from_slice(&[0x81, 0x80 | rcode, 0, 1, 0, answers.len() as u8, 0, 0, 0, 0]);
+        message.extend_from_slice(&query[12..]);
+        for (record_type, data) in answers {
+            message.extend_from_slice(&[0xC0, 12]);
+            message.extend_from_slice(&record_type.to_be_bytes());
+            message.extend_from_slice(&CLASS_IN.to_be_bytes());
+            message.extend_from_slice(&[0, 0, 0, 60, 0, data.len() as u8]);
+            message.extend_from_slice(data);
+        }
+        message
+    }
+
+    #[test]
+    fn queries_encode_each_label() {
+        let message = query(0xABCD, "api.example.com.", TYPE_AAAA).unwrap();
+        assert_eq!(&message[..12], [0xAB, 0xCD, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&message[12..], b"\x03api\x07example\x03com\x00\x00\x1c\x00\x01");
+        assert!(query(1, "a..b", TYPE_A).is_err());
+        assert!(query(1, &"x".repeat(64), TYPE_A).is_err());
+    }
+
+    #[test]
+    fn answers_are_read_past_cnames_and_compressed_names() {
+        let question = query(7, "www.example.com", TYPE_A).unwrap();
+        let cname = b"\x03cdn\xC0\x10";
+        let message = reply(&question, 0, &[(5, cname), (TYPE_A, &[93, 184, 216, 34]), (TYPE_AAAA, &[0; 16])]);
+        assert_eq!(parse_response(&message).unwrap(), [IpAddr::from([93, 184, 216, 34]), IpAddr::from([0u8; 16])]);
+    }
+
+    #[test]
+    fn failures_and_malformed_replies() {
+        let question = query(7, "missing.test", TYPE_A).unwrap();
+        assert_eq!(parse_response(&reply(&question, 3, &[])).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(parse_response(&reply(&question, 2, &[])).unwrap_err().to_string().contains("rcode 2"));
+        // A query echoed back, a short header, and an answer cut off in its data
+        assert_eq!(parse_response(&question).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(parse_response(&question[..11]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let cut = reply(&question, 0, &[(TYPE_A, &[127, 0, 0, 1])]);
+        assert_eq!(parse_response(&cut[..cut.len() - 1]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn resolve_puts_ipv6_first_and_times_out_on_a_silent_server() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; MAX_MESSAGE];
+            for _ in 0..2 {
+                let (n, from) = server.recv_from(&mut buf).unwrap();
+                let query = &buf[..n];
+                let record_type = u16::from_be_bytes([query[n - 4], query[n - 3]]);
+                let answer: &[u8] = if record_type == TYPE_AAAA { &Ipv6Addr::LOCALHOST.octets() } else { &[127, 0, 0, 1] };
+                server.send_to(&reply(query, 0, &[(record_type, answer)]), from).unwrap();
+            }
+        });
+        let found = resolve("api.test", 8080, address, Duration::from_secs(5)).unwrap();
+        assert_eq!(found, ["[::1]:8080".parse().unwrap(), "127.0.0.1:8080".parse::<SocketAddr>().unwrap()]);
+
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let error = resolve("api.test", 80, silent.local_addr().unwrap(), Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    // Needs network access: cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn resolves_a_public_name_against_google_dns() {
+        let found = resolve("example.com", 80, "8.8.8.8:53".parse().unwrap(), Duration::from_secs(5)).unwrap();
+        assert!(!found.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use std::io;
+use std::time::Duration;
+
+use crate::cancel;
+use crate::digest;
+use crate::reader;
+use crate::response::ConnectionInfo;
+use crate::transport;
+
+// Custom error types for better error handling
+#[derive(Debug)]
+pub enum HttpError {
+    NetworkError(String),
+    InvalidResponse(String),
+    // The connection closed before any of a response arrived
+    EmptyResponse,
+    InvalidRequest(String),
+    // A header name or value that can't be sent as given
+    InvalidHeader(String),
+    HttpError { code: u16, message: String },
+    // Stopped through a CancelToken
+    Cancelled,
+    // The proxy answered 407; `challenge` is its Proxy-Authenticate header
+    ProxyAuthRequired { challenge: String },
+    // The connection ended before the Content-Length worth of body bytes arrived
+    TruncatedBody { expected: u64, received: u64 },
+    // One of the RequestConfig time limits ran out; `limit` is the one that did
+    Timeout { phase: TimeoutPhase, limit: Duration },
+    // The body doesn't hash to the digest the response announced (RequestConfig::verify_digest);
+    // both as "algorithm=base64", e.g. "sha-256=X48E9q..."
+    DigestMismatch { expected: String, computed: String },
+}
+
+// Which limit a Timeout ran into. A connect attempt the operating system gives up on is a
+// NetworkError, as no limit of ours ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    // A lookup through RequestConfig::dns_server got no answer (within read_timeout, or 5s)
+    Resolve,
+    // From the request being written to the end of the response head (RequestConfig::header_timeout)
+    Headers,
+    // A single read waited this long without data (RequestConfig::read_timeout)
+    Read,
+    // The request as a whole, connecting and redirects included (RequestConfig::timeout)
+    Total,
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HttpError::NetworkError(msg) => write!(f, "Network error: {}", msg),
+            HttpError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
+            HttpError::EmptyResponse => write!(f, "Empty response: the connection closed before any response data arrived"),
+            HttpError::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
+            HttpError::InvalidHeader(msg) => write!(f, "Invalid header: {}", msg),
+            HttpError::HttpError { code, message } => write!(f, "HTTP {} error: {}", code, message),
+            HttpError::Cancelled => write!(f, "Request cancelled"),
+            HttpError::ProxyAuthRequired { challenge } => write!(f, "Proxy authentication required: {}", challenge),
+            HttpError::TruncatedBody { expected, received } => {
+                write!(f, "Truncated body: Content-Length is {} but only {} bytes arrived", expected, received)
+            }
+            HttpError::Timeout { phase: TimeoutPhase::Resolve, limit } => {
+                write!(f, "Timed out: no DNS answer within {:?}", limit)
+            }
+            HttpError::Timeout { phase: TimeoutPhase::Headers, limit } => {
+                write!(f, "Timed out: no response head within {:?}", limit)
+            }
+            HttpError::Timeout { phase: TimeoutPhase::Read, limit } => {
+                write!(f, "Timed out: no data arrived within {:?}", limit)
+            }
+            HttpError::Timeout { phase: TimeoutPhase::Total, limit } => {
+                write!(f, "Timed out: the request took longer than {:?}", limit)
+            }
+            HttpError::DigestMismatch { expected, computed } => {
+                write!(f, "Digest mismatch: the response announced {} but the body is {}", expected, computed)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl HttpError {
+    // A failed socket operation, e.g. from_io("Failed to read response", e).
+    // InvalidData is what the body decoders report malformed framing as.
+    pub(crate) fn from_io(context: &str, e: io::Error) -> HttpError {
+        if cancel::is_cancelled_io(&e) {
+            HttpError::Cancelled
+        } else if let Some((expected, received)) = reader::truncated(&e) {
+            HttpError::TruncatedBody { expected, received }
+        } else if let Some((phase, limit)) = transport::timed_out(&e) {
+            HttpError::Timeout { phase, limit }
+        } else if let Some((expected, computed)) = digest::mismatch(&e) {
+            HttpError::DigestMismatch { expected, computed }
+        } else if e.kind() == io::ErrorKind::InvalidData {
+            HttpError::InvalidResponse(e.to_string())
+        } else {
+            HttpError::NetworkError(format!("{}: {}", context, e))
+        }
+    }
+
+    // Note the idempotency key the failed request was sent with, e.g. [Idempotency-Key: ...]
+    pub(crate) fn with_idempotency_key(self, header: &str, key: &str) -> HttpError {
+        match self {
+            HttpError::NetworkError(msg) => HttpError::NetworkError(format!("{} [{}: {}]", msg, header, key)),
+            HttpError::InvalidResponse(msg) => HttpError::InvalidResponse(format!("{} [{}: {}]", msg, header, key)),
+            HttpError::HttpError { code, message } => HttpError::HttpError {
+                code,
+                message: format!("{} [{}: {}]", message, header, key),
+            },
+            other => other,
+        }
+    }
+
+    // Note which connection an error happened on, for errors raised after connecting
+    pub(crate) fn with_connection(self, connection: &ConnectionInfo) -> HttpError {
+        match self {
+            HttpError::NetworkError(msg) => HttpError::NetworkError(format!("{} [{}]", msg, connection)),
+            HttpError::InvalidResponse(msg) => HttpError::InvalidResponse(format!("{} [{}]", msg, connection)),
+            HttpError::HttpError { code, message } => HttpError::HttpError {
+                code,
+                message: format!("{} [{}]", message, connection),
+            },
+            other => other,
+        }
+    }
+}
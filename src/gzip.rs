@@ -0,0 +1,484 @@
+// gzip (RFC 1952) around DEFLATE (RFC 1951), for RequestConfig::gzip_request_body and
+// RequestConfig::decompress. Decoding handles everything DEFLATE allows; encoding uses a
+// single block with the fixed Huffman codes, which is simple and still compresses text well.
+use crate::error::HttpError;
+
+// Base lengths and extra bits of length codes 257..285, then of distance codes 0..29
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193,
+    12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+// The order code length code lengths come in, in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+// How many earlier positions with the same hash are compared when looking for a match
+const MAX_CHAIN: usize = 64;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |c, &b| CRC_TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8))
+}
+
+// Whether a Content-Encoding coding names gzip ("x-gzip" is the old spelling)
+pub(crate) fn is_gzip(coding: &str) -> bool {
+    coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip")
+}
+
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    // No file name or modification time; OS "unknown"
+    let mut out = BitWriter { out: vec![0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 0xFF], bits: 0, count: 0 };
+    // The only block, so the last one (BFINAL), with the fixed codes (BTYPE 01)
+    out.write(1, 1);
+    out.write(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut prev = vec![usize::MAX; data.len()];
+    let mut i = 0;
+    while i < data.len() {
+        let (length, distance) = longest_match(data, i, &head, &prev);
+        if length >= MIN_MATCH {
+            let code = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap_or(0);
+            out.literal(257 + code as u16);
+            out.write((length - LENGTH_BASE[code] as usize) as u32, LENGTH_EXTRA[code]);
+            let code = DISTANCE_BASE.iter().rposition(|&base| base as usize <= distance).unwrap_or(0);
+            out.write(reverse(code as u32, 5), 5);
+            out.write((distance - DISTANCE_BASE[code] as usize) as u32, DISTANCE_EXTRA[code]);
+        } else {
+            out.literal(data[i] as u16);
+        }
+        // Every position passed goes into the hash chains, matched or not
+        for at in i..i + length.max(1) {
+            if at + MIN_MATCH <= data.len() {
+                let h = hash(&data[at..]);
+                prev[at] = head[h];
+                head[h] = at;
+            }
+        }
+        i += length.max(1);
+    }
+    out.literal(256);
+
+    let mut out = out.finish();
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+fn hash(data: &[u8]) -> usize {
+    (((data[0] as usize) << 10) ^ ((data[1] as usize) << 5) ^ data[2] as usize) & ((1 << 15) - 1)
+}
+
+// The longest earlier repeat of the bytes at `i` within the window, as (length, distance);
+// a length under MIN_MATCH means none
+fn longest_match(data: &[u8], i: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if i + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+    let limit = (data.len() - i).min(MAX_MATCH);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(&data[i..])];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || i - candidate > WINDOW {
+            break;
+        }
+        let length = data[candidate..].iter().zip(&data[i..i + limit]).take_while(|(a, b)| a == b).count();
+        if length > best.0 {
+            best = (length, i - candidate);
+            if length == limit {
+                break;
+            }
+        }
+        candidate = prev[candidate];
+    }
+    best
+}
+
+// The low `length` bits of `code` in reverse order: Huffman codes are sent from their
+// most significant bit, everything else from the least
+fn reverse(code: u32, length: u8) -> u32 {
+    code.reverse_bits() >> (32 - length as u32)
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u8) {
+        self.bits |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // A literal/length symbol in the fixed code (RFC 1951 section 3.2.6)
+    fn literal(&mut self, symbol: u16) {
+        let (code, length) = match symbol {
+            0..=143 => (0x30 + symbol as u32, 8),
+            144..=255 => (0x190 + symbol as u32 - 144, 9),
+            256..=279 => (symbol as u32 - 256, 7),
+            _ => (0xC0 + symbol as u32 - 280, 8),
+        };
+        self.write(reverse(code, length), length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+// The data of one or more gzip members one after the other, as `gzip -d` gives it
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, HttpError> {
+    let invalid = |message: &str| HttpError::InvalidResponse(format!("Invalid gzip body: {}", message));
+    let mut out = Vec::new();
+    let mut at = 0;
+    loop {
+        let member_start = out.len();
+        let mut bits = BitReader { data, at: skip_header(data, at).map_err(invalid)?, bits: 0, count: 0 };
+        inflate(&mut bits, &mut out).map_err(invalid)?;
+        at = bits.at;
+        let trailer = data.get(at..at + 8).ok_or_else(|| invalid("it ends before the CRC and length"))?;
+        let member = &out[member_start..];
+        if trailer[..4] != crc32(member).to_le_bytes() {
+            return Err(invalid("CRC mismatch"));
+        }
+        if trailer[4..] != (member.len() as u32).to_le_bytes() {
+            return Err(invalid("length mismatch"));
+        }
+        at += 8;
+        if at == data.len() {
+            return Ok(out);
+        }
+    }
+}
+
+// Where the DEFLATE data of the member starting at `at` begins
+fn skip_header(data: &[u8], mut at: usize) -> Result<usize, &'static str> {
+    let header = data.get(at..at + 10).ok_or("truncated header")?;
+    if header[..2] != [0x1F, 0x8B] {
+        return Err("not gzip data");
+    }
+    if header[2] != 8 {
+        return Err("unsupported compression method");
+    }
+    let flags = header[3];
+    at += 10;
+    if flags & 4 != 0 {
+        let extra = data.get(at..at + 2).ok_or("truncated header")?;
+        at += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    // File name, then comment, each ending in a zero byte
+    for flag in [8, 16] {
+        if flags & flag != 0 {
+            at += data.get(at..).and_then(|rest| rest.iter().position(|&b| b == 0)).ok_or("truncated header")? + 1;
+        }
+    }
+    if flags & 2 != 0 {
+        at += 2;
+    }
+    if at > data.len() {
+        return Err("truncated header");
+    }
+    Ok(at)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    // Next byte not yet in `bits`
+    at: usize,
+    bits: u32,
+    count: u8,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u8) -> Result<u32, &'static str> {
+        while self.count < count {
+            let byte = *self.data.get(self.at).ok_or("truncated data")?;
+            self.at += 1;
+            self.bits |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.bits & ((1u64 << count) - 1) as u32;
+        self.bits >>= count;
+        self.count -= count;
+        Ok(value)
+    }
+
+    // Drop the rest of the current byte, before a stored block
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+}
+
+// A canonical Huffman code, decoded a bit at a time
+struct Huffman {
+    // How many codes there are of each length
+    counts: [u16; 16],
+    // Symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, &'static str> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        // More codes of some length than there is room for
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err("invalid Huffman code");
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length > 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, &'static str> {
+        // The first code of each length, and the index of its symbol
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code")
+    }
+}
+
+fn inflate(bits: &mut BitReader, out: &mut Vec<u8>) -> Result<(), &'static str> {
+    // Distances reach back into this member's output only
+    let start = out.len();
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align();
+                let header = bits.data.get(bits.at..bits.at + 4).ok_or("truncated data")?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err("invalid stored block length");
+                }
+                bits.at += 4;
+                let stored = bits.data.get(bits.at..bits.at + length as usize).ok_or("truncated data")?;
+                out.extend_from_slice(stored);
+                bits.at += length as usize;
+            }
+            1 => {
+                let mut lengths = [0u8; 288 + 30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                let (literals, distances) = (Huffman::new(&lengths[..288])?, Huffman::new(&lengths[288..])?);
+                inflate_block(bits, out, start, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(bits)?;
+                inflate_block(bits, out, start, &literals, &distances)?;
+            }
+            _ => return Err("invalid block type"),
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+// The literal/length and distance codes of a dynamic block (RFC 1951 section 3.2.7)
+fn dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), &'static str> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err("too many codes");
+    }
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = bits.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("repeat without a length before it")?, 3 + bits.bits(2)?),
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?),
+        };
+        if lengths.len() + repeat as usize > literal_count + distance_count {
+            return Err("too many code lengths");
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths[256] == 0 {
+        return Err("no end-of-block code");
+    }
+    Ok((Huffman::new(&lengths[..literal_count])?, Huffman::new(&lengths[literal_count..])?))
+}
+
+fn inflate_block(bits: &mut BitReader, out: &mut Vec<u8>, start: usize, literals: &Huffman, distances: &Huffman) -> Result<(), &'static str> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err("invalid length code");
+                }
+                let length = LENGTH_BASE[code] as usize + bits.bits(LENGTH_EXTRA[code])? as usize;
+                let code = distances.decode(bits)? as usize;
+                if code >= DISTANCE_BASE.len() {
+                    return Err("invalid distance code");
+                }
+                let distance = DISTANCE_BASE[code] as usize + bits.bits(DISTANCE_EXTRA[code])? as usize;
+                if distance > out.len() - start {
+                    return Err("distance too far back");
+                }
+                // Byte by byte: a match may overlap the bytes it produces
+                let from = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[from + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // Python's gzip.compress(.., 9, mtime=0) of "hello hello hello gzip world\n" three times
+    const LEVEL_9: &str = "1f8b0800000000000203cb48cdc9c957c84022d3ab320b14caf38b7252b832c896040073ebbe1757000000";
+    // Level 0: one stored block
+    const STORED: &str = "1f8b0800000000000403010600f9ff73746f7265640bf9435606000000";
+    // "flags" behind FEXTRA, FNAME, FCOMMENT and FHCRC
+    const ALL_FLAGS: &str = "1f8b081e0000000000ff0400616263646e616d6500636f6d6d656e74001d6e4bcb494c2f0600ba41050b05000000";
+    // Level 9 of `lcg_text(200)`, which zlib sends in a dynamic Huffman block
+    const DYNAMIC: &str = "1f8b0800000000000203158e4b0a00310843f73d85571326d040315033f71fc795be7c500504e0a3ad064e2c0c30db59e2484b2ca76e9583421039f66b5e140427e7ccdb468652236e1edb7aa3fbf6f6334d0b8b5588071aa350a9a067fd4740d9278d89793b86d763441f8b1cdef321a7197f20075cbbbb5f16de956a467d07bbb552c8000000";
+
+    fn lcg_text(len: usize) -> Vec<u8> {
+        let alphabet = b"eeeeeeeetttttaaaoooiinnsshrdlu  \n";
+        let mut x: u32 = 12345;
+        (0..len)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345) & 0x7FFF_FFFF;
+                alphabet[(x >> 16) as usize % alphabet.len()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_python_output() {
+        assert_eq!(decompress(&hex(LEVEL_9)).unwrap(), b"hello hello hello gzip world\n".repeat(3));
+        assert_eq!(decompress(&hex(STORED)).unwrap(), b"stored");
+        assert_eq!(decompress(&hex(ALL_FLAGS)).unwrap(), b"flags");
+        assert_eq!(decompress(&hex(DYNAMIC)).unwrap(), lcg_text(200));
+    }
+
+    #[test]
+    fn members_are_concatenated() {
+        let mut data = hex(STORED);
+        data.extend(hex(ALL_FLAGS));
+        assert_eq!(decompress(&data).unwrap(), b"storedflags");
+    }
+
+    #[test]
+    fn round_trips() {
+        let long = b"abcabcabd".repeat(10_000);
+        for data in [&b""[..], b"a", b"hello hello hello", &lcg_text(5000), &long] {
+            assert_eq!(decompress(&compress(data)).unwrap(), data);
+        }
+        // Matches shrink repetitive input, including ones at the full window distance
+        assert!(compress(&long).len() < long.len() / 20);
+        let spaced: Vec<u8> = [lcg_text(WINDOW), lcg_text(300)].concat();
+        assert_eq!(decompress(&compress(&spaced)).unwrap(), spaced);
+    }
+
+    #[test]
+    fn damaged_input_is_an_error() {
+        let good = compress(&lcg_text(500));
+        for end in 0..good.len() {
+            assert!(decompress(&good[..end]).is_err(), "cut at {}", end);
+        }
+        // Any flipped bit breaks the header, the data or the CRC, but must never panic
+        for i in 0..good.len() * 8 {
+            let mut bad = good.clone();
+            bad[i / 8] ^= 1 << (i % 8);
+            let _ = decompress(&bad);
+        }
+        let mut bad_crc = good.clone();
+        let crc_at = good.len() - 8;
+        bad_crc[crc_at] ^= 1;
+        assert!(matches!(decompress(&bad_crc), Err(HttpError::InvalidResponse(m)) if m.contains("CRC")));
+        assert!(decompress(b"plain text, not gzip").is_err());
+        let mut trailing = good;
+        trailing.push(0);
+        assert!(decompress(&trailing).is_err());
+    }
+
+    #[test]
+    fn gzip_coding_names() {
+        assert!(is_gzip("gzip") && is_gzip("GZip") && is_gzip("x-gzip"));
+        assert!(!is_gzip("deflate") && !is_gzip("br") && !is_gzip("gzip2"));
+    }
+}
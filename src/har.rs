@@ -0,0 +1,198 @@
+// Opt-in recording of traffic as a HAR 1.2 document for browser devtools
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::base64;
+use crate::date;
+use crate::json::Json;
+use crate::request::ParsedRequest;
+use crate::response::HttpResponse;
+
+// Shared handle: clones record into the same log, so keep one to save() after the requests
+#[derive(Clone)]
+pub struct HarRecorder {
+    entries: Arc<Mutex<Vec<Json>>>,
+    // Exchanges beyond this many are not recorded
+    max_entries: usize,
+    // Request and response bodies are cut off after this many bytes
+    max_body: usize,
+}
+
+impl HarRecorder {
+    pub fn new(max_entries: usize, max_body: usize) -> HarRecorder {
+        HarRecorder { entries: Arc::new(Mutex::new(Vec::new())), max_entries, max_body }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The complete HAR document
+    pub fn to_json(&self) -> Json {
+        let creator = object(vec![
+            ("name", env!("CARGO_PKG_NAME").into()),
+            ("version", env!("CARGO_PKG_VERSION").into()),
+        ]);
+        let log = object(vec![
+            ("version", "1.2".into()),
+            ("creator", creator),
+            ("pages", Json::Array(Vec::new())),
+            ("entries", Json::Array(self.lock().clone())),
+        ]);
+        object(vec![("log", log)])
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_json().to_string())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Json>> {
+        // A panic elsewhere while recording leaves the log usable
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Add one exchange, given the request bytes exactly as they were sent
+    pub(crate) fn record(&self, request: &[u8], response: &HttpResponse, started: SystemTime) {
+        let mut entries = self.lock();
+        if entries.len() >= self.max_entries {
+            return;
+        }
+        entries.push(self.entry(request, response, started));
+    }
+
+    fn entry(&self, request: &[u8], response: &HttpResponse, started: SystemTime) -> Json {
+        let timings = response.timings.unwrap_or_default();
+        let mut entry = vec![
+            ("startedDateTime", date::format_iso8601(started).into()),
+            ("time", millis(timings.total())),
+            ("request", self.request(request)),
+            ("response", self.response(response)),
+            ("cache", Json::Object(Vec::new())),
+            ("timings", object(vec![
+                ("blocked", Json::Number(-1.0)),
+                ("dns", millis(timings.dns)),
+                ("connect", millis(timings.connect)),
+                ("send", millis(timings.send)),
+                ("wait", millis(timings.wait)),
+                ("receive", millis(timings.receive)),
+                ("ssl", Json::Number(-1.0)),
+            ])),
+        ];
+        if let Some(connection) = response.connection {
+            entry.push(("serverIPAddress", connection.peer_addr.ip().to_string().into()));
+            entry.push(("connection", connection.local_addr.port().to_string().into()));
+            if connection.stale_retry {
+                entry.push(("comment", "sent again on a new connection after the reused one was found closed".into()));
+            }
+        }
+        object(entry)
+    }
+
+    fn request(&self, request: &[u8]) -> Json {
+        let sent = ParsedRequest::parse(request);
+
+        let query = sent.target.split_once('?').map(|(_, q)| q).unwrap_or("");
+        let query_string = query.split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                name_value(name, value)
+            })
+            .collect();
+
+        let mut fields = vec![
+            ("method", sent.method.as_str().into()),
+            ("url", sent.url().into()),
+            ("httpVersion", sent.version.as_str().into()),
+            ("cookies", Json::Array(Vec::new())),
+            ("headers", Json::Array(sent.headers.iter().map(|(n, v)| name_value(n, v)).collect())),
+            ("queryString", Json::Array(query_string)),
+            ("headersSize", Json::Number(sent.head_size as f64)),
+            ("bodySize", Json::Number(sent.body.len() as f64)),
+        ];
+        if !sent.body.is_empty() {
+            let (text, _) = self.capture(sent.body);
+            fields.push(("postData", object(vec![
+                ("mimeType", sent.header("Content-Type").unwrap_or("").into()),
+                ("text", text.into()),
+            ])));
+        }
+        object(fields)
+    }
+
+    fn response(&self, response: &HttpResponse) -> Json {
+        let version = response.status_line.split_whitespace().next().unwrap_or("HTTP/1.1");
+        let status_text = response.reason();
+
+        let headers = response.headers.lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| name_value(name.trim(), value.trim()))
+            .collect();
+
+        let (text, base64_encoded) = self.capture(&response.body);
+        let mut content = vec![
+            ("size", Json::Number(response.body.len() as f64)),
+            ("mimeType", response.header("Content-Type").unwrap_or("").into()),
+            ("text", text.into()),
+        ];
+        if base64_encoded {
+            content.push(("encoding", "base64".into()));
+        }
+        if response.body.len() > self.max_body {
+            content.push(("comment", format!("body truncated to {} bytes", self.max_body).into()));
+        }
+
+        object(vec![
+            ("status", Json::Number(response.status_code as f64)),
+            ("statusText", status_text.into()),
+            ("httpVersion", version.into()),
+            ("cookies", Json::Array(Vec::new())),
+            ("headers", Json::Array(headers)),
+            ("content", object(content)),
+            ("redirectURL", response.header("Location").unwrap_or("").into()),
+            ("headersSize", Json::Number(-1.0)),
+            ("bodySize", Json::Number(response.body.len() as f64)),
+        ])
+    }
+
+    // Body text within the size limit, base64-encoded (flagged true) if it isn't UTF-8
+    fn capture(&self, body: &[u8]) -> (String, bool) {
+        let body = &body[..body.len().min(self.max_body)];
+        match std::str::from_utf8(body) {
+            Ok(text) => (text.to_string(), false),
+            // Cutting may have split a character; keep the valid prefix
+            Err(e) if e.error_len().is_none() => (String::from_utf8_lossy(&body[..e.valid_up_to()]).into_owned(), false),
+            Err(_) => (base64::encode(body), true),
+        }
+    }
+}
+
+impl fmt::Debug for HarRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HarRecorder")
+            .field("entries", &self.len())
+            .field("max_entries", &self.max_entries)
+            .field("max_body", &self.max_body)
+            .finish()
+    }
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn name_value(name: &str, value: &str) -> Json {
+    object(vec![("name", name.into()), ("value", value.into())])
+}
+
+fn millis(duration: Duration) -> Json {
+    // Microsecond precision keeps the numbers readable
+    Json::Number(duration.as_micros() as f64 / 1000.0)
+}
@@ -0,0 +1,284 @@
+// Headers as ordered lists, so a name can repeat (two Accept-Encoding lines, several
+// Cookie or Set-Cookie lines): HeaderList for requests, written in the order they were added,
+// and HeaderMap for responses, kept in the order they arrived
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::redact;
+
+// What get(), post() and send_request() take for their custom headers. Existing callers
+// keep working: None and Some(HashMap) convert, sorted by name so the output is
+// reproducible; a Vec of pairs or an array of (&str, &str) keeps its order.
+//
+//     let mut headers = HeaderList::new();
+//     headers.append("X-Trace", "a");
+//     headers.append("X-Trace", "b");
+//     get(host, "/", headers)?;
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct HeaderList {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderList {
+    pub fn new() -> HeaderList {
+        HeaderList::default()
+    }
+
+    // Add a header after the others, even if one with this name is already there
+    pub fn append(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    // Replace every header with this name (case-insensitive) by one with `value`, where
+    // the first of them was; appended if there was none
+    pub fn set(&mut self, name: &str, value: &str) {
+        let Some(first) = self.headers.iter().position(|(key, _)| key.eq_ignore_ascii_case(name)) else {
+            return self.append(name, value);
+        };
+        self.headers[first] = (name.to_string(), value.to_string());
+        let mut seen = false;
+        self.headers.retain(|(key, _)| {
+            if !key.eq_ignore_ascii_case(name) {
+                return true;
+            }
+            let keep = !seen;
+            seen = true;
+            keep
+        });
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+
+    // First value of the header with this name (case-insensitive)
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).into_iter().next()
+    }
+
+    // Every value of the header with this name, in order
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.headers.iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    pub(crate) fn as_slice(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
+
+impl From<Option<HashMap<String, String>>> for HeaderList {
+    fn from(headers: Option<HashMap<String, String>>) -> HeaderList {
+        HeaderList::from(headers.unwrap_or_default())
+    }
+}
+
+impl From<HashMap<String, String>> for HeaderList {
+    fn from(headers: HashMap<String, String>) -> HeaderList {
+        let mut headers: Vec<_> = headers.into_iter().collect();
+        headers.sort();
+        HeaderList { headers }
+    }
+}
+
+impl From<Vec<(String, String)>> for HeaderList {
+    fn from(headers: Vec<(String, String)>) -> HeaderList {
+        HeaderList { headers }
+    }
+}
+
+impl<const N: usize> From<[(&str, &str); N]> for HeaderList {
+    fn from(headers: [(&str, &str); N]) -> HeaderList {
+        headers.into_iter().collect()
+    }
+}
+
+impl<'h> FromIterator<(&'h str, &'h str)> for HeaderList {
+    fn from_iter<I: IntoIterator<Item = (&'h str, &'h str)>>(headers: I) -> HeaderList {
+        HeaderList { headers: headers.into_iter().map(|(name, value)| (name.to_string(), value.to_string())).collect() }
+    }
+}
+
+// Sensitive header values (see mark_sensitive_header) are shown as ***
+impl fmt::Debug for HeaderList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&redact::Headers(&self.headers), f)
+    }
+}
+
+// HttpResponse::headers. Each header is kept as the line it arrived as, "Name: value" with
+// folded continuation lines joined, which lines() gives back; names compare case-insensitively.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    lines: Vec<String>,
+}
+
+impl HeaderMap {
+    pub fn new() -> HeaderMap {
+        HeaderMap::default()
+    }
+
+    // First value of the header with this name, without surrounding whitespace
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).into_iter().next()
+    }
+
+    // Every value of a header that may appear more than once, in order
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.iter().filter(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    // Replace every header with this name by one with `value`, where the first of them was;
+    // appended if there was none
+    pub fn insert(&mut self, name: &str, value: &str) {
+        let line = format!("{}: {}", name, value);
+        let Some(first) = self.iter().position(|(key, _)| key.eq_ignore_ascii_case(name)) else {
+            return self.lines.push(line);
+        };
+        self.lines[first] = line;
+        let mut index = 0;
+        self.lines.retain(|existing| {
+            let keep = index <= first || !name_of(existing).eq_ignore_ascii_case(name);
+            index += 1;
+            keep
+        });
+    }
+
+    // Add a header after the others, even if one with this name is already there
+    pub fn append(&mut self, name: &str, value: &str) {
+        self.lines.push(format!("{}: {}", name, value));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.lines.retain(|line| !name_of(line).eq_ignore_ascii_case(name));
+    }
+
+    // (name, value) pairs in order, trimmed; a line without a colon is all name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().map(|line| {
+            let (name, value) = line.split_once(':').unwrap_or((line, ""));
+            (name.trim(), value.trim())
+        })
+    }
+
+    // The header lines as they arrived
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // Add a line as it arrived
+    pub(crate) fn push_line(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    #[cfg(feature = "json")]
+    pub(crate) fn lines_mut(&mut self) -> &mut Vec<String> {
+        &mut self.lines
+    }
+
+    pub(crate) fn into_lines(self) -> Vec<String> {
+        self.lines
+    }
+}
+
+fn name_of(line: &str) -> &str {
+    line.split_once(':').map_or(line, |(name, _)| name).trim()
+}
+
+// From "Name: value" lines, e.g. those of a response parsed elsewhere
+impl From<Vec<String>> for HeaderMap {
+    fn from(lines: Vec<String>) -> HeaderMap {
+        HeaderMap { lines }
+    }
+}
+
+impl FromIterator<String> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = String>>(lines: I) -> HeaderMap {
+        HeaderMap { lines: lines.into_iter().collect() }
+    }
+}
+
+// Sensitive header values (see mark_sensitive_header) are shown as ***
+impl fmt::Debug for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&redact::Lines(&self.lines), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> HeaderMap {
+        HeaderMap::from(vec![
+            "Content-Type: text/html".to_string(),
+            "Set-Cookie: a=1".to_string(),
+            "X-Folded: one two".to_string(),
+            "set-cookie:b=2".to_string(),
+            "Authorization: Bearer secret".to_string(),
+        ])
+    }
+
+    #[test]
+    fn order_and_case_insensitive_lookup() {
+        let headers = map();
+        let names: Vec<&str> = headers.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["Content-Type", "Set-Cookie", "X-Folded", "set-cookie", "Authorization"]);
+        assert_eq!(headers.get("content-TYPE"), Some("text/html"));
+        assert_eq!(headers.get("x-folded"), Some("one two"));
+        assert_eq!(headers.get_all("SET-COOKIE"), ["a=1", "b=2"]);
+        assert!(headers.contains("authorization") && !headers.contains("Cookie"));
+        assert_eq!(headers.lines().nth(3), Some("set-cookie:b=2"));
+        assert_eq!(headers.len(), 5);
+    }
+
+    #[test]
+    fn insert_collapses_where_the_first_was() {
+        let mut headers = map();
+        headers.insert("SET-COOKIE", "c=3");
+        let lines: Vec<&str> = headers.lines().collect();
+        assert_eq!(lines, ["Content-Type: text/html", "SET-COOKIE: c=3", "X-Folded: one two", "Authorization: Bearer secret"]);
+        headers.insert("X-New", "1");
+        assert_eq!(headers.lines().last(), Some("X-New: 1"));
+    }
+
+    #[test]
+    fn append_remove_and_debug() {
+        let mut headers = HeaderMap::new();
+        assert!(headers.is_empty());
+        headers.append("Vary", "Accept");
+        headers.append("vary", "Origin");
+        assert_eq!(headers.get_all("Vary"), ["Accept", "Origin"]);
+        headers.remove("VARY");
+        assert!(headers.is_empty());
+
+        let debug = format!("{:?}", map());
+        assert!(debug.contains("text/html") && !debug.contains("secret"), "{}", debug);
+    }
+}
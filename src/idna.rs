@@ -0,0 +1,372 @@
+// Internationalized host names: Punycode (RFC 3492) and per-label ToASCII
+use crate::error::HttpError;
+
+const BASE: u32 = 36;
+const TMIN: u32 = 1;
+const TMAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+// ASCII form of a host name: labels with non-ASCII characters become xn--<punycode>.
+// Fullwidth forms are folded to ASCII and labels lowercased first; beyond that the UTS #46
+// mapping tables are not applied, and other compatibility characters are refused. The error
+// says what is wrong with the name.
+pub fn to_ascii(host: &str) -> Result<String, HttpError> {
+    ascii_name(host).map_err(|reason| invalid_host(host, reason))
+}
+
+// The error for a host name that can't be sent, saying why
+pub(crate) fn invalid_host(host: &str, reason: String) -> HttpError {
+    HttpError::InvalidRequest(format!("Invalid host name {:?}: {}", host, reason))
+}
+
+fn ascii_name(host: &str) -> Result<String, String> {
+    if host.is_ascii() {
+        check_ascii(host)?;
+        return Ok(host.to_string());
+    }
+    let folded: String = host.chars().map(fold_width).collect();
+    let host = folded.as_str();
+
+    // A trailing dot (a fully qualified name) leaves an empty last label
+    let labels: Vec<&str> = host.strip_suffix('.').unwrap_or(host).split('.').collect();
+    let mut ascii = Vec::new();
+    for label in &labels {
+        check_ldh(label)?;
+        if label.is_ascii() {
+            ascii.push(label.to_string());
+            continue;
+        }
+        if let Some(c) = label.chars().find(|&c| is_compatibility(c) || c.is_whitespace() || c.is_control()) {
+            return Err(format!("label {:?} contains {:?}", label, c));
+        }
+        let lower: Vec<char> = label.chars().flat_map(char::to_lowercase).collect();
+        if let Some((first, second)) = mixed_scripts(&lower) {
+            return Err(format!("label {:?} mixes {} and {} characters", label, first, second));
+        }
+        let encoded = encode(&lower).map(|encoded| format!("xn--{}", encoded))
+            .ok_or_else(|| format!("label {:?} can't be encoded", label))?;
+        if encoded.len() > 63 {
+            return Err(format!("label {:?} is longer than 63 characters once encoded", label));
+        }
+        ascii.push(encoded);
+    }
+
+    let mut ascii = ascii.join(".");
+    if ascii.len() > 253 {
+        return Err(format!("name is longer than 253 characters once encoded ({})", ascii.len()));
+    }
+    if host.ends_with('.') {
+        ascii.push('.');
+    }
+    Ok(ascii)
+}
+
+// Check an all-ASCII host name as it is sent: letters, digits and hyphens in labels
+// separated by dots, with an optional trailing dot
+pub(crate) fn check_ascii(host: &str) -> Result<(), String> {
+    let name = host.strip_suffix('.').unwrap_or(host);
+    if name.len() > 253 {
+        return Err(format!("name is longer than 253 characters ({})", name.len()));
+    }
+    name.split('.').try_for_each(check_ldh)
+}
+
+// A label is not empty and its ASCII characters are letters, digits or hyphens; the
+// non-ASCII ones are left to the Punycode step
+fn check_ldh(label: &str) -> Result<(), String> {
+    if label.is_empty() {
+        return Err("empty label".to_string());
+    }
+    if let Some(c) = label.chars().find(|&c| c.is_ascii() && !c.is_ascii_alphanumeric() && c != '-') {
+        return Err(format!("label {:?} contains {:?}", label, c));
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err(format!("label {:?} starts or ends with a hyphen", label));
+    }
+    Ok(())
+}
+
+// NFKC's width folding: fullwidth ASCII (U+FF01 to U+FF5E) to ASCII, the ideographic space
+// to a space, and the ideographic and halfwidth full stops to dots, as UTS #46 maps them
+fn fold_width(c: char) -> char {
+    match c as u32 {
+        0xFF01..=0xFF5E => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        0x3000 => ' ',
+        0x3002 | 0xFF61 => '.',
+        _ => c,
+    }
+}
+
+// Characters NFKC would replace that fold_width doesn't: halfwidth forms, ligatures,
+// presentation forms, super- and subscripts, enclosed and mathematical alphanumerics, and
+// the like. Encoding them as they are would name a different host than the one meant.
+fn is_compatibility(c: char) -> bool {
+    matches!(c as u32,
+        0xAA | 0xB2 | 0xB3 | 0xB5 | 0xB9 | 0xBA | 0xBC..=0xBE
+        | 0x2070..=0x209F
+        | 0x2100..=0x218F
+        | 0x2460..=0x24FF
+        | 0x3200..=0x33FF
+        | 0xFB00..=0xFDFF
+        | 0xFE30..=0xFE4F
+        | 0xFE70..=0xFEFF
+        | 0xFF00..=0xFFEF
+        | 0x1D400..=0x1D7FF
+        | 0x1F100..=0x1F1FF)
+}
+
+// Host name for display: xn-- labels decoded back to Unicode. Labels that don't decode
+// are left as they are.
+pub fn to_unicode(host: &str) -> String {
+    host.split('.')
+        .map(|label| {
+            let encoded = label.get(..4).filter(|prefix| prefix.eq_ignore_ascii_case("xn--")).map(|_| &label[4..]);
+            match encoded.and_then(decode) {
+                Some(decoded) => decoded.into_iter().collect(),
+                None => label.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Writing systems told apart when checking a label for look-alike mixes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Hangul,
+    Han,
+    Kana,
+}
+
+impl std::fmt::Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// The script of a letter, or None for digits, hyphens, combining marks and anything the
+// table doesn't know
+fn script(c: char) -> Option<Script> {
+    Some(match c as u32 {
+        0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+        0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x400..=0x52F => Script::Cyrillic,
+        0x530..=0x58F => Script::Armenian,
+        0x590..=0x5FF => Script::Hebrew,
+        0x600..=0x6FF | 0x750..=0x77F => Script::Arabic,
+        0x900..=0x97F => Script::Devanagari,
+        0xE00..=0xE7F => Script::Thai,
+        0x1100..=0x11FF | 0xAC00..=0xD7AF => Script::Hangul,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF => Script::Han,
+        0x3040..=0x30FF => Script::Kana,
+        _ => return None,
+    })
+}
+
+// The first two scripts in a label that shouldn't appear together, the way a Cyrillic "а"
+// passes for a Latin "a". Latin, Han and Kana mix in Japanese names, and Latin, Han and
+// Hangul in Korean ones; those combinations are allowed (roughly UTS #39's "highly
+// restrictive" level).
+fn mixed_scripts(label: &[char]) -> Option<(Script, Script)> {
+    const JAPANESE: &[Script] = &[Script::Latin, Script::Han, Script::Kana];
+    const KOREAN: &[Script] = &[Script::Latin, Script::Han, Script::Hangul];
+
+    let mut scripts: Vec<Script> = Vec::new();
+    for script in label.iter().filter_map(|&c| script(c)) {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    let allowed = scripts.len() <= 1
+        || scripts.iter().all(|s| JAPANESE.contains(s))
+        || scripts.iter().all(|s| KOREAN.contains(s));
+    if allowed { None } else { Some((scripts[0], scripts[1])) }
+}
+
+// Punycode-encode a single label (without the xn-- prefix)
+fn encode(input: &[char]) -> Option<String> {
+    let mut output: String = input.iter().filter(|c| c.is_ascii()).collect();
+    let basic = output.len() as u32;
+    let mut handled = basic;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+
+    while (handled as usize) < input.len() {
+        // The smallest code point not handled yet
+        let m = input.iter().map(|&c| c as u32).filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+
+        for &c in input {
+            let c = c as u32;
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    output.push(digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+    Some(output)
+}
+
+// Decode a single label's Punycode (without the xn-- prefix)
+fn decode(input: &str) -> Option<Vec<char>> {
+    // Basic code points come before the last hyphen, if there is one
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    let mut output: Vec<char> = basic.chars().collect();
+    if !basic.is_ascii() {
+        return None;
+    }
+
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let d = value(digits.next()?)?;
+            i = i.checked_add(d.checked_mul(w)?)?;
+            let t = threshold(k, bias);
+            if d < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let points = output.len() as u32 + 1;
+        bias = adapt(i - old_i, points, old_i == 0);
+        n = n.checked_add(i / points)?;
+        i %= points;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output)
+}
+
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        TMIN
+    } else if k >= bias + TMAX {
+        TMAX
+    } else {
+        k - bias
+    }
+}
+
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+    k + (BASE - TMIN + 1) * delta / (delta + SKEW)
+}
+
+fn digit(d: u32) -> char {
+    match d {
+        0..=25 => (b'a' + d as u8) as char,
+        _ => (b'0' + (d - 26) as u8) as char,
+    }
+}
+
+fn value(digit: u8) -> Option<u32> {
+    match digit {
+        b'a'..=b'z' => Some((digit - b'a') as u32),
+        b'A'..=b'Z' => Some((digit - b'A') as u32),
+        b'0'..=b'9' => Some((digit - b'0') as u32 + 26),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_vectors_encode_and_decode() {
+        for (unicode, ascii) in [
+            ("bücher.example", "xn--bcher-kva.example"),
+            ("münchen.de", "xn--mnchen-3ya.de"),
+            ("日本語.jp", "xn--wgv71a119e.jp"),
+            ("пример.рф", "xn--e1afmkfd.xn--p1ai"),
+            ("example.com.", "example.com."),
+        ] {
+            assert_eq!(to_ascii(unicode).unwrap(), ascii);
+            assert_eq!(to_unicode(ascii), unicode);
+        }
+    }
+
+    #[test]
+    fn labels_are_lowercased_before_encoding() {
+        assert_eq!(to_ascii("BÜCHER.example").unwrap(), "xn--bcher-kva.example");
+    }
+
+    #[test]
+    fn fullwidth_forms_fold_to_ascii() {
+        assert_eq!(to_ascii("ＡＢＣ.com").unwrap(), "ABC.com");
+        assert_eq!(to_ascii("bücher。example").unwrap(), "xn--bcher-kva.example");
+    }
+
+    #[test]
+    fn names_outside_letters_digits_and_hyphens_are_refused() {
+        for host in ["h\r\nX: y", "ex ample", "a/b", "a..b", "", "-a.com", "bü\r\ncher.de", "ｅｘ　ａｍｐｌｅ.com"] {
+            assert!(matches!(to_ascii(host), Err(HttpError::InvalidRequest(_))), "{:?}", host);
+        }
+    }
+
+    #[test]
+    fn compatibility_characters_are_refused() {
+        for host in ["ﬁle.com", "x².com", "①.com", "ｶﾀｶﾅ.jp"] {
+            assert!(to_ascii(host).is_err(), "{:?}", host);
+        }
+    }
+
+    #[test]
+    fn mixed_scripts_and_overlong_labels_are_refused() {
+        // A Cyrillic "а" in a Latin name
+        assert!(to_ascii("pаypal.com").is_err());
+        assert!(to_ascii("東京タワー.jp").is_ok());
+        assert!(to_ascii(&format!("{}.com", "ü".repeat(64))).is_err());
+    }
+}
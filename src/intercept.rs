@@ -0,0 +1,68 @@
+// Hooks that adjust every request a Client sends, e.g. to add auth or tracing headers
+use crate::error::HttpError;
+
+// Called in the order added (Client::with_interceptor) on each request just before it is
+// serialized: once per attempt, redirect hop and retry. An error stops the request.
+//
+//     client.with_interceptor(|request: &mut OutgoingRequest| {
+//         request.set_header("X-Request-Id", &next_id());
+//         Ok(())
+//     })
+pub trait RequestInterceptor: Send + Sync {
+    fn intercept(&self, request: &mut OutgoingRequest<'_>) -> Result<(), HttpError>;
+}
+
+impl<F> RequestInterceptor for F
+where
+    F: Fn(&mut OutgoingRequest<'_>) -> Result<(), HttpError> + Send + Sync,
+{
+    fn intercept(&self, request: &mut OutgoingRequest<'_>) -> Result<(), HttpError> {
+        self(request)
+    }
+}
+
+// A request about to be written. The headers are the final list, default headers and
+// Connection/Proxy-Authorization included; Host, Content-Length and framing headers are
+// added when it is serialized.
+pub struct OutgoingRequest<'r> {
+    pub method: &'r str,
+    pub host: &'r str,
+    // As it goes on the request line: absolute-form through a proxy without a tunnel
+    pub target: &'r str,
+    pub headers: &'r mut Vec<(String, String)>,
+}
+
+impl OutgoingRequest<'_> {
+    // First header with this name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Replace every header with this name by a single one
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.remove_header(name);
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn remove_header(&mut self, name: &str) {
+        self.headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_header_replaces_every_header_of_that_name() {
+        let mut headers = vec![("x-id".to_string(), "1".to_string()), ("Accept".to_string(), "*/*".to_string()), ("X-Id".to_string(), "2".to_string())];
+        let mut request = OutgoingRequest { method: "GET", host: "h", target: "/", headers: &mut headers };
+        assert_eq!(request.header("X-ID"), Some("1"));
+        request.set_header("X-Id", "3");
+        assert_eq!(request.header("x-id"), Some("3"));
+        request.remove_header("accept");
+        assert_eq!(*request.headers, [("X-Id".to_string(), "3".to_string())]);
+    }
+}
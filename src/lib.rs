@@ -0,0 +1,89 @@
+// A minimal HTTP client built on raw TCP sockets and the standard library
+mod auth;
+mod base64;
+mod builder;
+mod cancel;
+#[cfg(feature = "json")]
+mod cassette;
+mod chunked;
+mod client;
+mod cookie;
+mod date;
+mod digest;
+mod dns;
+mod error;
+mod gzip;
+#[cfg(feature = "json")]
+mod har;
+mod headers;
+mod idna;
+mod intercept;
+#[cfg(feature = "json")]
+mod json;
+mod md5;
+mod method;
+mod mime;
+mod multipart;
+mod net;
+mod netrc;
+mod parser;
+mod pool;
+mod proxy;
+mod ratelimit;
+mod redact;
+mod reader;
+mod response;
+mod request;
+mod resolver;
+mod retry;
+mod scratch;
+mod sha1;
+mod sha2;
+mod spool;
+mod status;
+#[cfg(feature = "test-util")]
+pub mod testing;
+mod trace;
+mod transport;
+mod upgrade;
+mod url;
+
+pub use auth::{Challenge, CredentialCallback, Credentials};
+pub use builder::RequestBuilder;
+pub use cancel::CancelToken;
+#[cfg(feature = "json")]
+pub use cassette::{Cassette, CassetteMode, Matching};
+pub use client::{
+    check, default_client, get, options, post, send_bytes, send_request, send_request_on, send_request_with, set_default_client, trace,
+    Client, RequestConfig, SocketConfig,
+};
+pub use cookie::{Cookie, SameSite};
+pub use date::parse_http_date;
+pub use error::{HttpError, TimeoutPhase};
+#[cfg(feature = "json")]
+pub use har::HarRecorder;
+pub use headers::{HeaderList, HeaderMap};
+pub use idna::{to_ascii, to_unicode};
+pub use intercept::{OutgoingRequest, RequestInterceptor};
+#[cfg(feature = "json")]
+pub use json::{Json, PatchFormat};
+pub use method::Method;
+pub use mime::Mime;
+pub use multipart::Multipart;
+pub use netrc::Netrc;
+pub use parser::{ParseEvent, ResponseParser};
+pub use pool::PoolStats;
+pub use proxy::Proxy;
+pub use redact::mark_sensitive_header;
+pub use reader::{Chunks, Lines, ResponseReader};
+#[cfg(feature = "json")]
+pub use reader::JsonLines;
+pub use request::{build_request, ext_param, to_curl, Request, RequestTemplate, SentRequest};
+pub use resolver::{Resolver, StaticResolver, SystemResolver};
+pub use response::{parse_response, parse_response_lenient, try_parse_response, ConnectionInfo, HttpResponse, Timings};
+pub use retry::{default_retry_policy, RetryContext, RetryPredicate};
+pub use spool::SpooledBody;
+pub use status::StatusCode;
+pub use trace::EchoedRequest;
+pub use upgrade::Upgraded;
+pub use url::join_url;
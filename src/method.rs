@@ -0,0 +1,75 @@
+// Request methods (RFC 9110 section 9), with room for extensions such as WebDAV's PROPFIND
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+    // Any other method, sent exactly as given (methods are case-sensitive)
+    Custom(String),
+}
+
+impl Method {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Connect => "CONNECT",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Patch => "PATCH",
+            Method::Custom(method) => method,
+        }
+    }
+
+    // Whether sending the request twice has the same effect as sending it once, which
+    // is what makes it safe to retry. Unknown methods are assumed not to be.
+    pub fn is_idempotent(&self) -> bool {
+        matches!(self, Method::Get | Method::Head | Method::Put | Method::Delete | Method::Options | Method::Trace)
+    }
+}
+
+impl From<&str> for Method {
+    fn from(method: &str) -> Method {
+        match method {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "CONNECT" => Method::Connect,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "PATCH" => Method::Patch,
+            other => Method::Custom(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for Method {
+    fn from(method: String) -> Method {
+        Method::from(method.as_str())
+    }
+}
+
+impl From<&Method> for Method {
+    fn from(method: &Method) -> Method {
+        method.clone()
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
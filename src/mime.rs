@@ -0,0 +1,279 @@
+// Media types as a Content-Type header gives them (RFC 9110 section 8.3.1),
+// e.g. application/json; charset=utf-8
+
+// From HttpResponse::content_type()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mime {
+    // Lowercased, e.g. "application"
+    pub type_: String,
+    // Lowercased, e.g. "problem+json"
+    pub subtype: String,
+    // Names lowercased, values unquoted, in the order sent
+    pub params: Vec<(String, String)>,
+}
+
+impl Mime {
+    // None unless the value is a type/subtype followed by well-formed name=value parameters
+    pub fn parse(value: &str) -> Option<Mime> {
+        let mut parser = Parser { text: value.as_bytes(), at: 0 };
+        parser.skip_whitespace();
+        let type_ = parser.token()?;
+        parser.expect(b'/')?;
+        let subtype = parser.token()?;
+        let params = parser.params()?;
+        Some(Mime { type_: type_.to_ascii_lowercase(), subtype: subtype.to_ascii_lowercase(), params })
+    }
+
+    // "type/subtype" without parameters, e.g. "text/html"
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    // Value of a parameter (case-insensitive name), e.g. param("boundary")
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // As sent, e.g. "utf-8"; compare case-insensitively
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    // application/json and other types with a +json suffix, like application/problem+json
+    pub fn is_json(&self) -> bool {
+        self.subtype == "json" || self.subtype.ends_with("+json")
+    }
+}
+
+// The Content-Type for a body sent without one, guessed from how it starts: JSON for { or [,
+// XML for <, form data for name=value pairs joined by &, else application/octet-stream
+pub(crate) fn sniff(body: &[u8]) -> &'static str {
+    match body.trim_ascii_start().first() {
+        Some(b'{' | b'[') => "application/json",
+        Some(b'<') => "application/xml",
+        _ if is_form(body) => "application/x-www-form-urlencoded",
+        _ => "application/octet-stream",
+    }
+}
+
+// Every &-separated pair a percent-encoded name=value, e.g. q=rust+http&page=2
+fn is_form(body: &[u8]) -> bool {
+    !body.is_empty() && body.split(|&b| b == b'&').all(|pair| {
+        pair.iter().position(|&b| b == b'=').is_some_and(|i| i > 0)
+            && pair.iter().all(|&b| b.is_ascii_alphanumeric() || b"%+=-._~*".contains(&b))
+    })
+}
+
+// The disposition type of a Content-Disposition value (RFC 6266), lowercased, and its
+// parameters as Mime::parse reads them
+pub(crate) fn parse_disposition(value: &str) -> Option<(String, Vec<(String, String)>)> {
+    let mut parser = Parser { text: value.as_bytes(), at: 0 };
+    parser.skip_whitespace();
+    let disposition = parser.token()?;
+    Some((disposition.to_ascii_lowercase(), parser.params()?))
+}
+
+// The links of a Link header value (RFC 8288 section 3), as each <target> with the relation
+// types its rel parameter lists, lowercased. Entries that don't parse are skipped.
+pub(crate) fn parse_links(value: &str) -> Vec<(String, Vec<String>)> {
+    let mut parser = Parser { text: value.as_bytes(), at: 0 };
+    let mut links = Vec::new();
+    loop {
+        while matches!(parser.peek(), Some(b' ' | b'\t' | b',')) {
+            parser.at += 1;
+        }
+        if parser.at == parser.text.len() {
+            return links;
+        }
+        match parser.link() {
+            Some(link) => links.push(link),
+            // On to the next entry
+            None => {
+                while parser.peek().is_some_and(|b| b != b',') {
+                    parser.at += 1;
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.at).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        (self.peek() == Some(b)).then(|| self.at += 1)
+    }
+
+    // `; name=value` parameters up to the end of the text, names lowercased
+    fn params(&mut self) -> Option<Vec<(String, String)>> {
+        let mut params = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.at == self.text.len() {
+                break;
+            }
+            self.expect(b';')?;
+            self.skip_whitespace();
+            // A trailing or doubled ';' carries no parameter
+            if matches!(self.peek(), None | Some(b';')) {
+                continue;
+            }
+            let name = self.token()?;
+            self.skip_whitespace();
+            self.expect(b'=')?;
+            self.skip_whitespace();
+            let value = if self.peek() == Some(b'"') { self.quoted()? } else { self.token()? };
+            params.push((name.to_ascii_lowercase(), value));
+        }
+        Some(params)
+    }
+
+    // <target> and its parameters, up to the comma before the next link
+    fn link(&mut self) -> Option<(String, Vec<String>)> {
+        self.expect(b'<')?;
+        let start = self.at;
+        while self.peek()? != b'>' {
+            self.at += 1;
+        }
+        let target = String::from_utf8_lossy(&self.text[start..self.at]).trim().to_string();
+        self.at += 1;
+        let mut rels = None;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(b',') => break,
+                Some(b';') => self.at += 1,
+                _ => return None,
+            }
+            self.skip_whitespace();
+            if matches!(self.peek(), None | Some(b',' | b';')) {
+                continue;
+            }
+            let name = self.token()?;
+            self.skip_whitespace();
+            // A parameter may come without a value
+            let value = match self.expect(b'=') {
+                Some(()) => {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'"') { self.quoted()? } else { self.token()? }
+                }
+                None => String::new(),
+            };
+            // Only the first rel counts (RFC 8288 section 3.3)
+            if name.eq_ignore_ascii_case("rel") && rels.is_none() {
+                rels = Some(value.split_ascii_whitespace().map(str::to_ascii_lowercase).collect());
+            }
+        }
+        Some((target, rels.unwrap_or_default()))
+    }
+
+    // A non-empty token (RFC 9110 section 5.6.2)
+    fn token(&mut self) -> Option<String> {
+        let start = self.at;
+        while self.peek().is_some_and(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)) {
+            self.at += 1;
+        }
+        (self.at > start).then(|| String::from_utf8_lossy(&self.text[start..self.at]).into_owned())
+    }
+
+    // A quoted-string with its backslash escapes removed; None if it is never closed
+    fn quoted(&mut self) -> Option<String> {
+        let mut value = Vec::new();
+        self.at += 1;
+        loop {
+            let b = self.peek()?;
+            self.at += 1;
+            match b {
+                b'"' => return Some(String::from_utf8_lossy(&value).into_owned()),
+                b'\\' => {
+                    value.push(self.peek()?);
+                    self.at += 1;
+                }
+                _ => value.push(b),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(mime: &Mime) -> Vec<(&str, &str)> {
+        mime.params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()
+    }
+
+    #[test]
+    fn types_and_parameters_are_normalized() {
+        let mime = Mime::parse("  Application/JSON ;  Charset = UTF-8 ;;").unwrap();
+        assert_eq!((mime.type_.as_str(), mime.subtype.as_str(), mime.essence()), ("application", "json", "application/json".to_string()));
+        assert_eq!(params(&mime), [("charset", "UTF-8")]);
+        assert_eq!(mime.charset(), Some("UTF-8"));
+    }
+
+    #[test]
+    fn quoted_values_are_unquoted() {
+        let mime = Mime::parse(r#"multipart/form-data; boundary="a; b=\"c\"\\"; x=1"#).unwrap();
+        assert_eq!(params(&mime), [("boundary", r#"a; b="c"\"#), ("x", "1")]);
+        assert_eq!(mime.param("BOUNDARY"), Some(r#"a; b="c"\"#));
+    }
+
+    #[test]
+    fn json_covers_plus_json_suffixes() {
+        for value in ["application/json", "application/problem+json", "application/vnd.api+JSON; charset=utf-8"] {
+            assert!(Mime::parse(value).unwrap().is_json(), "{}", value);
+        }
+        for value in ["text/html", "application/jsonp", "application/json-seq"] {
+            assert!(!Mime::parse(value).unwrap().is_json(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn malformed_values_are_none() {
+        for value in ["", "text", "text/", "/html", "text/html charset=utf-8", "text/html; charset", "text/html; charset=", "text/html; a=\"open", "text/html; =x", "te xt/html", "text/html; a=b c"] {
+            assert_eq!(Mime::parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn sniff_guesses_from_the_first_bytes() {
+        assert_eq!(sniff(b"{\"a\":1}"), "application/json");
+        assert_eq!(sniff(b"  \n[1, 2]"), "application/json");
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><a/>"), "application/xml");
+        assert_eq!(sniff(b"q=rust+http&page=2"), "application/x-www-form-urlencoded");
+        assert_eq!(sniff(b"name=J%C3%BCrgen&empty="), "application/x-www-form-urlencoded");
+        for body in [&b"\x89PNG\r\n\x1a\n"[..], b"plain text", b"=value", b"a=b&&c=d", b"a=b c", b"\0\xff"] {
+            assert_eq!(sniff(body), "application/octet-stream", "{:?}", body);
+        }
+    }
+
+    #[test]
+    fn parse_links_keeps_delimiters_inside_targets_and_quotes() {
+        let links = parse_links(
+            "<https://api/x?page=2>; rel=\"next\", <https://api/a,b>; rel=last; title=\"a, b; c\", garbage, <https://api/p>; REL=\"Prev First\"",
+        );
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(links, [
+            ("https://api/x?page=2".to_string(), strings(&["next"])),
+            ("https://api/a,b".to_string(), strings(&["last"])),
+            ("https://api/p".to_string(), strings(&["prev", "first"])),
+        ]);
+        assert_eq!(parse_links("<https://api/x>; title=\"no rel\""), [("https://api/x".to_string(), vec![])]);
+        assert!(parse_links("<https://api/unterminated; rel=next").is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+// multipart/form-data request bodies (RFC 7578) that are read part by part while they are
+// sent, so a file goes to the socket a chunk at a time instead of into memory first
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::error::HttpError;
+use crate::retry;
+
+// Sent with RequestBuilder::multipart, which sets the Content-Type and streams the form
+// with chunked encoding; the total length is never needed
+//
+//     let form = Multipart::new().text("title", "Holiday").file("photo", "/tmp/beach.jpg");
+//     client.request(Method::Post, host, "/upload").multipart(form).send()?;
+pub struct Multipart<'a> {
+    boundary: String,
+    parts: Vec<Part<'a>>,
+    // The next part to start
+    next: usize,
+    // Boundary and headers (or a text value) not read out yet, and how far they have been
+    buffer: Vec<u8>,
+    at: usize,
+    // The content of the part being read, once its headers are out
+    reader: Option<Box<dyn Read + 'a>>,
+    finished: bool,
+    // From file(), reported by RequestBuilder::send
+    error: Option<HttpError>,
+}
+
+struct Part<'a> {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    body: Body<'a>,
+}
+
+enum Body<'a> {
+    Text(String),
+    Reader(Box<dyn Read + 'a>),
+}
+
+impl<'a> Multipart<'a> {
+    // With a random boundary
+    pub fn new() -> Multipart<'a> {
+        Multipart {
+            boundary: format!("----http-client-from-scratch-{}", retry::idempotency_key().replace('-', "")),
+            parts: Vec::new(),
+            next: 0,
+            buffer: Vec::new(),
+            at: 0,
+            reader: None,
+            finished: false,
+            error: None,
+        }
+    }
+
+    // A form field
+    pub fn text(mut self, name: &str, value: &str) -> Multipart<'a> {
+        self.parts.push(Part { name: name.to_string(), filename: None, content_type: None, body: Body::Text(value.to_string()) });
+        self
+    }
+
+    // A file field, named after the file's last path component and sent as
+    // application/octet-stream. The file is opened now and read while the request is sent;
+    // one that can't be opened makes send() fail.
+    pub fn file(mut self, name: &str, path: impl AsRef<Path>) -> Multipart<'a> {
+        let path = path.as_ref();
+        match File::open(path) {
+            Ok(file) => {
+                let filename = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+                self.reader(name, &filename, "application/octet-stream", file)
+            }
+            Err(e) => {
+                self.error.get_or_insert(HttpError::InvalidRequest(format!("Failed to open {}: {}", path.display(), e)));
+                self
+            }
+        }
+    }
+
+    // A file field read from `reader` while the request is sent, e.g. for a file already open
+    // or a generated one
+    pub fn reader(mut self, name: &str, filename: &str, content_type: &str, reader: impl Read + 'a) -> Multipart<'a> {
+        self.parts.push(Part {
+            name: name.to_string(),
+            filename: Some(filename.to_string()),
+            content_type: Some(content_type.to_string()),
+            body: Body::Reader(Box::new(reader)),
+        });
+        self
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    // The Content-Type header to send the form with
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    pub(crate) fn take_error(&mut self) -> Option<HttpError> {
+        self.error.take()
+    }
+
+    // Queue the boundary and headers of the next part, or the closing boundary after the last
+    fn start_next(&mut self) {
+        self.buffer.clear();
+        self.at = 0;
+        if self.next > 0 {
+            self.buffer.extend_from_slice(b"\r\n");
+        }
+        let Some(part) = self.parts.get_mut(self.next) else {
+            self.buffer.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+            self.finished = true;
+            return;
+        };
+        self.next += 1;
+        let mut head = format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"", self.boundary, escape(&part.name));
+        if let Some(filename) = &part.filename {
+            head.push_str(&format!("; filename=\"{}\"", escape(filename)));
+        }
+        if let Some(content_type) = &part.content_type {
+            head.push_str(&format!("\r\nContent-Type: {}", content_type));
+        }
+        head.push_str("\r\n\r\n");
+        self.buffer.extend_from_slice(head.as_bytes());
+        match std::mem::replace(&mut part.body, Body::Text(String::new())) {
+            Body::Text(value) => self.buffer.extend_from_slice(value.as_bytes()),
+            Body::Reader(reader) => self.reader = Some(reader),
+        }
+    }
+}
+
+impl Default for Multipart<'_> {
+    fn default() -> Self {
+        Multipart::new()
+    }
+}
+
+// The form as it goes on the wire
+impl Read for Multipart<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.at < self.buffer.len() {
+                let n = buf.len().min(self.buffer.len() - self.at);
+                buf[..n].copy_from_slice(&self.buffer[self.at..self.at + n]);
+                self.at += n;
+                return Ok(n);
+            }
+            if let Some(reader) = &mut self.reader {
+                match reader.read(buf)? {
+                    0 => self.reader = None,
+                    n => return Ok(n),
+                }
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            self.start_next();
+        }
+    }
+}
+
+// Quotes and line breaks in a name or filename, percent-encoded as browsers do
+// (HTML's multipart/form-data encoding algorithm)
+fn escape(value: &str) -> String {
+    value.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+impl fmt::Debug for Multipart<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<&str> = self.parts.iter().map(|part| part.name.as_str()).collect();
+        f.debug_struct("Multipart")
+            .field("boundary", &self.boundary)
+            .field("parts", &parts)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wire(mut form: Multipart) -> String {
+        let mut out = Vec::new();
+        form.read_to_end(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn parts_on_the_wire() {
+        let mut form = Multipart::new().text("title", "Holiday").reader("photo", "beach.jpg", "image/jpeg", &b"JPEG"[..]);
+        form.boundary = "XyZ".to_string();
+        assert_eq!(form.content_type(), "multipart/form-data; boundary=XyZ");
+        assert_eq!(
+            wire(form),
+            "--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nHoliday\r\n\
+             --XyZ\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"beach.jpg\"\r\nContent-Type: image/jpeg\r\n\r\nJPEG\r\n\
+             --XyZ--\r\n"
+        );
+        let mut empty = Multipart::new();
+        empty.boundary = "XyZ".to_string();
+        assert_eq!(wire(empty), "--XyZ--\r\n");
+    }
+
+    #[test]
+    fn one_byte_reads_give_the_same_bytes() {
+        let big = vec![7u8; 10_000];
+        let mut whole = Vec::new();
+        let mut form = Multipart::new().text("a", "1").reader("f", "f.bin", "application/octet-stream", &big[..]).text("b", "2");
+        form.boundary = "B".to_string();
+        form.read_to_end(&mut whole).unwrap();
+
+        let mut form = Multipart::new().text("a", "1").reader("f", "f.bin", "application/octet-stream", &big[..]).text("b", "2");
+        form.boundary = "B".to_string();
+        let mut pieces = Vec::new();
+        let mut byte = [0; 1];
+        while form.read(&mut byte).unwrap() == 1 {
+            pieces.push(byte[0]);
+        }
+        assert_eq!(pieces, whole);
+        assert_eq!(form.read(&mut byte).unwrap(), 0);
+    }
+
+    #[test]
+    fn names_are_escaped() {
+        let mut form = Multipart::new().reader("a\"b", "x\r\ny.txt", "text/plain", io::empty());
+        form.boundary = "B".to_string();
+        assert!(wire(form).contains("name=\"a%22b\"; filename=\"x%0D%0Ay.txt\""));
+    }
+
+    #[test]
+    fn boundaries_are_random_and_missing_files_are_remembered() {
+        assert_ne!(Multipart::new().boundary(), Multipart::new().boundary());
+        let mut form = Multipart::new().file("f", "/nonexistent/dir/file.bin");
+        assert!(matches!(form.take_error(), Some(HttpError::InvalidRequest(m)) if m.contains("/nonexistent/dir/file.bin")));
+    }
+}
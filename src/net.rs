@@ -0,0 +1,303 @@
+// Socket setup that std::net doesn't expose, done through the platform's C socket API
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+// Connect to `remote` from `local`, letting the OS pick the local port, giving up with
+// TimedOut after `timeout` as TcpStream::connect_timeout does
+pub(crate) fn connect_from(local: IpAddr, remote: SocketAddr, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    sys::connect_from(SocketAddr::new(local, 0), remote, timeout)
+}
+
+// Turn on TCP keepalive, probing after `interval` idle and then every `interval`
+pub(crate) fn set_keepalive(stream: &TcpStream, interval: Duration) -> io::Result<()> {
+    let secs = interval.as_secs().clamp(1, i32::MAX as u64) as i32;
+    sys::set_keepalive(stream, secs)
+}
+
+// SO_RCVBUF
+pub(crate) fn set_recv_buffer_size(stream: &TcpStream, size: usize) -> io::Result<()> {
+    sys::set_buffer_size(stream, false, size.min(i32::MAX as usize) as i32)
+}
+
+// SO_SNDBUF
+pub(crate) fn set_send_buffer_size(stream: &TcpStream, size: usize) -> io::Result<()> {
+    sys::set_buffer_size(stream, true, size.min(i32::MAX as usize) as i32)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios"))]
+mod sys {
+    use std::ffi::c_int;
+    use std::io;
+    use std::net::{SocketAddr, TcpStream};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::time::{Duration, Instant};
+
+    unsafe extern "C" {
+        fn socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int;
+        fn setsockopt(fd: c_int, level: c_int, name: c_int, value: *const c_int, len: u32) -> c_int;
+        fn getsockopt(fd: c_int, level: c_int, name: c_int, value: *mut c_int, len: *mut u32) -> c_int;
+        fn bind(fd: c_int, addr: *const u8, len: u32) -> c_int;
+        fn connect(fd: c_int, addr: *const u8, len: u32) -> c_int;
+        fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+        fn poll(fds: *mut PollFd, nfds: NFds, timeout: c_int) -> c_int;
+        fn close(fd: c_int) -> c_int;
+    }
+
+    #[repr(C)]
+    struct PollFd {
+        fd: c_int,
+        events: i16,
+        revents: i16,
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    type NFds = std::ffi::c_ulong;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    type NFds = std::ffi::c_uint;
+
+    const F_GETFL: c_int = 3;
+    const F_SETFL: c_int = 4;
+    const POLLOUT: i16 = 4;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const O_NONBLOCK: c_int = 0x800;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    const O_NONBLOCK: c_int = 0x4;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const EINPROGRESS: i32 = 115;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    const EINPROGRESS: i32 = 36;
+
+    const AF_INET: u8 = 2;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const AF_INET6: u8 = 10;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    const AF_INET6: u8 = 30;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const SOCK_STREAM: c_int = 1 | 0x80000; // SOCK_CLOEXEC
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    const SOCK_STREAM: c_int = 1;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    mod opt {
+        pub const SOL_SOCKET: i32 = 1;
+        pub const SO_KEEPALIVE: i32 = 9;
+        pub const SO_SNDBUF: i32 = 7;
+        pub const SO_RCVBUF: i32 = 8;
+        pub const SO_ERROR: i32 = 4;
+        pub const IPPROTO_TCP: i32 = 6;
+        pub const TCP_KEEPIDLE: i32 = 4;
+        pub const TCP_KEEPINTVL: i32 = 5;
+    }
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    mod opt {
+        pub const SOL_SOCKET: i32 = 0xffff;
+        pub const SO_KEEPALIVE: i32 = 0x8;
+        pub const SO_SNDBUF: i32 = 0x1001;
+        pub const SO_RCVBUF: i32 = 0x1002;
+        pub const SO_ERROR: i32 = 0x1007;
+        pub const IPPROTO_TCP: i32 = 6;
+        // Called TCP_KEEPALIVE here
+        pub const TCP_KEEPIDLE: i32 = 0x10;
+        pub const TCP_KEEPINTVL: i32 = 0x101;
+    }
+
+    // Owns the descriptor until it is handed over to a TcpStream
+    struct Socket(c_int);
+
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            unsafe {
+                close(self.0);
+            }
+        }
+    }
+
+    pub fn connect_from(local: SocketAddr, remote: SocketAddr, timeout: Option<Duration>) -> io::Result<TcpStream> {
+        let family = if remote.is_ipv4() { AF_INET } else { AF_INET6 };
+        let fd = unsafe { socket(family as c_int, SOCK_STREAM, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = Socket(fd);
+
+        // Said apart from a failed connect, e.g. for an address this machine doesn't have
+        let bound = sockaddr(&local);
+        if unsafe { bind(socket.0, bound.as_ptr(), bound.len() as u32) } < 0 {
+            let e = io::Error::last_os_error();
+            return Err(io::Error::new(e.kind(), format!("can't bind to {}: {}", local.ip(), e)));
+        }
+
+        let remote = sockaddr(&remote);
+        match timeout {
+            None => {
+                if unsafe { connect(socket.0, remote.as_ptr(), remote.len() as u32) } < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Some(timeout) => connect_within(&socket, &remote, timeout)?,
+        }
+
+        let fd = socket.0;
+        std::mem::forget(socket);
+        Ok(unsafe { TcpStream::from_raw_fd(fd) })
+    }
+
+    // A non-blocking connect, waited for with poll() for up to `timeout`, after which the
+    // socket is made blocking again
+    fn connect_within(socket: &Socket, remote: &[u8], timeout: Duration) -> io::Result<()> {
+        let flags = unsafe { fcntl(socket.0, F_GETFL) };
+        if flags < 0 || unsafe { fcntl(socket.0, F_SETFL, flags | O_NONBLOCK) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { connect(socket.0, remote.as_ptr(), remote.len() as u32) } < 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() != Some(EINPROGRESS) {
+                return Err(e);
+            }
+            let deadline = Instant::now() + timeout;
+            loop {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "connection timed out"));
+                }
+                // Rounded up, so a wait under a millisecond isn't a busy loop
+                let millis = left.as_nanos().div_ceil(1_000_000).min(c_int::MAX as u128) as c_int;
+                let mut fds = PollFd { fd: socket.0, events: POLLOUT, revents: 0 };
+                match unsafe { poll(&mut fds, 1, millis) } {
+                    n if n < 0 => {
+                        let e = io::Error::last_os_error();
+                        if e.kind() != io::ErrorKind::Interrupted {
+                            return Err(e);
+                        }
+                    }
+                    0 => {}
+                    _ => break,
+                }
+            }
+            let mut error: c_int = 0;
+            let mut len = std::mem::size_of::<c_int>() as u32;
+            if unsafe { getsockopt(socket.0, opt::SOL_SOCKET, opt::SO_ERROR, &mut error, &mut len) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if error != 0 {
+                return Err(io::Error::from_raw_os_error(error));
+            }
+        }
+        if unsafe { fcntl(socket.0, F_SETFL, flags) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn set_keepalive(stream: &TcpStream, secs: c_int) -> io::Result<()> {
+        set_option(stream, opt::SOL_SOCKET, opt::SO_KEEPALIVE, 1)?;
+        set_option(stream, opt::IPPROTO_TCP, opt::TCP_KEEPIDLE, secs)?;
+        set_option(stream, opt::IPPROTO_TCP, opt::TCP_KEEPINTVL, secs)
+    }
+
+    pub fn set_buffer_size(stream: &TcpStream, send: bool, size: c_int) -> io::Result<()> {
+        set_option(stream, opt::SOL_SOCKET, if send { opt::SO_SNDBUF } else { opt::SO_RCVBUF }, size)
+    }
+
+    fn set_option(stream: &TcpStream, level: c_int, name: c_int, value: c_int) -> io::Result<()> {
+        let len = std::mem::size_of::<c_int>() as u32;
+        if unsafe { setsockopt(stream.as_raw_fd(), level, name, &value, len) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // Encode a sockaddr_in / sockaddr_in6 byte-for-byte
+    fn sockaddr(addr: &SocketAddr) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(28);
+        match addr {
+            SocketAddr::V4(v4) => {
+                bytes.extend_from_slice(&family_field(AF_INET, 16));
+                bytes.extend_from_slice(&v4.port().to_be_bytes());
+                bytes.extend_from_slice(&v4.ip().octets());
+                bytes.extend_from_slice(&[0; 8]);
+            }
+            SocketAddr::V6(v6) => {
+                bytes.extend_from_slice(&family_field(AF_INET6, 28));
+                bytes.extend_from_slice(&v6.port().to_be_bytes());
+                bytes.extend_from_slice(&v6.flowinfo().to_ne_bytes());
+                bytes.extend_from_slice(&v6.ip().octets());
+                bytes.extend_from_slice(&v6.scope_id().to_ne_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn family_field(family: u8, _len: u8) -> [u8; 2] {
+        (family as u16).to_ne_bytes()
+    }
+
+    // BSD-style sockaddrs lead with their own length
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn family_field(family: u8, len: u8) -> [u8; 2] {
+        [len, family]
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios")))]
+mod sys {
+    use std::io;
+    use std::net::{SocketAddr, TcpStream};
+    use std::time::Duration;
+
+    pub fn connect_from(_local: SocketAddr, _remote: SocketAddr, _timeout: Option<Duration>) -> io::Result<TcpStream> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "binding a local address is not supported on this platform",
+        ))
+    }
+
+    pub fn set_keepalive(_stream: &TcpStream, _secs: i32) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "TCP keepalive is not supported on this platform"))
+    }
+
+    pub fn set_buffer_size(_stream: &TcpStream, _send: bool, _size: i32) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "socket buffer sizes are not supported on this platform"))
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios")))]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    const LOOPBACK: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn connects_from_the_local_address_within_a_timeout() {
+        let listener = TcpListener::bind((LOOPBACK, 0)).unwrap();
+        let stream = connect_from(LOOPBACK, listener.local_addr().unwrap(), Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), LOOPBACK);
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+        // Blocking again once connected
+        stream.set_read_timeout(Some(Duration::from_millis(20))).unwrap();
+        let mut byte = [0];
+        let e = std::io::Read::read(&mut &stream, &mut byte).unwrap_err();
+        assert!(matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn refused_connection_is_reported_with_a_timeout() {
+        let port = TcpListener::bind((LOOPBACK, 0)).unwrap().local_addr().unwrap().port();
+        let e = connect_from(LOOPBACK, SocketAddr::new(LOOPBACK, port), Some(Duration::from_secs(5))).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    // 192.0.2.1 is a documentation address no interface has
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios"))]
+    #[test]
+    fn a_failed_bind_says_so() {
+        let listener = TcpListener::bind((LOOPBACK, 0)).unwrap();
+        let local = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let e = connect_from(local, listener.local_addr().unwrap(), Some(Duration::from_secs(5))).unwrap_err();
+        assert!(e.to_string().starts_with("can't bind to 192.0.2.1: "), "{}", e);
+    }
+}
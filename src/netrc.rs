@@ -0,0 +1,209 @@
+// Credentials from a .netrc file, as curl and ftp read them, for Client::with_netrc
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::auth::Credentials;
+use crate::error::HttpError;
+use crate::redact;
+use crate::url;
+
+// The machine entries of a .netrc file, plus its default entry if it has one
+#[derive(Clone, Default)]
+pub struct Netrc {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone)]
+struct Entry {
+    // None for the default entry
+    machine: Option<String>,
+    login: Option<String>,
+    password: Option<String>,
+}
+
+impl Netrc {
+    // Tokens separated by whitespace, across lines: machine NAME, default, login NAME,
+    // password SECRET and account (ignored). A token may be double-quoted, with \ escaping
+    // the next character; a # where a token would start comments out the rest of the line,
+    // and a macdef is skipped up to the blank line that ends it.
+    pub fn parse(text: &str) -> Netrc {
+        let mut tokens = Tokens { text, at: 0 };
+        let mut entries = Vec::new();
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "machine" => entries.push(Entry { machine: Some(tokens.next().unwrap_or_default()), login: None, password: None }),
+                "default" => entries.push(Entry { machine: None, login: None, password: None }),
+                "login" | "password" => {
+                    let value = tokens.next();
+                    if let Some(entry) = entries.last_mut() {
+                        if token == "login" { entry.login = value } else { entry.password = value }
+                    }
+                }
+                "account" => {
+                    tokens.next();
+                }
+                "macdef" => tokens.skip_macro(),
+                _ => {}
+            }
+        }
+        Netrc { entries }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Netrc, HttpError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| HttpError::InvalidRequest(format!("Failed to read netrc {}: {}", path.display(), e)))?;
+        Ok(Netrc::parse(&text))
+    }
+
+    // The file in NETRC, or else .netrc (_netrc on Windows) in the home directory; None
+    // when there is no such file
+    pub fn from_env() -> Option<Netrc> {
+        let path = match std::env::var_os("NETRC").filter(|value| !value.is_empty()) {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let (home, name) = if cfg!(windows) { ("USERPROFILE", "_netrc") } else { ("HOME", ".netrc") };
+                PathBuf::from(std::env::var_os(home)?).join(name)
+            }
+        };
+        Netrc::load(path).ok()
+    }
+
+    // Basic credentials of the first entry for the host of `authority` (its port ignored,
+    // the name compared case-insensitively), or of the default entry if none matches.
+    // An entry without a login has none to give.
+    pub fn credentials(&self, authority: &str) -> Option<Credentials> {
+        let (name, _) = url::split_authority(authority);
+        let entry = self.entries.iter()
+            .find(|entry| entry.machine.as_deref().is_some_and(|machine| machine.eq_ignore_ascii_case(name)))
+            .or_else(|| self.entries.iter().find(|entry| entry.machine.is_none()))?;
+        Some(Credentials::basic(entry.login.as_deref()?, entry.password.as_deref().unwrap_or("")))
+    }
+}
+
+impl fmt::Debug for Netrc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut list = f.debug_list();
+        for entry in &self.entries {
+            list.entry(&format_args!(
+                "{} login {:?} password {}",
+                entry.machine.as_deref().map_or("default".to_string(), |machine| format!("machine {:?}", machine)),
+                entry.login.as_deref().unwrap_or(""),
+                if entry.password.is_some() { redact::MASK } else { "none" },
+            ));
+        }
+        list.finish()
+    }
+}
+
+struct Tokens<'t> {
+    text: &'t str,
+    at: usize,
+}
+
+impl Tokens<'_> {
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let rest = &self.text[self.at..];
+            let trimmed = rest.trim_start();
+            self.at += rest.len() - trimmed.len();
+            if !trimmed.starts_with('#') {
+                break;
+            }
+            self.at += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+        let mut chars = self.text[self.at..].char_indices().peekable();
+        let mut token = String::new();
+        match chars.peek() {
+            None => return None,
+            Some((_, '"')) => {
+                chars.next();
+                let mut escaped = false;
+                for (i, c) in chars.by_ref() {
+                    match c {
+                        _ if escaped => {
+                            token.push(c);
+                            escaped = false;
+                        }
+                        '\\' => escaped = true,
+                        '"' => {
+                            self.at += i + 1;
+                            return Some(token);
+                        }
+                        _ => token.push(c),
+                    }
+                }
+                // Unterminated: the rest of the file
+                self.at = self.text.len();
+                return Some(token);
+            }
+            Some(_) => {}
+        }
+        for (i, c) in chars {
+            if c.is_whitespace() {
+                self.at += i;
+                return Some(token);
+            }
+            token.push(c);
+        }
+        self.at = self.text.len();
+        Some(token)
+    }
+
+    // The macro's name, then its lines up to and including the first empty one
+    fn skip_macro(&mut self) {
+        self.next();
+        let mut lines = self.text[self.at..].split_inclusive('\n');
+        // The rest of the macdef line itself
+        if let Some(line) = lines.next() {
+            self.at += line.len();
+        }
+        for line in lines {
+            self.at += line.len();
+            if line.trim().is_empty() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn machines_match_by_host_name_and_fall_back_to_default() {
+        let netrc = Netrc::parse(
+            "# credentials\nmachine api.example.com login alice password s3cret\n\
+             machine Other.Example.com\n  login bob\n  account ignored\n  password \"two words \\\"quoted\\\"\"\n\
+             default login anonymous password guest\n",
+        );
+        assert!(netrc.credentials("API.example.com:8443") == Some(Credentials::basic("alice", "s3cret")));
+        assert!(netrc.credentials("other.example.com") == Some(Credentials::basic("bob", "two words \"quoted\"")));
+        assert!(netrc.credentials("elsewhere.example") == Some(Credentials::basic("anonymous", "guest")));
+        assert!(Netrc::parse("machine a login x").credentials("b").is_none());
+    }
+
+    #[test]
+    fn macros_and_comments_are_skipped() {
+        let netrc = Netrc::parse(
+            "macdef init\nmachine fake login trap password trap\n\nmachine real login me # trailing comment password no\npassword pw\n",
+        );
+        assert!(netrc.credentials("fake").is_none());
+        assert!(netrc.credentials("real") == Some(Credentials::basic("me", "pw")));
+    }
+
+    #[test]
+    fn an_entry_without_a_login_gives_nothing() {
+        let netrc = Netrc::parse("machine host password only\ndefault login anyone");
+        assert!(netrc.credentials("host").is_none());
+        assert!(netrc.credentials("other") == Some(Credentials::basic("anyone", "")));
+    }
+
+    #[test]
+    fn debug_hides_passwords() {
+        let shown = format!("{:?}", Netrc::parse("machine host login me password hunter2"));
+        assert!(shown.contains("\"me\""));
+        assert!(!shown.contains("hunter2"));
+    }
+}
@@ -0,0 +1,362 @@
+// Incremental response parsing: bytes go in as they arrive, in pieces of any size, and
+// the parts of the response come out as events. parse_response and try_parse_response
+// are built on it.
+use crate::error::HttpError;
+use crate::response::{decode_field, find_head_end, framing, has_body, is_http09, parse_head, Framing, HTTP09_STATUS_LINE};
+
+// A part of the response, in the order they occur
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent {
+    // `line` as sent, e.g. "HTTP/1.1 200 OK"
+    StatusLine { code: u16, line: String },
+    // A "Name: value" line, folded continuation lines joined to it
+    Header(String),
+    HeadersComplete,
+    // Body bytes with the transfer framing removed
+    BodyChunk(Vec<u8>),
+    // A "Name: value" trailer line after a chunked body
+    Trailer(String),
+    // The end of the response; anything pushed after it isn't part of it
+    Complete,
+}
+
+//     let mut parser = ResponseParser::new();
+//     for piece in pieces {
+//         for event in parser.push(piece)? { ... }
+//     }
+//     for event in parser.finish()? { ... }
+#[derive(Debug, Default)]
+pub struct ResponseParser {
+    state: State,
+    // Take a reply that doesn't start with "HTTP/" as HTTP/0.9, as parse_response_lenient does
+    lenient: bool,
+    // Bytes pushed but not parsed yet, e.g. half a line
+    pending: Vec<u8>,
+    // Bytes pushed after the end of the response, which aren't parsed
+    trailing: usize,
+}
+
+#[derive(Debug, Default)]
+enum State {
+    // Not yet known whether a lenient parser has an HTTP/0.9 reply
+    #[default]
+    Start,
+    Head,
+    // Bytes left of a Content-Length body, and the length
+    Length(u64, u64),
+    Chunked(Chunk),
+    // Until finish()
+    Close,
+    Done,
+    // After an error
+    Failed,
+}
+
+#[derive(Debug)]
+enum Chunk {
+    Size,
+    // Data bytes left in the current chunk
+    Data(usize),
+    // The CRLF after a chunk's data
+    DataEnd,
+    Trailers,
+}
+
+impl ResponseParser {
+    pub fn new() -> ResponseParser {
+        ResponseParser::default()
+    }
+
+    pub fn lenient(mut self, lenient: bool) -> ResponseParser {
+        self.lenient = lenient;
+        self
+    }
+
+    // Parse `data` after what was pushed before, returning the events it completes. After
+    // an error or Complete, further input is ignored.
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<ParseEvent>, HttpError> {
+        if matches!(self.state, State::Done) {
+            self.trailing += data.len();
+        }
+        if matches!(self.state, State::Done | State::Failed) {
+            return Ok(Vec::new());
+        }
+        self.pending.extend_from_slice(data);
+        self.advance(false)
+    }
+
+    // The connection has closed: the rest of the events, ending in Complete, or the error
+    // for a response the end of input cut short
+    pub fn finish(&mut self) -> Result<Vec<ParseEvent>, HttpError> {
+        if matches!(self.state, State::Done | State::Failed) {
+            return Ok(Vec::new());
+        }
+        self.advance(true)
+    }
+
+    // Whether Complete has been returned
+    pub fn is_complete(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    // How many of the bytes pushed came after the end of the response
+    pub(crate) fn trailing(&self) -> usize {
+        self.trailing
+    }
+
+    fn advance(&mut self, eof: bool) -> Result<Vec<ParseEvent>, HttpError> {
+        let mut events = Vec::new();
+        let mut at = 0;
+        let result = self.step(&mut events, &mut at, eof);
+        self.pending.drain(..at);
+        if result.is_err() {
+            self.state = State::Failed;
+        }
+        if matches!(self.state, State::Done) {
+            self.trailing += self.pending.len();
+        }
+        if matches!(self.state, State::Done | State::Failed) {
+            self.pending = Vec::new();
+        }
+        result.map(|_| events)
+    }
+
+    // Parse from `at` for as long as the input allows, leaving `at` after what was used
+    fn step(&mut self, events: &mut Vec<ParseEvent>, at: &mut usize, eof: bool) -> Result<(), HttpError> {
+        loop {
+            let input = &self.pending[*at..];
+            match &mut self.state {
+                State::Start => {
+                    if !self.lenient || (input.is_empty() && eof) {
+                        self.state = State::Head;
+                    } else if !input.is_empty() && is_http09(input) {
+                        // All of it is the body of a response with no head
+                        events.push(ParseEvent::StatusLine { code: 200, line: HTTP09_STATUS_LINE.to_string() });
+                        events.push(ParseEvent::HeadersComplete);
+                        self.state = State::Close;
+                    } else if input.len() >= 5 || eof {
+                        self.state = State::Head;
+                    } else {
+                        return Ok(());
+                    }
+                }
+                State::Head => {
+                    // Input that ends without a blank line is all head, as parse_response takes it
+                    let (head_end, body_start) = match find_head_end(input) {
+                        Some(end) => end,
+                        None if eof => (input.len(), input.len()),
+                        None => return Ok(()),
+                    };
+                    let head = parse_head(&input[..head_end])?;
+                    *at += body_start;
+                    let framing = if has_body(head.status_code) { Some(framing(&head)?) } else { None };
+                    events.push(ParseEvent::StatusLine { code: head.status_code, line: head.status_line });
+                    events.extend(head.headers.into_lines().into_iter().map(ParseEvent::Header));
+                    events.push(ParseEvent::HeadersComplete);
+                    self.state = match framing {
+                        None => State::Length(0, 0),
+                        Some(Framing::Length(length)) => State::Length(length, length),
+                        Some(Framing::Chunked) => State::Chunked(Chunk::Size),
+                        Some(Framing::Close) => State::Close,
+                    };
+                }
+                State::Length(left, length) => {
+                    let n = (*left).min(input.len() as u64);
+                    if n > 0 {
+                        events.push(ParseEvent::BodyChunk(input[..n as usize].to_vec()));
+                        *at += n as usize;
+                        *left -= n;
+                    }
+                    if *left == 0 {
+                        events.push(ParseEvent::Complete);
+                        self.state = State::Done;
+                    } else if eof {
+                        return Err(HttpError::TruncatedBody { expected: *length, received: *length - *left });
+                    } else {
+                        return Ok(());
+                    }
+                }
+                State::Close => {
+                    if !input.is_empty() {
+                        events.push(ParseEvent::BodyChunk(input.to_vec()));
+                        *at += input.len();
+                    }
+                    if !eof {
+                        return Ok(());
+                    }
+                    events.push(ParseEvent::Complete);
+                    self.state = State::Done;
+                }
+                State::Chunked(Chunk::Data(left)) => {
+                    let n = (*left).min(input.len());
+                    if n > 0 {
+                        events.push(ParseEvent::BodyChunk(input[..n].to_vec()));
+                        *at += n;
+                        *left -= n;
+                    }
+                    if *left == 0 {
+                        self.state = State::Chunked(Chunk::DataEnd);
+                    } else if eof {
+                        return Err(invalid("Chunked body ended inside a chunk"));
+                    } else {
+                        return Ok(());
+                    }
+                }
+                State::Chunked(chunk) => {
+                    let Some((line, used)) = next_line(input, eof) else {
+                        return match chunk {
+                            _ if !eof => Ok(()),
+                            Chunk::Size => Err(invalid("Chunked body ended before the last chunk")),
+                            Chunk::DataEnd => Err(invalid("Missing CRLF after chunk data")),
+                            // A connection closed right after the last chunk is tolerated
+                            _ => {
+                                events.push(ParseEvent::Complete);
+                                self.state = State::Done;
+                                Ok(())
+                            }
+                        };
+                    };
+                    *at += used;
+                    match chunk {
+                        Chunk::Size => {
+                            // Chunk extensions after ';' carry nothing we use
+                            let size_text = line.split(';').next().unwrap_or("").trim();
+                            let size = usize::from_str_radix(size_text, 16)
+                                .map_err(|_| invalid(&format!("Invalid chunk size: {:?}", size_text)))?;
+                            *chunk = if size > 0 { Chunk::Data(size) } else { Chunk::Trailers };
+                        }
+                        Chunk::DataEnd if line.is_empty() => *chunk = Chunk::Size,
+                        Chunk::DataEnd => return Err(invalid("Missing CRLF after chunk data")),
+                        _ if line.is_empty() => {
+                            events.push(ParseEvent::Complete);
+                            self.state = State::Done;
+                        }
+                        _ => events.push(ParseEvent::Trailer(line)),
+                    }
+                }
+                State::Done | State::Failed => return Ok(()),
+            }
+        }
+    }
+}
+
+// The first line of `input` without its CRLF (or bare LF), and the bytes it took up. At
+// the end of input, what is left counts as a line.
+fn next_line(input: &[u8], eof: bool) -> Option<(String, usize)> {
+    let (line, used) = match input.iter().position(|&b| b == b'\n') {
+        Some(i) => (input[..i].strip_suffix(b"\r").unwrap_or(&input[..i]), i + 1),
+        None if eof && !input.is_empty() => (input, input.len()),
+        None => return None,
+    };
+    Some((decode_field(line).into_owned(), used))
+}
+
+fn invalid(message: &str) -> HttpError {
+    HttpError::InvalidResponse(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &[&[u8]] = &[
+        b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nX-A: 1\r\n\r\nhello",
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Sum: 11\r\n\r\n",
+        b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\nX-B: 2\r\n\r\n",
+        b"HTTP/1.1 200 OK\nX-Folded: a\n b\n\nuntil close",
+        b"HTTP/1.0 200 OK\r\n\r\n",
+    ];
+
+    // Events for `input` cut into pieces at `splits`, with neighbouring body chunks merged
+    // since their sizes depend on the pieces
+    fn events(input: &[u8], splits: &[usize]) -> Result<Vec<ParseEvent>, HttpError> {
+        let mut parser = ResponseParser::new();
+        let mut raw = Vec::new();
+        let mut start = 0;
+        for &end in splits.iter().chain([&input.len()]) {
+            raw.extend(parser.push(&input[start..end])?);
+            raw.extend(parser.push(b"")?);
+            start = end;
+        }
+        raw.extend(parser.finish()?);
+        let mut merged: Vec<ParseEvent> = Vec::new();
+        for event in raw {
+            match (merged.last_mut(), event) {
+                (Some(ParseEvent::BodyChunk(body)), ParseEvent::BodyChunk(more)) => body.extend(more),
+                (_, ParseEvent::BodyChunk(body)) if body.is_empty() => {}
+                (_, event) => merged.push(event),
+            }
+        }
+        Ok(merged)
+    }
+
+    #[test]
+    fn events_of_a_chunked_response() {
+        assert_eq!(events(FIXTURES[1], &[]).unwrap(), [
+            ParseEvent::StatusLine { code: 200, line: "HTTP/1.1 200 OK".to_string() },
+            ParseEvent::Header("Transfer-Encoding: chunked".to_string()),
+            ParseEvent::HeadersComplete,
+            ParseEvent::BodyChunk(b"hello world".to_vec()),
+            ParseEvent::Trailer("X-Sum: 11".to_string()),
+            ParseEvent::Complete,
+        ]);
+    }
+
+    #[test]
+    fn every_split_gives_the_same_events() {
+        for fixture in FIXTURES {
+            let whole = events(fixture, &[]);
+            assert!(whole.is_ok(), "{:?}", String::from_utf8_lossy(fixture));
+            for at in 0..=fixture.len() {
+                assert_eq!(events(fixture, &[at]).ok(), whole.as_ref().ok().cloned(), "split at {} of {:?}", at, String::from_utf8_lossy(fixture));
+            }
+            let every_byte: Vec<usize> = (1..fixture.len()).collect();
+            assert_eq!(events(fixture, &every_byte).ok(), whole.ok());
+        }
+    }
+
+    #[test]
+    fn malformed_input_errors_at_any_split() {
+        let malformed: &[&[u8]] = &[
+            b"",
+            b"garbage\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello",
+            b"HTTP/1.1 200 OK\r\nX-Cut",
+        ];
+        for input in malformed {
+            for at in 0..=input.len() {
+                assert!(events(input, &[at]).is_err(), "split at {} of {:?}", at, String::from_utf8_lossy(input));
+            }
+        }
+    }
+
+    #[test]
+    fn input_after_complete_is_counted_but_not_parsed() {
+        let mut parser = ResponseParser::new();
+        let events = parser.push(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nokNEXT").unwrap();
+        assert_eq!(events.last(), Some(&ParseEvent::Complete));
+        assert!(parser.is_complete());
+        assert_eq!(parser.push(b"MORE").unwrap(), []);
+        assert_eq!(parser.trailing(), 8);
+        assert_eq!(parser.finish().unwrap(), []);
+    }
+
+    #[test]
+    fn after_an_error_nothing_completes() {
+        let mut parser = ResponseParser::new();
+        assert!(parser.push(b"nonsense\r\n\r\n").is_err());
+        assert_eq!(parser.push(b"HTTP/1.1 200 OK\r\n\r\n").unwrap(), []);
+        assert!(!parser.is_complete());
+    }
+
+    #[test]
+    fn lenient_parsers_take_http09_replies() {
+        let mut parser = ResponseParser::new().lenient(true);
+        let mut all = parser.push(b"temperature=21").unwrap();
+        all.extend(parser.finish().unwrap());
+        assert!(all.contains(&ParseEvent::BodyChunk(b"temperature=21".to_vec())), "{:?}", all);
+        assert_eq!(all.last(), Some(&ParseEvent::Complete));
+    }
+}
@@ -0,0 +1,314 @@
+// Idle keep-alive connections, kept per route for the next request on it
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancelToken;
+use crate::error::{HttpError, TimeoutPhase};
+use crate::response::{ConnectionInfo, HttpResponse};
+use crate::transport::TimedStream;
+
+// Shared handle like HarRecorder: clones of a Client use the same connections
+#[derive(Clone, Default)]
+pub(crate) struct Pool {
+    idle: Arc<Mutex<HashMap<String, Vec<Idle>>>>,
+    counters: Arc<Counters>,
+}
+
+// Counts of what happened to the connections of a Client, from Client::pool_stats()
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    // Connections opened
+    pub created: u64,
+    // Responses that arrived on a connection taken from the pool (ConnectionInfo::reused)
+    pub reused: u64,
+    // Pooled connections dropped for being idle longer than the idle timeout
+    pub closed_idle: u64,
+    // Pooled connections found closed by the server, before or when a request was sent on them
+    pub closed_stale: u64,
+    // Right now: connections waiting in the pool
+    pub currently_idle: usize,
+    // Right now: connections carrying a request or a response body being read
+    pub currently_in_use: usize,
+}
+
+#[derive(Default)]
+struct Counters {
+    created: AtomicU64,
+    reused: AtomicU64,
+    closed_idle: AtomicU64,
+    closed_stale: AtomicU64,
+    in_use: AtomicUsize,
+    // Connections open right now, pooled or not, for RequestConfig::max_connections
+    open: Mutex<usize>,
+    // Notified when a connection is closed or pooled
+    changed: Condvar,
+}
+
+// Held by a connection while it is out of the pool, so dropping it anywhere counts
+pub(crate) struct InUse(Arc<Counters>);
+
+impl InUse {
+    fn new(counters: &Arc<Counters>) -> InUse {
+        counters.in_use.fetch_add(1, Ordering::Relaxed);
+        InUse(Arc::clone(counters))
+    }
+}
+
+impl Drop for InUse {
+    fn drop(&mut self) {
+        self.0.in_use.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Held by a connection from before it is opened until it is closed
+pub(crate) struct Slot(Arc<Counters>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *self.0.open.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        self.0.changed.notify_all();
+    }
+}
+
+struct Idle {
+    // With its read buffer, which the next response on the connection reuses
+    stream: BufReader<TimedStream>,
+    connection: ConnectionInfo,
+    keep_alive: KeepAlive,
+    since: Instant,
+}
+
+// Limits a server advertises with e.g. Keep-Alive: timeout=5, max=100
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct KeepAlive {
+    // How long the server keeps an idle connection open
+    pub(crate) timeout: Option<Duration>,
+    // How many requests the server accepts on one connection
+    pub(crate) max: Option<usize>,
+}
+
+impl KeepAlive {
+    // Parameters that are missing or malformed are left unset
+    pub(crate) fn parse(value: &str) -> KeepAlive {
+        let mut keep_alive = KeepAlive::default();
+        for param in value.split(',') {
+            let Some((name, value)) = param.split_once('=') else { continue };
+            let value = value.trim().trim_matches('"');
+            match name.trim().to_ascii_lowercase().as_str() {
+                "timeout" => keep_alive.timeout = value.parse().ok().map(Duration::from_secs),
+                "max" => keep_alive.max = value.parse().ok(),
+                _ => {}
+            }
+        }
+        keep_alive
+    }
+}
+
+impl Pool {
+    // The most recently returned connection for `key`, unless it has been idle longer than
+    // `idle_timeout` or the server's Keep-Alive timeout. Its stream still counts the requests
+    // sent on it and has the timeouts of the last one until they are reset.
+    pub(crate) fn take(&self, key: &str, idle_timeout: Duration) -> Option<(BufReader<TimedStream>, ConnectionInfo)> {
+        let mut idle = self.lock();
+        let connections = idle.get_mut(key)?;
+        self.prune(connections, idle_timeout);
+        // An emptied list is kept for the route, ready for the connection to be put back
+        let mut c = connections.pop()?;
+        c.stream.get_mut().in_use = Some(InUse::new(&self.counters));
+        Some((c.stream, ConnectionInfo { reused: true, ..c.connection }))
+    }
+
+    // Whether `key` has a connection take() would hand out, and its details; the expired
+    // ones are dropped as take() would
+    pub(crate) fn peek(&self, key: &str, idle_timeout: Duration) -> Option<ConnectionInfo> {
+        let mut idle = self.lock();
+        let connections = idle.get_mut(key)?;
+        self.prune(connections, idle_timeout);
+        connections.last().map(|c| c.connection)
+    }
+
+    // Room for one more connection when fewer than `max` (unlimited when None) are open,
+    // closing the longest idle connection of any route to make room if needed
+    pub(crate) fn slot(&self, max: Option<usize>) -> Option<Slot> {
+        let limit = max.unwrap_or(usize::MAX);
+        loop {
+            let mut open = self.open();
+            if *open < limit {
+                *open += 1;
+                return Some(Slot(Arc::clone(&self.counters)));
+            }
+            drop(open);
+            if !self.close_longest_idle() {
+                return None;
+            }
+        }
+    }
+
+    // Block until a connection is closed or put back into the pool, waking up every 50ms to
+    // check `cancel`; an error at the deadline
+    pub(crate) fn wait_for_slot(
+        &self,
+        max: Option<usize>,
+        deadline: Option<Instant>,
+        timeout: Option<Duration>,
+        cancel: Option<&CancelToken>
+    ) -> Result<(), HttpError> {
+        let limit = max.unwrap_or(usize::MAX);
+        if cancel.is_some_and(CancelToken::is_cancelled) {
+            return Err(HttpError::Cancelled);
+        }
+        let left = deadline.map_or(Duration::MAX, |d| d.saturating_duration_since(Instant::now()));
+        if left.is_zero() {
+            return Err(HttpError::Timeout { phase: TimeoutPhase::Total, limit: timeout.unwrap_or_default() });
+        }
+        // Counted first: the pool's lock is never taken while holding the one on the open count
+        let idle = self.idle_count();
+        let open = self.open();
+        if *open >= limit && idle == 0 {
+            let _ = self.counters.changed.wait_timeout(open, left.min(Duration::from_millis(50)));
+        }
+        Ok(())
+    }
+
+    // Whether there was an idle connection to close
+    fn close_longest_idle(&self) -> bool {
+        let mut idle = self.lock();
+        let oldest = idle.iter()
+            .flat_map(|(key, connections)| connections.iter().enumerate().map(move |(i, c)| (c.since, key, i)))
+            .min()
+            .map(|(_, key, i)| (key.clone(), i));
+        let Some((key, i)) = oldest else { return false };
+        if let Some(connections) = idle.get_mut(&key) {
+            connections.remove(i);
+        }
+        true
+    }
+
+    // A new connection has been opened; it counts as in use while the guard is held
+    pub(crate) fn opened(&self) -> InUse {
+        self.counters.created.fetch_add(1, Ordering::Relaxed);
+        InUse::new(&self.counters)
+    }
+
+    // A connection taken from the pool turned out to be closed by the server
+    pub(crate) fn closed_stale(&self) {
+        self.counters.closed_stale.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // A connection taken from the pool carried a request, and the response head arrived on it
+    pub(crate) fn reused(&self) {
+        self.counters.reused.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> PoolStats {
+        PoolStats {
+            created: self.counters.created.load(Ordering::Relaxed),
+            reused: self.counters.reused.load(Ordering::Relaxed),
+            closed_idle: self.counters.closed_idle.load(Ordering::Relaxed),
+            closed_stale: self.counters.closed_stale.load(Ordering::Relaxed),
+            currently_idle: self.idle_count(),
+            currently_in_use: self.counters.in_use.load(Ordering::Relaxed),
+        }
+    }
+
+    // Zero the running totals; the current counts stay what they are
+    pub(crate) fn reset_stats(&self) {
+        for counter in [&self.counters.created, &self.counters.reused, &self.counters.closed_idle, &self.counters.closed_stale] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    // Keep a connection whose last response was read completely. It is closed instead once
+    // the requests sent on it reach the server's Keep-Alive max, or beyond `max_idle` connections for the route.
+    pub(crate) fn put(
+        &self,
+        key: &str,
+        mut stream: BufReader<TimedStream>,
+        connection: ConnectionInfo,
+        keep_alive: KeepAlive,
+        max_idle: usize
+    ) {
+        if keep_alive.max.is_some_and(|max| stream.get_ref().requests >= max) {
+            return;
+        }
+        stream.get_mut().in_use = None;
+        let mut idle = self.lock();
+        // Only a new route needs its key copied
+        let connections = match idle.get_mut(key) {
+            Some(connections) => connections,
+            None => idle.entry(key.to_string()).or_default(),
+        };
+        if connections.len() < max_idle {
+            connections.push(Idle { stream, connection, keep_alive, since: Instant::now() });
+        }
+        drop(idle);
+        self.counters.changed.notify_all();
+    }
+
+    pub(crate) fn idle_count(&self) -> usize {
+        self.lock().values().map(Vec::len).sum()
+    }
+
+    // Drop connections idle longer than `idle_timeout` or the server's Keep-Alive timeout
+    fn prune(&self, connections: &mut Vec<Idle>, idle_timeout: Duration) {
+        let before = connections.len();
+        connections.retain(|c| {
+            let timeout = c.keep_alive.timeout.map_or(idle_timeout, |timeout| timeout.min(idle_timeout));
+            c.since.elapsed() < timeout
+        });
+        self.counters.closed_idle.fetch_add((before - connections.len()) as u64, Ordering::Relaxed);
+    }
+
+    fn open(&self) -> std::sync::MutexGuard<'_, usize> {
+        self.counters.open.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<Idle>>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Where a connection goes once its response has been read: back into `pool` under `key`
+pub(crate) struct Recycle {
+    pub(crate) pool: Pool,
+    pub(crate) key: String,
+    pub(crate) max_idle: usize,
+}
+
+impl Recycle {
+    // Pool the connection `response` arrived on, within the limits of its Keep-Alive header
+    pub(crate) fn put(self, stream: BufReader<TimedStream>, response: &HttpResponse) {
+        let Some(connection) = response.connection else { return };
+        let keep_alive = response.header("Keep-Alive").map(KeepAlive::parse).unwrap_or_default();
+        let connection = ConnectionInfo { stale_retry: false, ..connection };
+        self.pool.put(&self.key, stream, connection, keep_alive, self.max_idle);
+    }
+}
+
+impl std::fmt::Debug for Pool {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Pool").field("idle", &self.idle_count()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_alive_parameters_parse() {
+        assert_eq!(KeepAlive::parse("timeout=5, max=100"), KeepAlive { timeout: Some(Duration::from_secs(5)), max: Some(100) });
+        assert_eq!(KeepAlive::parse(" MAX = \"3\" ,Timeout=0"), KeepAlive { timeout: Some(Duration::ZERO), max: Some(3) });
+        assert_eq!(KeepAlive::parse("max=2, other=x, timeout"), KeepAlive { timeout: None, max: Some(2) });
+    }
+
+    #[test]
+    fn malformed_keep_alive_parameters_are_ignored() {
+        for value in ["", "timeout=soon", "timeout=-1, max=many", "timeout=1.5", "garbage"] {
+            assert_eq!(KeepAlive::parse(value), KeepAlive::default(), "{:?}", value);
+        }
+    }
+}
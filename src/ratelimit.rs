@@ -0,0 +1,117 @@
+// The requests-per-second budget of RequestConfig::rate_limit, shared by a Client and its clones
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancelToken;
+use crate::error::{HttpError, TimeoutPhase};
+
+// A token bucket holding one token: a request takes it and it comes back 1/rate seconds
+// later, so requests go out evenly spaced and never more than `rate` of them in a second.
+// Waiting requests are served in the order they asked.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RateLimiter {
+    // When the next request may go out; None before the first one
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimiter {
+    // Wait for the token, or fail at once instead if `fail` is set. A wait that would end
+    // after `deadline` isn't started.
+    pub(crate) fn acquire(
+        &self,
+        rate: f64,
+        fail: bool,
+        deadline: Option<Instant>,
+        timeout: Option<Duration>,
+        cancel: Option<&CancelToken>
+    ) -> Result<(), HttpError> {
+        let interval = Some(rate)
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .and_then(|rate| Duration::try_from_secs_f64(1.0 / rate).ok())
+            .ok_or_else(|| HttpError::InvalidRequest(format!("Invalid rate_limit: {} requests per second", rate)))?;
+        let at = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let at = next.map_or(now, |next| next.max(now));
+            if at > now && fail {
+                return Err(HttpError::NetworkError(format!("Rate limit reached: {} requests per second", rate)));
+            }
+            if deadline.is_some_and(|deadline| deadline < at) {
+                return Err(HttpError::Timeout { phase: TimeoutPhase::Total, limit: timeout.unwrap_or_default() });
+            }
+            *next = Some(at + interval);
+            at
+        };
+        // In steps, so a cancelled request doesn't sit out the rest of the wait
+        loop {
+            if cancel.is_some_and(CancelToken::is_cancelled) {
+                return Err(HttpError::Cancelled);
+            }
+            let left = at.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            thread::sleep(left.min(Duration::from_millis(50)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_come_back_one_interval_apart() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire(20.0, false, None, None, None).unwrap();
+        }
+        // The first goes at once, the other two 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_millis(400));
+    }
+
+    #[test]
+    fn failing_instead_of_waiting() {
+        let limiter = RateLimiter::default();
+        limiter.acquire(1.0, true, None, None, None).unwrap();
+        let err = limiter.acquire(1.0, true, None, None, None).unwrap_err();
+        assert!(matches!(err, HttpError::NetworkError(ref message) if message.contains("Rate limit")));
+    }
+
+    #[test]
+    fn a_wait_past_the_deadline_times_out_without_waiting() {
+        let limiter = RateLimiter::default();
+        let timeout = Duration::from_millis(200);
+        limiter.acquire(1.0, false, None, None, None).unwrap();
+        let start = Instant::now();
+        let err = limiter.acquire(1.0, false, Some(start + timeout), Some(timeout), None).unwrap_err();
+        assert!(matches!(err, HttpError::Timeout { phase: TimeoutPhase::Total, limit } if limit == timeout));
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn cancelling_stops_a_wait() {
+        let limiter = RateLimiter::default();
+        let token = CancelToken::new();
+        limiter.acquire(0.5, false, None, None, None).unwrap();
+        let canceller = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        let start = Instant::now();
+        assert!(matches!(limiter.acquire(0.5, false, None, None, Some(&token)), Err(HttpError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn rates_that_are_not_positive_numbers_are_rejected() {
+        let limiter = RateLimiter::default();
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(limiter.acquire(rate, false, None, None, None), Err(HttpError::InvalidRequest(_))));
+        }
+    }
+}
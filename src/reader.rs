@@ -0,0 +1,734 @@
+// Responses whose body is read from the connection as the caller consumes it
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Take};
+#[cfg(feature = "json")]
+use std::marker::PhantomData;
+
+use crate::chunked::ChunkedReader;
+use crate::digest::Verifier;
+use crate::error::HttpError;
+#[cfg(feature = "json")]
+use crate::json::Json;
+use crate::pool::Recycle;
+use crate::response::{framing, has_body, is_http09, parse_head, Framing, ConnectionInfo, HttpResponse, HTTP09_STATUS_LINE};
+use crate::scratch;
+use crate::spool::{self, SpooledBody};
+use crate::status::StatusCode;
+use crate::transport::TimedStream;
+use crate::upgrade::Upgraded;
+
+// Status and headers are available at once; the body is read on demand through
+// Read/BufRead, lines_iter(), chunks() or into_response()
+pub struct ResponseReader {
+    // The head, with an empty body
+    head: HttpResponse,
+    body: Body,
+    // Accept a Content-Length body that ends early instead of failing with TruncatedBody
+    allow_truncated: bool,
+    // Pool the connection goes back to once the body has been read or drained
+    recycle: Option<Recycle>,
+    // Checks the body against its Digest header, until the end of the body is reached
+    verifier: Option<Verifier>,
+    // How much of the body discard_body() reads through before closing the connection instead
+    discard_limit: u64,
+}
+
+// A body a caller leaves unread is read and thrown away up to this size when the
+// reader is dropped, so the connection can be reused; a longer one closes it instead
+const DRAIN_LIMIT: u64 = 64 * 1024;
+
+enum Body {
+    Chunked(ChunkedReader<BufReader<TimedStream>>),
+    Length(Take<BufReader<TimedStream>>),
+    // Until the server closes the connection
+    Close(BufReader<TimedStream>),
+    // None at all (HEAD, 1xx, 204, 304); the connection can carry the next request
+    Empty(BufReader<TimedStream>),
+    // Already in memory, e.g. replayed from a cassette
+    Buffered(Cursor<Vec<u8>>),
+}
+
+impl Body {
+    // What the underlying reader has buffered, without the end-of-body checks
+    fn buffered(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Body::Chunked(reader) => reader.fill_buf(),
+            Body::Length(reader) => reader.fill_buf(),
+            Body::Close(reader) => reader.fill_buf(),
+            Body::Empty(_) => Ok(&[]),
+            Body::Buffered(reader) => reader.fill_buf(),
+        }
+    }
+}
+
+impl ResponseReader {
+    // The response whose head `head` was read from `stream`
+    pub(crate) fn from_head(
+        stream: BufReader<TimedStream>,
+        head: &[u8],
+        method: &str,
+        connection: ConnectionInfo
+    ) -> Result<ResponseReader, HttpError> {
+        let mut head = parse_head(head)?;
+        head.connection = Some(connection);
+
+        let body = if method == "HEAD" || !has_body(head.status_code) {
+            Body::Empty(stream)
+        } else {
+            match framing(&head)? {
+                Framing::Chunked => Body::Chunked(ChunkedReader::new(stream)),
+                Framing::Length(length) => Body::Length(stream.take(length)),
+                Framing::Close => Body::Close(stream),
+            }
+        };
+        Ok(ResponseReader { head, body, allow_truncated: false, recycle: None, verifier: None, discard_limit: DRAIN_LIMIT })
+    }
+
+    pub(crate) fn allow_truncated(mut self, allow: bool) -> ResponseReader {
+        self.allow_truncated = allow;
+        self
+    }
+
+    pub(crate) fn discard_limit(mut self, limit: u64) -> ResponseReader {
+        self.discard_limit = limit;
+        self
+    }
+
+    pub(crate) fn with_url(mut self, url: Option<String>) -> ResponseReader {
+        self.head.url = url;
+        self
+    }
+
+    pub(crate) fn recycle_into(mut self, recycle: Option<Recycle>) -> ResponseReader {
+        self.recycle = recycle;
+        self
+    }
+
+    // Fail the read that reaches the end of the body if it doesn't match the digest in the
+    // response head. A body-less response has nothing to check.
+    pub(crate) fn verify_digest(mut self, verify: bool) -> ResponseReader {
+        if verify && !matches!(self.body, Body::Empty(_)) {
+            self.verifier = Verifier::for_response(&self.head);
+        }
+        self
+    }
+
+    // The end of the body has been reached
+    fn check_digest(&mut self) -> io::Result<()> {
+        self.verifier.take().map_or(Ok(()), Verifier::finish)
+    }
+
+    // Serve an already complete response through the streaming interface, e.g. in tests
+    pub fn from_response(mut response: HttpResponse) -> ResponseReader {
+        let body = std::mem::take(&mut response.body);
+        ResponseReader {
+            head: response,
+            body: Body::Buffered(Cursor::new(body)),
+            allow_truncated: false,
+            recycle: None,
+            verifier: None,
+            discard_limit: DRAIN_LIMIT,
+        }
+    }
+
+    // Status line and headers (the body field is empty)
+    pub fn head(&self) -> &HttpResponse {
+        &self.head
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.head.status()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.header(name)
+    }
+
+    // Trailer fields of a chunked body, known once the body has been read to the end
+    pub fn trailers(&self) -> &[String] {
+        match &self.body {
+            Body::Chunked(chunked) => &chunked.trailers,
+            _ => &self.head.trailers,
+        }
+    }
+
+    // The body one line at a time as it arrives, without the "\n" or "\r\n".
+    // A last line with no newline is yielded too.
+    pub fn lines_iter(self) -> Lines {
+        Lines { reader: self, done: false, max_line_len: DEFAULT_MAX_LINE_LEN }
+    }
+
+    // The body in pieces of `size` bytes (the last may be shorter) as it arrives, for
+    // large bodies that shouldn't be held in memory at once. A size of 0 is taken as 1.
+    pub fn chunks(self, size: usize) -> Chunks {
+        Chunks { reader: self, size: size.max(1), done: false }
+    }
+
+    // Newline-delimited JSON (NDJSON): each non-blank line parsed and converted to T as it
+    // arrives, e.g. json_lines::<Json>() or a type of your own with TryFrom<Json>
+    #[cfg(feature = "json")]
+    pub fn json_lines<T: TryFrom<Json>>(self) -> JsonLines<T> {
+        JsonLines { lines: self.lines_iter(), item: PhantomData }
+    }
+
+    // Read the rest of the body into an ordinary response
+    pub fn into_response(mut self) -> Result<HttpResponse, HttpError> {
+        let body = self.read_body()?;
+        Ok(self.finish(body))
+    }
+
+    // Read the rest of the body into memory while it's at most `threshold` bytes, and into a
+    // temporary file once it's longer, for bodies that may not fit in memory; the response
+    // comes back with an empty body (and any trailers)
+    //
+    //     let (response, body) = client.request(Method::Get, host, "/dump").send_streaming()?.spool(16 << 20)?;
+    //     io::copy(&mut body.into_reader(), &mut output)?;
+    pub fn spool(mut self, threshold: u64) -> Result<(HttpResponse, SpooledBody), HttpError> {
+        let fail = |e| HttpError::from_io("Failed to read response", e);
+        let mut start = Vec::new();
+        self.by_ref().take(threshold.saturating_add(1)).read_to_end(&mut start).map_err(fail)?;
+        let body = if start.len() as u64 <= threshold {
+            SpooledBody::Memory(start)
+        } else {
+            // A failure here may be the file's rather than the connection's
+            let file = spool::to_file(&start, &mut self)
+                .map_err(|e| HttpError::from_io("Failed to spool response body to a temporary file", e))?;
+            SpooledBody::File(file)
+        };
+        Ok((self.finish(Vec::new()), body))
+    }
+
+    pub(crate) fn read_body(&mut self) -> Result<Vec<u8>, HttpError> {
+        let mut body = Vec::new();
+        self.read_to_end(&mut body)
+            .map_err(|e| HttpError::from_io("Failed to read response", e))?;
+        Ok(body)
+    }
+
+    // The response with `body`, once the body has been read, giving up the connection
+    pub(crate) fn finish(mut self, body: Vec<u8>) -> HttpResponse {
+        let trailers = self.trailers().to_vec();
+        self.recycle();
+        let mut response = std::mem::replace(&mut self.head, HttpResponse::placeholder());
+        response.body = body;
+        response.trailers = trailers;
+        response
+    }
+
+    // Bytes written and read on the connection for this exchange so far (zero for a
+    // response that didn't come from one)
+    pub(crate) fn transferred(&self) -> (u64, u64) {
+        let stream = match &self.body {
+            Body::Chunked(reader) => reader.get_ref().get_ref(),
+            Body::Length(reader) => reader.get_ref().get_ref(),
+            Body::Close(reader) | Body::Empty(reader) => reader.get_ref(),
+            Body::Buffered(_) => return (0, 0),
+        };
+        (stream.bytes_written, stream.bytes_read)
+    }
+
+    // The connection after a 101 response, for the protocol it switched to
+    pub(crate) fn into_upgraded(mut self) -> Result<Upgraded, HttpError> {
+        self.recycle = None;
+        let Body::Empty(stream) = std::mem::replace(&mut self.body, Body::Buffered(Cursor::default())) else {
+            return Err(HttpError::InvalidResponse("Only a live connection can be upgraded, not a replayed response".to_string()));
+        };
+        let buffered = stream.buffer().to_vec();
+        let head = std::mem::replace(&mut self.head, HttpResponse::placeholder());
+        Ok(Upgraded::new(head, buffered, stream.into_inner().into_stream()))
+    }
+
+    // Skip the rest of the body, leaving a keep-alive connection ready for the next request.
+    // A body longer than 64 KiB is not read; the connection is closed instead.
+    pub fn consume_body(mut self) -> Result<(), HttpError> {
+        self.drain_body(DRAIN_LIMIT)
+            .map_err(|e| HttpError::from_io("Failed to read response", e))?;
+        self.recycle();
+        Ok(())
+    }
+
+    // Read the rest of the body and throw it away, for when only the status and headers
+    // matter; the response comes back with an empty body (and any trailers). A keep-alive
+    // connection goes back to the pool once the body ends, unless it runs past
+    // RequestConfig::discard_limit, which closes the connection rather than reading on.
+    pub fn discard_body(mut self) -> Result<HttpResponse, HttpError> {
+        self.skip_body()?;
+        Ok(self.finish(Vec::new()))
+    }
+
+    pub(crate) fn skip_body(&mut self) -> Result<(), HttpError> {
+        self.drain_body(self.discard_limit)
+            .map(|_| ())
+            .map_err(|e| HttpError::from_io("Failed to read response", e))
+    }
+
+    // Read and discard the body up to `limit` bytes; true if it ended within that
+    fn drain_body(&mut self, limit: u64) -> io::Result<bool> {
+        io::copy(&mut self.by_ref().take(limit), &mut io::sink())?;
+        Ok(self.fill_buf()?.is_empty())
+    }
+
+    // Hand the connection back to the pool if the body has been read to the end, nothing
+    // beyond the response has arrived and the server keeps the connection open
+    fn recycle(&mut self) {
+        let Some(recycle) = self.recycle.take() else { return };
+        let stream = match std::mem::replace(&mut self.body, Body::Buffered(Cursor::default())) {
+            Body::Chunked(reader) if reader.is_done() => Some(reader.into_inner()),
+            Body::Length(reader) if reader.limit() == 0 => Some(reader.into_inner()),
+            Body::Empty(stream) => Some(stream),
+            // A close-delimited body ends with the connection
+            _ => None,
+        };
+        if let Some(stream) = stream.filter(|stream| stream.buffer().is_empty() && keeps_alive(&self.head)) {
+            recycle.put(stream, &self.head);
+        }
+    }
+}
+
+impl Drop for ResponseReader {
+    fn drop(&mut self) {
+        if self.recycle.is_some() && self.drain_body(DRAIN_LIMIT).unwrap_or(false) {
+            self.recycle();
+        }
+    }
+}
+
+// Whether the server leaves the connection open after this response: HTTP/1.1 does
+// unless it says close, HTTP/1.0 only when it says keep-alive
+fn keeps_alive(response: &HttpResponse) -> bool {
+    if response.has_header_value("Connection", "close") {
+        return false;
+    }
+    response.status_line.starts_with("HTTP/1.1") || response.has_header_value("Connection", "keep-alive")
+}
+
+// Read up to and including the blank line after the headers, however many reads a long
+// header line takes, failing once the head passes `max_bytes` or if the connection closes
+// partway through. Empty if the connection was closed before anything arrived. When `lenient`, a reply that doesn't start with a
+// status line gets an HTTP/0.9 head, leaving all of it as the body.
+pub(crate) fn read_head_bytes<R: BufRead>(stream: &mut R, lenient: bool, max_bytes: usize) -> io::Result<Vec<u8>> {
+    if lenient {
+        let start = stream.fill_buf()?;
+        if !start.is_empty() && is_http09(start) {
+            return Ok(format!("{}\r\n\r\n", HTTP09_STATUS_LINE).into_bytes());
+        }
+    }
+    let mut head = scratch::take();
+    loop {
+        let start = head.len();
+        let read = stream.by_ref().take((max_bytes + 1 - start) as u64).read_until(b'\n', &mut head)?;
+        if head.len() > max_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Response head is larger than {} bytes", max_bytes),
+            ));
+        }
+        if read == 0 && !head.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Connection closed after {} bytes of the response head, before its end", head.len()),
+            ));
+        }
+        let line = &head[start..];
+        if read == 0 || line == b"\r\n" || line == b"\n" {
+            return Ok(head);
+        }
+    }
+}
+
+// read_head_bytes, passing over interim 1xx responses (RFC 9110 section 15.2) other than a
+// 101, after which the connection speaks another protocol
+pub(crate) fn read_final_head<R: BufRead>(stream: &mut R, lenient: bool, max_bytes: usize) -> io::Result<Vec<u8>> {
+    loop {
+        let head = read_head_bytes(stream, lenient, max_bytes)?;
+        if !is_interim(&head) {
+            return Ok(head);
+        }
+        scratch::give(head);
+    }
+}
+
+// "HTTP/1.1 1xx", leaving out 101
+pub(crate) fn is_interim(head: &[u8]) -> bool {
+    let code = head.get(9..12).unwrap_or_default();
+    head.starts_with(b"HTTP/") && code.starts_with(b"1") && code != b"101" && code.iter().all(u8::is_ascii_digit)
+}
+
+// Carried inside an io::Error from a Content-Length body that ended early
+#[derive(Debug)]
+struct Truncated {
+    expected: u64,
+    received: u64,
+}
+
+impl fmt::Display for Truncated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "body ended after {} of {} bytes", self.received, self.expected)
+    }
+}
+
+impl std::error::Error for Truncated {}
+
+// Expected and received byte counts if `e` reports a truncated body
+pub(crate) fn truncated(e: &io::Error) -> Option<(u64, u64)> {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<Truncated>())
+        .map(|t| (t.expected, t.received))
+}
+
+// Called when a Content-Length body reads nothing more: fails if that is short of the length
+fn check_length(reader: &Take<BufReader<TimedStream>>, head: &HttpResponse, allow_truncated: bool) -> io::Result<()> {
+    if reader.limit() == 0 || allow_truncated {
+        return Ok(());
+    }
+    let expected = head.content_length().unwrap_or(0);
+    Err(io::Error::new(io::ErrorKind::UnexpectedEof, Truncated { expected, received: expected - reader.limit() }))
+}
+
+impl Read for ResponseReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = match &mut self.body {
+            Body::Chunked(reader) => reader.read(buf)?,
+            Body::Length(reader) => {
+                let n = reader.read(buf)?;
+                if n == 0 && !buf.is_empty() {
+                    check_length(reader, &self.head, self.allow_truncated)?;
+                }
+                n
+            }
+            Body::Close(reader) => reader.read(buf)?,
+            Body::Empty(_) => 0,
+            Body::Buffered(reader) => reader.read(buf)?,
+        };
+        if let Some(verifier) = &mut self.verifier {
+            verifier.update(&buf[..n]);
+            if n == 0 && !buf.is_empty() {
+                self.check_digest()?;
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl BufRead for ResponseReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let at_end = match &mut self.body {
+            Body::Chunked(reader) => reader.fill_buf()?.is_empty(),
+            Body::Length(reader) => {
+                if reader.limit() > 0 && reader.get_mut().fill_buf()?.is_empty() {
+                    check_length(reader, &self.head, self.allow_truncated)?;
+                }
+                reader.fill_buf()?.is_empty()
+            }
+            Body::Close(reader) => reader.fill_buf()?.is_empty(),
+            Body::Empty(_) => true,
+            Body::Buffered(reader) => reader.fill_buf()?.is_empty(),
+        };
+        if at_end {
+            self.check_digest()?;
+        }
+        // Buffered by now, so this doesn't read again
+        self.body.buffered()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(verifier) = &mut self.verifier
+            && let Ok(buffered) = self.body.buffered()
+        {
+            verifier.update(&buffered[..amt.min(buffered.len())]);
+        }
+        match &mut self.body {
+            Body::Chunked(reader) => reader.consume(amt),
+            Body::Length(reader) => reader.consume(amt),
+            Body::Close(reader) => reader.consume(amt),
+            Body::Empty(_) => {}
+            Body::Buffered(reader) => reader.consume(amt),
+        }
+    }
+}
+
+impl fmt::Debug for ResponseReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let framing = match self.body {
+            Body::Chunked(_) => "chunked",
+            Body::Length(_) => "content-length",
+            Body::Close(_) => "close",
+            Body::Empty(_) => "none",
+            Body::Buffered(_) => "buffered",
+        };
+        f.debug_struct("ResponseReader")
+            .field("head", &self.head)
+            .field("body", &framing)
+            .finish()
+    }
+}
+
+// Lines longer than this are an error rather than buffered without bound
+const DEFAULT_MAX_LINE_LEN: usize = 1024 * 1024;
+
+// Iterator returned by ResponseReader::lines_iter(); stops after the first error
+#[derive(Debug)]
+pub struct Lines {
+    reader: ResponseReader,
+    done: bool,
+    max_line_len: usize,
+}
+
+impl Lines {
+    // Fail on a line longer than `max` bytes (1 MiB by default), not counting the newline
+    pub fn max_line_len(mut self, max: usize) -> Lines {
+        self.max_line_len = max;
+        self
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, HttpError> {
+        let mut line = Vec::new();
+        loop {
+            let available = self.reader.fill_buf()
+                .map_err(|e| HttpError::from_io("Failed to read response", e))?;
+            if available.is_empty() {
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            let (taken, complete) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), false),
+            };
+            // Room for the CRLF on top of the limit
+            if line.len() + taken > self.max_line_len + 2 {
+                return Err(self.too_long());
+            }
+            line.extend_from_slice(&available[..taken]);
+            self.reader.consume(taken);
+            if complete {
+                break;
+            }
+        }
+
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        if line.len() > self.max_line_len {
+            return Err(self.too_long());
+        }
+        String::from_utf8(line)
+            .map(Some)
+            .map_err(|e| HttpError::InvalidResponse(format!("Line is not valid UTF-8: {}", e)))
+    }
+
+    fn too_long(&self) -> HttpError {
+        HttpError::InvalidResponse(format!("Line longer than {} bytes", self.max_line_len))
+    }
+}
+
+impl Iterator for Lines {
+    type Item = Result<String, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_line() {
+            Ok(Some(line)) => Some(Ok(line)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+// Iterator returned by ResponseReader::chunks(); stops after the first error
+#[derive(Debug)]
+pub struct Chunks {
+    reader: ResponseReader,
+    size: usize,
+    done: bool,
+}
+
+impl Chunks {
+    // Up to `size` bytes, fewer only at the end of the body; only the first 64 KiB is
+    // preallocated, so a huge size grows the buffer as the body arrives
+    fn read_chunk(&mut self) -> Result<Vec<u8>, HttpError> {
+        let mut chunk = Vec::with_capacity(self.size.min(64 * 1024));
+        (&mut self.reader).take(self.size as u64).read_to_end(&mut chunk)
+            .map_err(|e| HttpError::from_io("Failed to read response", e))?;
+        Ok(chunk)
+    }
+}
+
+impl Iterator for Chunks {
+    type Item = Result<Vec<u8>, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_chunk() {
+            Ok(chunk) if chunk.is_empty() => {
+                self.done = true;
+                None
+            }
+            Ok(chunk) => Some(Ok(chunk)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+// Iterator returned by ResponseReader::json_lines(); a line that doesn't parse is yielded as
+// an error and the lines after it still are read, while an error reading the body ends it
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub struct JsonLines<T> {
+    lines: Lines,
+    item: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "json")]
+impl<T> JsonLines<T> {
+    // See Lines::max_line_len
+    pub fn max_line_len(mut self, max: usize) -> JsonLines<T> {
+        self.lines = self.lines.max_line_len(max);
+        self
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: TryFrom<Json>> Iterator for JsonLines<T>
+where
+    T::Error: fmt::Display,
+{
+    type Item = Result<T, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(Json::parse(&line).and_then(|json| {
+                T::try_from(json).map_err(|e| HttpError::InvalidResponse(format!("Unexpected JSON line: {}", e)))
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(body: &[u8]) -> ResponseReader {
+        ResponseReader::from_response(HttpResponse::new(200, &[], body))
+    }
+
+    #[test]
+    fn lines_drop_their_endings_and_keep_an_unterminated_last_line() {
+        let lines: Vec<String> = reader(b"one\r\ntwo\n\nlast").lines_iter().map(Result::unwrap).collect();
+        assert_eq!(lines, ["one", "two", "", "last"]);
+        assert_eq!(reader(b"").lines_iter().count(), 0);
+    }
+
+    #[test]
+    fn a_line_over_the_limit_ends_the_iterator_with_an_error() {
+        let mut lines = reader(b"short\r\nmuch too long\nnever read\n").lines_iter().max_line_len(5);
+        assert_eq!(lines.next().unwrap().unwrap(), "short");
+        assert!(matches!(lines.next(), Some(Err(HttpError::InvalidResponse(message))) if message.contains("longer than 5")));
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn invalid_utf8_lines_are_errors() {
+        let mut lines = reader(b"\xff\xfe\n").lines_iter();
+        assert!(matches!(lines.next(), Some(Err(HttpError::InvalidResponse(_)))));
+    }
+
+    #[cfg(feature = "json")]
+    struct Event(String);
+
+    #[cfg(feature = "json")]
+    impl TryFrom<Json> for Event {
+        type Error = String;
+
+        fn try_from(json: Json) -> Result<Event, String> {
+            json.get("event").and_then(Json::as_str).map(|event| Event(event.to_string())).ok_or_else(|| format!("no event in {}", json))
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_lines_skip_blank_lines_and_convert_each_item() {
+        let events: Vec<String> = reader(b"{\"event\":\"a\"}\r\n\n  \n{\"event\":\"b\"}")
+            .json_lines::<Event>()
+            .map(|event| event.unwrap().0)
+            .collect();
+        assert_eq!(events, ["a", "b"]);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_lines_report_bad_json_and_unexpected_items() {
+        let mut items = reader(b"{\"event\":\"a\"}\n{\"other\":1}\n{not json\n").json_lines::<Event>();
+        assert!(items.next().unwrap().is_ok());
+        assert!(matches!(items.next(), Some(Err(HttpError::InvalidResponse(message))) if message.contains("no event")));
+        assert!(items.next().unwrap().is_err());
+        assert!(items.next().is_none());
+
+        let mut long = reader(b"{\"event\":\"far too long\"}\n").json_lines::<Json>().max_line_len(8);
+        assert!(matches!(long.next(), Some(Err(HttpError::InvalidResponse(message))) if message.contains("longer than 8")));
+    }
+
+    fn head_with(value: &str) -> Vec<u8> {
+        format!("HTTP/1.1 200 OK\r\nX-Big: {}\r\nContent-Length: 0\r\n\r\nBODY", value).into_bytes()
+    }
+
+    #[test]
+    fn a_header_line_spanning_many_reads_stays_whole() {
+        let value = "j".repeat(64 * 1024);
+        let input = head_with(&value);
+        // A small buffer makes every line take many reads
+        let mut stream = io::BufReader::with_capacity(16, &input[..]);
+        let head = read_head_bytes(&mut stream, false, 128 * 1024).unwrap();
+        assert_eq!(head, &input[..input.len() - 4]);
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"BODY");
+    }
+
+    #[test]
+    fn a_head_past_the_limit_or_cut_short_is_an_error() {
+        let input = head_with(&"j".repeat(1000));
+        let error = read_head_bytes(&mut &input[..], false, 512).unwrap_err();
+        assert!(error.to_string().contains("larger than 512 bytes"), "{}", error);
+        // Exactly at the limit is fine
+        let head_len = input.len() - 4;
+        assert!(read_head_bytes(&mut &input[..], false, head_len).is_ok());
+
+        let error = read_head_bytes(&mut &input[..100], false, 4096).unwrap_err();
+        assert!(error.to_string().contains("before its end"), "{}", error);
+        assert!(read_head_bytes(&mut &b""[..], false, 4096).unwrap().is_empty());
+    }
+
+    #[test]
+    fn interim_heads_are_passed_over_except_101() {
+        let mut stream = &b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </a>\r\n\r\nHTTP/1.1 200 OK\r\n\r\n"[..];
+        assert_eq!(read_final_head(&mut stream, false, 1024).unwrap(), b"HTTP/1.1 200 OK\r\n\r\n");
+        let mut upgrade = &b"HTTP/1.1 101 Switching Protocols\r\n\r\nframes"[..];
+        assert_eq!(read_final_head(&mut upgrade, false, 1024).unwrap(), b"HTTP/1.1 101 Switching Protocols\r\n\r\n");
+        assert!(!is_interim(b"HTTP/1.1 1x0 Odd\r\n\r\n") && !is_interim(b"HTTP/1.1 200 OK\r\n\r\n") && !is_interim(b"HTTP/1.1"));
+    }
+}
@@ -0,0 +1,112 @@
+// Keeping credentials out of Debug and Display output; what goes on the wire is unaffected
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::response::decode_field;
+
+// Shown in place of a sensitive header value
+pub(crate) const MASK: &str = "***";
+
+// Names added by mark_sensitive_header(), on top of the built-in ones
+static MARKED: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+// Treat `name` as carrying credentials wherever this crate prints or records headers:
+// Debug and Display output, to_curl() and cassettes. Applies to the whole process.
+pub fn mark_sensitive_header(name: &str) {
+    let mut marked = MARKED.write().unwrap_or_else(|e| e.into_inner());
+    if !marked.iter().any(|existing| existing.eq_ignore_ascii_case(name)) {
+        marked.push(name.to_string());
+    }
+}
+
+// Headers that carry credentials
+pub(crate) fn is_sensitive_header(name: &str) -> bool {
+    let builtin = ["Authorization", "Proxy-Authorization", "Cookie", "Set-Cookie"]
+        .iter()
+        .any(|sensitive| sensitive.eq_ignore_ascii_case(name));
+    builtin || MARKED.read().unwrap_or_else(|e| e.into_inner()).iter().any(|marked| marked.eq_ignore_ascii_case(name))
+}
+
+// The value as it may be shown
+pub(crate) fn value<'v>(name: &str, value: &'v str) -> &'v str {
+    if is_sensitive_header(name) { MASK } else { value }
+}
+
+// A "Name: value" header line as it may be shown
+pub(crate) fn line(line: &str) -> Cow<'_, str> {
+    match line.split_once(':') {
+        Some((name, _)) if is_sensitive_header(name.trim()) => Cow::Owned(format!("{}: {}", name, MASK)),
+        _ => Cow::Borrowed(line),
+    }
+}
+
+// Debug for (name, value) pairs
+pub(crate) struct Headers<'h>(pub(crate) &'h [(String, String)]);
+
+impl fmt::Debug for Headers<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(|(name, v)| (name, value(name, v)))).finish()
+    }
+}
+
+// Debug for raw header lines
+pub(crate) struct Lines<'h>(pub(crate) &'h [String]);
+
+impl fmt::Debug for Lines<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(|l| line(l))).finish()
+    }
+}
+
+// Debug for a serialized request: the head as text, then the size of the body
+pub(crate) struct Request<'r>(pub(crate) &'r [u8]);
+
+impl fmt::Debug for Request<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let end = self.0.windows(4).position(|w| w == b"\r\n\r\n").map_or(self.0.len(), |i| i + 4);
+        let mut text = String::new();
+        for head_line in self.0[..end].split(|&b| b == b'\n') {
+            let head_line = decode_field(head_line.strip_suffix(b"\r").unwrap_or(head_line));
+            if !head_line.is_empty() {
+                text.push_str(&line(&head_line));
+                text.push_str("\r\n");
+            }
+        }
+        text.push_str("\r\n");
+        if end < self.0.len() {
+            text.push_str(&format!("<{} bytes of body>", self.0.len() - end));
+        }
+        fmt::Debug::fmt(&text, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credential_headers_are_masked_whatever_their_case() {
+        for name in ["authorization", "Proxy-Authorization", "COOKIE", "set-cookie"] {
+            assert_eq!(value(name, "secret"), MASK, "{}", name);
+        }
+        assert_eq!(value("X-Trace", "visible"), "visible");
+        assert_eq!(line("Set-Cookie: sid=1; HttpOnly"), "Set-Cookie: ***");
+        assert_eq!(line("Accept: */*"), "Accept: */*");
+    }
+
+    #[test]
+    fn marked_headers_are_masked_too() {
+        assert!(!is_sensitive_header("X-Redact-Unit-Test"));
+        mark_sensitive_header("x-redact-unit-test");
+        mark_sensitive_header("X-Redact-Unit-Test");
+        assert!(is_sensitive_header("X-REDACT-UNIT-TEST"));
+        assert_eq!(MARKED.read().unwrap().iter().filter(|m| m.eq_ignore_ascii_case("X-Redact-Unit-Test")).count(), 1);
+    }
+
+    #[test]
+    fn requests_show_their_head_redacted_and_only_the_body_size() {
+        let shown = format!("{:?}", Request(b"POST / HTTP/1.1\r\nAuthorization: Bearer abc\r\nHost: h\r\n\r\nsecret body"));
+        assert_eq!(shown, "\"POST / HTTP/1.1\\r\\nAuthorization: ***\\r\\nHost: h\\r\\n\\r\\n<11 bytes of body>\"");
+    }
+}
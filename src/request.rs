@@ -0,0 +1,581 @@
+use std::io::Write;
+use std::net::SocketAddr;
+
+use crate::auth::Credentials;
+use crate::error::HttpError;
+use crate::headers::HeaderList;
+use crate::method::Method;
+use crate::mime;
+use crate::redact::{self, is_sensitive_header};
+#[cfg(feature = "json")]
+use crate::response::decode_field;
+use crate::response::find_head_end;
+use crate::scratch;
+use crate::url;
+
+// Serialize a request exactly as it would be written to the socket, without sending it.
+// A body of Some("") is still a body and goes out with Content-Length: 0, which some servers
+// require on a POST; None sends no Content-Length at all.
+pub fn build_request(
+    method: impl Into<Method>,
+    host: &str,
+    path: &str,
+    body: Option<&str>,
+    custom_headers: impl Into<HeaderList>
+) -> Result<Vec<u8>, HttpError> {
+    let headers = custom_headers.into();
+    for (name, value) in headers.iter() {
+        require_ascii(name, value)?;
+    }
+    build_request_from(method.into().as_str(), host, path, body.map(str::as_bytes), headers.as_slice())
+}
+
+// build_request with the custom headers emitted in the given order
+pub(crate) fn build_request_from(
+    method: &str,
+    host: &str,
+    path: &str,
+    body: Option<&[u8]>,
+    custom_headers: &[(String, String)]
+) -> Result<Vec<u8>, HttpError> {
+    // The asterisk-form target ("OPTIONS * HTTP/1.1") addresses the server itself
+    if path == "*" && method != "OPTIONS" {
+        return Err(HttpError::InvalidRequest(format!("{} does not accept the \"*\" request target", method)));
+    }
+    validate_request_line(method, path)?;
+    if method == "TRACE" && body.is_some() {
+        return Err(HttpError::InvalidRequest("A TRACE request can't have a body".to_string()));
+    }
+
+    // Written piece by piece into a reused buffer rather than through temporary strings
+    let mut request = scratch::take();
+
+    // Build request line
+    for part in [method, " ", path, " HTTP/1.1\r\nHost: ", &url::ascii_authority(host)?, "\r\n"] {
+        request.extend_from_slice(part.as_bytes());
+    }
+
+    // Content-Length and Content-Type if body exists; ones of the caller's are sent instead.
+    // The type is guessed from the body (mime::sniff) unless it is empty.
+    if let Some(body_content) = body {
+        let given = |name: &str| custom_headers.iter().any(|(key, _)| key.eq_ignore_ascii_case(name));
+        if !given("Content-Length") {
+            let _ = write!(request, "Content-Length: {}\r\n", body_content.len());
+        }
+        if !given("Content-Type") && !body_content.is_empty() {
+            let _ = write!(request, "Content-Type: {}\r\n", mime::sniff(body_content));
+        }
+    }
+
+    for (key, value) in custom_headers {
+        validate_header(key, value)?;
+        request.extend_from_slice(key.as_bytes());
+        request.extend_from_slice(b": ");
+        // One byte per character: ISO-8859-1, which for ASCII is the same bytes
+        request.extend(value.chars().map(|c| c as u8));
+        request.extend_from_slice(b"\r\n");
+    }
+
+    // A Connection header of the caller's (e.g. keep-alive for the pool) replaces the default
+    if !custom_headers.iter().any(|(key, _)| key.eq_ignore_ascii_case("Connection")) {
+        request.extend_from_slice(b"Connection: close\r\n");
+    }
+    request.extend_from_slice(b"\r\n");
+
+    // body if it exists
+    if let Some(body_content) = body {
+        request.extend_from_slice(body_content);
+    }
+
+    Ok(request)
+}
+
+// Take out the Host line build_request_from writes after the request line
+pub(crate) fn remove_host_header(request: &mut Vec<u8>) {
+    let Some(line_end) = request.windows(2).position(|w| w == b"\r\n") else {
+        return;
+    };
+    let start = line_end + 2;
+    if request[start..].starts_with(b"Host: ")
+        && let Some(length) = request[start..].windows(2).position(|w| w == b"\r\n")
+    {
+        request.drain(start..start + length + 2);
+    }
+}
+
+// Turn the CRLFs of the head (up to the blank line) into bare LFs
+pub(crate) fn end_head_lines_with_lf(request: &mut Vec<u8>) {
+    let head_end = request.windows(4).position(|w| w == b"\r\n\r\n").map_or(request.len(), |i| i + 4);
+    let mut head: Vec<u8> = request.drain(..head_end).collect();
+    head.retain(|&b| b != b'\r');
+    request.splice(..0, head);
+}
+
+// The request as HTTP/1.0, for RequestConfig::http10_fallback: that version in the request
+// line and no Connection header, as closing after the response is HTTP/1.0's default.
+// Whatever else the head has is left, Host included, which HTTP/1.0 servers ignore or need
+// for virtual hosts.
+pub(crate) fn downgrade_to_http10(request: &mut Vec<u8>) {
+    let head_end = find_head_end(request).map_or(request.len(), |(_, body_start)| body_start);
+    let mut head = Vec::with_capacity(head_end);
+    for (i, line) in request[..head_end].split_inclusive(|&b| b == b'\n').enumerate() {
+        if i > 0 && line.len() >= 11 && line[..11].eq_ignore_ascii_case(b"Connection:") {
+            continue;
+        }
+        let start = head.len();
+        head.extend_from_slice(line);
+        if i == 0 && let Some(at) = line.windows(8).rposition(|w| w == b"HTTP/1.1") {
+            head[start + at + 7] = b'0';
+        }
+    }
+    request.splice(..head_end, head);
+}
+
+// Where the body of `request` starts, if its head has Expect: 100-continue (RFC 9110
+// section 10.1.1), so the body can wait for the server's go-ahead
+pub(crate) fn expects_continue(request: &[u8]) -> Option<usize> {
+    let (head_end, body_start) = find_head_end(request)?;
+    let head = String::from_utf8_lossy(&request[..head_end]);
+    head.lines().skip(1).filter_map(|line| line.split_once(':')).any(|(name, value)| {
+        name.trim().eq_ignore_ascii_case("Expect") && value.trim().eq_ignore_ascii_case("100-continue")
+    }).then_some(body_start)
+}
+
+// A request as it went out, from RequestBuilder::send_debug()
+#[derive(Clone, PartialEq, Eq)]
+pub struct SentRequest {
+    // Exactly what was written to the socket; only the head for a streamed body
+    pub bytes: Vec<u8>,
+    // The address connected to (the proxy's when going through one); None for a
+    // response replayed from a cassette
+    pub address: Option<SocketAddr>,
+}
+
+impl std::fmt::Debug for SentRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SentRequest")
+            .field("bytes", &redact::Request(&self.bytes))
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+// A whole request with a buffered body, from RequestBuilder::build(), that can be sent any
+// number of times with Client::execute() and cloned to send variations of it
+#[derive(Clone, PartialEq, Eq)]
+pub struct Request {
+    pub(crate) method: Method,
+    pub(crate) host: String,
+    pub(crate) path: String,
+    // Written in this order, after the client's defaults
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Option<String>,
+}
+
+impl Request {
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // The request's own headers in order; the client's default headers aren't included
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    // First header with this name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+}
+
+impl std::fmt::Debug for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Request")
+            .field("method", &self.method)
+            .field("host", &self.host)
+            .field("path", &self.path)
+            .field("headers", &redact::Headers(&self.headers))
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+// The parts many requests share, e.g. the host, a base path and an Authorization header, to
+// turn into a Request for each path and body with instantiate(), which also reports invalid
+// headers.
+//
+//     let api = RequestTemplate::new(Method::Get, "api.example.com").base_path("/v2")
+//         .credentials(&Credentials::bearer(token));
+//     client.execute(&api.instantiate("/users", None)?)?;
+#[derive(Clone)]
+pub struct RequestTemplate {
+    method: Method,
+    host: String,
+    base_path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl RequestTemplate {
+    pub fn new(method: impl Into<Method>, host: &str) -> RequestTemplate {
+        RequestTemplate { method: method.into(), host: host.to_string(), base_path: String::new(), headers: Vec::new() }
+    }
+
+    // Put in front of every instantiated path, e.g. "/api/v2"; slashes where the two meet
+    // are taken care of
+    pub fn base_path(mut self, base_path: &str) -> RequestTemplate {
+        self.base_path = base_path.trim_end_matches('/').to_string();
+        self
+    }
+
+    // Sent with every request, before any added to an instantiated one. The value must be
+    // visible ASCII, as with RequestBuilder::header().
+    pub fn header(mut self, name: &str, value: &str) -> RequestTemplate {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // An Authorization header for these credentials on every request
+    pub fn credentials(self, credentials: &Credentials) -> RequestTemplate {
+        self.header("Authorization", &credentials.authorization())
+    }
+
+    // A request for `path` under the base path, with `body` if given
+    pub fn instantiate(&self, path: &str, body: Option<&str>) -> Result<Request, HttpError> {
+        for (name, value) in &self.headers {
+            require_ascii(name, value)?;
+        }
+        let path = if self.base_path.is_empty() || path.is_empty() || path.starts_with(['/', '?']) {
+            format!("{}{}", self.base_path, path)
+        } else {
+            format!("{}/{}", self.base_path, path)
+        };
+        Ok(Request {
+            method: self.method.clone(),
+            host: self.host.clone(),
+            path,
+            headers: self.headers.clone(),
+            body: body.map(str::to_string),
+        })
+    }
+}
+
+impl std::fmt::Debug for RequestTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RequestTemplate")
+            .field("method", &self.method)
+            .field("host", &self.host)
+            .field("base_path", &self.base_path)
+            .field("headers", &redact::Headers(&self.headers))
+            .finish()
+    }
+}
+
+// Equivalent curl command line for a request, for reproducing problems outside this client.
+// Headers come out in the order given. Credential headers are replaced by a placeholder
+// unless `include_secrets` is set.
+pub fn to_curl(
+    method: impl Into<Method>,
+    host: &str,
+    path: &str,
+    body: Option<&str>,
+    custom_headers: impl Into<HeaderList>,
+    include_secrets: bool
+) -> String {
+    let custom = custom_headers.into();
+    let mut command = format!("curl -X {} {}", shell_quote(method.into().as_str()), shell_quote(&format!("http://{}{}", host, path)));
+
+    let mut headers: Vec<(&str, &str)> = Vec::new();
+    let typed = custom.get("Content-Type").is_some();
+    if let Some(body) = body.filter(|body| !typed && !body.is_empty()) {
+        headers.push(("Content-Type", mime::sniff(body.as_bytes())));
+    }
+    headers.extend(custom.iter());
+
+    for (name, value) in headers {
+        let value = if !include_secrets && is_sensitive_header(name) { "<redacted>" } else { value };
+        command.push_str(&format!(" -H {}", shell_quote(&format!("{}: {}", name, value))));
+    }
+
+    if let Some(body_content) = body {
+        command.push_str(&format!(" --data-raw {}", shell_quote(body_content)));
+    }
+
+    command
+}
+
+// Single-quote for a POSIX shell; embedded quotes become '\''
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Refuse a method that isn't a token, and a request target with spaces or control
+// characters: either would end the request line early and let the rest pass as headers
+fn validate_request_line(method: &str, target: &str) -> Result<(), HttpError> {
+    if !is_token(method) {
+        return Err(HttpError::InvalidRequest(format!("Invalid method: {:?}", method)));
+    }
+    if target.bytes().any(|b| b.is_ascii_control() || b == b' ') {
+        return Err(HttpError::InvalidRequest(format!("Request target contains a space or control character: {:?}", target)));
+    }
+    Ok(())
+}
+
+// A token (RFC 9110 section 5.6.2), such as a method or a header name
+fn is_token(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+// Refuse header names and values that would break out of their line, or that have
+// characters the ISO-8859-1 encoding of values on the wire can't represent
+pub(crate) fn validate_header(name: &str, value: &str) -> Result<(), HttpError> {
+    if !is_token(name) {
+        return Err(HttpError::InvalidHeader(format!("Invalid header name: {:?}", name)));
+    }
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(HttpError::InvalidHeader(format!("Header {} contains a line break or NUL", name)));
+    }
+    if let Some(c) = value.chars().find(|&c| u32::from(c) > 0xFF) {
+        return Err(HttpError::InvalidHeader(format!("Header {} contains {:?}, which ISO-8859-1 can't encode", name, c)));
+    }
+    Ok(())
+}
+
+// Values are only sent as given if they are visible ASCII, spaces and tabs: raw UTF-8 is
+// mangled or rejected by some servers. RequestBuilder::header_latin1() and ext_param()
+// are the explicit ways to send other text.
+pub(crate) fn require_ascii(name: &str, value: &str) -> Result<(), HttpError> {
+    match value.chars().find(|&c| c != '\t' && !(' '..='~').contains(&c)) {
+        Some(c) => Err(HttpError::InvalidHeader(format!(
+            "Header {} contains {:?}; only visible ASCII is sent as given", name, c
+        ))),
+        None => Ok(()),
+    }
+}
+
+// A header parameter in RFC 8187 form, for text that isn't plain ASCII: ext_param("filename",
+// "€ rates.pdf") is filename*=UTF-8''%E2%82%AC%20rates.pdf, as used in
+// .header("Content-Disposition", &format!("attachment; {}", ext_param("filename", name)))
+pub fn ext_param(name: &str, value: &str) -> String {
+    let mut param = format!("{}*=UTF-8''", name);
+    for byte in value.bytes() {
+        // attr-char (RFC 8187 section 3.2.1)
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            param.push(char::from(byte));
+        } else {
+            param.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    param
+}
+
+// The parts of a serialized request, for recording what was actually sent
+#[cfg(feature = "json")]
+pub(crate) struct ParsedRequest<'a> {
+    pub(crate) method: String,
+    pub(crate) target: String,
+    pub(crate) version: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: &'a [u8],
+    // Bytes up to and including the blank line
+    pub(crate) head_size: usize,
+}
+
+#[cfg(feature = "json")]
+impl ParsedRequest<'_> {
+    pub(crate) fn parse(request: &[u8]) -> ParsedRequest<'_> {
+        let (head, body) = match request.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(i) => (&request[..i], &request[i + 4..]),
+            None => (request, &[][..]),
+        };
+        let mut lines = head.split(|&b| b == b'\n')
+            .map(|line| decode_field(line.strip_suffix(b"\r").unwrap_or(line)));
+        let request_line = lines.next().unwrap_or_default();
+        let mut request_line = request_line.split(' ');
+        let method = request_line.next().unwrap_or("").to_string();
+        let target = request_line.next().unwrap_or("").to_string();
+        let version = request_line.next().unwrap_or("HTTP/1.1").to_string();
+
+        let headers = lines
+            .filter_map(|line| line.split_once(':').map(|(name, value)| (name.trim().to_string(), value.trim().to_string())))
+            .collect();
+
+        ParsedRequest { method, target, version, headers, body, head_size: head.len() + 4 }
+    }
+
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn url(&self) -> String {
+        format!("http://{}{}", self.header("Host").unwrap_or(""), self.target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str) -> Result<Vec<u8>, HttpError> {
+        build_request(method, "example.com", path, None, HeaderList::new())
+    }
+
+    #[test]
+    fn extension_methods_that_are_tokens_are_sent() {
+        let request = request("PROPFIND", "/dav").unwrap();
+        assert!(request.starts_with(b"PROPFIND /dav HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn methods_that_are_not_tokens_are_refused() {
+        for method in ["GET\r\nX-Evil: 1\r\n", "GET /", "", "G(ET", "GÉT"] {
+            assert!(matches!(request(method, "/"), Err(HttpError::InvalidRequest(_))), "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn targets_with_spaces_or_controls_are_refused() {
+        for path in ["/x HTTP/1.1\r\nX-Injected: 1\r\nY:", "/a b", "/a\tb", "/a\0", "/\x7f"] {
+            assert!(matches!(request("GET", path), Err(HttpError::InvalidRequest(_))), "{:?}", path);
+        }
+        assert!(request("GET", "/search?q=a%20b&x=[1]").is_ok());
+    }
+
+    #[test]
+    fn curl_command_keeps_repeated_headers_in_order() {
+        let headers = [("X-Trace", "a"), ("Accept", "*/*"), ("X-Trace", "b")];
+        assert_eq!(
+            to_curl("GET", "example.com", "/", None, headers, false),
+            "curl -X 'GET' 'http://example.com/' -H 'X-Trace: a' -H 'Accept: */*' -H 'X-Trace: b'"
+        );
+    }
+
+    #[test]
+    fn curl_command_hides_credentials_and_types_the_body() {
+        let headers = [("Authorization", "Bearer secret")];
+        let command = to_curl("POST", "example.com", "/", Some("{\"a\":1}"), headers, false);
+        assert!(command.contains("-H 'Content-Type: application/json' -H 'Authorization: <redacted>'"), "{}", command);
+        assert!(to_curl("GET", "example.com", "/", None, headers, true).contains("Bearer secret"));
+    }
+
+    #[test]
+    fn asterisk_form_is_only_for_options() {
+        let sent = request("OPTIONS", "*").unwrap();
+        assert!(sent.starts_with(b"OPTIONS * HTTP/1.1\r\nHost: example.com\r\n"));
+        assert!(matches!(request("GET", "*"), Err(HttpError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn curl_quotes_single_quotes_and_newlines() {
+        let command = to_curl("POST", "example.com", "/q?b='x'", Some("it's\nmultiline"), [("Content-Type", "text/plain"), ("X-Note", "don't")], false);
+        assert_eq!(
+            command,
+            r#"curl -X 'POST' 'http://example.com/q?b='\''x'\''' -H 'Content-Type: text/plain' -H 'X-Note: don'\''t' --data-raw 'it'\''s"#
+                .to_string() + "\nmultiline'"
+        );
+    }
+
+    #[test]
+    fn only_visible_ascii_spaces_and_tabs_pass_as_given() {
+        assert!(require_ascii("X-A", "plain value\twith tab ~!").is_ok());
+        for value in ["café", "🙂", "bell\u{7}", "del\u{7f}"] {
+            assert!(matches!(require_ascii("X-A", value), Err(HttpError::InvalidHeader(_))), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn ext_params_percent_encode_utf8() {
+        assert_eq!(ext_param("filename", "résumé.pdf"), "filename*=UTF-8''r%C3%A9sum%C3%A9.pdf");
+        assert_eq!(ext_param("filename", "€ rates.pdf"), "filename*=UTF-8''%E2%82%AC%20rates.pdf");
+        assert_eq!(ext_param("title", "a'b\"c;d"), "title*=UTF-8''a%27b%22c%3Bd");
+    }
+
+    #[test]
+    fn remove_host_header_takes_out_only_the_host_line() {
+        let mut request = b"GET / HTTP/1.1\r\nHost: example.com\r\nX-Host: kept\r\n\r\n".to_vec();
+        remove_host_header(&mut request);
+        assert_eq!(request, b"GET / HTTP/1.1\r\nX-Host: kept\r\n\r\n");
+        // A request without a Host line right after the request line is left alone
+        remove_host_header(&mut request);
+        assert_eq!(request, b"GET / HTTP/1.1\r\nX-Host: kept\r\n\r\n");
+        let mut no_line_end = b"GET / HTTP/1.1".to_vec();
+        remove_host_header(&mut no_line_end);
+        assert_eq!(no_line_end, b"GET / HTTP/1.1");
+    }
+
+    #[test]
+    fn template_paths_join_with_one_slash() {
+        let template = RequestTemplate::new(Method::Get, "api.example.com").base_path("/v2/");
+        let path = |path: &str| template.instantiate(path, None).unwrap().path().to_string();
+        assert_eq!(path("/users"), "/v2/users");
+        assert_eq!(path("users"), "/v2/users");
+        assert_eq!(path("?page=2"), "/v2?page=2");
+        assert_eq!(path(""), "/v2");
+        let bare = RequestTemplate::new(Method::Get, "api.example.com");
+        assert_eq!(bare.instantiate("/users", None).unwrap().path(), "/users");
+    }
+
+    #[test]
+    fn template_headers_are_checked_and_masked() {
+        let template = RequestTemplate::new(Method::Post, "api.example.com")
+            .credentials(&Credentials::bearer("TOKEN"))
+            .header("Accept", "application/json");
+        let request = template.instantiate("/items", Some("{}")).unwrap();
+        assert_eq!(request.header("Authorization"), Some("Bearer TOKEN"));
+        assert_eq!(request.header("accept"), Some("application/json"));
+        assert_eq!((request.method(), request.host(), request.body()), (&Method::Post, "api.example.com", Some("{}")));
+        assert!(!format!("{:?}", template).contains("TOKEN"));
+
+        let bad = RequestTemplate::new(Method::Get, "api.example.com").header("X-Name", "J\u{fc}rgen");
+        assert!(matches!(bad.instantiate("/", None), Err(HttpError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn only_the_head_loses_its_crs() {
+        let mut request = b"POST / HTTP/1.1\r\nHost: h\r\nContent-Length: 4\r\n\r\na\r\nb".to_vec();
+        end_head_lines_with_lf(&mut request);
+        assert_eq!(request, b"POST / HTTP/1.1\nHost: h\nContent-Length: 4\n\na\r\nb");
+        // A head alone (a streamed body follows separately) is converted the same way
+        let mut head = b"GET / HTTP/1.1\r\nHost: h\r\n\r\n".to_vec();
+        end_head_lines_with_lf(&mut head);
+        assert_eq!(head, b"GET / HTTP/1.1\nHost: h\n\n");
+    }
+
+    #[test]
+    fn an_empty_body_has_a_zero_length_and_no_body_has_none() {
+        let empty = String::from_utf8(build_request("POST", "example.com", "/", Some(""), HeaderList::new()).unwrap()).unwrap();
+        assert!(empty.contains("\r\nContent-Length: 0\r\n") && !empty.contains("Content-Type"), "{}", empty);
+        let none = String::from_utf8(request("POST", "/").unwrap()).unwrap();
+        assert!(!none.contains("Content-Length"), "{}", none);
+    }
+
+    #[test]
+    fn expects_continue_finds_the_body_start() {
+        let request = b"POST / HTTP/1.1\r\nHost: h\r\nexpect:  100-Continue \r\nContent-Length: 4\r\n\r\nbody";
+        assert_eq!(expects_continue(request), Some(request.len() - 4));
+        assert_eq!(expects_continue(b"POST / HTTP/1.1\r\nHost: h\r\nContent-Length: 4\r\n\r\nbody"), None);
+        assert_eq!(expects_continue(b"POST / HTTP/1.1\r\nX-Expect: 100-continue\r\n\r\n"), None);
+        // The body isn't searched
+        assert_eq!(expects_continue(b"POST / HTTP/1.1\r\nContent-Length: 22\r\n\r\nExpect: 100-continue\r\n"), None);
+    }
+
+    #[test]
+    fn downgrade_changes_the_version_and_drops_connection() {
+        let mut request = b"POST /HTTP/1.1 HTTP/1.1\r\nHost: h\r\nconnection: close\r\nX-Latin: caf\xe9\r\nContent-Length: 4\r\n\r\nConnection: body".to_vec();
+        downgrade_to_http10(&mut request);
+        assert_eq!(request, b"POST /HTTP/1.1 HTTP/1.0\r\nHost: h\r\nX-Latin: caf\xe9\r\nContent-Length: 4\r\n\r\nConnection: body");
+    }
+}
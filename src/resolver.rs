@@ -0,0 +1,84 @@
+// Turning host names into addresses, for a Client that shouldn't ask the system resolver
+// (Client::with_resolver), e.g. service discovery or a fixed map in tests
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+use crate::error::HttpError;
+
+// Gets the ASCII (punycode) form of the name, without brackets, and the port to connect
+// to. An empty list fails the connection like a name with no addresses. Candidates are
+// tried in the order given, with happy eyeballs when both families are there.
+pub trait Resolver: Send + Sync {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, HttpError>;
+}
+
+// The system resolver, as used when none is set; for a Resolver to fall back on
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, HttpError> {
+        (host, port).to_socket_addrs()
+            .map(Iterator::collect)
+            .map_err(|e| HttpError::NetworkError(format!("Failed to resolve {}: {}", host, e)))
+    }
+}
+
+// Fixed addresses per host name (case-insensitive), e.g. api.internal -> 127.0.0.1 in a
+// test. IP literals resolve to themselves; any other name fails.
+//
+//     let resolver = StaticResolver::new(HashMap::from([
+//         ("api.internal".to_string(), vec![Ipv4Addr::LOCALHOST.into()]),
+//     ]));
+//     let client = Client::new().with_resolver(resolver);
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+}
+
+impl StaticResolver {
+    pub fn new(hosts: HashMap<String, Vec<IpAddr>>) -> StaticResolver {
+        let hosts = hosts.into_iter().map(|(host, addrs)| (host.to_ascii_lowercase(), addrs)).collect();
+        StaticResolver { hosts }
+    }
+
+    // Add or replace the addresses of one host
+    pub fn with_host(mut self, host: &str, addrs: Vec<IpAddr>) -> StaticResolver {
+        self.hosts.insert(host.to_ascii_lowercase(), addrs);
+        self
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, HttpError> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        match self.hosts.get(&host.to_ascii_lowercase()) {
+            Some(addrs) => Ok(addrs.iter().map(|&ip| SocketAddr::new(ip, port)).collect()),
+            None => Err(HttpError::NetworkError(format!("Failed to resolve {}: not in the static resolver", host))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn static_names_match_case_insensitively_and_literals_resolve_to_themselves() {
+        let resolver = StaticResolver::new(HashMap::from([("API.internal".to_string(), vec![Ipv4Addr::LOCALHOST.into()])]))
+            .with_host("Dual.Internal", vec!["::1".parse().unwrap(), Ipv4Addr::LOCALHOST.into()]);
+        assert_eq!(resolver.resolve("api.INTERNAL", 8080).unwrap(), ["127.0.0.1:8080".parse().unwrap()]);
+        assert_eq!(resolver.resolve("dual.internal", 80).unwrap(), ["[::1]:80".parse().unwrap(), "127.0.0.1:80".parse().unwrap()]);
+        assert_eq!(resolver.resolve("10.0.0.1", 1).unwrap(), ["10.0.0.1:1".parse().unwrap()]);
+        assert_eq!(resolver.resolve("::1", 2).unwrap(), ["[::1]:2".parse().unwrap()]);
+        assert!(matches!(resolver.resolve("other.internal", 80), Err(HttpError::NetworkError(_))));
+    }
+
+    #[test]
+    fn the_system_resolver_handles_literals() {
+        assert_eq!(SystemResolver.resolve("127.0.0.1", 80).unwrap(), ["127.0.0.1:80".parse().unwrap()]);
+    }
+}
@@ -0,0 +1,1227 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+use crate::auth::Challenge;
+#[cfg(feature = "json")]
+use crate::base64;
+use crate::cookie::Cookie;
+use crate::date;
+use crate::error::HttpError;
+use crate::gzip;
+use crate::headers::HeaderMap;
+#[cfg(feature = "json")]
+use crate::json::Json;
+use crate::mime::{self, Mime};
+use crate::parser::{ParseEvent, ResponseParser};
+use crate::reader;
+use crate::redact;
+use crate::status::StatusCode;
+use crate::trace::EchoedRequest;
+use crate::url;
+
+// Enhanced response struct with status code parsing
+#[derive(Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status_line: String,
+    pub status_code: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    // Trailer fields sent after a chunked body, as raw lines like the headers
+    pub trailers: Vec<String>,
+    // Socket the response arrived on (None when not read from a TCP connection)
+    pub connection: Option<ConnectionInfo>,
+    // How long each phase of the exchange took (None when not read from a TCP connection)
+    pub timings: Option<Timings>,
+    // The key every attempt was sent with, when RequestConfig::idempotency_key_header is set
+    pub idempotency_key: Option<String>,
+    // The URL this answers, e.g. "http://example.com/a?b" (the last hop after redirects);
+    // None when the response wasn't received for a request
+    pub url: Option<String>,
+    // Redirects followed to get here (RequestConfig::max_redirects); 0 when there were none
+    pub redirect_count: usize,
+}
+
+// Phase durations of a single exchange, in the spirit of HAR timings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timings {
+    // Waiting for a free connection (max_connections) and looking up the host name, or taking
+    // a pooled connection; with a proxy, the proxy's name
+    pub dns: Duration,
+    // Opening the TCP connection, through a proxy tunnel if there is one
+    pub connect: Duration,
+    // Writing the request
+    pub send: Duration,
+    // From the request being written to the first response byte
+    pub wait: Duration,
+    // From the first response byte to the end of the response
+    pub receive: Duration,
+    // What went over the connection for this exchange, head and body with their framing
+    // (chunk sizes, trailers); a proxy tunnel's CONNECT isn't counted
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.dns + self.connect + self.send + self.wait + self.receive
+    }
+}
+
+// Where a response actually came from, for debugging and audit logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    pub peer_addr: SocketAddr,
+    pub local_addr: SocketAddr,
+    // Whether the connection was reused rather than newly opened
+    pub reused: bool,
+    // Whether the request was sent again on this connection because the reused one
+    // it first went out on had already been closed by the server
+    pub stale_retry: bool,
+    // Whether every option of RequestConfig::socket could be set on the connection; the
+    // request goes ahead without those that couldn't
+    pub socket_config_applied: bool,
+}
+
+impl std::fmt::Display for ConnectionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.local_addr, self.peer_addr)?;
+        if self.reused {
+            write!(f, " (reused)")?;
+        }
+        if self.stale_retry {
+            write!(f, " (retried after a stale connection)")?;
+        }
+        if !self.socket_config_applied {
+            write!(f, " (socket options not all set)")?;
+        }
+        Ok(())
+    }
+}
+
+// Sensitive header values (see mark_sensitive_header) are shown as ***
+impl std::fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status_line", &self.status_line)
+            .field("status_code", &self.status_code)
+            .field("headers", &self.headers)
+            .field("body", &self.body)
+            .field("trailers", &redact::Lines(&self.trailers))
+            .field("connection", &self.connection)
+            .field("timings", &self.timings)
+            .field("idempotency_key", &self.idempotency_key)
+            .field("url", &self.url)
+            .field("redirect_count", &self.redirect_count)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for HttpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.dump(usize::MAX))
+    }
+}
+
+impl HttpResponse {
+    // Build a response without a socket, e.g. for tests and fixtures
+    pub fn new(status_code: u16, headers: &[(&str, &str)], body: impl Into<Vec<u8>>) -> HttpResponse {
+        let reason = StatusCode(status_code).canonical_reason().unwrap_or("");
+        HttpResponse {
+            status_line: format!("HTTP/1.1 {} {}", status_code, reason).trim_end().to_string(),
+            status_code,
+            headers: headers.iter().map(|(name, value)| format!("{}: {}", name, value)).collect(),
+            body: body.into(),
+            trailers: Vec::new(),
+            connection: None,
+            timings: None,
+            idempotency_key: None,
+            url: None,
+            redirect_count: 0,
+        }
+    }
+
+    // Left behind where a response is moved out, without allocating
+    pub(crate) fn placeholder() -> HttpResponse {
+        HttpResponse {
+            status_line: String::new(),
+            status_code: 0,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            trailers: Vec::new(),
+            connection: None,
+            timings: None,
+            idempotency_key: None,
+            url: None,
+            redirect_count: 0,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode(self.status_code)
+    }
+
+    // Reason phrase from the status line, e.g. "Not Found"; empty if the server sent none
+    pub fn reason(&self) -> &str {
+        let after_version = self.status_line.trim_start().split_once(char::is_whitespace).map_or("", |(_, rest)| rest);
+        after_version.trim_start().split_once(char::is_whitespace).map_or("", |(_, reason)| reason).trim()
+    }
+
+    // First header with this name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.header_all(name).into_iter().next()
+    }
+
+    // Every value of a header that may appear more than once
+    pub fn header_all(&self, name: &str) -> Vec<&str> {
+        self.headers.get_all(name)
+    }
+
+    // The Date header: when the server produced the response
+    pub fn date(&self) -> Option<SystemTime> {
+        self.header("Date").and_then(date::parse_http_date)
+    }
+
+    // The Last-Modified header
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.header("Last-Modified").and_then(date::parse_http_date)
+    }
+
+    // The Content-Type header parsed; None when it is missing or malformed
+    pub fn content_type(&self) -> Option<Mime> {
+        self.header("Content-Type").and_then(Mime::parse)
+    }
+
+    // The file name a Content-Disposition header suggests saving the body under: its
+    // filename* (RFC 8187, UTF-8 or ISO-8859-1) when that decodes, else its filename. Only the
+    // last path component is kept, so the name can't lead out of the directory it is saved
+    // in. None when there is no such parameter, or the header is malformed.
+    pub fn suggested_filename(&self) -> Option<String> {
+        let (_, params) = mime::parse_disposition(self.header("Content-Disposition")?)?;
+        let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        let filename = param("filename*").and_then(decode_ext_value).or_else(|| param("filename").map(str::to_string))?;
+        let filename = filename.rsplit(['/', '\\']).next().unwrap_or("").trim();
+        (!matches!(filename, "" | "." | "..")).then(|| filename.to_string())
+    }
+
+    // The challenges of every WWW-Authenticate header, in order, e.g. to pick a scheme on a
+    // 401 (Client::with_credential_callback answers Basic and Bearer ones by itself)
+    pub fn auth_challenges(&self) -> Vec<Challenge> {
+        self.header_all("WWW-Authenticate").into_iter().flat_map(Challenge::parse_all).collect()
+    }
+
+    // The targets of every Link header by relation type, e.g. links()["next"] for the next
+    // page of a paginated API. Relation types are lowercased, and one listed for several
+    // targets keeps the first. Relative targets are resolved against `url` when it is set.
+    pub fn links(&self) -> HashMap<String, String> {
+        let mut links = HashMap::new();
+        for (target, rels) in self.header_all("Link").into_iter().flat_map(mime::parse_links) {
+            let target = match &self.url {
+                Some(base) => url::join_url(base, &target).unwrap_or(target),
+                None => target,
+            };
+            for rel in rels {
+                links.entry(rel).or_insert_with(|| target.clone());
+            }
+        }
+        links
+    }
+
+    // Cookies from every Set-Cookie header, skipping any without a name=value pair
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.header_all("Set-Cookie").into_iter().filter_map(|value| Cookie::parse(value).ok()).collect()
+    }
+
+    // Where a 3xx response points, as an absolute URL: its Location resolved against `url`,
+    // for following a redirect by hand (max_redirects 0) after looking at it. None for other
+    // statuses, without a Location, or when a relative one has no `url` to resolve against.
+    pub fn redirect_target(&self) -> Option<String> {
+        if !self.status().is_redirect() {
+            return None;
+        }
+        let location = self.header("Location")?;
+        match &self.url {
+            Some(base) => url::join_url(base, location).ok(),
+            None => url::is_absolute_url(location).then(|| location.to_string()),
+        }
+    }
+
+    // The request echoed in the body of a response to TRACE
+    pub fn echoed_request(&self) -> Result<EchoedRequest, HttpError> {
+        EchoedRequest::parse(&self.body)
+    }
+
+    // First trailer field with this name (case-insensitive)
+    pub fn trailer(&self, name: &str) -> Option<&str> {
+        self.trailers.iter()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    }
+
+    // Append the trailer fields the Trailer header announced to the headers.
+    // Unannounced ones stay in `trailers` only, so they can't pose as framing headers.
+    pub fn merge_trailers(&mut self) {
+        let announced: Vec<String> = self.header_all("Trailer")
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim().to_ascii_lowercase())
+            .collect();
+        for line in &self.trailers {
+            let name = line.split_once(':').map(|(name, _)| name.trim().to_ascii_lowercase());
+            if name.is_some_and(|name| announced.contains(&name)) {
+                self.headers.push_line(line.clone());
+            }
+        }
+    }
+
+    // A gRPC-style "trailers-only" response: no body, just a grpc-status, which such a
+    // response carries in its headers (as HTTP/2 sends it) or in chunked trailers
+    pub fn is_trailers_only(&self) -> bool {
+        self.body.is_empty() && (self.trailer("grpc-status").is_some() || self.header("grpc-status").is_some())
+    }
+
+    // Undo a gzip Content-Encoding, as RequestConfig::decompress does. Only the coding
+    // applied last is undone; it is taken off Content-Encoding, which goes once nothing is
+    // left, and Content-Length, which was the encoded size, is dropped. A body in another
+    // coding, or an empty one (as for HEAD), is left as it is.
+    pub fn decompress(&mut self) -> Result<(), HttpError> {
+        let mut codings: Vec<String> = self.header_all("Content-Encoding")
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+            .map(str::to_string)
+            .collect();
+        if self.body.is_empty() || !codings.last().is_some_and(|coding| gzip::is_gzip(coding)) {
+            return Ok(());
+        }
+        self.body = gzip::decompress(&self.body)?;
+        codings.pop();
+        self.headers.remove("Content-Encoding");
+        self.headers.remove("Content-Length");
+        if !codings.is_empty() {
+            self.headers.append("Content-Encoding", &codings.join(", "));
+        }
+        Ok(())
+    }
+
+    // Whether any comma/semicolon-separated element of the header equals `value` (both case-insensitive),
+    // e.g. has_header_value("Content-Type", "application/json") or ("Cache-Control", "no-cache")
+    pub fn has_header_value(&self, name: &str, value: &str) -> bool {
+        self.header_all(name)
+            .into_iter()
+            .flat_map(|v| v.split([',', ';']))
+            .any(|element| element.trim().eq_ignore_ascii_case(value.trim()))
+    }
+
+    // Also accepts the value repeated, e.g. "5, 5"; None if values differ or aren't numbers
+    pub fn content_length(&self) -> Option<u64> {
+        let mut values = self.header_all("Content-Length").into_iter().flat_map(|value| value.split(','));
+        let first: u64 = values.next()?.trim().parse().ok()?;
+        values.all(|value| value.trim().parse() == Ok(first)).then_some(first)
+    }
+
+    pub fn body_len(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn is_empty_body(&self) -> bool {
+        self.body.is_empty()
+    }
+
+    pub fn body_equals(&self, other: &HttpResponse) -> bool {
+        self.body == other.body
+    }
+
+    // Same status, body and headers, e.g. for snapshot tests: header names are compared
+    // case-insensitively, values with surrounding whitespace trimmed, and only fields of the
+    // same name in order. Headers that change from one response to the next (Date, Age,
+    // Set-Cookie, X-Request-Id) are left out, and so are `ignore_headers`.
+    pub fn semantically_equal(&self, other: &HttpResponse, ignore_headers: &[&str]) -> bool {
+        const VOLATILE: [&str; 4] = ["Date", "Age", "Set-Cookie", "X-Request-Id"];
+        let fields = |response: &HttpResponse| {
+            let mut fields: Vec<(String, String)> = response.headers.iter()
+                .filter(|(name, _)| !VOLATILE.iter().chain(ignore_headers).any(|ignored| ignored.eq_ignore_ascii_case(name)))
+                .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string()))
+                .collect();
+            // Stable, so fields of the same name keep their order
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            fields
+        };
+        self.status_code == other.status_code && self.body_equals(other) && fields(self) == fields(other)
+    }
+
+    // Body as UTF-8 text, failing if it isn't valid UTF-8
+    pub fn body_text(&self) -> Result<&str, HttpError> {
+        std::str::from_utf8(&self.body)
+            .map_err(|e| HttpError::InvalidResponse(format!("Body is not valid UTF-8: {}", e)))
+    }
+
+    // Body as UTF-8 text with invalid bytes replaced by U+FFFD, e.g. for logging an error page
+    pub fn body_text_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    // The response itself if its Content-Type is `mime` (case-insensitive, parameters such as
+    // charset ignored), e.g. to stop at an HTML error page where JSON was expected
+    pub fn expect_content_type(&self, mime: &str) -> Result<&HttpResponse, HttpError> {
+        match (self.content_type(), self.header("Content-Type")) {
+            (Some(actual), _) if actual.essence().eq_ignore_ascii_case(mime.trim()) => Ok(self),
+            (Some(actual), _) => Err(HttpError::InvalidResponse(format!("Expected Content-Type {}, got {}", mime, actual.essence()))),
+            (None, Some(raw)) => Err(HttpError::InvalidResponse(format!("Expected Content-Type {}, got {:?}", mime, raw))),
+            (None, None) => Err(HttpError::InvalidResponse(format!("Expected Content-Type {}, got none", mime))),
+        }
+    }
+
+    // Readable rendering like Display, but with the body cut off after `max_body` bytes
+    pub fn dump(&self, max_body: usize) -> String {
+        let mut out = self.head_text();
+        match std::str::from_utf8(&self.body) {
+            Ok(text) if text.len() > max_body => {
+                let mut cut = max_body;
+                while !text.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                out.push_str(&text[..cut]);
+                out.push_str(&format!("\n<{} more bytes>", text.len() - cut));
+            }
+            Ok(text) => out.push_str(text),
+            Err(_) => out.push_str(&format!("<{} bytes of binary data>", self.body.len())),
+        }
+        out
+    }
+
+    // The response as it goes on the wire, e.g. for a proxy to forward it, which
+    // parse_response reads back the same. A chunked body, stored decoded, is sent again as
+    // one chunk followed by the trailers; a body framed by neither header gets a
+    // Content-Length, unless its status has none. Headers are written as they are, so a
+    // Content-Length that no longer matches the body (as after a HEAD) is kept.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!("{}\r\n", self.status_line);
+        for header in self.headers.lines() {
+            head.push_str(header);
+            head.push_str("\r\n");
+        }
+        let chunked = matches!(framing(self), Ok(Framing::Chunked));
+        let framed = self.header("Content-Length").is_some() || self.header("Transfer-Encoding").is_some();
+        if !framed && has_body(self.status_code) {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("\r\n");
+
+        let mut out = head.into_bytes();
+        if !chunked {
+            out.extend_from_slice(&self.body);
+            return out;
+        }
+        if !self.body.is_empty() {
+            out.extend_from_slice(format!("{:x}\r\n", self.body.len()).as_bytes());
+            out.extend_from_slice(&self.body);
+            out.extend_from_slice(b"\r\n");
+        }
+        out.extend_from_slice(b"0\r\n");
+        for trailer in &self.trailers {
+            out.extend_from_slice(trailer.as_bytes());
+            out.extend_from_slice(b"\r\n");
+        }
+        out.extend_from_slice(b"\r\n");
+        out
+    }
+
+    // Status line and headers, one per line, followed by the blank line; values of
+    // sensitive headers are shown as ***
+    fn head_text(&self) -> String {
+        let mut out = format!("{}\n", self.status_line);
+        for header in self.headers.lines() {
+            out.push_str(&redact::line(header));
+            out.push('\n');
+        }
+        out.push('\n');
+        out
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.connection.map(|info| info.local_addr)
+    }
+
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.connection.map(|info| info.peer_addr)
+    }
+}
+
+// The text of an RFC 8187 ext-value such as UTF-8''%E2%82%AC%20rates.pdf (the inverse of
+// ext_param); None for other charsets or bytes that aren't valid in the one given
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, text) = rest.split_once('\'')?;
+    let bytes = url::percent_decode(text);
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
+
+// Parse a complete response (status line, headers, body framed by Content-Length or chunked
+// encoding, or running to the end of the text). Interim 1xx responses before it are passed
+// over, as they are on a connection, and text after its end is an Err. Any input gives Ok
+// or Err, never a panic; an error status (400 and up) is an Err too.
+pub fn parse_response(response: &str) -> Result<HttpResponse, HttpError> {
+    check_status(parse_response_bytes(response.as_bytes(), false)?)
+}
+
+// Like parse_response, but text that doesn't start with "HTTP/" is taken as an
+// HTTP/0.9 reply, as some embedded devices still send: all body, status 200, no headers
+pub fn parse_response_lenient(response: &str) -> Result<HttpResponse, HttpError> {
+    check_status(parse_response_bytes(response.as_bytes(), true)?)
+}
+
+// Like parse_response, for bytes that needn't be text (a capture, fuzzer input), and
+// without judging the status: a well-formed 404 is Ok. Bare LF line endings are accepted,
+// as they are from a server unless RequestConfig::strict_line_endings is set.
+pub fn try_parse_response(response: &[u8]) -> Result<HttpResponse, HttpError> {
+    parse_response_bytes(response, false)
+}
+
+// Status line given to HTTP/0.9 replies, which have none
+pub(crate) const HTTP09_STATUS_LINE: &str = "HTTP/0.9 200 OK";
+
+// Whether the first bytes of a response can't be the start of a status line
+pub(crate) fn is_http09(start: &[u8]) -> bool {
+    let n = start.len().min(5);
+    start[..n] != b"HTTP/"[..n]
+}
+
+// Parse without judging the status code
+pub(crate) fn parse_response_bytes(response: &[u8], lenient: bool) -> Result<HttpResponse, HttpError> {
+    let mut response = response;
+    while reader::is_interim(response) {
+        match find_head_end(response) {
+            Some((_, body_start)) if body_start < response.len() => response = &response[body_start..],
+            _ => return Err(HttpError::InvalidResponse("Only an interim 1xx response, with no final one".to_string())),
+        }
+    }
+
+    let mut parser = ResponseParser::new().lenient(lenient);
+    let mut events = parser.push(response)?;
+    events.extend(parser.finish()?);
+    if parser.trailing() > 0 {
+        return Err(HttpError::InvalidResponse(format!("{} bytes after the end of the response", parser.trailing())));
+    }
+
+    let mut response = HttpResponse::placeholder();
+    for event in events {
+        match event {
+            ParseEvent::StatusLine { code, line } => {
+                response.status_code = code;
+                response.status_line = line;
+            }
+            ParseEvent::Header(line) => response.headers.push_line(line),
+            ParseEvent::BodyChunk(data) => response.body.extend_from_slice(&data),
+            ParseEvent::Trailer(line) => response.trailers.push(line),
+            ParseEvent::HeadersComplete | ParseEvent::Complete => {}
+        }
+    }
+    Ok(response)
+}
+
+// How the end of a response body is found (RFC 9112 section 6.3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Framing {
+    Chunked,
+    Length(u64),
+    // Everything until the server closes the connection, as with an HTTP/1.0 response that
+    // has neither header. Such a connection is never pooled, even if it says keep-alive.
+    Close,
+}
+
+// The framing of a response that has a body. Framing headers that contradict each
+// other are what request smuggling exploits, so instead of picking one:
+// - Transfer-Encoding and Content-Length together are rejected, though RFC 9112 section 6.3
+//   lets the Content-Length be ignored, as a proxy may have framed the body by the other one
+// - another final transfer coding is only accepted on a connection the server closes
+// - repeated Content-Length values must all be the same number
+pub(crate) fn framing(response: &HttpResponse) -> Result<Framing, HttpError> {
+    let codings: Vec<&str> = response.header_all("Transfer-Encoding")
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|coding| !coding.is_empty())
+        .collect();
+    if let Some(last) = codings.last() {
+        if response.header("Content-Length").is_some() {
+            return Err(HttpError::InvalidResponse(
+                "Both Transfer-Encoding and Content-Length are set, so the body's end is ambiguous".to_string(),
+            ));
+        }
+        if last.eq_ignore_ascii_case("chunked") {
+            return Ok(Framing::Chunked);
+        }
+        let closes = response.has_header_value("Connection", "close") || response.status_line.starts_with("HTTP/1.0");
+        if !closes {
+            return Err(HttpError::InvalidResponse(format!(
+                "Transfer-Encoding {} does not end in chunked and the connection stays open", last
+            )));
+        }
+        return Ok(Framing::Close);
+    }
+
+    let mut length = None;
+    for value in response.header_all("Content-Length").into_iter().flat_map(|value| value.split(',')) {
+        let value = value.trim();
+        let parsed = value.parse::<u64>().ok().filter(|_| value.bytes().all(|b| b.is_ascii_digit()))
+            .ok_or_else(|| HttpError::InvalidResponse(format!("Invalid Content-Length: {:?}", value)))?;
+        match length {
+            Some(previous) if previous != parsed => {
+                return Err(HttpError::InvalidResponse(format!("Conflicting Content-Length values {} and {}", previous, parsed)));
+            }
+            _ => length = Some(parsed),
+        }
+    }
+    Ok(length.map_or(Framing::Close, Framing::Length))
+}
+
+// Header bytes as text: UTF-8 where they are valid UTF-8, otherwise ISO-8859-1, which maps
+// every byte to a character, so nothing a server sent is lost to replacement characters
+pub(crate) fn decode_field(bytes: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()),
+    }
+}
+
+// Status line and headers only, with an empty body. Each line is decoded on its own.
+pub(crate) fn parse_head(head: &[u8]) -> Result<HttpResponse, HttpError> {
+    if head.is_empty() {
+        return Err(HttpError::EmptyResponse);
+    }
+    let mut lines = head.split(|&b| b == b'\n')
+        .map(|line| decode_field(line.strip_suffix(b"\r").unwrap_or(line)));
+
+    let status_line = lines.next().unwrap_or_default().into_owned();
+
+    // Version and a three-digit code; the reason phrase may be missing and
+    // fields may be separated by more than one space
+    let mut fields = status_line.split_whitespace();
+    let version_ok = fields.next().is_some_and(|version| version.starts_with("HTTP/"));
+    let status_code = fields.next()
+        .filter(|code| version_ok && code.len() == 3 && code.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| HttpError::InvalidResponse(format!("Invalid status line: {:?}", status_line)))?;
+
+    let mut headers: Vec<String> = Vec::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        // Obsolete line folding (RFC 9112 section 5.2): the continuation joins the previous value
+        if line.starts_with([' ', '\t']) {
+            let Some(previous) = headers.last_mut() else {
+                return Err(HttpError::InvalidResponse(format!("Folded header line without a header before it: {:?}", line)));
+            };
+            let continuation = line.trim_matches([' ', '\t']);
+            if !continuation.is_empty() {
+                // Only an empty value leaves the line ending in the space after the colon
+                if !previous.ends_with(' ') {
+                    previous.push(' ');
+                }
+                previous.push_str(continuation);
+            }
+            continue;
+        }
+        headers.push(normalize_header(line)?);
+    }
+
+    Ok(HttpResponse {
+        status_line,
+        status_code,
+        headers: HeaderMap::from(headers),
+        body: Vec::new(),
+        trailers: Vec::new(),
+        connection: None,
+        timings: None,
+        idempotency_key: None,
+        url: None,
+        redirect_count: 0,
+    })
+}
+
+// "Name: value" with the whitespace around the value trimmed. A line without a name, or
+// with whitespace before the colon, is refused (RFC 9112 section 5.1).
+fn normalize_header(line: Cow<'_, str>) -> Result<String, HttpError> {
+    let Some((name, value)) = line.split_once(':') else {
+        return Err(HttpError::InvalidResponse(format!("Header line without a colon: {:?}", line)));
+    };
+    if name.is_empty() || name.contains(|c: char| c.is_ascii_whitespace()) {
+        return Err(HttpError::InvalidResponse(format!("Invalid header name in {:?}", line)));
+    }
+    let value = value.trim_matches([' ', '\t']);
+    if line.len() == name.len() + 2 + value.len() && line[name.len()..].starts_with(": ") {
+        return Ok(line.into_owned());
+    }
+    Ok(format!("{}: {}", name, value))
+}
+
+// 1xx, 204 and 304 never have a body (RFC 9112 section 6.3), whatever Content-Length says
+pub(crate) fn has_body(status_code: u16) -> bool {
+    !matches!(status_code, 100..=199 | 204 | 304)
+}
+
+// Check for HTTP errors
+pub(crate) fn check_status(response: HttpResponse) -> Result<HttpResponse, HttpError> {
+    if response.status_code >= 400 {
+        return Err(HttpError::HttpError {
+            code: response.status_code,
+            message: format!("Server returned error: {}", response.status_line),
+        });
+    }
+    Ok(response)
+}
+
+// Position of the blank line that ends the head, and where the body starts after it.
+// Lines may end in CRLF or a bare LF, mixed freely; the head ends at the line break
+// before the blank line, so a CR left at its end is stripped along with the others.
+pub(crate) fn find_head_end(response: &[u8]) -> Option<(usize, usize)> {
+    (0..response.len()).filter(|&i| response[i] == b'\n').find_map(|i| {
+        if response[i + 1..].starts_with(b"\n") {
+            Some((i, i + 2))
+        } else if response[i + 1..].starts_with(b"\r\n") {
+            Some((i, i + 3))
+        } else {
+            None
+        }
+    })
+}
+
+// For RequestConfig::strict_line_endings: fail on a line of the head ending in a bare LF
+pub(crate) fn require_crlf(head: &[u8]) -> Result<(), HttpError> {
+    match (0..head.len()).find(|&i| head[i] == b'\n' && (i == 0 || head[i - 1] != b'\r')) {
+        Some(i) => Err(HttpError::InvalidResponse(format!("Response head line ends in a bare LF at byte {}", i))),
+        None => Ok(()),
+    }
+}
+
+// Stable JSON form for fixtures: headers as ordered pairs, body as text or base64
+#[cfg(feature = "json")]
+impl HttpResponse {
+    pub fn to_json(&self) -> Json {
+        let headers = self.headers.iter()
+            .map(|(name, value)| Json::Array(vec![name.into(), value.into()]))
+            .collect();
+        let body = match std::str::from_utf8(&self.body) {
+            Ok(text) => ("body", text.into()),
+            Err(_) => ("body_base64", base64::encode(&self.body).into()),
+        };
+        let mut fields = vec![
+            ("status_code".to_string(), (self.status_code as u64).into()),
+            ("status_line".to_string(), self.status_line.as_str().into()),
+            ("headers".to_string(), Json::Array(headers)),
+            (body.0.to_string(), body.1),
+        ];
+        if !self.trailers.is_empty() {
+            fields.push(("trailers".to_string(), Json::Array(self.trailers.iter().map(|line| line.as_str().into()).collect())));
+        }
+        Json::Object(fields)
+    }
+
+    pub fn from_json(json: &Json) -> Result<HttpResponse, HttpError> {
+        let invalid = |what: &str| HttpError::InvalidResponse(format!("Invalid response JSON: {}", what));
+
+        let status_code = json.get("status_code").and_then(Json::as_u64)
+            .and_then(|code| u16::try_from(code).ok())
+            .ok_or_else(|| invalid("missing status_code"))?;
+        let status_line = json.get("status_line").and_then(Json::as_str)
+            .ok_or_else(|| invalid("missing status_line"))?
+            .to_string();
+
+        let mut headers = Vec::new();
+        for pair in json.get("headers").and_then(Json::as_array).ok_or_else(|| invalid("missing headers"))? {
+            match pair.as_array() {
+                Some([name, value]) => match (name.as_str(), value.as_str()) {
+                    (Some(name), Some(value)) => headers.push(format!("{}: {}", name, value)),
+                    _ => return Err(invalid("header names and values must be strings")),
+                },
+                _ => return Err(invalid("headers must be [name, value] pairs")),
+            }
+        }
+
+        let body = match (json.get("body"), json.get("body_base64")) {
+            (Some(Json::String(text)), None) => text.clone().into_bytes(),
+            (None, Some(Json::String(encoded))) => base64::decode(encoded).ok_or_else(|| invalid("bad body_base64"))?,
+            (None, None) => Vec::new(),
+            _ => return Err(invalid("expected one of body or body_base64")),
+        };
+
+        let trailers = match json.get("trailers") {
+            Some(Json::Array(lines)) => lines.iter()
+                .map(|line| line.as_str().map(str::to_string).ok_or_else(|| invalid("trailers must be strings")))
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(invalid("trailers must be an array")),
+            None => Vec::new(),
+        };
+
+        Ok(HttpResponse { status_line, status_code, headers: HeaderMap::from(headers), body, trailers, connection: None, timings: None, idempotency_key: None, url: None, redirect_count: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interim_responses_are_passed_over() {
+        let response = try_parse_response(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </a>\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+        ).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"ok");
+        assert_eq!(response.header("Link"), None);
+    }
+
+    #[test]
+    fn interim_response_alone_is_an_error() {
+        for input in ["HTTP/1.1 100 Continue\r\n\r\n", "HTTP/1.1 100 Continue\r\n"] {
+            assert!(matches!(try_parse_response(input.as_bytes()), Err(HttpError::InvalidResponse(_))), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn switching_protocols_is_the_final_response() {
+        let response = try_parse_response(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n").unwrap();
+        assert_eq!(response.status_code, 101);
+    }
+
+    #[test]
+    fn bytes_after_the_response_are_an_error() {
+        for input in [
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nokEXTRA",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\nEXTRA",
+            "HTTP/1.1 204 No Content\r\n\r\nEXTRA",
+        ] {
+            assert!(matches!(try_parse_response(input.as_bytes()), Err(HttpError::InvalidResponse(_))), "{:?}", input);
+        }
+        assert_eq!(parse_response("HTTP/1.0 200 OK\r\n\r\nall of it").unwrap().body, b"all of it");
+    }
+
+    #[test]
+    fn clone_is_independent_of_the_original() {
+        let original = try_parse_response(b"HTTP/1.1 200 OK\r\nX-A: 1\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        let mut clone = original.clone();
+        assert_eq!(clone, original);
+        clone.body.push(b'!');
+        clone.headers.push_line("X-B: 2".to_string());
+        clone.status_code = 500;
+        assert_eq!(original.body, b"ok");
+        assert_eq!(original.header("X-B"), None);
+        assert_eq!(original.status_code, 200);
+    }
+
+    #[test]
+    fn display_matches_the_fixture() {
+        let response = HttpResponse::new(200, &[("Content-Type", "text/plain"), ("X-A", "1")], "line one\r\nline two");
+        assert_eq!(response.to_string(), "HTTP/1.1 200 OK\nContent-Type: text/plain\nX-A: 1\n\nline one\r\nline two");
+    }
+
+    #[test]
+    fn dump_cuts_long_bodies_at_a_character_boundary() {
+        let response = HttpResponse::new(404, &[], "héllo world");
+        assert_eq!(response.dump(2), "HTTP/1.1 404 Not Found\n\nh\n<11 more bytes>");
+        assert_eq!(response.dump(100), response.to_string());
+    }
+
+    #[test]
+    fn dump_summarizes_binary_bodies() {
+        let response = HttpResponse::new(200, &[], vec![0xff, 0xfe, 0x00]);
+        assert_eq!(response.dump(100), "HTTP/1.1 200 OK\n\n<3 bytes of binary data>");
+    }
+
+    #[test]
+    fn header_values_match_case_insensitively() {
+        let response = HttpResponse::new(200, &[("content-type", "Application/JSON; charset=utf-8")], "");
+        assert!(response.has_header_value("Content-Type", "application/json"));
+        assert!(response.has_header_value("CONTENT-TYPE", "charset=UTF-8"));
+        assert!(!response.has_header_value("Content-Type", "json"));
+        assert!(!response.has_header_value("Accept", "application/json"));
+    }
+
+    #[test]
+    fn multi_valued_cache_control_matches_each_directive() {
+        let response = HttpResponse::new(200, &[("Cache-Control", "no-cache, max-age=0"), ("Cache-Control", "private")], "");
+        for directive in ["no-cache", "max-age=0", "private"] {
+            assert!(response.has_header_value("cache-control", directive), "{}", directive);
+        }
+        assert!(!response.has_header_value("Cache-Control", "public"));
+        assert_eq!(response.header_all("Cache-Control"), ["no-cache, max-age=0", "private"]);
+    }
+
+    #[test]
+    fn content_length_is_parsed_when_present() {
+        assert_eq!(HttpResponse::new(200, &[("Content-Length", "42")], "").content_length(), Some(42));
+        assert_eq!(HttpResponse::new(200, &[("Content-Length", "lots")], "").content_length(), None);
+        assert_eq!(HttpResponse::new(200, &[], "").content_length(), None);
+    }
+
+    #[test]
+    fn body_len_and_is_empty_body() {
+        let ok = try_parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+        assert_eq!(ok.body_len(), 5);
+        assert!(!ok.is_empty_body());
+
+        for (head, length) in [("HTTP/1.1 204 No Content\r\nContent-Length: 5\r\n\r\n", 5), ("HTTP/1.1 304 Not Modified\r\nContent-Length: 12\r\n\r\n", 12)] {
+            let response = try_parse_response(head.as_bytes()).unwrap();
+            assert!(response.is_empty_body(), "{}", head);
+            assert_eq!(response.body_len(), 0);
+            // The header is kept as sent; only the body is dropped
+            assert_eq!(response.content_length(), Some(length));
+        }
+    }
+
+    #[test]
+    fn chunked_trailers_are_kept_and_only_announced_ones_merge() {
+        let mut response = try_parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: X-Checksum\r\n\r\n3\r\nabc\r\n0\r\nX-Checksum: 900150983cd24fb0\r\nContent-Length: 1\r\n\r\n",
+        ).unwrap();
+        assert_eq!(response.body, b"abc");
+        assert_eq!(response.trailer("x-checksum"), Some("900150983cd24fb0"));
+        assert_eq!(response.trailer("Content-Length"), Some("1"));
+        assert_eq!(response.header("X-Checksum"), None);
+
+        response.merge_trailers();
+        assert_eq!(response.header("X-Checksum"), Some("900150983cd24fb0"));
+        assert_eq!(response.header("Content-Length"), None);
+    }
+
+    #[test]
+    fn expect_content_type_ignores_parameters_and_case() {
+        let json = HttpResponse::new(200, &[("Content-Type", "Application/JSON; charset=utf-8")], "{}");
+        assert!(json.expect_content_type("application/json").is_ok());
+
+        let html = HttpResponse::new(200, &[("Content-Type", "text/html")], "<h1>Error</h1>");
+        let error = html.expect_content_type("application/json").unwrap_err();
+        assert!(matches!(&error, HttpError::InvalidResponse(message) if message.contains("got text/html")), "{:?}", error);
+
+        let untyped = HttpResponse::new(200, &[], "");
+        assert!(matches!(untyped.expect_content_type("application/json"), Err(HttpError::InvalidResponse(message)) if message.contains("got none")));
+        let garbled = HttpResponse::new(200, &[("Content-Type", "not a type")], "");
+        assert!(matches!(garbled.expect_content_type("application/json"), Err(HttpError::InvalidResponse(message)) if message.contains("\"not a type\"")));
+    }
+
+    #[test]
+    fn a_body_short_of_its_content_length_is_truncated() {
+        let error = try_parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc").unwrap_err();
+        assert!(matches!(error, HttpError::TruncatedBody { expected: 10, received: 3 }), "{:?}", error);
+        assert_eq!(error.to_string(), "Truncated body: Content-Length is 10 but only 3 bytes arrived");
+    }
+
+    #[test]
+    fn odd_status_lines_accepted_in_both_modes() {
+        let accepted = [
+            ("HTTP/1.1 200\r\nContent-Length: 2\r\n\r\nok", 200, ""),
+            ("HTTP/1.1 200 \r\nContent-Length: 2\r\n\r\nok", 200, ""),
+            ("HTTP/1.1   404   Not   Found \r\nContent-Length: 2\r\n\r\nok", 404, "Not   Found"),
+            ("HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\nok", 200, "OK"),
+        ];
+        for lenient in [false, true] {
+            for (raw, code, reason) in accepted {
+                let response = parse_response_bytes(raw.as_bytes(), lenient).unwrap_or_else(|e| panic!("{:?}: {}", raw, e));
+                assert_eq!((response.status_code, response.reason()), (code, reason), "{:?}", raw);
+                assert_eq!(response.body, b"ok");
+            }
+        }
+    }
+
+    #[test]
+    fn replies_without_a_status_line_are_http09_only_when_lenient() {
+        for raw in ["<html>device status</html>", "OK\r\nnot: a header\r\n\r\n"] {
+            assert!(matches!(parse_response_bytes(raw.as_bytes(), false), Err(HttpError::InvalidResponse(_))), "{:?}", raw);
+            let response = parse_response_bytes(raw.as_bytes(), true).unwrap();
+            assert_eq!(response.status_line, HTTP09_STATUS_LINE);
+            assert_eq!(response.status_code, 200);
+            assert!(response.headers.is_empty());
+            assert_eq!(response.body, raw.as_bytes());
+        }
+        assert!(parse_response_lenient("plain body").is_ok());
+        assert!(parse_response("plain body").is_err());
+    }
+
+    #[test]
+    fn malformed_status_lines_are_rejected_in_both_modes() {
+        for raw in ["HTTP/1.1 20 OK\r\n\r\n", "HTTP/1.1 2000 OK\r\n\r\n", "HTTP/1.1 abc OK\r\n\r\n", "HTTP/1.1\r\n\r\n"] {
+            for lenient in [false, true] {
+                assert!(parse_response_bytes(raw.as_bytes(), lenient).is_err(), "{:?} lenient={}", raw, lenient);
+            }
+        }
+    }
+
+    #[test]
+    fn framing_follows_the_framing_headers() {
+        let framed = |status_line: &str, headers: &[(&str, &str)]| {
+            let mut response = HttpResponse::new(200, headers, "");
+            response.status_line = status_line.to_string();
+            framing(&response)
+        };
+        type Headers = &'static [(&'static str, &'static str)];
+        let cases: [(&str, Headers, Framing); 8] = [
+            ("HTTP/1.1 200 OK", &[("Transfer-Encoding", "chunked")], Framing::Chunked),
+            ("HTTP/1.1 200 OK", &[("Transfer-Encoding", "gzip, chunked")], Framing::Chunked),
+            ("HTTP/1.1 200 OK", &[("Transfer-Encoding", "gzip"), ("Transfer-Encoding", "Chunked")], Framing::Chunked),
+            ("HTTP/1.1 200 OK", &[("Transfer-Encoding", "gzip"), ("Connection", "close")], Framing::Close),
+            ("HTTP/1.0 200 OK", &[("Transfer-Encoding", "gzip")], Framing::Close),
+            ("HTTP/1.1 200 OK", &[("Content-Length", "5"), ("Content-Length", "5")], Framing::Length(5)),
+            ("HTTP/1.1 200 OK", &[("Content-Length", "5, 5")], Framing::Length(5)),
+            ("HTTP/1.1 200 OK", &[], Framing::Close),
+        ];
+        for (status_line, headers, expected) in cases {
+            assert_eq!(framed(status_line, headers).unwrap(), expected, "{:?}", headers);
+        }
+
+        let refused: [Headers; 6] = [
+            &[("Transfer-Encoding", "chunked"), ("Content-Length", "5")],
+            &[("Content-Length", "5"), ("Content-Length", "6")],
+            &[("Content-Length", "5, 6")],
+            &[("Content-Length", "+5")],
+            &[("Content-Length", "five")],
+            &[("Transfer-Encoding", "chunked, gzip")],
+        ];
+        for headers in refused {
+            assert!(matches!(framed("HTTP/1.1 200 OK", headers), Err(HttpError::InvalidResponse(_))), "{:?}", headers);
+        }
+    }
+
+    #[test]
+    fn header_bytes_that_are_not_utf8_decode_as_latin1() {
+        let response = try_parse_response(b"HTTP/1.1 200 OK\r\nX-Latin1: caf\xe9\r\nX-Utf8: caf\xc3\xa9\r\nContent-Length: 0\r\n\r\n").unwrap();
+        assert_eq!(response.header("X-Latin1"), Some("café"));
+        assert_eq!(response.header("X-Utf8"), Some("café"));
+        assert_eq!(decode_field(b"\xff\x80"), "\u{ff}\u{80}");
+    }
+
+    #[test]
+    fn lossy_text_replaces_invalid_utf8() {
+        let response = HttpResponse::new(500, &[], &b"Error \xff\xfe page"[..]);
+        assert!(response.body_text().is_err());
+        assert_eq!(response.body_text_lossy(), "Error \u{fffd}\u{fffd} page");
+        assert_eq!(HttpResponse::new(200, &[], "fine").body_text_lossy(), "fine");
+    }
+
+    #[test]
+    fn lf_only_and_mixed_heads_parse_without_touching_the_body() {
+        let lf = parse_response("HTTP/1.1 200 OK\nX-A: 1\nContent-Length: 6\n\nab\r\ncd").unwrap();
+        assert_eq!((lf.status_code, lf.header("X-A")), (200, Some("1")));
+        assert_eq!(lf.body, b"ab\r\ncd");
+        for raw in ["HTTP/1.1 200 OK\r\nX-A: 1\nContent-Length: 2\r\n\nok", "HTTP/1.1 200 OK\nX-A: 1\r\nContent-Length: 2\n\r\nok"] {
+            let mixed = parse_response(raw).unwrap();
+            assert_eq!(mixed.header("X-A"), Some("1"), "{:?}", raw);
+            assert_eq!(mixed.body, b"ok", "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn require_crlf_points_at_the_first_bare_lf() {
+        assert!(require_crlf(b"HTTP/1.1 200 OK\r\nA: b\r\n").is_ok());
+        let error = require_crlf(b"HTTP/1.1 200 OK\r\nA: b\n").unwrap_err();
+        assert!(matches!(&error, HttpError::InvalidResponse(message) if message.ends_with("byte 21")), "{:?}", error);
+        assert!(require_crlf(b"\n").is_err());
+    }
+
+    #[test]
+    fn header_values_are_trimmed_and_folded_lines_unfolded() {
+        let response = parse_response(
+            "HTTP/1.1 200 OK\r\nX-Padded:   spaced \t\r\nX-Tight:tight\r\nX-Empty:\r\n  later\r\nX-Folded: one\r\n two\r\n\tthree  \r\n\r\n",
+        ).unwrap();
+        assert_eq!(response.header("X-Padded"), Some("spaced"));
+        assert_eq!(response.header("X-Tight"), Some("tight"));
+        assert_eq!(response.header("X-Empty"), Some("later"));
+        assert_eq!(response.header("X-Folded"), Some("one two three"));
+        assert_eq!(parse_response("HTTP/1.1 200 OK\r\nX-None:\r\n\r\n").unwrap().header("X-None"), Some(""));
+    }
+
+    #[test]
+    fn malformed_header_lines_are_refused() {
+        for head in [
+            "HTTP/1.1 200 OK\r\n continued\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nno colon here\r\n\r\n",
+            "HTTP/1.1 200 OK\r\n: no name\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nX-Space : value\r\n\r\n",
+        ] {
+            assert!(matches!(parse_response(head), Err(HttpError::InvalidResponse(_))), "{:?}", head);
+        }
+    }
+
+    #[test]
+    fn date_and_last_modified_accept_each_http_date_format() {
+        let instant = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(784_111_777);
+        let response = HttpResponse::new(200, &[
+            ("Date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ("Last-Modified", "Sunday, 06-Nov-94 08:49:37 GMT"),
+        ], "");
+        assert_eq!((response.date(), response.last_modified()), (Some(instant), Some(instant)));
+        let asctime = HttpResponse::new(200, &[("Date", "Sun Nov  6 08:49:37 1994"), ("Last-Modified", "yesterday")], "");
+        assert_eq!((asctime.date(), asctime.last_modified()), (Some(instant), None));
+        assert_eq!(HttpResponse::new(200, &[], "").date(), None);
+    }
+
+    #[test]
+    fn redirect_target_resolves_location_against_url() {
+        let mut response = HttpResponse::new(302, &[("Location", "../c?d=1#frag")], "");
+        assert_eq!(response.redirect_target(), None);
+        response.url = Some("http://host/a/b/x?q".to_string());
+        assert_eq!(response.redirect_target().as_deref(), Some("http://host/a/c?d=1#frag"));
+
+        let mut other_host = HttpResponse::new(301, &[("Location", "//other:8080/p")], "");
+        other_host.url = Some("http://host/a".to_string());
+        assert_eq!(other_host.redirect_target().as_deref(), Some("http://other:8080/p"));
+        let absolute = HttpResponse::new(307, &[("Location", "https://elsewhere/x")], "");
+        assert_eq!(absolute.redirect_target().as_deref(), Some("https://elsewhere/x"));
+
+        let mut not_redirect = HttpResponse::new(200, &[("Location", "/x")], "");
+        not_redirect.url = Some("http://host/".to_string());
+        assert_eq!(not_redirect.redirect_target(), None);
+        assert_eq!(HttpResponse::new(304, &[], "").redirect_target(), None);
+    }
+
+    #[test]
+    fn suggested_filename_forms() {
+        let name = |value: &str| HttpResponse::new(200, &[("Content-Disposition", value)], "").suggested_filename();
+        assert_eq!(name("attachment; filename=report.pdf").as_deref(), Some("report.pdf"));
+        assert_eq!(name("attachment; filename=\"x y.zip\"").as_deref(), Some("x y.zip"));
+        assert_eq!(name(r#"attachment; filename="say \"hi\".txt""#).as_deref(), Some("say \"hi\".txt"));
+        assert_eq!(name("attachment; filename*=UTF-8''%E2%82%AC%20rates.txt").as_deref(), Some("\u{20ac} rates.txt"));
+        assert_eq!(name("attachment; filename*=iso-8859-1'en'%A3%20rates.txt").as_deref(), Some("\u{a3} rates.txt"));
+        // filename* wins when it decodes; otherwise the fallback is used
+        assert_eq!(name("attachment; filename=\"fallback.txt\"; filename*=UTF-8''%C3%A9t%C3%A9.txt").as_deref(), Some("\u{e9}t\u{e9}.txt"));
+        assert_eq!(name("attachment; filename=\"fallback.txt\"; filename*=UTF-8''%FF.txt").as_deref(), Some("fallback.txt"));
+        assert_eq!(name("attachment; filename=\"fallback.txt\"; filename*=KOI8-R''%C1.txt").as_deref(), Some("fallback.txt"));
+        assert_eq!(name("inline; filename=view.html").as_deref(), Some("view.html"));
+    }
+
+    #[test]
+    fn suggested_filename_keeps_only_the_last_component() {
+        let name = |value: &str| HttpResponse::new(200, &[("Content-Disposition", value)], "").suggested_filename();
+        assert_eq!(name("attachment; filename=\"../../etc/passwd\"").as_deref(), Some("passwd"));
+        assert_eq!(name("attachment; filename*=UTF-8''C%3A%5CWindows%5Cevil.dll").as_deref(), Some("evil.dll"));
+        assert_eq!(name("attachment; filename*=UTF-8''..%2F..%2Fescape").as_deref(), Some("escape"));
+        for value in ["attachment", "attachment; filename=\"\"", "attachment; filename=\"..\"", "attachment; filename=\"a/\""] {
+            assert_eq!(name(value), None, "{}", value);
+        }
+        assert_eq!(HttpResponse::new(200, &[], "").suggested_filename(), None);
+    }
+
+    #[test]
+    fn auth_challenges_from_every_header_in_order() {
+        let response = HttpResponse::new(401, &[
+            ("WWW-Authenticate", "Basic realm=\"x\", Digest realm=\"y\", nonce=\"z\""),
+            ("WWW-Authenticate", "Bearer realm=\"api\", error=\"invalid_token\", Negotiate abc123=="),
+        ], "");
+        let challenges = response.auth_challenges();
+        let schemes: Vec<&str> = challenges.iter().map(|c| c.scheme.as_str()).collect();
+        assert_eq!(schemes, ["Basic", "Digest", "Bearer", "Negotiate"]);
+        assert_eq!(challenges[0].realm(), Some("x"));
+        assert_eq!((challenges[1].realm(), challenges[1].param("nonce")), (Some("y"), Some("z")));
+        assert_eq!(challenges[2].param("error"), Some("invalid_token"));
+        assert_eq!(challenges[3].token68.as_deref(), Some("abc123=="));
+        assert!(HttpResponse::new(401, &[], "").auth_challenges().is_empty());
+    }
+
+    #[test]
+    fn conflicting_framing_headers_are_refused() {
+        for raw in [
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 2\r\n\r\n2\r\nok\r\n0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nContent-Length: 4\r\n\r\nabcd",
+            "HTTP/1.1 200 OK\r\nContent-Length: 3, 4\r\n\r\nabcd",
+        ] {
+            assert!(matches!(parse_response(raw), Err(HttpError::InvalidResponse(_))), "{:?}", raw);
+            let mut parser = crate::parser::ResponseParser::new();
+            assert!(parser.push(raw.as_bytes()).and_then(|_| parser.finish()).is_err(), "parser: {:?}", raw);
+        }
+    }
+
+    #[test]
+    fn agreeing_or_bodiless_framing_is_accepted() {
+        assert_eq!(parse_response("HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 2\r\n\r\nok").unwrap().body, b"ok");
+        assert_eq!(parse_response("HTTP/1.1 200 OK\r\nContent-Length: 2, 2\r\n\r\nok").unwrap().body, b"ok");
+        assert_eq!(parse_response("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n").unwrap().body, b"ok");
+        let not_modified = parse_response("HTTP/1.1 304 Not Modified\r\nTransfer-Encoding: chunked\r\nContent-Length: 2\r\n\r\n").unwrap();
+        assert!(not_modified.body.is_empty());
+    }
+
+    #[test]
+    fn links_by_relation_type() {
+        let mut response = HttpResponse::new(200, &[
+            ("Link", "<https://api.example.com/items?page=2>; rel=\"next\", <https://api.example.com/items?page=9>; rel=\"last\""),
+            ("Link", "</items?page=3>; rel=next, <?page=1>; rel=\"FIRST\""),
+        ], "");
+        let links = response.links();
+        assert_eq!(links["next"], "https://api.example.com/items?page=2");
+        assert_eq!(links["last"], "https://api.example.com/items?page=9");
+        assert_eq!(links["first"], "?page=1");
+
+        response.url = Some("http://host/items?page=2".to_string());
+        assert_eq!(response.links()["first"], "http://host/items?page=1");
+        assert!(HttpResponse::new(200, &[], "").links().is_empty());
+    }
+
+    #[test]
+    fn responses_differing_only_in_volatile_headers_are_semantically_equal() {
+        let a = HttpResponse::new(200, &[("Date", "Sun, 06 Nov 1994 08:49:37 GMT"), ("Content-Type", "text/plain"), ("X-Request-Id", "1")], "same");
+        let b = HttpResponse::new(200, &[("content-type", "text/plain "), ("X-Request-Id", "2"), ("Date", "Mon, 07 Nov 1994 08:49:37 GMT")], "same");
+        assert!(a.semantically_equal(&b, &[]) && b.semantically_equal(&a, &[]));
+        assert!(a.body_equals(&b));
+
+        let other_status = HttpResponse::new(201, &[("Content-Type", "text/plain")], "same");
+        assert!(!a.semantically_equal(&other_status, &[]) && a.body_equals(&other_status));
+        assert!(!a.body_equals(&HttpResponse::new(200, &[], "different")));
+    }
+
+    #[test]
+    fn same_name_order_matters_and_extra_headers_can_be_ignored() {
+        let vary = |values: [&str; 2]| HttpResponse::new(200, &[("Vary", values[0]), ("X-Other", "1"), ("Vary", values[1])], "");
+        assert!(!vary(["A", "B"]).semantically_equal(&vary(["B", "A"]), &[]));
+        assert!(vary(["A", "B"]).semantically_equal(&vary(["A", "B"]), &[]));
+
+        let tagged = HttpResponse::new(200, &[("ETag", "\"v1\"")], "");
+        let untagged = HttpResponse::new(200, &[], "");
+        assert!(!tagged.semantically_equal(&untagged, &[]));
+        assert!(tagged.semantically_equal(&untagged, &["etag"]));
+    }
+
+    #[test]
+    fn trailers_only_needs_an_empty_body_and_a_grpc_status() {
+        let mut trailered = HttpResponse::new(200, &[("Content-Type", "application/grpc")], "");
+        trailered.trailers = vec!["grpc-status: 5".to_string(), "grpc-message: not found".to_string()];
+        assert!(trailered.is_trailers_only());
+        assert!(HttpResponse::new(200, &[("Grpc-Status", "0")], "").is_trailers_only());
+
+        trailered.body = b"\0\0\0\0\x02hi".to_vec();
+        assert!(!trailered.is_trailers_only());
+        assert!(!HttpResponse::new(200, &[("Content-Type", "application/grpc")], "").is_trailers_only());
+    }
+
+    #[test]
+    fn to_bytes_round_trips() {
+        for raw in [
+            &b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nX-A: 1\r\nX-A: 2\r\n\r\nhello"[..],
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: X-Sum\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\nX-Sum: 9\r\n\r\n",
+            b"HTTP/1.1 204 No Content\r\nX-Empty: \r\n\r\n",
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\ngone",
+        ] {
+            let response = try_parse_response(raw).unwrap();
+            let again = try_parse_response(&response.to_bytes()).unwrap();
+            assert!(again == response, "{:?}", String::from_utf8_lossy(raw));
+        }
+    }
+
+    #[test]
+    fn to_bytes_frames_bodies_that_came_without_framing() {
+        let close_delimited = parse_response_bytes(b"HTTP/1.0 200 OK\r\nServer: old\r\n\r\nuntil close", false).unwrap();
+        let bytes = close_delimited.to_bytes();
+        assert_eq!(bytes, b"HTTP/1.0 200 OK\r\nServer: old\r\nContent-Length: 11\r\n\r\nuntil close");
+        assert_eq!(try_parse_response(&bytes).unwrap().body, b"until close");
+
+        let not_modified = HttpResponse::new(304, &[("ETag", "\"v1\"")], "");
+        assert!(!String::from_utf8(not_modified.to_bytes()).unwrap().contains("Content-Length"));
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::error::HttpError;
+use crate::method::Method;
+use crate::response::HttpResponse;
+
+// Decides whether a failed attempt should be tried again
+pub type RetryPredicate = Arc<dyn Fn(&RetryContext) -> bool + Send + Sync>;
+
+// What a retry predicate gets to look at after each attempt
+#[derive(Debug)]
+pub struct RetryContext<'a> {
+    // 1 for the first attempt
+    pub attempt: u32,
+    pub method: &'a str,
+    // Status code of the response, including error statuses reported as HttpError::HttpError
+    pub status: Option<u16>,
+    pub error: Option<&'a HttpError>,
+    // Time since the first attempt started
+    pub elapsed: Duration,
+    // Sent with every attempt when RequestConfig::idempotency_key_header is set
+    pub idempotency_key: Option<&'a str>,
+}
+
+impl<'a> RetryContext<'a> {
+    pub(crate) fn new(
+        attempt: u32,
+        method: &'a str,
+        outcome: &'a Result<HttpResponse, HttpError>,
+        elapsed: Duration,
+        idempotency_key: Option<&'a str>,
+    ) -> Self {
+        let (status, error) = match outcome {
+            Ok(response) => (Some(response.status_code), None),
+            Err(e @ HttpError::HttpError { code, .. }) => (Some(*code), Some(e)),
+            Err(e) => (None, Some(e)),
+        };
+        RetryContext { attempt, method, status, error, elapsed, idempotency_key }
+    }
+}
+
+// Retry idempotent requests, and others sent with an idempotency key, after network
+// failures (including bodies cut short and timeouts) and 502/503/504 responses
+pub fn default_retry_policy(ctx: &RetryContext) -> bool {
+    let idempotent = Method::from(ctx.method).is_idempotent() || ctx.idempotency_key.is_some();
+    let retryable = matches!(ctx.error, Some(HttpError::NetworkError(_) | HttpError::TruncatedBody { .. } | HttpError::Timeout { .. })) || matches!(ctx.status, Some(502..=504));
+    idempotent && retryable
+}
+
+// A fresh idempotency key: 128 random bits formatted as a version 4 UUID. std's randomly
+// seeded hasher is enough, the key only has to be unique.
+pub(crate) fn idempotency_key() -> String {
+    let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    let mut bytes = [0u8; 16];
+    for (round, half) in bytes.chunks_mut(8).enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(time.as_nanos());
+        hasher.write_usize(round);
+        half.copy_from_slice(&hasher.finish().to_be_bytes());
+    }
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retried(method: &str, outcome: Result<HttpResponse, HttpError>, key: Option<&str>) -> bool {
+        default_retry_policy(&RetryContext::new(1, method, &outcome, Duration::ZERO, key))
+    }
+
+    fn status(code: u16) -> Result<HttpResponse, HttpError> {
+        Ok(HttpResponse::new(code, &[], ""))
+    }
+
+    #[test]
+    fn default_policy_retries_idempotent_methods_after_gateway_errors() {
+        for code in [502, 503, 504] {
+            assert!(retried("GET", status(code), None), "{}", code);
+        }
+        for code in [200, 500, 501, 429] {
+            assert!(!retried("GET", status(code), None), "{}", code);
+        }
+        assert!(!retried("POST", status(503), None));
+        assert!(retried("POST", status(503), Some("key")));
+    }
+
+    #[test]
+    fn default_policy_retries_network_failures() {
+        assert!(retried("PUT", Err(HttpError::NetworkError("reset".to_string())), None));
+        assert!(retried("GET", Err(HttpError::TruncatedBody { expected: 10, received: 2 }), None));
+        assert!(!retried("GET", Err(HttpError::InvalidResponse("bad".to_string())), None));
+        assert!(!retried("PATCH", Err(HttpError::NetworkError("reset".to_string())), None));
+    }
+
+    #[test]
+    fn context_carries_the_status_of_error_responses() {
+        let outcome = Err(HttpError::HttpError { code: 503, message: String::new() });
+        let ctx = RetryContext::new(2, "GET", &outcome, Duration::from_secs(1), None);
+        assert_eq!((ctx.attempt, ctx.status), (2, Some(503)));
+        assert!(ctx.error.is_some());
+    }
+
+    #[test]
+    fn idempotency_keys_are_unique_version_4_uuids() {
+        let (a, b) = (idempotency_key(), idempotency_key());
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 36);
+        assert_eq!(&a[14..15], "4");
+        assert!(matches!(&a[19..20], "8" | "9" | "a" | "b"), "{}", a);
+    }
+}
@@ -0,0 +1,34 @@
+// Byte buffers kept per thread and reused from one request to the next, so a loop of small
+// requests doesn't allocate a fresh buffer for every serialized request and response head
+use std::cell::RefCell;
+
+// A buffer that has grown past this (for a huge head, say) is freed rather than kept
+const MAX_RETAINED: usize = 64 * 1024;
+// Enough for a request and a response head in flight at once, with room for a redirect
+const MAX_BUFFERS: usize = 4;
+
+thread_local! {
+    static BUFFERS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+// An empty buffer, one given back earlier if there is one
+pub(crate) fn take() -> Vec<u8> {
+    BUFFERS.try_with(|buffers| buffers.borrow_mut().pop())
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+// Keep `buffer` for a later take(), once its contents are no longer needed
+pub(crate) fn give(mut buffer: Vec<u8>) {
+    if buffer.capacity() == 0 || buffer.capacity() > MAX_RETAINED {
+        return;
+    }
+    buffer.clear();
+    let _ = BUFFERS.try_with(|buffers| {
+        let mut buffers = buffers.borrow_mut();
+        if buffers.len() < MAX_BUFFERS {
+            buffers.push(buffer);
+        }
+    });
+}
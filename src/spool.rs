@@ -0,0 +1,88 @@
+// Response bodies kept in memory while small and moved to a temporary file once they grow
+// past a threshold, see ResponseReader::spool
+use std::fs::{File, OpenOptions};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::retry;
+
+// A body held in memory, or in a temporary file, read back from its start, that is deleted
+// when closed, so nothing is left behind in the temp directory
+#[derive(Debug)]
+pub enum SpooledBody {
+    Memory(Vec<u8>),
+    File(File),
+}
+
+impl SpooledBody {
+    pub fn is_file(&self) -> bool {
+        matches!(self, SpooledBody::File(_))
+    }
+
+    // The body from its start, whichever way it is held
+    pub fn into_reader(self) -> Box<dyn Read + Send> {
+        match self {
+            SpooledBody::Memory(bytes) => Box::new(Cursor::new(bytes)),
+            SpooledBody::File(file) => Box::new(file),
+        }
+    }
+}
+
+// `start`, then the rest of `body`, in a new temporary file positioned at its start
+pub(crate) fn to_file(start: &[u8], body: &mut impl Read) -> io::Result<File> {
+    let mut file = temp_file()?;
+    file.write_all(start)?;
+    io::copy(body, &mut file)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+// Unlinked at once where an open file can be (Unix), opened to be deleted on close elsewhere
+fn temp_file() -> io::Result<File> {
+    let path = std::env::temp_dir().join(format!("http-client-from-scratch-{}.body", retry::idempotency_key()));
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create_new(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // FILE_FLAG_DELETE_ON_CLOSE
+        options.custom_flags(0x0400_0000);
+    }
+    let file = options.open(&path)?;
+    #[cfg(not(windows))]
+    std::fs::remove_file(&path)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_file_holds_start_then_rest_from_its_beginning() {
+        let mut rest = Cursor::new(b" and the rest".to_vec());
+        let body = SpooledBody::File(to_file(b"start", &mut rest).unwrap());
+        assert!(body.is_file());
+        let mut read = String::new();
+        body.into_reader().read_to_string(&mut read).unwrap();
+        assert_eq!(read, "start and the rest");
+    }
+
+    // The open file's name is already gone from the temp directory
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn temp_files_leave_nothing_behind() {
+        use std::os::fd::AsRawFd;
+        let file = temp_file().unwrap();
+        let path = std::fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())).unwrap();
+        assert!(path.to_string_lossy().ends_with(" (deleted)"), "{}", path.display());
+    }
+
+    #[test]
+    fn memory_bodies_read_back_too() {
+        let body = SpooledBody::Memory(b"small".to_vec());
+        assert!(!body.is_file());
+        let mut read = Vec::new();
+        body.into_reader().read_to_end(&mut read).unwrap();
+        assert_eq!(read, b"small");
+    }
+}
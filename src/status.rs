@@ -0,0 +1,139 @@
+use std::fmt;
+
+// Typed status code so callers don't have to remember the ranges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StatusCode(pub u16);
+
+impl StatusCode {
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    // Standard reason phrase, if the code is a registered one
+    pub fn canonical_reason(&self) -> Option<&'static str> {
+        let reason = match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Content Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            422 => "Unprocessable Content",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            _ => return None,
+        };
+        Some(reason)
+    }
+
+    // 1xx
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    // 2xx
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    // 3xx
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    // 4xx
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    // 5xx
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+}
+
+impl From<u16> for StatusCode {
+    fn from(code: u16) -> Self {
+        StatusCode(code)
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The categories a code falls in: informational, success, redirect, client error, server error
+    fn categories(code: u16) -> [bool; 5] {
+        let status = StatusCode(code);
+        [status.is_informational(), status.is_success(), status.is_redirect(), status.is_client_error(), status.is_server_error()]
+    }
+
+    #[test]
+    fn boundary_codes_fall_in_one_category() {
+        assert_eq!(categories(100), [true, false, false, false, false]);
+        assert_eq!(categories(199), [true, false, false, false, false]);
+        assert_eq!(categories(200), [false, true, false, false, false]);
+        assert_eq!(categories(299), [false, true, false, false, false]);
+        assert_eq!(categories(300), [false, false, true, false, false]);
+        assert_eq!(categories(399), [false, false, true, false, false]);
+        assert_eq!(categories(400), [false, false, false, true, false]);
+        assert_eq!(categories(499), [false, false, false, true, false]);
+        assert_eq!(categories(500), [false, false, false, false, true]);
+        assert_eq!(categories(599), [false, false, false, false, true]);
+    }
+
+    #[test]
+    fn codes_outside_the_ranges_are_in_none() {
+        for code in [0, 99, 600, 999] {
+            assert_eq!(categories(code), [false; 5], "{}", code);
+        }
+    }
+
+    #[test]
+    fn registered_codes_have_reasons() {
+        assert_eq!(StatusCode(404).canonical_reason(), Some("Not Found"));
+        assert_eq!(StatusCode(299).canonical_reason(), None);
+        assert_eq!(StatusCode::from(201).to_string(), "201");
+    }
+}
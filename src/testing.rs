@@ -0,0 +1,244 @@
+// A local HTTP server for integration tests of code built on this client (feature "test-util")
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::chunked;
+use crate::redact;
+use crate::response::HttpResponse;
+
+// How often idle connections check whether the server is shutting down
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Listens on an ephemeral 127.0.0.1 port and answers each request with the next queued
+// response, in order; with none queued it answers 500. Stops when dropped.
+//
+//     let server = TestServer::start();
+//     server.respond_with(200, &[("Content-Type", "text/plain")], "hello");
+//     let response = get(&server.address(), "/", None)?;
+//     assert_eq!(server.requests()[0].target, "/");
+pub struct TestServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+// A request as the server received it
+#[derive(Clone, PartialEq, Eq)]
+pub struct ReceivedRequest {
+    pub method: String,
+    // e.g. "/search?q=x"
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    // Decoded if it was sent chunked
+    pub body: Vec<u8>,
+    // The trailer fields after a chunked body
+    pub trailers: Vec<(String, String)>,
+}
+
+impl ReceivedRequest {
+    // First header with this name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl std::fmt::Debug for ReceivedRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ReceivedRequest")
+            .field("method", &self.method)
+            .field("target", &self.target)
+            .field("headers", &redact::Headers(&self.headers))
+            .field("body", &self.body)
+            .field("trailers", &redact::Headers(&self.trailers))
+            .finish()
+    }
+}
+
+type Handler = Box<dyn FnOnce(&ReceivedRequest) -> HttpResponse + Send>;
+
+#[derive(Default)]
+struct State {
+    responses: VecDeque<Handler>,
+    requests: Vec<ReceivedRequest>,
+}
+
+impl TestServer {
+    // Panics if no local port can be bound, as befits a test helper
+    pub fn start() -> TestServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("TestServer: failed to bind a local port");
+        let address = listener.local_addr().expect("TestServer: failed to read the local address");
+        let state = Arc::new(Mutex::new(State::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = Arc::clone(&state);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let state = Arc::clone(&state);
+                    let stop = Arc::clone(&stop);
+                    thread::spawn(move || serve(stream, &state, &stop));
+                }
+            })
+        };
+        TestServer { address, state, stop, thread: Some(thread) }
+    }
+
+    // host:port to pass as the host of a request
+    pub fn address(&self) -> String {
+        self.address.to_string()
+    }
+
+    // Queue a response, sent as HttpResponse::to_bytes writes it: Content-Length is added
+    // unless framing headers are given, and with Transfer-Encoding: chunked the body goes
+    // out as one chunk
+    pub fn respond_with(&self, status: u16, headers: &[(&str, &str)], body: impl Into<Vec<u8>>) {
+        let response = HttpResponse::new(status, headers, body);
+        self.respond_with_fn(move |_| response);
+    }
+
+    // Queue a response built from the request it answers
+    pub fn respond_with_fn<F>(&self, handler: F)
+    where
+        F: FnOnce(&ReceivedRequest) -> HttpResponse + Send + 'static,
+    {
+        self.lock().responses.push_back(Box::new(handler));
+    }
+
+    // Every request received so far, in order
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl std::fmt::Debug for TestServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let state = self.lock();
+        f.debug_struct("TestServer")
+            .field("address", &self.address)
+            .field("queued", &state.responses.len())
+            .field("received", &state.requests.len())
+            .finish()
+    }
+}
+
+fn lock(state: &Mutex<State>) -> std::sync::MutexGuard<'_, State> {
+    // A panicking handler leaves the recorded requests usable
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Answer requests on one connection until the client closes it, asks for close, or the server stops
+fn serve(stream: TcpStream, state: &Mutex<State>, stop: &AtomicBool) {
+    let Ok(mut writer) = stream.try_clone() else { return };
+    if stream.set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+        return;
+    }
+    let mut reader = BufReader::new(Interruptible { stream, stop });
+
+    while let Ok(Some(request)) = read_request(&mut reader) {
+        let close = request.header("Connection").is_some_and(|value| value.eq_ignore_ascii_case("close"));
+        let handler = {
+            let mut state = lock(state);
+            state.requests.push(request.clone());
+            state.responses.pop_front()
+        };
+        let response = match handler {
+            Some(handler) => handler(&request),
+            None => HttpResponse::new(500, &[], "TestServer: no response queued"),
+        };
+        if writer.write_all(&response.to_bytes()).and_then(|_| writer.flush()).is_err() || close {
+            return;
+        }
+    }
+}
+
+// Reads that wait out the socket's short timeout until data arrives or the server stops
+struct Interruptible<'a> {
+    stream: TcpStream,
+    stop: &'a AtomicBool,
+}
+
+impl Read for Interruptible<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.read(buf) {
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    if self.stop.load(Ordering::SeqCst) {
+                        return Err(e);
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+// The next request on the connection, or None once the client has closed it
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<ReceivedRequest>> {
+    let mut head = Vec::new();
+    loop {
+        let start = head.len();
+        if reader.read_until(b'\n', &mut head)? == 0 {
+            return Ok(None);
+        }
+        let line = &head[start..];
+        if line == b"\r\n" || line == b"\n" {
+            break;
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("").to_string();
+    let target = request_line.next().unwrap_or("").to_string();
+    let headers = fields(lines);
+    let mut request = ReceivedRequest { method, target, headers, body: Vec::new(), trailers: Vec::new() };
+
+    let chunked = request.header("Transfer-Encoding").is_some_and(|value| value.to_ascii_lowercase().contains("chunked"));
+    if chunked {
+        let (body, trailers) = chunked::decode(reader)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        request.body = body;
+        request.trailers = fields(trailers.iter().map(String::as_str));
+    } else if let Some(length) = request.header("Content-Length").and_then(|value| value.parse::<usize>().ok()) {
+        request.body = vec![0; length];
+        reader.read_exact(&mut request.body)?;
+    }
+    Ok(Some(request))
+}
+
+// "Name: value" lines as pairs, the value trimmed
+fn fields<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+    lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
@@ -0,0 +1,98 @@
+// The request a server or proxy echoes back in answer to TRACE (RFC 9110 section 9.3.8),
+// showing what arrived after every hop on the way
+use std::fmt;
+
+use crate::error::HttpError;
+use crate::redact;
+
+// Max-Forwards sent with a TRACE request that doesn't set its own, so a request caught in a
+// loop between proxies is answered after this many hops instead of going round forever
+pub(crate) const TRACE_MAX_FORWARDS: &str = "10";
+
+// From HttpResponse::echoed_request()
+#[derive(Clone, PartialEq, Eq)]
+pub struct EchoedRequest {
+    pub method: String,
+    pub target: String,
+    // e.g. "HTTP/1.1"
+    pub version: String,
+    // Raw "Name: value" lines, as they arrived at the server
+    pub headers: Vec<String>,
+}
+
+impl EchoedRequest {
+    // The request line and headers at the start of a TRACE response body (message/http).
+    // Line endings may be CRLF or LF; a body after the head is ignored.
+    pub(crate) fn parse(body: &[u8]) -> Result<EchoedRequest, HttpError> {
+        let invalid = |why: &str| HttpError::InvalidResponse(format!("TRACE response doesn't echo a request: {}", why));
+        let text = std::str::from_utf8(body).map_err(|_| invalid("not UTF-8"))?;
+        let mut lines = text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line));
+
+        let request_line = lines.next().unwrap_or("");
+        let mut parts = request_line.split(' ');
+        let (Some(method), Some(target), Some(version), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid("no request line"));
+        };
+        if method.is_empty() || !version.starts_with("HTTP/") {
+            return Err(invalid("no request line"));
+        }
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(EchoedRequest { method: method.to_string(), target: target.to_string(), version: version.to_string(), headers })
+    }
+
+    // First value of a header (case-insensitive), e.g. header("Via")
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.header_all(name).into_iter().next()
+    }
+
+    pub fn header_all(&self, name: &str) -> Vec<&str> {
+        self.headers.iter()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for EchoedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EchoedRequest")
+            .field("method", &self.method)
+            .field("target", &self.target)
+            .field("version", &self.version)
+            .field("headers", &redact::Lines(&self.headers))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_echoed_head_parses_and_a_trailing_body_is_ignored() {
+        let echoed = EchoedRequest::parse(b"TRACE /a?b=1 HTTP/1.1\r\nHost: example.com\nVia: 1.1 p1\r\nvia: 1.1 p2\r\n\r\nignored").unwrap();
+        assert_eq!((echoed.method.as_str(), echoed.target.as_str(), echoed.version.as_str()), ("TRACE", "/a?b=1", "HTTP/1.1"));
+        assert_eq!(echoed.headers.len(), 3);
+        assert_eq!(echoed.header("host"), Some("example.com"));
+        assert_eq!(echoed.header_all("Via"), ["1.1 p1", "1.1 p2"]);
+    }
+
+    #[test]
+    fn bodies_that_are_not_a_request_head_are_refused() {
+        for body in [&b""[..], b"hello", b"TRACE / FTP/1.0\r\n", b"TRACE  /  HTTP/1.1\r\n", b"TRACE / HTTP/1.1 extra\r\n", b"\xff / HTTP/1.1\r\n"] {
+            assert!(matches!(EchoedRequest::parse(body), Err(HttpError::InvalidResponse(_))), "{:?}", String::from_utf8_lossy(body));
+        }
+    }
+
+    #[test]
+    fn debug_masks_sensitive_echoed_headers() {
+        let echoed = EchoedRequest::parse(b"TRACE / HTTP/1.1\r\nCookie: session=s3cret\r\nX-Plain: shown\r\n\r\n").unwrap();
+        let debug = format!("{:?}", echoed);
+        assert!(!debug.contains("s3cret") && debug.contains("shown"), "{}", debug);
+    }
+}
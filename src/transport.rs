@@ -0,0 +1,512 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cancel::{self, CancelToken};
+use crate::client::{RequestConfig, SocketConfig};
+use crate::dns;
+use crate::error::{HttpError, TimeoutPhase};
+use crate::net;
+use crate::pool::{InUse, Slot};
+use crate::response::ConnectionInfo;
+use crate::url;
+
+// How long a dns_server lookup may take when there is no read_timeout
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) fn connection_info(stream: &TcpStream) -> Result<ConnectionInfo, HttpError> {
+    let peer_addr = stream.peer_addr()
+        .map_err(|e| HttpError::NetworkError(format!("Failed to read peer address: {}", e)))?;
+    let local_addr = stream.local_addr()
+        .map_err(|e| HttpError::NetworkError(format!("Failed to read local address: {}", e)))?;
+    Ok(ConnectionInfo { peer_addr, local_addr, reused: false, stale_retry: false, socket_config_applied: true })
+}
+
+// A connection to `host`, and when its name was resolved (for Timings::dns)
+pub(crate) fn connect(host: &str, config: &RequestConfig, deadline: Option<Instant>) -> Result<(TcpStream, Instant), HttpError> {
+    // Resolve the ASCII form of internationalized names; `host` stays as given for messages
+    let ascii = url::ascii_authority(host)?;
+    let (name, port) = url::split_authority(&ascii);
+    let addrs = resolve(host, name, port, config, deadline)?;
+    let resolved = Instant::now();
+
+    // Only destinations of the same family as the local address can be reached from it
+    let candidates: Vec<SocketAddr> = addrs.into_iter()
+        .filter(|addr| config.local_address.is_none_or(|local| addr.is_ipv4() == local.is_ipv4()))
+        .collect();
+    let dual_stack = candidates.iter().any(SocketAddr::is_ipv6) && candidates.iter().any(SocketAddr::is_ipv4);
+    let candidates = if dual_stack { interleave(candidates) } else { candidates };
+
+    let mut last_error = None;
+    for round in 0..=config.connect_retries {
+        if round > 0 {
+            // Only when every address refused: something may still be starting up there
+            if last_error.as_ref().is_none_or(|e: &io::Error| e.kind() != io::ErrorKind::ConnectionRefused) {
+                break;
+            }
+            let delay = remaining(deadline, config.timeout)?.map_or(config.connect_retry_delay, |left| left.min(config.connect_retry_delay));
+            std::thread::sleep(delay);
+        }
+        if dual_stack {
+            match race(&candidates, config, deadline)? {
+                Ok(stream) => {
+                    return Ok((stream, resolved));
+                }
+                Err(e) => last_error = Some(e),
+            }
+            continue;
+        }
+        for addr in &candidates {
+            if config.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                return Err(HttpError::Cancelled);
+            }
+            match connect_one(config.local_address, *addr, remaining(deadline, config.timeout)?) {
+                Ok(stream) => {
+                    return Ok((stream, resolved));
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+    }
+
+    // An attempt the deadline cut short is the deadline's failure, not the address's
+    remaining(deadline, config.timeout)?;
+    match (last_error, config.local_address) {
+        (Some(e), Some(local)) => Err(HttpError::NetworkError(format!("Failed to connect to {} from {}: {}", host, local, e))),
+        (Some(e), None) => Err(HttpError::NetworkError(format!("Failed to connect to {}: {}", host, e))),
+        (None, Some(local)) => Err(HttpError::NetworkError(format!(
+            "Local address {} does not match the address family of {}", local, host
+        ))),
+        (None, None) => Err(HttpError::NetworkError(format!("Failed to connect to {}: no addresses found", host))),
+    }
+}
+
+// Addresses for a host name: the one RequestConfig::connect_to maps it to, or else from the
+// configured resolver or dns_server. IP literals are never sent to a dns_server.
+fn resolve(host: &str, name: &str, port: u16, config: &RequestConfig, deadline: Option<Instant>) -> Result<Vec<SocketAddr>, HttpError> {
+    let mapped = config.connect_to.iter().find(|(authority, _)| {
+        let (mapped_name, mapped_port) = url::split_authority(authority);
+        mapped_name.eq_ignore_ascii_case(name) && mapped_port == port
+    });
+    if let Some((_, addr)) = mapped {
+        return Ok(vec![*addr]);
+    }
+    if let Some(resolver) = &config.resolver {
+        return resolver.resolve(name, port);
+    }
+    let addrs = match config.dns_server {
+        Some(server) if name.parse::<IpAddr>().is_err() => {
+            let timeout = config.read_timeout.unwrap_or(DNS_TIMEOUT);
+            let timeout = deadline.map_or(timeout, |deadline| timeout.min(deadline.saturating_duration_since(Instant::now())));
+            match dns::resolve(name, port, server, timeout) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    remaining(deadline, config.timeout)?;
+                    return Err(HttpError::Timeout {
+                        phase: TimeoutPhase::Resolve,
+                        limit: config.read_timeout.unwrap_or(DNS_TIMEOUT),
+                    });
+                }
+                addrs => addrs,
+            }
+        }
+        _ => (name, port).to_socket_addrs().map(Iterator::collect),
+    };
+    addrs.map_err(|e| HttpError::NetworkError(format!("Failed to connect to {}: {}", host, e)))
+}
+
+fn connect_one(local: Option<IpAddr>, addr: SocketAddr, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    match (local, timeout) {
+        (Some(local), timeout) => net::connect_from(local, addr, timeout),
+        (None, Some(timeout)) => TcpStream::connect_timeout(&addr, timeout),
+        (None, None) => TcpStream::connect(addr),
+    }
+}
+
+// The order to try addresses in: alternating families, starting with IPv6 (RFC 8305 section 4)
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let mut ordered = Vec::with_capacity(v6.len() + v4.len());
+    let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+// Happy eyeballs: connect to the first address and, whenever happy_eyeballs_delay passes
+// without a connection (or an attempt fails), start on the next one too. The first
+// connection made wins; attempts still running finish on their own threads and are closed.
+fn race(addrs: &[SocketAddr], config: &RequestConfig, deadline: Option<Instant>) -> Result<io::Result<TcpStream>, HttpError> {
+    let (sender, receiver) = mpsc::channel();
+    let mut started = 0;
+    let mut pending = 0;
+    let mut next_start = Instant::now();
+    let mut last_error = None;
+
+    loop {
+        if config.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(HttpError::Cancelled);
+        }
+        let left = remaining(deadline, config.timeout)?;
+        if started < addrs.len() && Instant::now() >= next_start {
+            let (addr, local, sender) = (addrs[started], config.local_address, sender.clone());
+            thread::spawn(move || {
+                let _ = sender.send(connect_one(local, addr, left));
+            });
+            started += 1;
+            pending += 1;
+            next_start = Instant::now() + config.happy_eyeballs_delay;
+        }
+        if pending == 0 {
+            return Ok(Err(last_error.unwrap_or_else(|| io::Error::other("no addresses to connect to"))));
+        }
+
+        // Until the next attempt is due, the deadline passes, or it's time to check for cancellation
+        let mut wait = (started < addrs.len()).then(|| next_start.saturating_duration_since(Instant::now()));
+        for limit in [left, config.cancel.as_ref().map(|_| cancel::POLL_INTERVAL)].into_iter().flatten() {
+            wait = Some(wait.map_or(limit, |wait| wait.min(limit)));
+        }
+        let result = match wait {
+            Some(wait) => receiver.recv_timeout(wait).ok(),
+            None => receiver.recv().ok(),
+        };
+        match result {
+            Some(Ok(stream)) => return Ok(Ok(stream)),
+            Some(Err(e)) => {
+                pending -= 1;
+                last_error = Some(e);
+                next_start = Instant::now();
+            }
+            None => {}
+        }
+    }
+}
+
+// Set the options of `socket` on a new connection, best effort: an option that can't be set
+// doesn't fail the request, and only shows in ConnectionInfo::socket_config_applied
+pub(crate) fn apply_socket_config(stream: &TcpStream, socket: &SocketConfig) -> bool {
+    let mut applied = stream.set_nodelay(socket.nodelay).is_ok();
+    if let Some(interval) = socket.keepalive {
+        applied &= net::set_keepalive(stream, interval).is_ok();
+    }
+    if let Some(size) = socket.recv_buffer_size {
+        applied &= net::set_recv_buffer_size(stream, size).is_ok();
+    }
+    if let Some(size) = socket.send_buffer_size {
+        applied &= net::set_send_buffer_size(stream, size).is_ok();
+    }
+    applied
+}
+
+// Time left before the deadline, or once it has passed, the Timeout for `limit` (the
+// RequestConfig::timeout it was set from)
+pub(crate) fn remaining(deadline: Option<Instant>, limit: Option<Duration>) -> Result<Option<Duration>, HttpError> {
+    match deadline {
+        Some(deadline) => {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(HttpError::Timeout { phase: TimeoutPhase::Total, limit: limit.unwrap_or_default() });
+            }
+            Ok(Some(left))
+        }
+        None => Ok(None),
+    }
+}
+
+// Applies the read timeout to every read, shortened to whatever is left of the deadline
+pub(crate) struct TimedStream {
+    stream: TcpStream,
+    read_timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    // The RequestConfig::timeout the deadline comes from
+    timeout: Option<Duration>,
+    cancel: Option<CancelToken>,
+    header_timeout: Option<Duration>,
+    // While the response head is awaited: when header_timeout runs out
+    head_by: Option<Instant>,
+    // When the host name was resolved, the connection handed over, and the first response
+    // bytes arrived, for the timings; a pooled connection counts as resolved when handed over
+    pub(crate) resolved: Instant,
+    pub(crate) connected: Instant,
+    pub(crate) first_byte: Option<Instant>,
+    // Bytes written and read since the current request started going out, framing included
+    pub(crate) bytes_written: u64,
+    pub(crate) bytes_read: u64,
+    // Requests sent on the connection so far, including the current one (it may come from the pool)
+    pub(crate) requests: usize,
+    // For a Client's PoolStats, while the connection is out of the pool
+    pub(crate) in_use: Option<InUse>,
+    // Counts the connection against RequestConfig::max_connections until it is closed
+    pub(crate) slot: Option<Slot>,
+}
+
+impl TimedStream {
+    pub(crate) fn new(stream: TcpStream, config: &RequestConfig, deadline: Option<Instant>) -> Self {
+        TimedStream {
+            stream,
+            read_timeout: config.read_timeout,
+            deadline,
+            timeout: config.timeout,
+            cancel: config.cancel.clone(),
+            header_timeout: config.header_timeout,
+            head_by: None,
+            resolved: Instant::now(),
+            connected: Instant::now(),
+            first_byte: None,
+            bytes_written: 0,
+            bytes_read: 0,
+            requests: 0,
+            in_use: None,
+            slot: None,
+        }
+    }
+
+    // Ready for the next request on a pooled connection, with that request's timeouts
+    pub(crate) fn reset(&mut self, config: &RequestConfig, deadline: Option<Instant>) {
+        self.read_timeout = config.read_timeout;
+        self.deadline = deadline;
+        self.timeout = config.timeout;
+        self.cancel = config.cancel.clone();
+        self.header_timeout = config.header_timeout;
+        self.head_by = None;
+        self.connected = Instant::now();
+        self.resolved = self.connected;
+        self.first_byte = None;
+    }
+
+    // Whether an idle connection can still carry a request: one the server has closed reads
+    // as EOF (or a reset) without blocking, and anything the server sent unasked, e.g. a 408,
+    // means it is done with the connection too
+    pub(crate) fn is_open(&self) -> bool {
+        if self.stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let mut byte = [0; 1];
+        let open = matches!(self.stream.peek(&mut byte), Err(e) if e.kind() == io::ErrorKind::WouldBlock);
+        self.stream.set_nonblocking(false).is_ok() && open
+    }
+
+    // The socket itself, left without timeouts, e.g. for a connection upgraded to another protocol
+    pub(crate) fn into_stream(self) -> TcpStream {
+        let _ = self.stream.set_read_timeout(None);
+        let _ = self.stream.set_write_timeout(None);
+        self.stream
+    }
+
+    // Reads from now on wait at most `timeout` (None: as long as it takes); returns the one
+    // they waited for before
+    pub(crate) fn replace_read_timeout(&mut self, timeout: Option<Duration>) -> Option<Duration> {
+        std::mem::replace(&mut self.read_timeout, timeout)
+    }
+
+    // No more bytes will be sent; the server sees EOF while the response can still be read
+    pub(crate) fn shutdown_write(&self) -> io::Result<()> {
+        self.stream.shutdown(Shutdown::Write)
+    }
+
+    // The request has been written: reads until head_done() also count against header_timeout
+    pub(crate) fn await_head(&mut self) {
+        self.head_by = self.header_timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    pub(crate) fn head_done(&mut self) {
+        self.head_by = None;
+    }
+
+    fn check_cancelled(&self) -> io::Result<()> {
+        match &self.cancel {
+            Some(token) if token.is_cancelled() => Err(cancel::cancelled_io()),
+            _ => Ok(()),
+        }
+    }
+
+    fn read_within(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        self.stream.set_read_timeout(timeout)?;
+        match self.stream.read(buf) {
+            // A read cut short by the deadline should say so rather than look like an idle timeout
+            Err(e) if is_timeout(&e) => {
+                self.budget(None)?;
+                match self.read_timeout {
+                    Some(limit) => Err(elapsed(TimeoutPhase::Read, limit)),
+                    None => Err(e),
+                }
+            }
+            Ok(n) => {
+                if n > 0 && self.first_byte.is_none() {
+                    self.first_byte = Some(Instant::now());
+                }
+                self.bytes_read += n as u64;
+                Ok(n)
+            }
+            result => result,
+        }
+    }
+
+    fn budget(&self, timeout: Option<Duration>) -> io::Result<Option<Duration>> {
+        let timeout = match remaining(self.deadline, self.timeout) {
+            Ok(Some(left)) => Some(timeout.map_or(left, |t| t.min(left))),
+            Ok(None) => timeout,
+            Err(_) => return Err(elapsed(TimeoutPhase::Total, self.timeout.unwrap_or_default())),
+        };
+        let Some(head_by) = self.head_by else { return Ok(timeout) };
+        let left = head_by.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(elapsed(TimeoutPhase::Headers, self.header_timeout.unwrap_or_default()));
+        }
+        Ok(Some(timeout.map_or(left, |t| t.min(left))))
+    }
+}
+
+impl Read for TimedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = self.budget(self.read_timeout)?;
+        if self.cancel.is_none() {
+            return self.read_within(buf, timeout);
+        }
+
+        // Wait in short slices so a cancellation is noticed promptly
+        let started = Instant::now();
+        loop {
+            self.check_cancelled()?;
+            let left = timeout.map(|t| t.saturating_sub(started.elapsed()));
+            let slice = left.map_or(cancel::POLL_INTERVAL, |left| left.min(cancel::POLL_INTERVAL));
+            match self.read_within(buf, Some(slice.max(Duration::from_millis(1)))) {
+                // Only the end of a slice; the read timeout runs on until `left` is used up
+                Err(e) if is_timeout(&e) && timed_out(&e).is_none_or(|(phase, _)| phase == TimeoutPhase::Read)
+                    && left.is_none_or(|left| left > slice) => continue,
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Write for TimedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_cancelled()?;
+        let timeout = self.budget(None)?;
+        self.stream.set_write_timeout(timeout)?;
+        let n = self.stream.write(buf)?;
+        self.bytes_written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+// Carried inside an io::Error when one of the time limits runs out during a read or write
+#[derive(Debug)]
+struct Elapsed {
+    phase: TimeoutPhase,
+    limit: Duration,
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} timeout of {:?} ran out", self.phase, self.limit)
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+fn elapsed(phase: TimeoutPhase, limit: Duration) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, Elapsed { phase, limit })
+}
+
+// The limit that ran out, if `e` reports one
+pub(crate) fn timed_out(e: &io::Error) -> Option<(TimeoutPhase, Duration)> {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<Elapsed>())
+        .map(|elapsed| (elapsed.phase, elapsed.limit))
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn loopback_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn socket_config_is_applied_to_the_stream() {
+        let (stream, _server) = loopback_pair();
+        let socket = SocketConfig { nodelay: true, keepalive: Some(Duration::from_secs(30)), recv_buffer_size: Some(64 * 1024), send_buffer_size: None };
+        assert!(apply_socket_config(&stream, &socket));
+        assert!(stream.nodelay().unwrap());
+
+        assert!(apply_socket_config(&stream, &SocketConfig { nodelay: false, ..SocketConfig::default() }));
+        assert!(!stream.nodelay().unwrap());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn an_option_that_cant_be_set_is_reported_not_fatal() {
+        let (stream, _server) = loopback_pair();
+        // Linux caps TCP_KEEPIDLE at 32767 seconds
+        let socket = SocketConfig { keepalive: Some(Duration::from_secs(40_000)), ..SocketConfig::default() };
+        assert!(!apply_socket_config(&stream, &socket));
+        // The options after it are still set
+        assert!(stream.nodelay().unwrap());
+    }
+
+    #[test]
+    fn addresses_alternate_families_starting_with_ipv6() {
+        let addrs: Vec<SocketAddr> = ["1.1.1.1:80", "2.2.2.2:80", "3.3.3.3:80", "[::1]:80", "[::2]:80"]
+            .iter().map(|a| a.parse().unwrap()).collect();
+        let ordered: Vec<String> = interleave(addrs).iter().map(SocketAddr::to_string).collect();
+        assert_eq!(ordered, ["[::1]:80", "1.1.1.1:80", "[::2]:80", "2.2.2.2:80", "3.3.3.3:80"]);
+        assert!(interleave(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn a_race_is_won_by_the_address_that_connects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        // Nothing listens on the first, so it fails and the second starts at once
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let config = RequestConfig { happy_eyeballs_delay: Duration::from_secs(5), ..RequestConfig::default() };
+        let start = Instant::now();
+        let stream = race(&[closed, listener.local_addr().unwrap()], &config, None).unwrap().unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+        assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+
+        let error = race(&[closed], &config, None).unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[test]
+    fn is_open_spots_closed_and_chatty_connections() {
+        let (stream, server) = loopback_pair();
+        let quiet = TimedStream::new(stream, &RequestConfig::default(), None);
+        assert!(quiet.is_open());
+        // Back to blocking: a read waits for its timeout instead of failing at once
+        quiet.stream.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        let start = Instant::now();
+        assert!((&quiet.stream).read(&mut [0; 1]).is_err());
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        let (stream, mut server_side) = loopback_pair();
+        let chatty = TimedStream::new(stream, &RequestConfig::default(), None);
+        server_side.write_all(b"HTTP/1.1 408 Request Timeout\r\n\r\n").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(!chatty.is_open());
+
+        drop(server);
+        thread::sleep(Duration::from_millis(50));
+        assert!(!quiet.is_open());
+    }
+}
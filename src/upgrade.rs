@@ -0,0 +1,203 @@
+// The HTTP/1.1 upgrade handshake that starts a WebSocket connection (RFC 6455 section 4).
+// Framing is left to the caller: after a 101 the connection is handed over as it is.
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Cursor, Read, Write};
+use std::net::TcpStream;
+use std::time::SystemTime;
+
+use crate::base64;
+use crate::error::HttpError;
+use crate::response::HttpResponse;
+use crate::sha1;
+
+// Appended to the key before hashing, fixed by RFC 6455
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// A connection switched to another protocol by a 101 response, from
+// RequestBuilder::upgrade_websocket(). Reads return the bytes after the response head
+// (some may have arrived with it); writes go straight to the socket. No timeouts apply.
+pub struct Upgraded {
+    head: HttpResponse,
+    // Read from the socket along with the head
+    buffered: Cursor<Vec<u8>>,
+    stream: TcpStream,
+}
+
+impl Upgraded {
+    pub(crate) fn new(head: HttpResponse, buffered: Vec<u8>, stream: TcpStream) -> Upgraded {
+        Upgraded { head, buffered: Cursor::new(buffered), stream }
+    }
+
+    // The 101 response (the body field is empty)
+    pub fn head(&self) -> &HttpResponse {
+        &self.head
+    }
+
+    // The socket, and any bytes already read from it that the caller hasn't yet
+    pub fn into_inner(self) -> (TcpStream, Vec<u8>) {
+        let position = self.buffered.position() as usize;
+        let mut buffered = self.buffered.into_inner();
+        buffered.drain(..position);
+        (self.stream, buffered)
+    }
+}
+
+impl Read for Upgraded {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.buffered.read(buf)? {
+            0 => self.stream.read(buf),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for Upgraded {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl fmt::Debug for Upgraded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Upgraded")
+            .field("head", &self.head)
+            .field("peer_addr", &self.stream.peer_addr().ok())
+            .finish()
+    }
+}
+
+// A fresh Sec-WebSocket-Key: 16 unpredictable bytes, base64-encoded. The key only has to
+// differ between handshakes, so std's randomly seeded hasher is enough.
+pub(crate) fn websocket_key() -> String {
+    let mut bytes = Vec::with_capacity(16);
+    let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    for round in 0..2u8 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(time.as_nanos());
+        hasher.write_u8(round);
+        bytes.extend_from_slice(&hasher.finish().to_be_bytes());
+    }
+    base64::encode(&bytes)
+}
+
+// The Sec-WebSocket-Accept a server must answer `key` with
+pub(crate) fn websocket_accept(key: &str) -> String {
+    base64::encode(&sha1::digest(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()))
+}
+
+// Whether `head` completes the handshake started with `key`
+pub(crate) fn check_websocket_response(head: &HttpResponse, key: &str) -> Result<(), HttpError> {
+    if head.status_code != 101 {
+        return Err(HttpError::InvalidResponse(format!(
+            "Expected 101 Switching Protocols for a WebSocket upgrade, got {:?}", head.status_line
+        )));
+    }
+    if !head.has_header_value("Upgrade", "websocket") || !head.has_header_value("Connection", "upgrade") {
+        return Err(HttpError::InvalidResponse(
+            "101 response without Upgrade: websocket and Connection: Upgrade".to_string(),
+        ));
+    }
+    let expected = websocket_accept(key);
+    match head.header("Sec-WebSocket-Accept") {
+        Some(accept) if accept == expected => Ok(()),
+        Some(accept) => Err(HttpError::InvalidResponse(format!(
+            "Sec-WebSocket-Accept is {:?}, expected {:?}", accept, expected
+        ))),
+        None => Err(HttpError::InvalidResponse("101 response without Sec-WebSocket-Accept".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use crate::{Client, Method};
+
+    fn head(raw: &str) -> HttpResponse {
+        crate::response::parse_response(raw).unwrap()
+    }
+
+    #[test]
+    fn accept_matches_the_rfc_sample() {
+        assert_eq!(websocket_accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn keys_are_16_bytes_and_fresh() {
+        let (a, b) = (websocket_key(), websocket_key());
+        assert_eq!(base64::decode(&a).map(|bytes| bytes.len()), Some(16));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn only_a_matching_101_completes_the_handshake() {
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let good = "HTTP/1.1 101 Switching Protocols\r\nUpgrade: WebSocket\r\nConnection: keep-alive, Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n";
+        assert!(check_websocket_response(&head(good), key).is_ok());
+        for bad in [
+            good.replace("101 Switching Protocols", "200 OK"),
+            good.replace("Upgrade: WebSocket\r\n", ""),
+            good.replace("keep-alive, Upgrade", "keep-alive"),
+            good.replace("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=", "wrong="),
+            good.replace("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n", ""),
+        ] {
+            assert!(matches!(check_websocket_response(&head(&bad), key), Err(HttpError::InvalidResponse(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn the_upgraded_stream_carries_early_bytes_and_echoes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let (mut line, mut lines) = (String::new(), Vec::new());
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                lines.push(line.trim_end().to_string());
+                line.clear();
+            }
+            let key = lines.iter().find_map(|l| l.strip_prefix("Sec-WebSocket-Key: ")).unwrap().to_string();
+            let reply = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\nhello",
+                websocket_accept(&key)
+            );
+            stream.write_all(reply.as_bytes()).unwrap();
+            let mut echo = [0u8; 4];
+            reader.read_exact(&mut echo).unwrap();
+            stream.write_all(&echo).unwrap();
+            lines
+        });
+
+        let mut upgraded = Client::new().request(Method::Get, &address, "/chat").upgrade_websocket().unwrap();
+        assert_eq!(upgraded.head().status_code, 101);
+        let mut early = [0u8; 5];
+        upgraded.read_exact(&mut early).unwrap();
+        assert_eq!(&early, b"hello");
+        upgraded.write_all(b"ping").unwrap();
+        let mut echo = [0u8; 4];
+        upgraded.read_exact(&mut echo).unwrap();
+        assert_eq!(&echo, b"ping");
+
+        let lines = server.join().unwrap();
+        assert_eq!(lines[0], "GET /chat HTTP/1.1");
+        for expected in ["Upgrade: websocket", "Connection: Upgrade", "Sec-WebSocket-Version: 13"] {
+            assert!(lines.iter().any(|l| l == expected), "{:?}", lines);
+        }
+    }
+
+    #[test]
+    fn only_a_bodiless_get_can_upgrade() {
+        let result = Client::new().request(Method::Post, "127.0.0.1:9", "/").upgrade_websocket();
+        assert!(matches!(result, Err(HttpError::InvalidRequest(_))), "{:?}", result);
+    }
+}
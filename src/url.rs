@@ -0,0 +1,359 @@
+// Minimal http:// URL handling: authorities and redirect targets
+use std::borrow::Cow;
+use std::net::Ipv6Addr;
+
+use crate::error::HttpError;
+use crate::idna;
+
+// Split "host[:port]" into the name to resolve and the port (80 when absent)
+pub(crate) fn split_authority(authority: &str) -> (&str, u16) {
+    if let Some(rest) = authority.strip_prefix('[') {
+        // IPv6 literal, e.g. [::1]:8080
+        if let Some((ip, after)) = rest.split_once(']') {
+            let port = after.strip_prefix(':').and_then(|p| p.parse().ok()).unwrap_or(80);
+            return (ip, port);
+        }
+    }
+    match authority.rsplit_once(':') {
+        Some((name, port)) if !name.contains(':') => match port.parse() {
+            Ok(port) => (name, port),
+            Err(_) => (authority, 80),
+        },
+        _ => (authority, 80),
+    }
+}
+
+// "host:port", with an IPv6 literal in brackets
+pub(crate) fn join_authority(name: &str, port: u16) -> String {
+    Authority(name, port).to_string()
+}
+
+// join_authority's text, written without building a String first
+pub(crate) struct Authority<'a>(pub(crate) &'a str, pub(crate) u16);
+
+impl std::fmt::Display for Authority<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let Authority(name, port) = *self;
+        if name.contains(':') { write!(f, "[{}]:{}", name, port) } else { write!(f, "{}:{}", name, port) }
+    }
+}
+
+// The authority with its host name in ASCII (punycode) form, as DNS and the Host header need
+// it. Anything but a host name and a bracketed IPv6 literal, each with an optional port, is
+// refused, so the authority can go into a request as it is.
+pub(crate) fn ascii_authority(authority: &str) -> Result<Cow<'_, str>, HttpError> {
+    let (name, port) = split_authority(authority);
+    let invalid = |reason: String| idna::invalid_host(name, reason);
+    if let Some((ip, after)) = authority.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        if ip.parse::<Ipv6Addr>().is_err() {
+            return Err(invalid("not an IPv6 address".to_string()));
+        }
+        if !(after.is_empty() || after.strip_prefix(':').is_some_and(|p| p.parse::<u16>().is_ok())) {
+            return Err(invalid(format!("{:?} follows the address", after)));
+        }
+        return Ok(Cow::Borrowed(authority));
+    }
+    if authority.is_ascii() {
+        idna::check_ascii(name).map_err(invalid)?;
+        return Ok(Cow::Borrowed(authority));
+    }
+    let ascii = idna::to_ascii(name)?;
+    Ok(Cow::Owned(match authority.strip_prefix(name) {
+        Some(rest) if !rest.is_empty() => format!("{}:{}", ascii, port),
+        _ => ascii,
+    }))
+}
+
+// Resolve a Location header against the current host and path, giving the next host and
+// path, the path with spaces, control and non-ASCII characters percent-encoded so it can go
+// into a request line
+pub(crate) fn resolve_location(host: &str, path: &str, location: &str) -> Option<(String, String)> {
+    let location = location.trim().split('#').next().unwrap_or("");
+
+    let resolved = if let Some(rest) = location.strip_prefix("http://") {
+        split_absolute(rest).map(|(authority, path)| (authority, remove_dot_segments(&path)))
+    } else if has_scheme(location) {
+        // Only plain HTTP is supported
+        None
+    } else {
+        resolve_reference(host, path, location)
+    };
+    resolved.map(|(authority, path)| (authority, escape_target(&path)))
+}
+
+// Bytes a request target can't carry as they are, percent-encoded
+fn escape_target(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for &b in path.as_bytes() {
+        if b.is_ascii_graphic() {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+// Whether two authorities name the same origin: host names compared case-insensitively,
+// a trailing dot ignored, and the same port (80 when absent)
+pub(crate) fn same_origin(a: &str, b: &str) -> bool {
+    let (a_name, a_port) = split_authority(a);
+    let (b_name, b_port) = split_authority(b);
+    a_port == b_port && a_name.trim_end_matches('.').eq_ignore_ascii_case(b_name.trim_end_matches('.'))
+}
+
+// Resolve `location` against the absolute URL `base`: an absolute URL is returned as it is;
+// //authority/path, /path, relative paths and ?query are taken relative to `base`, with
+// "." and ".." segments applied. e.g. join_url("http://a/b/c", "../d") gives "http://a/d"
+pub fn join_url(base: &str, location: &str) -> Result<String, HttpError> {
+    let invalid = || HttpError::InvalidRequest(format!("Base is not an absolute URL: {}", base));
+    if !is_absolute_url(base) {
+        return Err(invalid());
+    }
+    let location = location.trim();
+    if has_scheme(location) {
+        return Ok(location.to_string());
+    }
+
+    let (reference, fragment) = match location.split_once('#') {
+        Some((reference, fragment)) => (reference, Some(fragment)),
+        None => (location, None),
+    };
+    let (scheme, rest) = base.split_once("://").ok_or_else(invalid)?;
+    let rest = rest.split('#').next().unwrap_or("");
+    let (authority, path) = split_absolute(rest).ok_or_else(invalid)?;
+    let (authority, path) = resolve_reference(&authority, &path, reference)
+        .ok_or_else(|| HttpError::InvalidRequest(format!("Invalid location: {}", location)))?;
+
+    let mut url = format!("{}://{}{}", scheme, authority, path);
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.push_str(fragment);
+    }
+    Ok(url)
+}
+
+// A reference without a scheme (or fragment) against the current authority and path
+fn resolve_reference(authority: &str, path: &str, reference: &str) -> Option<(String, String)> {
+    if let Some(rest) = reference.strip_prefix("//") {
+        return split_absolute(rest).map(|(authority, path)| (authority, remove_dot_segments(&path)));
+    }
+    let base = path.split('?').next().unwrap_or("");
+    let path = if reference.is_empty() {
+        path.to_string()
+    } else if reference.starts_with('/') {
+        remove_dot_segments(reference)
+    } else if reference.starts_with('?') {
+        format!("{}{}", base, reference)
+    } else {
+        // Relative to the directory of the current path
+        let dir = match base.rfind('/') {
+            Some(i) => &base[..=i],
+            None => "/",
+        };
+        remove_dot_segments(&format!("{}{}", dir, reference))
+    };
+    Some((authority.to_string(), path))
+}
+
+// Apply "." and ".." segments (RFC 3986 section 5.2.4) to a path starting with '/'; the query
+// is left alone
+fn remove_dot_segments(path: &str) -> String {
+    let (path, query) = path.split_at(path.find('?').unwrap_or(path.len()));
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    let mut out: Vec<&str> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match *segment {
+            "." => {}
+            ".." => {
+                out.pop();
+            }
+            segment => {
+                out.push(segment);
+                continue;
+            }
+        }
+        // A trailing "." or ".." still names a directory
+        if last {
+            out.push("");
+        }
+    }
+    format!("/{}{}", out.join("/"), query)
+}
+
+// scheme://authority[/...] with no whitespace, control or non-ASCII characters
+pub(crate) fn is_absolute_url(url: &str) -> bool {
+    if !has_scheme(url) || !url.bytes().all(|b| b.is_ascii_graphic()) {
+        return false;
+    }
+    let rest = url.split_once(':').map(|(_, rest)| rest).unwrap_or("");
+    match rest.strip_prefix("//") {
+        Some(rest) => !rest.starts_with(['/', '?', '#']) && !rest.is_empty(),
+        None => false,
+    }
+}
+
+// The host and path of an absolute http:// URL, without its fragment
+pub(crate) fn split_url(url: &str) -> Option<(String, String)> {
+    let rest = url.split('#').next()?.strip_prefix("http://")?;
+    split_absolute(rest)
+}
+
+fn split_absolute(rest: &str) -> Option<(String, String)> {
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(end);
+    if authority.is_empty() {
+        return None;
+    }
+    let path = match path {
+        "" => "/".to_string(),
+        p if p.starts_with('/') => p.to_string(),
+        p => format!("/{}", p),
+    };
+    Some((authority.to_string(), path))
+}
+
+// The scheme of an absolute URL, e.g. "file" for file:///etc/passwd
+pub(crate) fn scheme(location: &str) -> Option<&str> {
+    let location = location.trim();
+    has_scheme(location).then(|| location.split_once(':').map_or("", |(scheme, _)| scheme))
+}
+
+fn has_scheme(location: &str) -> bool {
+    match location.split_once(':') {
+        Some((scheme, _)) => {
+            !scheme.is_empty()
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+// The bytes `text` stands for with its %XX escapes decoded; a '%' not followed by two hex
+// digits is kept as it is
+pub(crate) fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) if bytes[i] == b'%' => {
+                out.push(byte);
+                i += 3;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_authorities_are_borrowed() {
+        assert!(matches!(ascii_authority("example.com:8080"), Ok(Cow::Borrowed("example.com:8080"))));
+        assert!(matches!(ascii_authority("[::1]:8080"), Ok(Cow::Borrowed("[::1]:8080"))));
+        assert_eq!(ascii_authority("bücher.example:8080").unwrap(), "xn--bcher-kva.example:8080");
+    }
+
+    #[test]
+    fn authorities_that_are_not_host_names_are_refused() {
+        for authority in ["h\r\nX: y", "ex ample", "host:", "host:99999", "::1", "[::1]x", "[nope]", "a@b"] {
+            assert!(matches!(ascii_authority(authority), Err(HttpError::InvalidRequest(_))), "{:?}", authority);
+        }
+    }
+
+    #[test]
+    fn absolute_urls_need_a_scheme_a_host_and_visible_ascii() {
+        for url in ["https://example.com", "http://example.com/a?b=c#d", "ftp://h:21/x"] {
+            assert!(is_absolute_url(url), "{:?}", url);
+        }
+        for url in ["/relative", "example.com/a", "https:///path", "http://", "mailto:a@b", "http://a b", "http://a\r\nX: y", "http://é.example"] {
+            assert!(!is_absolute_url(url), "{:?}", url);
+        }
+    }
+
+    #[test]
+    fn locations_join_in_every_form() {
+        let base = "http://a/b/c/d;p?q";
+        // RFC 3986 section 5.4, plus the forms redirects use most
+        for (location, joined) in [
+            ("https://other.example/x", "https://other.example/x"),
+            ("//g", "http://g/"),
+            ("//g:8080/x/../y", "http://g:8080/y"),
+            ("/g", "http://a/g"),
+            ("/./g/../h", "http://a/h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("sub/path", "http://a/b/c/sub/path"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("../../../../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+        ] {
+            assert_eq!(join_url(base, location).unwrap(), joined, "{:?}", location);
+        }
+    }
+
+    #[test]
+    fn a_base_must_be_absolute() {
+        for base in ["/relative", "example.com/path", "http://"] {
+            assert!(matches!(join_url(base, "/x"), Err(HttpError::InvalidRequest(_))), "{:?}", base);
+        }
+    }
+
+    #[test]
+    fn join_authority_brackets_ipv6_literals() {
+        assert_eq!(join_authority("example.com", 80), "example.com:80");
+        assert_eq!(join_authority("127.0.0.1", 8080), "127.0.0.1:8080");
+        assert_eq!(join_authority("::1", 443), "[::1]:443");
+    }
+
+    #[test]
+    fn percent_decode_leaves_bad_escapes() {
+        assert_eq!(percent_decode("a%20b%2Fc"), b"a b/c");
+        assert_eq!(percent_decode("%e2%82%ac"), "\u{20ac}".as_bytes());
+        assert_eq!(percent_decode("100%"), b"100%");
+        assert_eq!(percent_decode("%zz%4"), b"%zz%4");
+    }
+
+    #[test]
+    fn split_url_takes_host_and_path() {
+        let split = |url: &str| split_url(url).map(|(host, path)| format!("{} {}", host, path));
+        assert_eq!(split("http://example.com:8080/health?x=1#top").as_deref(), Some("example.com:8080 /health?x=1"));
+        assert_eq!(split("http://example.com").as_deref(), Some("example.com /"));
+        assert_eq!(split("https://example.com/"), None);
+        assert_eq!(split("example.com/"), None);
+    }
+
+    #[test]
+    fn scheme_of_absolute_locations_only() {
+        assert_eq!(scheme("file:///etc/passwd"), Some("file"));
+        assert_eq!(scheme(" GOPHER://host/1 "), Some("GOPHER"));
+        assert_eq!(scheme("http://example.com/"), Some("http"));
+        assert_eq!(scheme("/next"), None);
+        assert_eq!(scheme("next?page=2"), None);
+    }
+}
@@ -0,0 +1,60 @@
+// Counts the allocations the client makes on its own thread for each small GET on a pooled
+// connection; TestServer's thread isn't counted
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations_for<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn allocations_per_request_stay_small_and_flat() {
+    let server = TestServer::start();
+    let address = server.address();
+    for _ in 0..12 {
+        server.respond_with(200, &[], "ok");
+    }
+    let client = Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() });
+    let get = || {
+        let response = client.request(Method::Get, &address, "/").send().unwrap();
+        assert_eq!(response.body, b"ok");
+    };
+    // The first requests open the connection and fill the scratch pool
+    for _ in 0..2 {
+        get();
+    }
+    assert_eq!(client.pool_stats().created, 1);
+    let counts: Vec<usize> = (0..10).map(|_| allocations_for(get)).collect();
+    assert!(counts.iter().all(|&n| n == counts[0]), "allocations per request changed: {:?}", counts);
+    assert!(counts[0] <= 12, "{} allocations per request", counts[0]);
+}
@@ -0,0 +1,66 @@
+use std::io::{Cursor, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+// Sends the bytes of one request, read until they end in `last`, back over the channel
+fn capturing_server(last: &'static [u8]) -> (String, mpsc::Receiver<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0; 1024];
+        while !received.ends_with(last) {
+            match stream.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+            }
+        }
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let _ = sender.send(received);
+    });
+    (address, receiver)
+}
+
+fn buffered(bare_lf: bool) -> Vec<u8> {
+    let (address, received) = capturing_server(b"END");
+    let client = Client::with_config(RequestConfig { bare_lf, ..RequestConfig::default() });
+    client.request(Method::Post, &address, "/").header("X-A", "1").body("line\r\nEND").send().unwrap();
+    received.recv().unwrap()
+}
+
+fn chunked(bare_lf: bool) -> Vec<u8> {
+    let (address, received) = capturing_server(b"0\r\n\r\n");
+    let client = Client::with_config(RequestConfig { bare_lf, ..RequestConfig::default() });
+    client.request(Method::Post, &address, "/").body_stream(Cursor::new(b"streamed".to_vec())).send().unwrap();
+    received.recv().unwrap()
+}
+
+fn head_and_body(request: &[u8], blank_line: &[u8]) -> (String, Vec<u8>) {
+    let at = request.windows(blank_line.len()).position(|w| w == blank_line).unwrap();
+    (String::from_utf8(request[..at + blank_line.len()].to_vec()).unwrap(), request[at + blank_line.len()..].to_vec())
+}
+
+#[test]
+fn crlf_by_default() {
+    for request in [buffered(false), chunked(false)] {
+        let (head, _) = head_and_body(&request, b"\r\n\r\n");
+        assert_eq!(head.matches('\n').count(), head.matches("\r\n").count(), "{:?}", head);
+    }
+}
+
+#[test]
+fn bare_lf_ends_head_lines_in_lf_only() {
+    let (head, body) = head_and_body(&buffered(true), b"\n\n");
+    assert!(head.starts_with("POST / HTTP/1.1\n") && head.contains("\nX-A: 1\n"), "{:?}", head);
+    assert!(!head.contains('\r'), "{:?}", head);
+    assert_eq!(body, b"line\r\nEND");
+
+    let (head, body) = head_and_body(&chunked(true), b"\n\n");
+    assert!(!head.contains('\r') && head.contains("\nTransfer-Encoding: chunked\n"), "{:?}", head);
+    assert_eq!(body, b"8\r\nstreamed\r\n0\r\n\r\n");
+}
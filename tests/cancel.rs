@@ -0,0 +1,77 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{CancelToken, Client, HttpError, Method, RequestConfig};
+
+fn cancellable(token: &CancelToken) -> Client {
+    Client::with_config(RequestConfig { cancel: Some(token.clone()), ..RequestConfig::default() })
+}
+
+// Announces a large body, sends a little of it, then stalls
+fn slow_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 1024];
+        let _ = stream.read(&mut buf);
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000000\r\n\r\nfirst bytes");
+        thread::sleep(Duration::from_secs(3));
+    });
+    address
+}
+
+#[test]
+fn a_stalled_download_is_cancelled_promptly() {
+    let address = slow_server();
+    let token = CancelToken::new();
+    let canceller = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        canceller.cancel();
+    });
+
+    let start = Instant::now();
+    let result = cancellable(&token).request(Method::Get, &address, "/big").send();
+    let elapsed = start.elapsed();
+    assert!(matches!(result, Err(HttpError::Cancelled)), "{:?}", result);
+    assert!(elapsed < Duration::from_millis(600), "took {:?}", elapsed);
+}
+
+#[test]
+fn cancelling_after_completion_only_stops_later_requests() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "done");
+    let token = CancelToken::new();
+    let client = cancellable(&token);
+    let response = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    token.cancel();
+    assert_eq!(response.body, b"done");
+
+    let result = client.request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(result, Err(HttpError::Cancelled)), "{:?}", result);
+    assert_eq!(server.requests().len(), 1);
+}
+
+// A flag the caller already shares, set from another thread during a slow read
+#[test]
+fn a_shared_atomic_flag_cancels_a_slow_read() {
+    let address = slow_server();
+    let flag = Arc::new(AtomicBool::new(false));
+    let setter = flag.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        setter.store(true, Ordering::SeqCst);
+    });
+
+    let start = Instant::now();
+    let result = cancellable(&CancelToken::from(flag)).request(Method::Get, &address, "/big").send();
+    let elapsed = start.elapsed();
+    assert!(matches!(result, Err(HttpError::Cancelled)), "{:?}", result);
+    assert!(elapsed >= Duration::from_millis(200) && elapsed < Duration::from_millis(700), "took {:?}", elapsed);
+}
@@ -0,0 +1,68 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Cassette, CassetteMode, Client, HttpError, Matching, Method};
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()))
+}
+
+#[test]
+fn recorded_exchanges_replay_without_the_network() {
+    let server = TestServer::start();
+    let address = server.address();
+    server.respond_with(200, &[("Content-Type", "text/plain")], "first");
+    server.respond_with(201, &[], "created");
+
+    let recording = Cassette::recording();
+    let client = Client::new().with_cassette(recording.clone());
+    client.request(Method::Get, &address, "/items?page=1").header("Authorization", "Bearer secret").send().unwrap();
+    client.request(Method::Post, &address, "/items").body("{\"a\":1}").send().unwrap();
+    assert_eq!(recording.len(), 2);
+
+    let path = temp_path("cassette-replay");
+    recording.save(&path).unwrap();
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("secret"));
+    // Nothing is listening any more, so any request that reached the network would fail
+    drop(server);
+
+    let replay = Cassette::load(&path).unwrap().with_matching(Matching { headers: false, body: true });
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(replay.mode(), CassetteMode::Replay);
+    let client = Client::new().with_cassette(replay);
+    let get = client.request(Method::Get, &address, "/items?page=1").send().unwrap();
+    assert_eq!(get.status().as_u16(), 200);
+    assert_eq!(get.body, b"first");
+    assert_eq!(get.header("Content-Type"), Some("text/plain"));
+    let post = client.request(Method::Post, &address, "/items").body("{\"a\":1}").send().unwrap();
+    assert_eq!(post.status().as_u16(), 201);
+
+    let error = client.request(Method::Post, &address, "/items").body("{\"a\":2}").send().unwrap_err();
+    assert!(matches!(&error, HttpError::InvalidRequest(message) if message.contains("No cassette interaction")), "{:?}", error);
+}
+
+#[test]
+fn filtered_headers_are_stored_as_placeholders() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("X-Session", "server-secret")], "ok");
+    let recording = Cassette::recording().filter_header("X-Api-Key").filter_header("x-session");
+    let client = Client::new().with_cassette(recording.clone());
+    client.request(Method::Get, &server.address(), "/").header("X-Api-Key", "client-secret").send().unwrap();
+
+    let json = recording.to_json().to_string();
+    assert!(!json.contains("client-secret") && !json.contains("server-secret"), "{}", json);
+    assert_eq!(json.matches("<filtered>").count(), 2, "{}", json);
+}
+
+#[test]
+fn error_statuses_replay_as_errors() {
+    let server = TestServer::start();
+    let address = server.address();
+    server.respond_with(404, &[], "missing");
+    let recording = Cassette::recording();
+    let client = Client::new().with_cassette(recording.clone());
+    assert!(matches!(client.request(Method::Get, &address, "/gone").send(), Err(HttpError::HttpError { code: 404, .. })));
+    drop(server);
+
+    let replay = Cassette::from_json(&recording.to_json()).unwrap();
+    let client = Client::new().with_cassette(replay);
+    assert!(matches!(client.request(Method::Get, &address, "/gone").send(), Err(HttpError::HttpError { code: 404, .. })));
+}
@@ -0,0 +1,41 @@
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{check, Client, HttpError, TimeoutPhase};
+
+#[test]
+fn a_reachable_host_gives_timings_whatever_the_status() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    server.respond_with(503, &[], "");
+    let client = Client::new();
+    let up = client.check(&format!("http://{}/health", server.address()), Duration::from_secs(5)).unwrap();
+    let unavailable = check(&format!("http://{}", server.address()), Duration::from_secs(5)).unwrap();
+    assert!(up.total() > Duration::ZERO && unavailable.total() > Duration::ZERO);
+    assert!(up.bytes_received > 0);
+
+    let requests = server.requests();
+    assert_eq!((requests[0].method.as_str(), requests[0].target.as_str()), ("HEAD", "/health"));
+    assert_eq!((requests[1].method.as_str(), requests[1].target.as_str()), ("HEAD", "/"));
+}
+
+#[test]
+fn an_unanswering_host_times_out_within_the_bound() {
+    // Accepted by the kernel, but never read from or answered
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let limit = Duration::from_millis(300);
+    let start = Instant::now();
+    let result = Client::new().check(&format!("http://{}/", listener.local_addr().unwrap()), limit);
+    assert!(matches!(result, Err(HttpError::Timeout { phase: TimeoutPhase::Total, .. })), "{:?}", result);
+    assert!(start.elapsed() < limit * 3, "took {:?}", start.elapsed());
+}
+
+#[test]
+fn a_closed_port_or_non_http_url_fails_at_once() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let result = Client::new().check(&format!("http://127.0.0.1:{}/", port), Duration::from_secs(5));
+    assert!(matches!(result, Err(HttpError::NetworkError(_))), "{:?}", result);
+    let result = Client::new().check("https://example.com/", Duration::from_secs(5));
+    assert!(matches!(result, Err(HttpError::InvalidRequest(_))), "{:?}", result);
+}
@@ -0,0 +1,23 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+#[test]
+fn streamed_bodies_arrive_intact_without_a_content_length() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    server.respond_with(200, &[], "");
+    server.respond_with(200, &[], "");
+    let source: Vec<u8> = (0..100_000u32).map(|i| (i * 7) as u8).collect();
+    let small = Client::with_config(RequestConfig { request_chunk_size: 4096, ..RequestConfig::default() });
+    for client in [Client::new(), small] {
+        client.request(Method::Post, &server.address(), "/upload").body_stream(&source[..]).send().unwrap();
+    }
+    Client::new().request(Method::Post, &server.address(), "/upload").body_stream(&b""[..]).send().unwrap();
+
+    let requests = server.requests();
+    for (request, expected) in requests.iter().zip([&source[..], &source[..], b""]) {
+        assert_eq!(request.header("Transfer-Encoding"), Some("chunked"));
+        assert_eq!(request.header("Content-Length"), None);
+        assert_eq!(request.body, expected);
+    }
+}
@@ -0,0 +1,51 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// A port nothing listens on, for now
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn retrying_connects(connect_retries: u32) -> Client {
+    Client::with_config(RequestConfig { connect_retries, connect_retry_delay: Duration::from_millis(50), ..RequestConfig::default() })
+}
+
+#[test]
+fn connects_are_retried_until_a_late_server_is_up() {
+    let port = free_port();
+    let server = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        (&stream).write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nready").unwrap();
+    });
+
+    let start = Instant::now();
+    let response = retrying_connects(20).request(Method::Get, &format!("127.0.0.1:{}", port), "/").send().unwrap();
+    assert_eq!(response.body, b"ready");
+    assert!(start.elapsed() >= Duration::from_millis(150), "{:?}", start.elapsed());
+    server.join().unwrap();
+}
+
+#[test]
+fn without_connect_retries_a_refused_connect_fails_at_once() {
+    let address = format!("127.0.0.1:{}", free_port());
+    let start = Instant::now();
+    let result = retrying_connects(0).request(Method::Get, &address, "/").send();
+    assert!(matches!(result, Err(HttpError::NetworkError(_))), "{:?}", result);
+    assert!(start.elapsed() < Duration::from_millis(50), "{:?}", start.elapsed());
+
+    let start = Instant::now();
+    assert!(retrying_connects(3).request(Method::Get, &address, "/").send().is_err());
+    // Three pauses before the three extra attempts
+    assert!(start.elapsed() >= Duration::from_millis(150), "{:?}", start.elapsed());
+}
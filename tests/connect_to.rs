@@ -0,0 +1,63 @@
+use std::net::SocketAddr;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+fn mapped(authority: &str, addr: SocketAddr) -> Client {
+    let mut config = RequestConfig::default();
+    config.connect_to.insert(authority.to_string(), addr);
+    Client::with_config(config)
+}
+
+#[test]
+fn connects_to_the_mapped_address_and_keeps_the_logical_host() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let addr: SocketAddr = server.address().parse().unwrap();
+    let response = mapped("api.example.test", addr).request(Method::Get, "api.example.test", "/").send().unwrap();
+
+    assert_eq!(response.connection.unwrap().peer_addr, addr);
+    assert_eq!(server.requests()[0].header("Host"), Some("api.example.test"));
+}
+
+#[test]
+fn mapping_matches_host_and_port_ignoring_case() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let addr: SocketAddr = server.address().parse().unwrap();
+    let client = mapped("api.example.test:8080", addr);
+    client.request(Method::Get, "API.example.test:8080", "/").send().unwrap();
+    assert_eq!(server.requests()[0].header("Host"), Some("API.example.test:8080"));
+}
+
+#[test]
+fn of_two_backends_the_request_lands_on_the_mapped_one() {
+    let (blue, green) = (TestServer::start(), TestServer::start());
+    blue.respond_with(200, &[], "blue");
+    green.respond_with(200, &[], "green");
+    let client = mapped("app.example:8080", green.address().parse().unwrap());
+    let response = client.request(Method::Get, "app.example:8080", "/").send().unwrap();
+    assert_eq!(response.body, b"green");
+    assert!(blue.requests().is_empty());
+    assert_eq!(green.requests()[0].header("Host"), Some("app.example:8080"));
+}
+
+#[test]
+fn redirects_follow_a_mapping_only_for_the_host_it_names() {
+    let (first, second) = (TestServer::start(), TestServer::start());
+    first.respond_with(302, &[("Location", "http://other.example:8080/next")], "");
+    first.respond_with(302, &[("Location", "http://unmapped.invalid/")], "");
+    second.respond_with(200, &[], "second");
+    let mut config = RequestConfig { max_redirects: 1, ..RequestConfig::default() };
+    config.connect_to.insert("app.example:8080".to_string(), first.address().parse().unwrap());
+    config.connect_to.insert("other.example:8080".to_string(), second.address().parse().unwrap());
+    let client = Client::with_config(config);
+
+    let response = client.request(Method::Get, "app.example:8080", "/").send().unwrap();
+    assert_eq!(response.body, b"second");
+    assert_eq!(second.requests()[0].header("Host"), Some("other.example:8080"));
+
+    assert!(client.request(Method::Get, "app.example:8080", "/").send().is_err());
+    assert_eq!(first.requests().len(), 2);
+    assert_eq!(second.requests().len(), 1);
+}
@@ -0,0 +1,32 @@
+use std::net::SocketAddr;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{get, Client, HeaderList, HttpError, HttpResponse, Method};
+
+#[test]
+fn first_response_reports_the_peer_and_a_new_connection() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let response = get(&server.address(), "/", HeaderList::new()).unwrap();
+
+    let addr: SocketAddr = server.address().parse().unwrap();
+    let connection = response.connection.unwrap();
+    assert_eq!(connection.peer_addr, addr);
+    assert_eq!(response.peer_addr(), Some(addr));
+    assert!(connection.local_addr.ip().is_loopback());
+    assert_ne!(connection.local_addr.port(), addr.port());
+    assert!(!connection.reused);
+}
+
+#[test]
+fn errors_after_connecting_name_the_connection() {
+    let server = TestServer::start();
+    server.respond_with_fn(|_| {
+        let mut response = HttpResponse::new(200, &[], "ok");
+        response.status_line = "not a status line".to_string();
+        response
+    });
+    let error = Client::new().request(Method::Get, &server.address(), "/").send().unwrap_err();
+    assert!(matches!(error, HttpError::InvalidResponse(_)), "{:?}", error);
+    assert!(error.to_string().contains(&server.address()), "{}", error);
+}
@@ -0,0 +1,109 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// A server that answers each request after 20ms and records the most connections it ever
+// had open at once. Unless `keep_alive`, it closes after one response, and stops counting
+// the connection before the client can see that response.
+fn counting_server(keep_alive: bool) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (open, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let peak = most.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let (open, most) = (open.clone(), most.clone());
+            most.fetch_max(open.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                'requests: loop {
+                    loop {
+                        line.clear();
+                        match reader.read_line(&mut line) {
+                            Ok(0) | Err(_) => break 'requests,
+                            Ok(_) if line == "\r\n" => break,
+                            Ok(_) => {}
+                        }
+                    }
+                    thread::sleep(Duration::from_millis(20));
+                    if !keep_alive {
+                        // Before the client can read the answer and open its next connection
+                        open.fetch_sub(1, Ordering::SeqCst);
+                        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+                        return;
+                    }
+                    if stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").is_err() {
+                        break;
+                    }
+                }
+                open.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    (address, peak)
+}
+
+fn limited(keep_alive: bool) -> Client {
+    Client::with_config(RequestConfig {
+        keep_alive,
+        max_connections: Some(2),
+        timeout: Some(Duration::from_secs(10)),
+        ..RequestConfig::default()
+    })
+}
+
+#[test]
+fn many_threads_never_hold_more_than_the_limit_open() {
+    for keep_alive in [true, false] {
+        let (address, peak) = counting_server(keep_alive);
+        let client = limited(keep_alive);
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let (client, address) = (client.clone(), address.clone());
+                thread::spawn(move || {
+                    for _ in 0..3 {
+                        assert_eq!(client.request(Method::Get, &address, "/").send().unwrap().body, b"ok");
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 2, "keep_alive {}: {} open at once", keep_alive, peak.load(Ordering::SeqCst));
+    }
+}
+
+#[test]
+fn at_the_limit_a_request_fails_at_once_or_waits_for_room() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "held");
+    server.respond_with(200, &[], "held");
+    server.respond_with(200, &[], "after");
+    let client = Client::with_config(RequestConfig { keep_alive: true, max_connections: Some(1), fail_at_connection_limit: true, ..RequestConfig::default() });
+    let held = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap();
+    let started = Instant::now();
+    let result = client.request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(&result, Err(HttpError::NetworkError(message)) if message.contains("Connection limit")), "{:?}", result);
+    assert!(started.elapsed() < Duration::from_millis(500));
+    drop(held);
+
+    let waiting = Client::with_config(RequestConfig { max_connections: Some(1), ..RequestConfig::default() });
+    let held = waiting.request(Method::Get, &server.address(), "/").send_streaming().unwrap();
+    let releaser = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        held.consume_body().unwrap();
+    });
+    let started = Instant::now();
+    assert_eq!(waiting.request(Method::Get, &server.address(), "/").send().unwrap().body, b"after");
+    assert!(started.elapsed() >= Duration::from_millis(150), "{:?}", started.elapsed());
+    releaser.join().unwrap();
+}
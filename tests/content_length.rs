@@ -0,0 +1,85 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+fn content_lengths(server: &TestServer, i: usize) -> Vec<String> {
+    server.requests()[i].headers.iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+        .map(|(_, value)| value.clone())
+        .collect()
+}
+
+#[test]
+fn an_explicit_length_is_sent_even_when_it_is_wrong() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    Client::new().request(Method::Post, &server.address(), "/").body("hello").content_length(3).send().unwrap();
+    assert_eq!(content_lengths(&server, 0), ["3"]);
+    // The server takes the length at its word
+    assert_eq!(server.requests()[0].body, b"hel");
+}
+
+// No warning is printed for a mismatch; what went out shows it
+#[test]
+fn send_debug_shows_the_length_next_to_the_body_written() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let (sent, _) = Client::new().request(Method::Post, &server.address(), "/").body("hello").content_length(3).send_debug().unwrap();
+    let sent = String::from_utf8(sent.bytes).unwrap();
+    assert!(sent.contains("\r\nContent-Length: 3\r\n"), "{}", sent);
+    assert!(sent.ends_with("\r\n\r\nhello"), "{}", sent);
+}
+
+#[test]
+fn a_content_length_header_replaces_the_computed_one() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    Client::new().request(Method::Post, &server.address(), "/").header("Content-Length", "5").body("hello").send().unwrap();
+    assert_eq!(content_lengths(&server, 0), ["5"]);
+}
+
+#[test]
+fn a_stream_with_a_length_is_sent_raw_and_cut_at_it() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    Client::new().request(Method::Put, &server.address(), "/").body_stream(&b"0123456789"[..]).content_length(4).send().unwrap();
+    let received = &server.requests()[0];
+    assert_eq!(received.header("Transfer-Encoding"), None);
+    assert_eq!(content_lengths(&server, 0), ["4"]);
+    assert_eq!(received.body, b"0123");
+}
+
+#[test]
+fn a_stream_shorter_than_its_length_or_with_trailers_fails() {
+    let server = TestServer::start();
+    let short = Client::new().request(Method::Put, &server.address(), "/").body_stream(&b"abc"[..]).content_length(10).send();
+    assert!(matches!(&short, Err(HttpError::InvalidRequest(message)) if message.contains("3 of the 10 bytes")), "{:?}", short);
+
+    let trailers = Client::new().request(Method::Put, &server.address(), "/")
+        .body_stream(&b"abc"[..])
+        .content_length(3)
+        .trailer("X-Sum", "1")
+        .send();
+    assert!(matches!(trailers, Err(HttpError::InvalidRequest(_))), "{:?}", trailers);
+}
+
+// Some("") is an empty body with Content-Length: 0; None is no body and no Content-Length
+#[test]
+fn an_empty_body_is_sent_with_a_zero_length() {
+    for gzip_request_body in [false, true] {
+        let server = TestServer::start();
+        for _ in 0..4 {
+            server.respond_with(200, &[], "");
+        }
+        let client = Client::with_config(RequestConfig { gzip_request_body, ..RequestConfig::default() });
+        client.request(Method::Post, &server.address(), "/").send().unwrap();
+        client.request(Method::Post, &server.address(), "/").body("").send().unwrap();
+        client.post(&server.address(), "/", "", None).unwrap();
+        client.request(Method::Get, &server.address(), "/").body("").send().unwrap();
+
+        assert!(content_lengths(&server, 0).is_empty());
+        for i in 1..4 {
+            assert_eq!(content_lengths(&server, i), ["0"], "request {} with gzip {}", i, gzip_request_body);
+            assert_eq!(server.requests()[i].header("Content-Encoding"), None);
+        }
+    }
+}
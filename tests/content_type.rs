@@ -0,0 +1,31 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+#[test]
+fn an_html_error_page_fails_where_json_was_expected() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Content-Type", "application/json; charset=utf-8")], "{\"ok\":true}");
+    server.respond_with(200, &[("Content-Type", "text/html; charset=utf-8")], "<html>Maintenance</html>");
+    let client = Client::new();
+
+    let json = client.request(Method::Get, &server.address(), "/api").send().unwrap();
+    assert_eq!(json.expect_content_type("application/json").unwrap().body, b"{\"ok\":true}");
+
+    let html = client.request(Method::Get, &server.address(), "/api").send().unwrap();
+    let error = html.expect_content_type("application/json").unwrap_err();
+    assert!(matches!(&error, HttpError::InvalidResponse(message) if message.contains("application/json") && message.contains("text/html")), "{:?}", error);
+}
+
+#[test]
+fn content_type_parses_the_header_a_server_sent() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Content-Type", "Application/Problem+JSON; charset=\"utf-8\"")], "{}");
+    server.respond_with(200, &[], "untyped");
+    let client = Client::new();
+
+    let mime = client.request(Method::Get, &server.address(), "/").send().unwrap().content_type().unwrap();
+    assert_eq!(mime.essence(), "application/problem+json");
+    assert!(mime.is_json());
+    assert_eq!(mime.charset(), Some("utf-8"));
+    assert_eq!(client.request(Method::Get, &server.address(), "/").send().unwrap().content_type(), None);
+}
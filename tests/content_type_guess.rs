@@ -0,0 +1,39 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{build_request, to_curl, Client, Method};
+
+// The Content-Type each body arrives with when sent without one
+fn sent_type(body: &str, headers: &[(&str, &str)]) -> Option<String> {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let client = Client::new();
+    let mut request = client.request(Method::Post, &server.address(), "/").body(body);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    request.send().unwrap();
+    let received = server.requests().remove(0);
+    assert!(received.headers.iter().filter(|(name, _)| name.eq_ignore_ascii_case("Content-Type")).count() <= 1);
+    received.header("Content-Type").map(str::to_string)
+}
+
+#[test]
+fn json_xml_form_and_binary_bodies() {
+    assert_eq!(sent_type("{\"name\":\"x\"}", &[]).as_deref(), Some("application/json"));
+    assert_eq!(sent_type("<note><to>x</to></note>", &[]).as_deref(), Some("application/xml"));
+    assert_eq!(sent_type("a=1&b=two", &[]).as_deref(), Some("application/x-www-form-urlencoded"));
+    assert_eq!(sent_type("\u{1}\u{2}binary\u{0}", &[]).as_deref(), Some("application/octet-stream"));
+}
+
+#[test]
+fn an_explicit_type_wins_and_empty_bodies_get_none() {
+    assert_eq!(sent_type("{\"name\":\"x\"}", &[("content-type", "text/plain")]).as_deref(), Some("text/plain"));
+    assert_eq!(sent_type("", &[]), None);
+}
+
+#[test]
+fn build_request_and_to_curl_guess_the_same() {
+    let request = String::from_utf8(build_request(Method::Post, "example.com", "/", Some("<a/>"), Vec::<(String, String)>::new()).unwrap()).unwrap();
+    assert!(request.contains("\r\nContent-Type: application/xml\r\n"), "{}", request);
+    let curl = to_curl(Method::Post, "example.com", "/", Some("<a/>"), Vec::<(String, String)>::new(), false);
+    assert!(curl.contains("Content-Type: application/xml"), "{}", curl);
+}
@@ -0,0 +1,52 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, SameSite};
+
+#[test]
+fn every_set_cookie_header_becomes_a_cookie() {
+    let server = TestServer::start();
+    server.respond_with(200, &[
+        ("Set-Cookie", "session=abc=def; Path=/; HttpOnly; SameSite=Lax"),
+        ("Content-Type", "text/plain"),
+        ("set-cookie", "theme=dark; Max-Age=31536000; Domain=.example.com"),
+        ("Set-Cookie", "=no-name"),
+    ], "");
+    let response = Client::new().request(Method::Get, &server.address(), "/").send().unwrap();
+    let cookies = response.cookies();
+
+    // The one without a name is left out
+    assert_eq!(cookies.len(), 2);
+    assert_eq!((cookies[0].name.as_str(), cookies[0].value.as_str()), ("session", "abc=def"));
+    assert!(cookies[0].http_only);
+    assert_eq!(cookies[0].same_site, Some(SameSite::Lax));
+    assert_eq!(cookies[1].name, "theme");
+    assert_eq!(cookies[1].domain.as_deref(), Some("example.com"));
+    assert_eq!(cookies[1].max_age.map(|max_age| max_age.as_secs()), Some(31_536_000));
+}
+
+#[test]
+fn builder_cookies_collapse_into_one_header() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    server.respond_with(200, &[], "");
+    let client = Client::new().with_default_header("Cookie", "session=s; a=old");
+    client.request(Method::Get, &server.address(), "/")
+        .header("Cookie", "early=0")
+        .cookie("a", "1")
+        .cookies([("b", "2"), ("c", "\"xy\"")])
+        .send()
+        .unwrap();
+    client.request(Method::Get, &server.address(), "/").cookies(vec![("a".to_string(), "new".to_string())]).send().unwrap();
+
+    for (request, expected) in server.requests().iter().zip(["session=s; early=0; a=1; b=2; c=\"xy\"", "session=s; a=new"]) {
+        let cookies: Vec<&str> = request.headers.iter().filter(|(name, _)| name.eq_ignore_ascii_case("Cookie")).map(|(_, value)| value.as_str()).collect();
+        assert_eq!(cookies, [expected]);
+    }
+}
+
+#[test]
+fn an_invalid_cookie_fails_the_send() {
+    let server = TestServer::start();
+    let result = Client::new().request(Method::Get, &server.address(), "/").cookie("a", "x; injected=1").send();
+    assert!(matches!(result, Err(HttpError::InvalidHeader(_))), "{:?}", result);
+    assert!(server.requests().is_empty());
+}
@@ -0,0 +1,84 @@
+use std::sync::{Arc, Mutex};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Challenge, Client, Credentials, HttpError, HttpResponse, Method};
+
+// Basic realm "api" accepting Aladdin / open sesame, for up to two requests
+fn protected() -> TestServer {
+    let server = TestServer::start();
+    for _ in 0..2 {
+        server.respond_with_fn(|request| {
+            if request.header("Authorization") == Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==") {
+                HttpResponse::new(200, &[], "secret")
+            } else {
+                HttpResponse::new(401, &[("WWW-Authenticate", "Newauth realm=\"other\", Basic realm=\"api\", charset=\"UTF-8\"")], "")
+            }
+        });
+    }
+    server
+}
+
+// A client answering with `password` (or declining), and the realms it was asked for
+fn client(password: Option<&'static str>) -> (Client, Arc<Mutex<Vec<String>>>) {
+    let asked = Arc::new(Mutex::new(Vec::new()));
+    let log = asked.clone();
+    let client = Client::new().with_credential_callback(move |challenge: &Challenge| {
+        log.lock().unwrap().push(format!("{} {}", challenge.scheme, challenge.realm().unwrap_or("")));
+        password.map(|password| Credentials::basic("Aladdin", password))
+    });
+    (client, asked)
+}
+
+#[test]
+fn the_right_password_is_sent_only_after_the_challenge() {
+    let server = protected();
+    let (client, asked) = client(Some("open sesame"));
+    let response = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.body, b"secret");
+    assert_eq!(*asked.lock().unwrap(), ["Basic api"]);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].header("Authorization"), None);
+}
+
+#[test]
+fn a_wrong_password_is_tried_once_and_the_401_returned() {
+    let server = protected();
+    let (client, asked) = client(Some("guess"));
+    let result = client.request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(result, Err(HttpError::HttpError { code: 401, .. })), "{:?}", result);
+    assert_eq!(asked.lock().unwrap().len(), 1);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn a_declined_callback_returns_the_401_without_a_retry() {
+    let server = protected();
+    let (client, asked) = client(None);
+    let result = client.request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(result, Err(HttpError::HttpError { code: 401, .. })), "{:?}", result);
+    assert_eq!(asked.lock().unwrap().len(), 1);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn a_request_with_its_own_authorization_is_left_alone() {
+    let server = protected();
+    let (client, asked) = client(Some("open sesame"));
+    let result = client.request(Method::Get, &server.address(), "/").header("Authorization", "Basic bm9wZQ==").send();
+    assert!(matches!(result, Err(HttpError::HttpError { code: 401, .. })), "{:?}", result);
+    assert!(asked.lock().unwrap().is_empty());
+}
+
+// The callback is asked about the Basic challenge even when a Digest one comes first
+#[test]
+fn basic_is_picked_from_behind_a_digest_challenge() {
+    let server = TestServer::start();
+    server.respond_with(401, &[("WWW-Authenticate", "Digest realm=\"y\", nonce=\"z\", Basic realm=\"x\"")], "");
+    server.respond_with(200, &[], "in");
+    let (client, asked) = client(Some("open sesame"));
+    let response = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.body, b"in");
+    assert_eq!(*asked.lock().unwrap(), ["Basic x"]);
+    assert_eq!(server.requests()[1].header("Authorization"), Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+}
@@ -0,0 +1,84 @@
+use http_client_from_scratch_rust::testing::{ReceivedRequest, TestServer};
+use http_client_from_scratch_rust::{Client, HttpResponse, Method, RequestConfig};
+
+fn decompressing() -> Client {
+    Client::with_config(RequestConfig { decompress: true, ..RequestConfig::default() })
+}
+
+// There is no brotli decoder, so br is neither asked for nor decoded
+#[test]
+fn brotli_is_not_requested_and_passes_through() {
+    let server = TestServer::start();
+    let encoded = [0x0b, 0x02, 0x80, 0x68, 0x69, 0x03];
+    server.respond_with(200, &[("Content-Encoding", "br")], encoded);
+    let response = decompressing().request(Method::Get, &server.address(), "/").send().unwrap();
+
+    assert_eq!(server.requests()[0].header("Accept-Encoding"), Some("gzip"));
+    assert_eq!(response.header("Content-Encoding"), Some("br"));
+    assert_eq!(response.body, encoded);
+}
+
+fn count(request: &ReceivedRequest, name: &str) -> usize {
+    request.headers.iter().filter(|(key, _)| key.eq_ignore_ascii_case(name)).count()
+}
+
+// The server sends the compressed request body straight back as a gzip response, so the
+// client decoding it shows the request was valid gzip too
+#[test]
+fn compressed_post_gets_a_compressed_reply() {
+    let server = TestServer::start();
+    server.respond_with_fn(|request| {
+        HttpResponse::new(200, &[("Content-Encoding", "gzip"), ("Content-Type", "text/plain")], request.body.clone())
+    });
+    let client = Client::with_config(RequestConfig { gzip_request_body: true, decompress: true, ..RequestConfig::default() });
+    let body = "compress me, compress me, compress me, compress me";
+    let response = client.request(Method::Post, &server.address(), "/upload")
+        .header("Content-Encoding", "identity")
+        .header("Content-Length", "999")
+        .body(body)
+        .send()
+        .unwrap();
+
+    let sent = &server.requests()[0];
+    assert_eq!(sent.header("Content-Encoding"), Some("gzip"));
+    assert_eq!(sent.header("Accept-Encoding"), Some("gzip"));
+    for name in ["Content-Encoding", "Content-Length", "Accept-Encoding"] {
+        assert_eq!(count(sent, name), 1, "{}", name);
+    }
+    assert_eq!(sent.header("Content-Length"), Some(sent.body.len().to_string().as_str()));
+    assert_eq!(&sent.body[..2], [0x1F, 0x8B]);
+    assert!(sent.body.len() < body.len());
+
+    assert_eq!(response.body, body.as_bytes());
+    assert_eq!(response.header("Content-Encoding"), None);
+    assert_eq!(response.header("Content-Length"), None);
+    assert_eq!(response.header("Content-Type"), Some("text/plain"));
+}
+
+// Another coding set by the caller means the body is encoded already
+#[test]
+fn body_with_its_own_coding_is_sent_as_is() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let client = Client::with_config(RequestConfig { gzip_request_body: true, ..RequestConfig::default() });
+    client.request(Method::Post, &server.address(), "/").header("Content-Encoding", "br").body("xyz").send().unwrap();
+
+    let sent = &server.requests()[0];
+    assert_eq!(sent.header("Content-Encoding"), Some("br"));
+    assert_eq!(count(sent, "Content-Encoding"), 1);
+    assert_eq!(sent.body, b"xyz");
+}
+
+// The codings under gzip stay listed
+#[test]
+fn only_the_last_coding_is_undone() {
+    let mut response = HttpResponse::new(200, &[("Content-Encoding", "br, gzip")], Vec::new());
+    // Python's gzip.compress(b"stored", 0, mtime=0)
+    response.body = vec![
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x06, 0x00, 0xf9, 0xff, 0x73, 0x74, 0x6f, 0x72,
+        0x65, 0x64, 0x0b, 0xf9, 0x43, 0x56, 0x06, 0x00, 0x00, 0x00,
+    ];
+    response.decompress().unwrap();
+    assert_eq!(response.body, b"stored");
+    assert_eq!(response.header("Content-Encoding"), Some("br"));
+}
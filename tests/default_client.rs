@@ -0,0 +1,30 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{default_client, get, post, set_default_client, Client, Method};
+
+// One test, as the default client is shared by every test in this binary
+#[test]
+fn the_default_client_is_used_by_the_free_functions_only() {
+    let server = TestServer::start();
+    for _ in 0..4 {
+        server.respond_with(200, &[], "");
+    }
+
+    get(&server.address(), "/before", vec![]).unwrap();
+    set_default_client(Client::new().with_default_header("User-Agent", "configured-once/1.0"));
+    get(&server.address(), "/get", vec![]).unwrap();
+    post(&server.address(), "/post", "body", vec![("User-Agent".to_string(), "per-call".to_string())]).unwrap();
+    Client::new().request(Method::Get, &server.address(), "/explicit").send().unwrap();
+
+    let agents: Vec<Option<String>> = server.requests().iter().map(|r| r.header("User-Agent").map(str::to_string)).collect();
+    assert_ne!(agents[0].as_deref(), Some("configured-once/1.0"));
+    assert_eq!(agents[1].as_deref(), Some("configured-once/1.0"));
+    // A header the request sets itself wins over the default one
+    assert_eq!(agents[2].as_deref(), Some("per-call"));
+    assert_eq!(agents[3], agents[0]);
+
+    // default_client() is a copy; changing it doesn't change the default
+    let _ = default_client().with_default_header("User-Agent", "changed-copy");
+    server.respond_with(200, &[], "");
+    get(&server.address(), "/again", vec![]).unwrap();
+    assert_eq!(server.requests()[4].header("User-Agent"), Some("configured-once/1.0"));
+}
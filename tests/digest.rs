@@ -0,0 +1,49 @@
+use std::io::Read;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// Digests of "hello world"
+const SHA256: &str = "sha-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=";
+const MD5: &str = "XrY7u+Ae7tCTyyK7j1rNww==";
+
+fn verifying(verify_digest: bool) -> Client {
+    Client::with_config(RequestConfig { verify_digest, ..RequestConfig::default() })
+}
+
+// TestServer sends a body with Transfer-Encoding: chunked as one chunk
+#[test]
+fn matching_bodies_pass_with_either_framing() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Digest", SHA256)], "hello world");
+    server.respond_with(200, &[("Transfer-Encoding", "chunked"), ("Content-MD5", MD5)], "hello world");
+    let client = verifying(true);
+    assert_eq!(client.request(Method::Get, &server.address(), "/").send().unwrap().body, b"hello world");
+    assert_eq!(client.request(Method::Get, &server.address(), "/").send().unwrap().body, b"hello world");
+}
+
+#[test]
+fn a_corrupted_body_is_a_digest_mismatch() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Digest", SHA256)], "hello worlD");
+    server.respond_with(200, &[("Transfer-Encoding", "chunked"), ("Digest", SHA256)], "hello worlD");
+    let client = verifying(true);
+    let result = client.request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(&result, Err(HttpError::DigestMismatch { expected, .. }) if expected == SHA256), "{:?}", result);
+
+    // A streamed read fails at the end of the body, once all of it has been hashed
+    let mut reader = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap();
+    let mut body = Vec::new();
+    let error = reader.read_to_end(&mut body).unwrap_err();
+    assert!(error.to_string().contains("digest"), "{}", error);
+    assert_eq!(body, b"hello worlD");
+}
+
+#[test]
+fn unsupported_digests_and_the_option_off_check_nothing() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Digest", "unixsum=1")], "anything");
+    server.respond_with(200, &[("Digest", SHA256)], "not hello");
+    assert_eq!(verifying(true).request(Method::Get, &server.address(), "/").send().unwrap().body, b"anything");
+    assert_eq!(verifying(false).request(Method::Get, &server.address(), "/").send().unwrap().body, b"not hello");
+}
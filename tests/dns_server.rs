@@ -0,0 +1,42 @@
+use std::net::UdpSocket;
+use std::thread;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// Answers A queries for "api.test" with 127.0.0.1, AAAA queries with nothing, and anything
+// else with NXDOMAIN
+fn dns() -> String {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = socket.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((n, from)) = socket.recv_from(&mut buf) {
+            let query = &buf[..n];
+            let known = query[12..n - 4] == *b"\x03api\x04test\x00";
+            let a = known && query[n - 3] == 1;
+            let mut reply = query[..2].to_vec();
+            reply.extend_from_slice(&[0x81, if known { 0x80 } else { 0x83 }, 0, 1, 0, u8::from(a), 0, 0, 0, 0]);
+            reply.extend_from_slice(&query[12..]);
+            if a {
+                reply.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 127, 0, 0, 1]);
+            }
+            let _ = socket.send_to(&reply, from);
+        }
+    });
+    address
+}
+
+#[test]
+fn a_name_only_the_configured_server_knows_is_reached() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "found");
+    let client = Client::with_config(RequestConfig { dns_server: Some(dns().parse().unwrap()), ..RequestConfig::default() });
+    let port = server.address().rsplit(':').next().unwrap().to_string();
+    let response = client.request(Method::Get, &format!("api.test:{}", port), "/").send().unwrap();
+    assert_eq!(response.body, b"found");
+    assert_eq!(server.requests()[0].header("Host"), Some(format!("api.test:{}", port).as_str()));
+
+    let result = client.request(Method::Get, &format!("missing.test:{}", port), "/").send();
+    assert!(matches!(&result, Err(HttpError::NetworkError(message)) if message.contains("not found")), "{:?}", result);
+}
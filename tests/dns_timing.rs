@@ -0,0 +1,42 @@
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig, Resolver, SystemResolver};
+
+const LOOKUP: Duration = Duration::from_millis(150);
+
+// Answers like the system resolver, but slowly
+struct Slow;
+
+impl Resolver for Slow {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, HttpError> {
+        thread::sleep(LOOKUP);
+        SystemResolver.resolve(host, port)
+    }
+}
+
+#[test]
+fn resolution_time_is_reported_apart_from_connecting() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let response = Client::new().with_resolver(Slow).request(Method::Get, &server.address(), "/").send().unwrap();
+
+    let timings = response.timings.unwrap();
+    assert!(timings.dns >= LOOKUP, "{:?}", timings);
+    assert!(timings.connect < LOOKUP / 2, "{:?}", timings);
+    assert!(timings.total() >= timings.dns + timings.connect);
+}
+
+// A pooled connection isn't looked up again
+#[test]
+fn a_reused_connection_has_no_lookup() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "one");
+    server.respond_with(200, &[], "two");
+    let client = Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() }).with_resolver(Slow);
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    let timings = client.request(Method::Get, &server.address(), "/").send().unwrap().timings.unwrap();
+    assert!(timings.dns < LOOKUP / 2 && timings.connect < LOOKUP / 2, "{:?}", timings);
+}
@@ -0,0 +1,100 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpResponse, Method, RequestConfig};
+
+fn pooling() -> Client {
+    Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() })
+}
+
+// Stream the first response and leave its body unread, then send a second request
+fn second_after_ignoring(body: Vec<u8>, consume: bool) -> (Client, bool) {
+    let server = TestServer::start();
+    server.respond_with(200, &[], body);
+    server.respond_with(200, &[], "second");
+    let client = pooling();
+    let first = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap();
+    if consume {
+        first.consume_body().unwrap();
+    } else {
+        drop(first);
+    }
+    let second = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(second.body, b"second");
+    let reused = second.connection.unwrap().reused;
+    (client, reused)
+}
+
+#[test]
+fn an_ignored_body_is_drained_and_the_connection_reused() {
+    let (client, reused) = second_after_ignoring(b"nobody reads this".repeat(100), false);
+    assert!(reused);
+    assert_eq!(client.pool_stats().created, 1);
+}
+
+#[test]
+fn consume_body_leaves_the_connection_reusable() {
+    let (_, reused) = second_after_ignoring(b"skipped".to_vec(), true);
+    assert!(reused);
+}
+
+#[test]
+fn a_body_past_the_drain_limit_closes_the_connection_instead() {
+    let (client, reused) = second_after_ignoring(vec![b'x'; 200 * 1024], false);
+    assert!(!reused);
+    assert_eq!(client.pool_stats().created, 2);
+
+    let (_, reused) = second_after_ignoring(vec![b'x'; 200 * 1024], true);
+    assert!(!reused);
+}
+
+#[test]
+fn discard_body_reads_up_to_the_configured_limit() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], vec![b'x'; 200 * 1024]);
+    server.respond_with(200, &[], "second");
+    let client = pooling();
+    let head = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap().discard_body().unwrap();
+    assert!(head.body.is_empty());
+    let second = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    // The default discard_limit of 1 MiB covers a 200 KiB body
+    assert!(second.connection.unwrap().reused);
+}
+
+#[test]
+fn a_discarded_chunked_body_keeps_its_trailers_and_the_connection() {
+    let server = TestServer::start();
+    let mut chunked = HttpResponse::new(200, &[("Transfer-Encoding", "chunked"), ("Trailer", "X-Sum")], vec![b'y'; 500 * 1024]);
+    chunked.trailers.push("X-Sum: 42".to_string());
+    server.respond_with_fn(move |_| chunked);
+    server.respond_with(200, &[], "after");
+    let client = pooling();
+    let head = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap().discard_body().unwrap();
+    assert!(head.body.is_empty());
+    assert_eq!(head.trailer("X-Sum"), Some("42"));
+    let next = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(next.body, b"after");
+    assert!(next.connection.unwrap().reused);
+}
+
+#[test]
+fn a_body_past_the_discard_limit_closes_the_connection() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], vec![b'z'; 64 * 1024]);
+    server.respond_with(200, &[], "fresh");
+    let client = Client::with_config(RequestConfig { keep_alive: true, discard_limit: 1024, ..RequestConfig::default() });
+    client.request(Method::Get, &server.address(), "/").send_streaming().unwrap().discard_body().unwrap();
+    let next = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(next.body, b"fresh");
+    assert!(!next.connection.unwrap().reused);
+}
+
+#[test]
+fn ignore_body_discards_every_buffered_body() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], vec![b'i'; 100 * 1024]);
+    server.respond_with(200, &[], "also ignored");
+    let client = Client::with_config(RequestConfig { keep_alive: true, ignore_body: true, ..RequestConfig::default() });
+    let first = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    let second = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert!(first.body.is_empty() && second.body.is_empty());
+    assert!(second.connection.unwrap().reused);
+}
@@ -0,0 +1,56 @@
+use std::io::{self, Cursor, Read, Write};
+
+use http_client_from_scratch_rust::{build_request, send_request_on, HeaderList, HttpError};
+
+#[test]
+fn get_with_custom_headers_is_byte_for_byte() {
+    let request = build_request("GET", "example.com", "/search?q=1", None, [("X-B", "2"), ("Accept", "text/plain")]).unwrap();
+    assert_eq!(
+        String::from_utf8(request).unwrap(),
+        "GET /search?q=1 HTTP/1.1\r\nHost: example.com\r\nX-B: 2\r\nAccept: text/plain\r\nConnection: close\r\n\r\n"
+    );
+}
+
+#[test]
+fn post_with_a_body_is_byte_for_byte() {
+    let request = build_request("POST", "example.com:8080", "/items", Some("{\"a\":1}"), HeaderList::new()).unwrap();
+    assert_eq!(
+        String::from_utf8(request).unwrap(),
+        "POST /items HTTP/1.1\r\nHost: example.com:8080\r\nContent-Length: 7\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n{\"a\":1}"
+    );
+}
+
+#[test]
+fn invalid_headers_fail_before_anything_is_sent() {
+    let result = build_request("GET", "example.com", "/", None, [("Bad Name", "x")]);
+    assert!(matches!(result, Err(HttpError::InvalidHeader(_) | HttpError::InvalidRequest(_))), "{:?}", result);
+    let result = build_request("GET", "example.com", "/", None, [("X-A", "a\r\nX-B: b")]);
+    assert!(result.is_err());
+}
+
+// Answers 204 and keeps what was written
+struct Recorder(Cursor<&'static [u8]>, Vec<u8>);
+
+impl Read for Recorder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for Recorder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.1.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn dry_run_matches_what_a_send_writes() {
+    let headers = [("X-Trace", "t"), ("Accept", "*/*")];
+    let mut stream = Recorder(Cursor::new(b"HTTP/1.1 204 No Content\r\n\r\n"), Vec::new());
+    send_request_on(&mut stream, "PUT", "example.com", "/x", Some("body"), headers).unwrap();
+    assert_eq!(stream.1, build_request("PUT", "example.com", "/x", Some("body"), headers).unwrap());
+}
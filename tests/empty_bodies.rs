@@ -0,0 +1,21 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+#[test]
+fn no_content_ignores_a_stray_content_length() {
+    let server = TestServer::start();
+    server.respond_with(204, &[("Content-Length", "5")], "");
+    server.respond_with(200, &[], "hello");
+    let client = Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() });
+
+    // A client that trusted Content-Length would wait for 5 bytes that never come
+    let empty = client.request(Method::Get, &server.address(), "/empty").send().unwrap();
+    assert_eq!(empty.status().as_u16(), 204);
+    assert!(empty.is_empty_body());
+    assert_eq!(empty.body_len(), 0);
+
+    let full = client.request(Method::Get, &server.address(), "/full").send().unwrap();
+    assert!(!full.is_empty_body());
+    assert_eq!(full.body_len(), 5);
+    assert_eq!(client.pool_stats().created, 1);
+}
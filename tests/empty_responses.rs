@@ -0,0 +1,57 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// Answers the nth connection with replies[n] as it is, then closes it
+fn server(replies: &'static [&'static [u8]]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        for reply in replies {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let _ = (&stream).write_all(reply);
+        }
+    });
+    address
+}
+
+fn get(address: &str) -> Result<Vec<u8>, HttpError> {
+    Client::new().request(Method::Get, address, "/").send().map(|response| response.body)
+}
+
+#[test]
+fn a_connection_closed_before_any_byte_is_an_empty_response() {
+    let result = get(&server(&[b""]));
+    assert!(matches!(result, Err(HttpError::EmptyResponse)), "{:?}", result);
+}
+
+#[test]
+fn headers_without_their_body_are_a_truncated_body() {
+    let result = get(&server(&[b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n"]));
+    assert!(matches!(result, Err(HttpError::TruncatedBody { expected: 10, received: 0 })), "{:?}", result);
+}
+
+#[test]
+fn a_head_cut_off_before_its_blank_line_says_so() {
+    let result = get(&server(&[b"HTTP/1.1 200 OK\r\nContent-Len"]));
+    assert!(matches!(&result, Err(HttpError::InvalidResponse(message)) if message.contains("head")), "{:?}", result);
+}
+
+#[test]
+fn empty_responses_are_retried_only_when_asked() {
+    const REPLIES: &[&[u8]] = &[b"", b"", b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"];
+    let retrying = RequestConfig { max_retries: 2, retry_empty_response: true, ..RequestConfig::default() };
+    let response = Client::with_config(retrying).request(Method::Post, &server(REPLIES), "/").body("x").send().unwrap();
+    assert_eq!(response.body, b"ok");
+
+    let not_asked = RequestConfig { max_retries: 2, ..RequestConfig::default() };
+    let result = Client::with_config(not_asked).request(Method::Post, &server(REPLIES), "/").body("x").send();
+    assert!(matches!(result, Err(HttpError::EmptyResponse)), "{:?}", result);
+}
@@ -0,0 +1,55 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+#[test]
+fn a_built_request_and_its_clone_arrive_identically() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "one");
+    server.respond_with(200, &[], "two");
+    let client = Client::new();
+    let request = client
+        .request(Method::Post, &server.address(), "/orders?page=2")
+        .header("X-A", "1")
+        .header("X-B", "2")
+        .body("{\"id\":7}")
+        .build()
+        .unwrap();
+    let copy = request.clone();
+    assert_eq!(client.execute(&request).unwrap().body, b"one");
+    assert_eq!(client.execute(&copy).unwrap().body, b"two");
+
+    let requests = server.requests();
+    assert!(requests[0] == requests[1]);
+    assert_eq!(requests[0].target, "/orders?page=2");
+    assert_eq!(requests[0].body, b"{\"id\":7}");
+}
+
+#[test]
+fn getters_show_every_part_and_debug_masks_secrets() {
+    let request = Client::new()
+        .request(Method::Put, "example.com", "/x")
+        .header("Authorization", "Bearer s3cret")
+        .body("data")
+        .build()
+        .unwrap();
+    assert_eq!(*request.method(), Method::Put);
+    assert_eq!((request.host(), request.path(), request.body()), ("example.com", "/x", Some("data")));
+    assert_eq!(request.header("authorization"), Some("Bearer s3cret"));
+    assert_eq!(request.headers().len(), 1);
+    assert!(!format!("{:?}", request).contains("s3cret"));
+}
+
+#[test]
+fn request_from_extends_a_copy_and_streams_cant_be_built() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let client = Client::new();
+    let base = client.request(Method::Get, &server.address(), "/").header("X-Base", "b").build().unwrap();
+    client.request_from(base.clone()).header("X-Extra", "e").send().unwrap();
+    let sent = &server.requests()[0];
+    assert_eq!((sent.header("X-Base"), sent.header("X-Extra")), (Some("b"), Some("e")));
+    assert_eq!(base.header("X-Extra"), None);
+
+    let result = client.request(Method::Post, &server.address(), "/").body_stream(&b"once"[..]).build();
+    assert!(matches!(result, Err(HttpError::InvalidRequest(_))), "{:?}", result);
+}
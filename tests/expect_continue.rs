@@ -0,0 +1,77 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+const WAIT: Duration = Duration::from_millis(300);
+
+// When the body arrived after the head, and what it was; None if it never came
+type Arrival = Option<(Duration, Vec<u8>)>;
+
+// Reads the head, sends `interim` (if any) at once, then reports how long the 5-byte body took
+// to arrive (None if the client sent none) and answers with `reply`
+fn server(interim: Option<&'static [u8]>, reply: &'static [u8]) -> (String, mpsc::Receiver<Arrival>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        let start = Instant::now();
+        if let Some(interim) = interim {
+            stream.write_all(interim).unwrap();
+        }
+        if reply.starts_with(b"HTTP/1.1 417") {
+            stream.write_all(reply).unwrap();
+            let mut rest = Vec::new();
+            let _ = reader.read_to_end(&mut rest);
+            let _ = sender.send((!rest.is_empty()).then(|| (start.elapsed(), rest)));
+            return;
+        }
+        let mut body = vec![0; 5];
+        reader.read_exact(&mut body).unwrap();
+        let _ = sender.send(Some((start.elapsed(), body)));
+        stream.write_all(reply).unwrap();
+    });
+    (address, receiver)
+}
+
+fn post(address: &str) -> Result<Vec<u8>, HttpError> {
+    let client = Client::with_config(RequestConfig { continue_timeout: WAIT, ..RequestConfig::default() });
+    client.request(Method::Post, address, "/").header("Expect", "100-continue").body("hello").send().map(|r| r.body)
+}
+
+const OK: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+
+#[test]
+fn a_server_that_never_sends_100_gets_the_body_after_the_timeout() {
+    let (address, arrived) = server(None, OK);
+    assert_eq!(post(&address).unwrap(), b"ok");
+    let (after, body) = arrived.recv().unwrap().unwrap();
+    assert_eq!(body, b"hello");
+    assert!(after >= WAIT - Duration::from_millis(20), "the body came after {:?}", after);
+}
+
+#[test]
+fn a_100_continue_gets_the_body_at_once() {
+    let (address, arrived) = server(Some(b"HTTP/1.1 100 Continue\r\n\r\n"), OK);
+    assert_eq!(post(&address).unwrap(), b"ok");
+    let (after, body) = arrived.recv().unwrap().unwrap();
+    assert_eq!(body, b"hello");
+    assert!(after < WAIT / 2, "the body came after {:?}", after);
+}
+
+#[test]
+fn a_final_response_instead_means_no_body() {
+    let (address, arrived) = server(None, b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\r\n");
+    let result = post(&address);
+    assert!(matches!(result, Err(HttpError::HttpError { code: 417, .. })), "{:?}", result);
+    assert_eq!(arrived.recv().unwrap(), None);
+}
@@ -0,0 +1,53 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+fn server_sending(reply: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        let _ = (&stream).write_all(reply);
+    });
+    address
+}
+
+fn fetch(reply: &'static [u8]) -> Result<Vec<u8>, HttpError> {
+    Client::new().request(Method::Get, &server_sending(reply), "/").send().map(|response| response.body)
+}
+
+#[test]
+fn repeated_identical_content_lengths_are_collapsed() {
+    assert_eq!(fetch(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 2\r\n\r\nok").unwrap(), b"ok");
+}
+
+#[test]
+fn ambiguous_framing_is_refused() {
+    for reply in [
+        &b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 20\r\n\r\nok"[..],
+        &b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 2\r\n\r\n2\r\nok\r\n0\r\n\r\n"[..],
+        &b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip\r\n\r\nok"[..],
+    ] {
+        let result = fetch(reply);
+        assert!(matches!(result, Err(HttpError::InvalidResponse(_))), "{:?}: {:?}", String::from_utf8_lossy(reply), result);
+    }
+}
+
+#[test]
+fn content_length_before_transfer_encoding_is_refused_too() {
+    for reply in [
+        &b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n"[..],
+        &b"HTTP/1.1 200 OK\r\nContent-Length: 3, 4\r\n\r\nabcd"[..],
+        &b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"[..],
+    ] {
+        let result = fetch(reply);
+        assert!(matches!(result, Err(HttpError::InvalidResponse(_))), "{:?}: {:?}", String::from_utf8_lossy(reply), result);
+    }
+}
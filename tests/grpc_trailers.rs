@@ -0,0 +1,20 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpResponse, Method};
+
+#[test]
+fn an_empty_chunked_response_with_grpc_status_trailers_is_detected() {
+    let server = TestServer::start();
+    server.respond_with_fn(|_| {
+        let mut response = HttpResponse::new(200, &[("Content-Type", "application/grpc"), ("Transfer-Encoding", "chunked")], "");
+        response.trailers = vec!["grpc-status: 12".to_string(), "grpc-message: unimplemented".to_string()];
+        response
+    });
+    server.respond_with(200, &[("Content-Type", "application/grpc"), ("Transfer-Encoding", "chunked")], "\0\0\0\0\0");
+    let client = Client::new();
+
+    let trailers_only = client.request(Method::Post, &server.address(), "/pkg.Service/Method").body("").send().unwrap();
+    assert!(trailers_only.is_trailers_only());
+    assert_eq!(trailers_only.trailer("grpc-status"), Some("12"));
+    let with_message = client.request(Method::Post, &server.address(), "/pkg.Service/Method").body("").send().unwrap();
+    assert!(!with_message.is_trailers_only());
+}
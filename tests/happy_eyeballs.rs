@@ -0,0 +1,35 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig, Resolver};
+
+// Both families for every name: an IPv6 address that can't be reached, then the server
+struct DualStack(SocketAddr);
+
+impl Resolver for DualStack {
+    fn resolve(&self, _host: &str, port: u16) -> Result<Vec<SocketAddr>, HttpError> {
+        // 100::/64 is the IPv6 discard prefix (RFC 6666)
+        Ok(vec![SocketAddr::new("100::1".parse().unwrap(), port), self.0])
+    }
+}
+
+#[test]
+fn an_unreachable_ipv6_address_does_not_hold_up_ipv4() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "over ipv4");
+    let address: SocketAddr = server.address().parse().unwrap();
+    let config = RequestConfig {
+        resolver: Some(Arc::new(DualStack(address))),
+        happy_eyeballs_delay: Duration::from_millis(100),
+        ..RequestConfig::default()
+    };
+
+    let start = Instant::now();
+    let response = Client::with_config(config).request(Method::Get, &format!("dual.test:{}", address.port()), "/").send().unwrap();
+    assert_eq!(response.body, b"over ipv4");
+    assert_eq!(response.connection.unwrap().peer_addr, address);
+    // At most one happy_eyeballs_delay spent on the IPv6 attempt, far short of a connect timeout
+    assert!(start.elapsed() < Duration::from_secs(1), "{:?}", start.elapsed());
+}
@@ -0,0 +1,87 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HarRecorder, Json, Method};
+
+fn field<'a>(json: &'a Json, path: &str) -> &'a Json {
+    path.split('.').fold(json, |json, key| match key.parse::<usize>() {
+        Ok(i) => &json.as_array().unwrap()[i],
+        Err(_) => json.get(key).unwrap_or_else(|| panic!("no {} in {}", key, json)),
+    })
+}
+
+fn text<'a>(json: &'a Json, path: &str) -> &'a str {
+    field(json, path).as_str().unwrap()
+}
+
+#[test]
+fn exchanges_are_recorded_as_har_entries() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Content-Type", "text/plain")], "hello");
+    server.respond_with(201, &[], vec![0xff, 0x00]);
+    let recorder = HarRecorder::new(10, 1024);
+    let client = Client::new().with_har_recorder(recorder.clone());
+    client.request(Method::Get, &server.address(), "/search?q=rust&page=2").send().unwrap();
+    client.request(Method::Post, &server.address(), "/items").body("{\"a\":1}").send().unwrap();
+
+    let har = Json::parse(&recorder.to_json().to_string()).unwrap();
+    assert_eq!(text(&har, "log.version"), "1.2");
+    assert!(field(&har, "log.creator.name").as_str().is_some());
+    let entries = field(&har, "log.entries").as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let get = &entries[0];
+    assert_eq!(text(get, "request.method"), "GET");
+    assert_eq!(text(get, "request.url"), format!("http://{}/search?q=rust&page=2", server.address()));
+    assert_eq!(text(get, "request.httpVersion"), "HTTP/1.1");
+    assert_eq!(text(get, "request.queryString.1.name"), "page");
+    assert_eq!(text(get, "request.queryString.1.value"), "2");
+    assert_eq!(field(get, "response.status").as_u64(), Some(200));
+    assert_eq!(text(get, "response.statusText"), "OK");
+    assert_eq!(text(get, "response.content.text"), "hello");
+    assert_eq!(text(get, "response.content.mimeType"), "text/plain");
+    assert!(text(get, "startedDateTime").ends_with('Z'));
+    assert_eq!(text(get, "serverIPAddress"), "127.0.0.1");
+    for phase in ["dns", "connect", "send", "wait", "receive"] {
+        assert!(field(get, &format!("timings.{}", phase)).as_f64().unwrap() >= 0.0, "{}", phase);
+    }
+    for phase in ["blocked", "ssl"] {
+        assert_eq!(field(get, &format!("timings.{}", phase)).as_f64(), Some(-1.0));
+    }
+
+    let post = &entries[1];
+    assert_eq!(text(post, "request.method"), "POST");
+    assert_eq!(text(post, "request.postData.text"), "{\"a\":1}");
+    assert_eq!(text(post, "request.postData.mimeType"), "application/json");
+    assert_eq!(text(post, "response.content.encoding"), "base64");
+    assert_eq!(text(post, "response.content.text"), "/wA=");
+}
+
+#[test]
+fn recording_is_bounded() {
+    let server = TestServer::start();
+    for _ in 0..3 {
+        server.respond_with(200, &[], "a long response body");
+    }
+    let recorder = HarRecorder::new(2, 6);
+    let client = Client::new().with_har_recorder(recorder.clone());
+    for _ in 0..3 {
+        client.request(Method::Get, &server.address(), "/").send().unwrap();
+    }
+    assert_eq!(recorder.len(), 2);
+    let har = recorder.to_json();
+    assert_eq!(text(&har, "log.entries.0.response.content.text"), "a long");
+    assert!(text(&har, "log.entries.0.response.content.comment").contains("truncated"));
+}
+
+#[test]
+fn save_writes_the_document() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let recorder = HarRecorder::new(10, 1024);
+    Client::new().with_har_recorder(recorder.clone()).request(Method::Get, &server.address(), "/").send().unwrap();
+
+    let path = std::env::temp_dir().join(format!("har-test-{}.har", std::process::id()));
+    recorder.save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(Json::parse(&saved).unwrap(), recorder.to_json());
+}
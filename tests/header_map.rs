@@ -0,0 +1,16 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HeaderMap, Method};
+
+#[test]
+fn response_headers_keep_order_and_repeats() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Set-Cookie", "a=1"), ("X-Between", "x"), ("Set-Cookie", "b=2")], "ok");
+    let response = Client::new().request(Method::Get, &server.address(), "/").send().unwrap();
+
+    let headers: &HeaderMap = &response.headers;
+    assert_eq!(headers.get_all("set-cookie"), ["a=1", "b=2"]);
+    assert_eq!(response.header_all("SET-COOKIE"), ["a=1", "b=2"]);
+    assert_eq!(headers.get("x-between"), Some("x"));
+    let names: Vec<&str> = headers.iter().map(|(name, _)| name).filter(|name| name.starts_with(['S', 'X'])).collect();
+    assert_eq!(names, ["Set-Cookie", "X-Between", "Set-Cookie"]);
+}
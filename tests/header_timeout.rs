@@ -0,0 +1,55 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig, TimeoutPhase};
+
+// Reads the request, waits `stall`, then sends a head and the body a byte at a time every `drip`
+fn server(stall: Duration, drip: Duration) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        thread::sleep(stall);
+        if stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n").is_err() {
+            return;
+        }
+        for byte in b"drips" {
+            thread::sleep(drip);
+            let _ = stream.write_all(&[*byte]);
+        }
+    });
+    address
+}
+
+fn client() -> Client {
+    Client::with_config(RequestConfig { header_timeout: Some(Duration::from_millis(300)), ..RequestConfig::default() })
+}
+
+#[test]
+fn a_server_stalling_before_the_head_fails_within_the_header_timeout() {
+    let address = server(Duration::from_secs(3), Duration::ZERO);
+    let started = Instant::now();
+    let result = client().request(Method::Get, &address, "/").send();
+    assert!(
+        matches!(result, Err(HttpError::Timeout { phase: TimeoutPhase::Headers, limit }) if limit == Duration::from_millis(300)),
+        "{:?}",
+        result
+    );
+    assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+}
+
+#[test]
+fn a_slow_body_after_a_prompt_head_is_not_cut_off() {
+    let address = server(Duration::ZERO, Duration::from_millis(150));
+    let started = Instant::now();
+    let response = client().request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(response.body, b"drips");
+    assert!(started.elapsed() > Duration::from_millis(600));
+}
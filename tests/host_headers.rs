@@ -0,0 +1,67 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+fn port(server: &TestServer) -> String {
+    server.address().rsplit(':').next().unwrap().to_string()
+}
+
+#[test]
+fn host_header_is_sent_only_to_the_matching_host() {
+    let server = TestServer::start();
+    for _ in 0..2 {
+        server.respond_with(200, &[], "ok");
+    }
+    let client = Client::new().with_host_header("localhost", "X-Api-Key", "secret");
+    client.request(Method::Get, &format!("localhost:{}", port(&server)), "/").send().unwrap();
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].header("X-Api-Key"), Some("secret"));
+    assert_eq!(requests[1].header("X-Api-Key"), None);
+}
+
+#[test]
+fn pattern_with_a_port_matches_its_host() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let client = Client::new().with_host_header(&server.address(), "X-Api-Key", "secret");
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(server.requests()[0].header("X-Api-Key"), Some("secret"));
+}
+
+#[test]
+fn wildcard_matches_subdomains_and_replaces_global_defaults() {
+    let server = TestServer::start();
+    for _ in 0..3 {
+        server.respond_with(200, &[], "ok");
+    }
+    let mut config = RequestConfig::default();
+    for name in ["api.example.test", "example.test"] {
+        config.connect_to.insert(format!("{}:{}", name, port(&server)), server.address().parse().unwrap());
+    }
+    let client = Client::with_config(config)
+        .with_default_header("X-Api-Key", "global")
+        .with_host_header("*.example.test", "X-Api-Key", "sub");
+    client.request(Method::Get, &format!("api.example.test:{}", port(&server)), "/").send().unwrap();
+    client.request(Method::Get, &format!("example.test:{}", port(&server)), "/").send().unwrap();
+    client.request(Method::Get, &format!("api.example.test:{}", port(&server)), "/")
+        .header("X-Api-Key", "mine")
+        .send()
+        .unwrap();
+
+    let keys: Vec<Option<String>> = server.requests().iter().map(|r| r.header("X-Api-Key").map(str::to_string)).collect();
+    assert_eq!(keys, [Some("sub".to_string()), Some("global".to_string()), Some("mine".to_string())]);
+}
+
+#[test]
+fn names_compare_case_insensitively_and_the_last_pattern_added_wins() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let mut config = RequestConfig::default();
+    config.connect_to.insert(format!("api.example.test:{}", port(&server)), server.address().parse().unwrap());
+    let client = Client::with_config(config)
+        .with_host_header("*.example.test", "X-Api-Key", "wildcard")
+        .with_host_header("API.Example.Test.", "X-Api-Key", "exact");
+    client.request(Method::Get, &format!("api.example.test:{}", port(&server)), "/").send().unwrap();
+    assert_eq!(server.requests()[0].header("X-Api-Key"), Some("exact"));
+}
@@ -0,0 +1,75 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{
+    build_request, to_ascii, to_unicode, Client, HeaderList, HttpError, Method, RequestConfig, Resolver,
+};
+
+fn port(server: &TestServer) -> String {
+    server.address().rsplit(':').next().unwrap().to_string()
+}
+
+fn client_for(server: &TestServer, name: &str) -> Client {
+    let mut config = RequestConfig::default();
+    config.connect_to.insert(format!("{}:{}", name, port(server)), server.address().parse().unwrap());
+    Client::with_config(config)
+}
+
+#[test]
+fn unicode_host_goes_out_in_punycode() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let client = client_for(&server, "xn--bcher-kva.example");
+    client.request(Method::Get, &format!("bücher.example:{}", port(&server)), "/").send().unwrap();
+    assert_eq!(server.requests()[0].header("Host"), Some(format!("xn--bcher-kva.example:{}", port(&server))).as_deref());
+}
+
+#[test]
+fn fullwidth_host_is_folded_to_ascii() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let client = client_for(&server, "abc.test");
+    client.request(Method::Get, &format!("ＡＢＣ.test:{}", port(&server)), "/").send().unwrap();
+    assert_eq!(server.requests()[0].header("Host"), Some(format!("ABC.test:{}", port(&server))).as_deref());
+}
+
+#[test]
+fn host_that_would_inject_a_header_is_refused() {
+    let server = TestServer::start();
+    let host = format!("{}\r\nX-Injected: 1\r\nY:", server.address());
+    let result = Client::new().request(Method::Get, &host, "/").send();
+    assert!(matches!(result, Err(HttpError::InvalidRequest(_))));
+    assert!(server.requests().is_empty());
+
+    for host in ["h\r\nX: y", "ex ample", "[::1]junk"] {
+        let request = build_request("GET", host, "/", None, HeaderList::new());
+        assert!(matches!(request, Err(HttpError::InvalidRequest(_))), "{:?}", host);
+    }
+}
+
+struct Recording(Mutex<Vec<String>>, SocketAddr);
+
+impl Resolver for Recording {
+    fn resolve(&self, host: &str, _port: u16) -> Result<Vec<SocketAddr>, HttpError> {
+        self.0.lock().unwrap().push(host.to_string());
+        Ok(vec![self.1])
+    }
+}
+
+#[test]
+fn resolver_is_asked_for_the_punycode_name() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let resolver = Arc::new(Recording(Mutex::new(Vec::new()), server.address().parse().unwrap()));
+    let config = RequestConfig { resolver: Some(resolver.clone()), ..RequestConfig::default() };
+    Client::with_config(config).request(Method::Get, "münchen.de", "/").send().unwrap();
+    assert_eq!(*resolver.0.lock().unwrap(), ["xn--mnchen-3ya.de"]);
+}
+
+#[test]
+fn conversions_are_public() {
+    assert_eq!(to_ascii("münchen.de").unwrap(), "xn--mnchen-3ya.de");
+    assert_eq!(to_unicode("xn--mnchen-3ya.de"), "münchen.de");
+    assert!(matches!(to_ascii("ex ample"), Err(HttpError::InvalidRequest(_))));
+}
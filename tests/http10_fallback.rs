@@ -0,0 +1,60 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// Answers HTTP/1.1 requests with `reply` and HTTP/1.0 ones with 200, one per connection,
+// keeping the heads it got
+fn old_server(reply: &'static [u8]) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let seen = heads.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { return };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            while reader.read_line(&mut head).is_ok_and(|n| n > 0) && !head.ends_with("\r\n\r\n") {}
+            let answer = if head.starts_with("GET / HTTP/1.1") { reply } else { b"HTTP/1.0 200 OK\r\n\r\nold" };
+            seen.lock().unwrap().push(head);
+            let _ = stream.write_all(answer);
+        }
+    });
+    (address, heads)
+}
+
+fn fallback(http10_fallback: bool) -> Client {
+    Client::with_config(RequestConfig { http10_fallback, ..RequestConfig::default() })
+}
+
+#[test]
+fn a_505_or_garbage_is_retried_as_http10() {
+    for reply in [&b"HTTP/1.1 505 HTTP Version Not Supported\r\nContent-Length: 0\r\n\r\n"[..], b"garbage\r\n\r\n"] {
+        let (address, heads) = old_server(reply);
+        let response = fallback(true).request(Method::Get, &address, "/").send().unwrap();
+        assert_eq!(response.status_line, "HTTP/1.0 200 OK");
+        assert_eq!(response.body, b"old");
+
+        let heads = heads.lock().unwrap();
+        assert_eq!(heads.len(), 2);
+        assert!(heads[0].starts_with("GET / HTTP/1.1\r\n"));
+        assert!(heads[1].starts_with("GET / HTTP/1.0\r\n") && heads[1].contains("\r\nHost: "), "{}", heads[1]);
+        assert!(!heads[1].to_ascii_lowercase().contains("connection:"), "{}", heads[1]);
+    }
+}
+
+#[test]
+fn without_the_option_or_for_other_errors_there_is_no_retry() {
+    let (address, heads) = old_server(b"HTTP/1.1 505 HTTP Version Not Supported\r\nContent-Length: 0\r\n\r\n");
+    let result = fallback(false).request(Method::Get, &address, "/").send();
+    assert!(matches!(result, Err(HttpError::HttpError { code: 505, .. })), "{:?}", result);
+    assert_eq!(heads.lock().unwrap().len(), 1);
+
+    let (address, heads) = old_server(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    let result = fallback(true).request(Method::Get, &address, "/").send();
+    assert!(matches!(result, Err(HttpError::HttpError { code: 404, .. })), "{:?}", result);
+    assert_eq!(heads.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,15 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{parse_http_date, Client, Method};
+
+#[test]
+fn date_headers_from_a_server_are_typed() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Date", "Thu, 01 Jan 2026 00:00:00 GMT"), ("Last-Modified", "Wed, 31 Dec 2025 23:59:59 GMT")], "");
+    let response = Client::new().request(Method::Get, &server.address(), "/").send().unwrap();
+    let date = response.date().unwrap();
+    assert_eq!(date, UNIX_EPOCH + Duration::from_secs(1_767_225_600));
+    assert_eq!(date.duration_since(response.last_modified().unwrap()).unwrap(), Duration::from_secs(1));
+    assert_eq!(parse_http_date("Thu, 01 Jan 2026 00:00:00 GMT"), Some(date));
+}
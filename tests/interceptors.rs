@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, OutgoingRequest, RequestConfig};
+
+#[test]
+fn an_injected_request_id_appears_on_the_wire() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let counter = Arc::new(AtomicUsize::new(0));
+    let ids = counter.clone();
+    let client = Client::new().with_interceptor(move |request: &mut OutgoingRequest| {
+        request.set_header("X-Request-Id", &format!("req-{}", ids.fetch_add(1, Ordering::SeqCst)));
+        Ok(())
+    });
+    let (sent, _) = client.request(Method::Get, &server.address(), "/").send_debug().unwrap();
+    assert!(String::from_utf8_lossy(&sent.bytes).contains("\r\nX-Request-Id: req-0\r\n"));
+    assert_eq!(server.requests()[0].header("X-Request-Id"), Some("req-0"));
+}
+
+#[test]
+fn interceptors_run_in_order_on_every_redirect_hop() {
+    let server = TestServer::start();
+    server.respond_with(302, &[("Location", "/next")], "");
+    server.respond_with(200, &[], "");
+    let client = Client::with_config(RequestConfig { max_redirects: 1, ..RequestConfig::default() })
+        .with_interceptor(|request: &mut OutgoingRequest| {
+            let seen = request.header("X-Trail").unwrap_or("").to_string();
+            request.set_header("X-Trail", &format!("{}first:{} ", seen, request.target));
+            Ok(())
+        })
+        .with_interceptor(|request: &mut OutgoingRequest| {
+            let seen = request.header("X-Trail").unwrap_or("").to_string();
+            request.set_header("X-Trail", &format!("{}second", seen));
+            Ok(())
+        });
+    client.request(Method::Get, &server.address(), "/start").send().unwrap();
+    let trails: Vec<String> = server.requests().iter().map(|r| r.header("X-Trail").unwrap().to_string()).collect();
+    assert_eq!(trails, ["first:/start second", "first:/next second"]);
+}
+
+#[test]
+fn an_interceptor_error_stops_the_request_and_bad_headers_are_refused() {
+    let server = TestServer::start();
+    let refusing = Client::new().with_interceptor(|_: &mut OutgoingRequest| Err(HttpError::InvalidRequest("no token".to_string())));
+    let result = refusing.request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(&result, Err(HttpError::InvalidRequest(message)) if message == "no token"), "{:?}", result);
+
+    let injecting = Client::new().with_interceptor(|request: &mut OutgoingRequest| {
+        request.set_header("X-Evil", "a\r\nX-Injected: 1");
+        Ok(())
+    });
+    assert!(injecting.request(Method::Get, &server.address(), "/").send().is_err());
+    assert!(server.requests().is_empty());
+}
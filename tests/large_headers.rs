@@ -0,0 +1,22 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+#[test]
+fn a_64k_header_value_is_parsed_intact() {
+    let token = "eyJ".to_string() + &"a".repeat(64 * 1024) + ".sig";
+    let server = TestServer::start();
+    server.respond_with(200, &[("Set-Cookie", &format!("jwt={}", token)), ("X-After", "1")], "body");
+    let response = Client::new().request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.header("Set-Cookie"), Some(format!("jwt={}", token).as_str()));
+    assert_eq!(response.header("X-After"), Some("1"));
+    assert_eq!(response.body, b"body");
+}
+
+#[test]
+fn a_head_larger_than_max_header_bytes_is_refused() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("X-Big", &"a".repeat(64 * 1024))], "");
+    let client = Client::with_config(RequestConfig { max_header_bytes: 16 * 1024, ..RequestConfig::default() });
+    let result = client.request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(&result, Err(error) if error.to_string().contains("larger than 16384 bytes")), "{:?}", result);
+}
@@ -0,0 +1,59 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// Firmware-style server: writes `reply` to the first request and closes
+fn device(reply: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        let _ = (&stream).write_all(reply);
+    });
+    address
+}
+
+fn client(lenient: bool) -> Client {
+    Client::with_config(RequestConfig { lenient, ..RequestConfig::default() })
+}
+
+#[test]
+fn a_status_line_without_a_reason_phrase_is_accepted() {
+    let address = device(b"HTTP/1.1  200\r\nContent-Length: 4\r\n\r\ntemp");
+    let response = client(false).request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.reason(), "");
+    assert_eq!(response.body, b"temp");
+}
+
+#[test]
+fn an_http09_reply_needs_lenient_mode() {
+    let address = device(b"temperature=21.5\n");
+    let result = client(false).request(Method::Get, &address, "/").send();
+    assert!(matches!(result, Err(HttpError::InvalidResponse(_))), "{:?}", result);
+
+    let address = device(b"temperature=21.5\n");
+    let response = client(true).request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.status_line, "HTTP/0.9 200 OK");
+    assert_eq!(response.body, b"temperature=21.5\n");
+}
+
+#[test]
+fn an_lf_only_head_is_accepted_unless_line_endings_are_strict() {
+    let address = device(b"HTTP/1.1 200 OK\nContent-Length: 4\n\nab\r\n");
+    let response = client(false).request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(response.body, b"ab\r\n");
+
+    let address = device(b"HTTP/1.1 200 OK\r\nContent-Length: 2\n\r\nok");
+    let strict = Client::with_config(RequestConfig { strict_line_endings: true, ..RequestConfig::default() });
+    let result = strict.request(Method::Get, &address, "/").send();
+    assert!(matches!(&result, Err(HttpError::InvalidResponse(message)) if message.contains("bare LF")), "{:?}", result);
+}
@@ -0,0 +1,25 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method};
+
+// Pages 1 to 3, each linking to the next with a relative target, the last with none
+#[test]
+fn a_pagination_loop_follows_next_links() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Link", "</items?page=2>; rel=\"next\", </items?page=3>; rel=\"last\"")], "one");
+    server.respond_with(200, &[("Link", "</items?page=3>; rel=\"next\", </items?page=1>; rel=\"first\"")], "two");
+    server.respond_with(200, &[("Link", "</items?page=1>; rel=\"first\"")], "three");
+    let client = Client::new();
+    let prefix = format!("http://{}", server.address());
+
+    let mut path = "/items?page=1".to_string();
+    let mut bodies = Vec::new();
+    loop {
+        let response = client.request(Method::Get, &server.address(), &path).send().unwrap();
+        bodies.push(String::from_utf8(response.body.clone()).unwrap());
+        let Some(next) = response.links().remove("next") else { break };
+        path = next.strip_prefix(&prefix).unwrap().to_string();
+    }
+    assert_eq!(bodies, ["one", "two", "three"]);
+    let targets: Vec<String> = server.requests().into_iter().map(|request| request.target).collect();
+    assert_eq!(targets, ["/items?page=1", "/items?page=2", "/items?page=3"]);
+}
@@ -0,0 +1,34 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+fn from(local_address: IpAddr) -> Client {
+    Client::with_config(RequestConfig { local_address: Some(local_address), ..RequestConfig::default() })
+}
+
+#[test]
+fn requests_go_out_from_the_local_address() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let response = from(Ipv4Addr::LOCALHOST.into()).request(Method::Get, &server.address(), "/").send().unwrap();
+    let local = response.connection.unwrap().local_addr;
+    assert!(local.is_ipv4());
+    assert_eq!(local.ip(), Ipv4Addr::LOCALHOST);
+}
+
+#[test]
+fn an_address_of_another_family_is_reported() {
+    let server = TestServer::start();
+    let result = from(Ipv6Addr::LOCALHOST.into()).request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(&result, Err(HttpError::NetworkError(m)) if m.contains("address family")), "{:?}", result);
+}
+
+// 192.0.2.1 is a documentation address no interface has
+#[test]
+fn a_failed_bind_is_told_apart_from_a_refused_connection() {
+    let server = TestServer::start();
+    let result = from(Ipv4Addr::new(192, 0, 2, 1).into()).request(Method::Get, &server.address(), "/").send();
+    assert!(matches!(&result, Err(HttpError::NetworkError(m)) if m.contains("can't bind to 192.0.2.1")), "{:?}", result);
+    assert!(server.requests().is_empty());
+}
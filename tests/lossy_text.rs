@@ -0,0 +1,11 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+#[test]
+fn an_error_page_with_invalid_utf8_can_still_be_logged() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Content-Type", "text/html")], &b"<p>caf\xe9 closed</p>"[..]);
+    let response = Client::new().request(Method::Get, &server.address(), "/").send().unwrap();
+    assert!(matches!(response.body_text(), Err(HttpError::InvalidResponse(_))));
+    assert_eq!(response.body_text_lossy(), "<p>caf\u{fffd} closed</p>");
+}
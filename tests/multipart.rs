@@ -0,0 +1,110 @@
+// Uploads a file larger than anything the client may hold, counting the bytes the client's
+// own thread has allocated and not freed; TestServer's thread isn't counted
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs;
+use std::io::Write;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, Multipart};
+
+struct Tracking;
+
+thread_local! {
+    static LIVE: Cell<usize> = const { Cell::new(0) };
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+fn grew(size: usize) {
+    let _ = LIVE.try_with(|live| {
+        live.set(live.get() + size);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+fn shrank(size: usize) {
+    let _ = LIVE.try_with(|live| live.set(live.get().saturating_sub(size)));
+}
+
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        grew(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        shrank(layout.size());
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        shrank(layout.size());
+        grew(new_size);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Tracking = Tracking;
+
+const FILE_SIZE: usize = 16 * 1024 * 1024;
+
+fn contents() -> Vec<u8> {
+    let mut x: u32 = 1;
+    (0..FILE_SIZE)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        })
+        .collect()
+}
+
+#[test]
+fn a_large_file_is_streamed_and_reassembled() {
+    let path = std::env::temp_dir().join(format!("multipart-upload-{}.bin", std::process::id()));
+    let data = contents();
+    fs::File::create(&path).unwrap().write_all(&data).unwrap();
+
+    let server = TestServer::start();
+    server.respond_with(200, &[], "stored");
+    let client = Client::new();
+    let form = Multipart::new().text("title", "Holiday").file("upload", &path);
+    let content_type = form.content_type();
+    let boundary = form.boundary().to_string();
+
+    let live_before = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(live_before));
+    let response = client.request(Method::Post, &server.address(), "/upload").multipart(form).send().unwrap();
+    let peak = PEAK.with(Cell::get) - live_before;
+    fs::remove_file(&path).unwrap();
+    assert_eq!(response.body, b"stored");
+    assert!(peak < 1024 * 1024, "the client held {} bytes at once", peak);
+
+    let sent = &server.requests()[0];
+    assert_eq!(sent.header("Content-Type"), Some(content_type.as_str()));
+    assert_eq!(sent.header("Transfer-Encoding"), Some("chunked"));
+    assert_eq!(sent.header("Content-Length"), None);
+
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    let mut expected = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nHoliday\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"{f}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        b = boundary,
+        f = file_name
+    )
+    .into_bytes();
+    expected.extend_from_slice(&data);
+    expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    assert!(sent.body == expected, "the server received {} bytes, not {}", sent.body.len(), expected.len());
+}
+
+#[test]
+fn a_missing_file_fails_the_send() {
+    let server = TestServer::start();
+    let form = Multipart::new().file("upload", "/nonexistent/upload.bin");
+    let error = Client::new().request(Method::Post, &server.address(), "/").multipart(form).send().unwrap_err();
+    assert!(matches!(error, HttpError::InvalidRequest(_)), "{:?}", error);
+    assert!(server.requests().is_empty());
+}
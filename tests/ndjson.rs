@@ -0,0 +1,72 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Json, Method};
+
+const EVENTS: usize = 4;
+
+// A chunked NDJSON stream, one line flushed every 50ms, on a connection left open
+fn streaming_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        let mut out = &stream;
+        out.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+        for i in 0..EVENTS {
+            let line = format!("{{\"seq\":{}}}\r\n", i);
+            out.write_all(format!("{:x}\r\n{}\r\n", line.len(), line).as_bytes()).unwrap();
+            out.flush().unwrap();
+            thread::sleep(Duration::from_millis(50));
+        }
+        out.write_all(b"0\r\n\r\n").unwrap();
+        // Keep the connection open: the end of the chunked body alone must end the iterator
+        thread::sleep(Duration::from_secs(2));
+    });
+    address
+}
+
+#[test]
+fn json_lines_arrive_as_they_are_written() {
+    let address = streaming_server();
+    let start = Instant::now();
+    let reader = Client::new().request(Method::Get, &address, "/events").send_streaming().unwrap();
+
+    let mut arrivals = Vec::new();
+    for (i, item) in reader.json_lines::<Json>().enumerate() {
+        assert_eq!(item.unwrap().get("seq").and_then(Json::as_u64), Some(i as u64));
+        arrivals.push(start.elapsed());
+    }
+    assert_eq!(arrivals.len(), EVENTS);
+    // The first line comes well before the stream ends, and the rest about 50ms apart
+    assert!(arrivals[0] < Duration::from_millis(100), "{:?}", arrivals);
+    for pair in arrivals.windows(2) {
+        assert!(pair[1] - pair[0] >= Duration::from_millis(25), "{:?}", arrivals);
+    }
+    assert!(start.elapsed() < Duration::from_secs(1), "{:?}", start.elapsed());
+}
+
+// Three objects with a blank line and a bad line among them
+#[test]
+fn a_bad_line_is_an_error_between_the_good_items() {
+    let server = TestServer::start();
+    let body = "{\"id\":1}\n\n{\"id\":2}\nnot json\n{\"id\":3}\n";
+    server.respond_with(200, &[("Content-Type", "application/x-ndjson"), ("Transfer-Encoding", "chunked")], body);
+    let reader = Client::new().request(Method::Get, &server.address(), "/stream").send_streaming().unwrap();
+
+    let items: Vec<Result<u64, String>> = reader.json_lines::<Json>()
+        .map(|item| item.map(|json| json.get("id").and_then(Json::as_u64).unwrap()).map_err(|e| e.to_string()))
+        .collect();
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[..2], [Ok(1), Ok(2)]);
+    assert!(items[2].is_err());
+    assert_eq!(items[3], Ok(3));
+}
@@ -0,0 +1,43 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, Netrc};
+
+// Writes `text` to a temp file and loads it the way a CLI would
+fn netrc_file(name: &str, text: &str) -> Netrc {
+    let path = std::env::temp_dir().join(format!("netrc-{}-{}", name, std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    let netrc = Netrc::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    netrc
+}
+
+#[test]
+fn a_matching_machine_sends_basic_credentials() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    server.respond_with(200, &[], "ok");
+    let netrc = netrc_file("match", "# test\nmachine 127.0.0.1\n  login alice\n  password \"open sesame\"\n");
+    let client = Client::new().with_netrc(netrc);
+
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    client.request(Method::Get, &server.address(), "/").header("Authorization", "Bearer mine").send().unwrap();
+    let requests = server.requests();
+    assert_eq!(requests[0].header("Authorization"), Some("Basic YWxpY2U6b3BlbiBzZXNhbWU="));
+    // Auth set on the request wins over the file
+    assert_eq!(requests[1].header("Authorization"), Some("Bearer mine"));
+}
+
+#[test]
+fn a_netrc_for_other_hosts_sends_nothing() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let client = Client::new().with_netrc(netrc_file("other", "machine api.example.com login alice password pw\n"));
+
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(server.requests()[0].header("Authorization"), None);
+}
+
+#[test]
+fn a_missing_file_is_an_error() {
+    let missing = std::env::temp_dir().join(format!("netrc-missing-{}", std::process::id()));
+    assert!(Netrc::load(missing).is_err());
+}
@@ -0,0 +1,67 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{ext_param, Client, HttpError, Method};
+
+// Returns the raw head it received, after answering with `reply`
+fn raw_server(reply: &'static [u8]) -> (String, thread::JoinHandle<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") && reader.read_until(b'\n', &mut head).unwrap() > 0 {}
+        (&stream).write_all(reply).unwrap();
+        head
+    });
+    (address, handle)
+}
+
+const OK: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+#[test]
+fn non_ascii_values_are_refused_before_sending() {
+    let server = TestServer::start();
+    for value in ["café", "👍"] {
+        let result = Client::new().request(Method::Get, &server.address(), "/").header("X-Name", value).send();
+        assert!(matches!(result, Err(HttpError::InvalidHeader(_))), "{:?}: {:?}", value, result);
+        let result = Client::new().get(&server.address(), "/", vec![("X-Name".to_string(), value.to_string())]);
+        assert!(matches!(result, Err(HttpError::InvalidHeader(_))), "{:?}: {:?}", value, result);
+    }
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn header_latin1_sends_one_byte_per_character() {
+    let (address, server) = raw_server(OK);
+    Client::new().request(Method::Get, &address, "/").header_latin1("X-Name", "café").send().unwrap();
+    let head = server.join().unwrap();
+    assert!(head.windows(14).any(|w| w == b"X-Name: caf\xe9\r\n"), "{:?}", String::from_utf8_lossy(&head));
+
+    let server = TestServer::start();
+    let result = Client::new().request(Method::Get, &server.address(), "/").header_latin1("X-Name", "€").send();
+    assert!(matches!(result, Err(HttpError::InvalidHeader(_))), "{:?}", result);
+}
+
+#[test]
+fn ext_param_values_go_out_as_ascii() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let disposition = format!("attachment; {}", ext_param("filename", "résumé.pdf"));
+    Client::new().request(Method::Post, &server.address(), "/upload").header("Content-Disposition", &disposition).body("%PDF").send().unwrap();
+    assert_eq!(
+        server.requests()[0].header("Content-Disposition"),
+        Some("attachment; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf")
+    );
+}
+
+#[test]
+fn latin1_response_headers_are_kept_losslessly() {
+    let (address, server) = raw_server(b"HTTP/1.1 200 OK\r\nX-City: Z\xfcrich\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    let response = Client::new().request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(response.header("X-City"), Some("Zürich"));
+    server.join().unwrap();
+}
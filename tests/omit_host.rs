@@ -0,0 +1,43 @@
+use std::io::Cursor;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+// A GET, a streamed POST and a send_debug POST over one keep-alive connection
+fn send_three(client: &Client, server: &TestServer) -> Vec<u8> {
+    for _ in 0..3 {
+        server.respond_with(200, &[], "ok");
+    }
+    client.request(Method::Get, &server.address(), "/get").header("X-Other", "1").send().unwrap();
+    client.request(Method::Post, &server.address(), "/stream").body_stream(Cursor::new(b"streamed".to_vec())).send().unwrap();
+    let (sent, _) = client.request(Method::Post, &server.address(), "/debug").body("debug").send_debug().unwrap();
+    sent.bytes
+}
+
+#[test]
+fn host_is_sent_by_default() {
+    let server = TestServer::start();
+    let bytes = send_three(&Client::new(), &server);
+    for request in server.requests() {
+        assert_eq!(request.header("Host"), Some(server.address().as_str()), "{}", request.target);
+    }
+    assert!(String::from_utf8(bytes).unwrap().contains("\r\nHost: "));
+}
+
+#[test]
+fn omit_host_header_leaves_it_out_of_every_request() {
+    let server = TestServer::start();
+    let client = Client::with_config(RequestConfig { omit_host_header: true, keep_alive: true, ..RequestConfig::default() });
+    let bytes = send_three(&client, &server);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    for request in &requests {
+        assert_eq!(request.header("Host"), None, "{}", request.target);
+    }
+    assert_eq!(requests[0].header("X-Other"), Some("1"));
+    assert_eq!(requests[1].body, b"streamed");
+    assert_eq!(requests[2].body, b"debug");
+    let bytes = String::from_utf8(bytes).unwrap();
+    assert!(bytes.starts_with("POST /debug HTTP/1.1\r\n") && !bytes.contains("Host:"), "{}", bytes);
+}
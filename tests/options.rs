@@ -0,0 +1,14 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{options, HeaderList};
+
+#[test]
+fn server_wide_options_sends_an_asterisk_target_and_host() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Allow", "GET, OPTIONS")], "");
+    let response = options(&server.address(), "*", HeaderList::new()).unwrap();
+    assert_eq!(response.header("Allow"), Some("GET, OPTIONS"));
+
+    let request = &server.requests()[0];
+    assert_eq!((request.method.as_str(), request.target.as_str()), ("OPTIONS", "*"));
+    assert_eq!(request.header("Host"), Some(server.address().as_str()));
+}
@@ -0,0 +1,92 @@
+use http_client_from_scratch_rust::{parse_response, parse_response_lenient, try_parse_response};
+
+const SAMPLES: &[&[u8]] = &[
+    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nX-Test: a\r\n\r\nhello",
+    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;ext=1\r\nhello\r\n0\r\nX-Trailer: t\r\n\r\n",
+    b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+    b"HTTP/1.0 200 OK\r\n\r\nuntil close",
+];
+
+// Every prefix of well-formed responses, and each with one byte replaced by a few
+// troublesome values: Ok or Err, never a panic
+#[test]
+fn truncated_and_corrupted_inputs_never_panic() {
+    for sample in SAMPLES {
+        assert!(try_parse_response(sample).is_ok(), "{:?}", String::from_utf8_lossy(sample));
+        for end in 0..sample.len() {
+            let _ = try_parse_response(&sample[..end]);
+        }
+        for i in 0..sample.len() {
+            for byte in [0, b'\r', b'\n', b':', b' ', b'0', b'f', 0x7f, 0xff] {
+                let mut input = sample.to_vec();
+                input[i] = byte;
+                let _ = try_parse_response(&input);
+                if let Ok(text) = std::str::from_utf8(&input) {
+                    let _ = parse_response(text);
+                    let _ = parse_response_lenient(text);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn malformed_inputs_are_errors() {
+    for input in [
+        &b""[..],
+        b"garbage",
+        b"HTTP/1.1 2000 OK\r\n\r\n",
+        b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+        b"HTTP/1.1 200 OK\r\nContent-Length: -1\r\n\r\n",
+        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 3\r\n\r\nok",
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 2\r\n\r\nok",
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffffffff\r\n",
+        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nokEXTRA",
+        b"HTTP/1.1 100 Continue\r\n\r\n",
+    ] {
+        assert!(try_parse_response(input).is_err(), "{:?}", String::from_utf8_lossy(input));
+    }
+}
+
+#[test]
+fn final_response_follows_interim_ones() {
+    let response = try_parse_response(SAMPLES[2]).unwrap();
+    assert_eq!(response.status_code, 404);
+    assert!(parse_response(std::str::from_utf8(SAMPLES[2]).unwrap()).is_err());
+}
+
+#[test]
+fn chunk_extensions_are_ignored() {
+    let with = try_parse_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5 ; a=\"q;x\"\r\nhello\r\n0;last\r\n\r\n").unwrap();
+    let without = try_parse_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n").unwrap();
+    assert_eq!(with.body, b"hello");
+    assert_eq!(without.body, b"hello");
+    assert!(try_parse_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5x;a\r\nhello\r\n0\r\n\r\n").is_err());
+}
+
+#[test]
+fn heads_ended_by_bare_line_feeds_parse() {
+    let response = try_parse_response(b"HTTP/1.1 200 OK\nContent-Length: 2\n\nok").unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body, b"ok");
+    let mixed = try_parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\nok").unwrap();
+    assert_eq!(mixed.body, b"ok");
+}
+
+#[test]
+fn head_ending_at_the_end_of_input_parses() {
+    let response = try_parse_response(b"HTTP/1.1 204 No Content\r\nX-A: 1\r\n").unwrap();
+    assert_eq!(response.status_code, 204);
+    assert_eq!(response.header("X-A"), Some("1"));
+    let unterminated = try_parse_response(b"HTTP/1.1 200 OK\r\nX-A: 1").unwrap();
+    assert_eq!(unterminated.header("X-A"), Some("1"));
+    assert!(unterminated.body.is_empty());
+}
+
+#[test]
+fn a_folded_header_is_unfolded_before_framing_reads_it() {
+    let response = try_parse_response(b"HTTP/1.1 200 OK\r\nContent-Length:   2  \r\nX-Long: a\r\n  b\r\n\r\nok").unwrap();
+    assert_eq!(response.body, b"ok");
+    assert_eq!(response.header("X-Long"), Some("a b"));
+}
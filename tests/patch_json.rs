@@ -0,0 +1,33 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Json, PatchFormat};
+
+#[test]
+fn each_format_sends_its_content_type() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    server.respond_with(200, &[], "");
+    let client = Client::new();
+    let merge = Json::parse(r#"{"name": "new", "old": null}"#).unwrap();
+    let operations = Json::parse(r#"[{"op": "replace", "path": "/name", "value": "new"}]"#).unwrap();
+    client.patch_json(&server.address(), "/users/1", &merge, PatchFormat::MergePatch).unwrap();
+    client.patch_json(&server.address(), "/users/1", &operations, PatchFormat::JsonPatch).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "PATCH");
+    assert_eq!(requests[0].header("Content-Type"), Some("application/merge-patch+json"));
+    assert_eq!(requests[0].body, br#"{"name":"new","old":null}"#);
+    assert_eq!(requests[1].header("Content-Type"), Some("application/json-patch+json"));
+    assert_eq!(requests[1].body, br#"[{"op":"replace","path":"/name","value":"new"}]"#);
+    for request in &requests {
+        assert_eq!(request.headers.iter().filter(|(name, _)| name.eq_ignore_ascii_case("Content-Type")).count(), 1);
+    }
+}
+
+#[test]
+fn a_json_patch_must_be_an_array() {
+    let server = TestServer::start();
+    let object = Json::parse(r#"{"op": "remove", "path": "/a"}"#).unwrap();
+    let result = Client::new().patch_json(&server.address(), "/", &object, PatchFormat::JsonPatch);
+    assert!(matches!(result, Err(HttpError::InvalidRequest(_))), "{:?}", result);
+    assert!(server.requests().is_empty());
+}
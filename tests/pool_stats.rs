@@ -0,0 +1,45 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, PoolStats, RequestConfig};
+
+fn pooling() -> Client {
+    Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() })
+}
+
+#[test]
+fn three_sequential_requests_create_one_connection_and_reuse_it_twice() {
+    let server = TestServer::start();
+    let client = pooling();
+    let mut reused = Vec::new();
+    for _ in 0..3 {
+        server.respond_with(200, &[], "ok");
+        reused.push(client.request(Method::Get, &server.address(), "/").send().unwrap().connection.unwrap().reused);
+    }
+    assert_eq!(reused, [false, true, true]);
+    let stats = client.pool_stats();
+    assert_eq!((stats.created, stats.reused), (1, 2));
+    assert_eq!((stats.currently_idle, stats.currently_in_use), (1, 0));
+
+    // Clones share the pool and its counters
+    assert_eq!(client.clone().pool_stats(), stats);
+}
+
+#[test]
+fn a_streaming_response_counts_as_in_use_until_dropped() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "streamed");
+    let client = pooling();
+    let response = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap();
+    assert_eq!((client.pool_stats().currently_in_use, client.pool_stats().currently_idle), (1, 0));
+    response.consume_body().unwrap();
+    assert_eq!((client.pool_stats().currently_in_use, client.pool_stats().currently_idle), (0, 1));
+}
+
+#[test]
+fn reset_zeroes_the_totals_but_not_what_is_pooled() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let client = pooling();
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    client.reset_pool_stats();
+    assert_eq!(client.pool_stats(), PoolStats { currently_idle: 1, ..PoolStats::default() });
+}
@@ -0,0 +1,205 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpResponse, Method, RequestConfig};
+
+fn pooling() -> Client {
+    Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() })
+}
+
+fn http10(headers: &[(&str, &str)]) -> HttpResponse {
+    let mut response = HttpResponse::new(200, headers, "ok");
+    response.status_line = "HTTP/1.0 200 OK".to_string();
+    response
+}
+
+// Two requests on one client: whether the second was sent on the first's connection
+fn second_reused(server: &TestServer, client: &Client) -> bool {
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    let second = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    second.connection.unwrap().reused
+}
+
+#[test]
+fn http11_response_is_pooled() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    server.respond_with(200, &[], "ok");
+    let client = pooling();
+    assert!(second_reused(&server, &client));
+    assert_eq!(client.pool_stats().created, 1);
+}
+
+#[test]
+fn connection_close_response_is_not_pooled() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Connection", "close")], "ok");
+    server.respond_with(200, &[], "ok");
+    let client = pooling();
+    assert!(!second_reused(&server, &client));
+    assert_eq!(client.pool_stats().created, 2);
+}
+
+#[test]
+fn http10_response_is_pooled_only_with_keep_alive() {
+    let server = TestServer::start();
+    server.respond_with_fn(|_| http10(&[]));
+    server.respond_with(200, &[], "ok");
+    let client = pooling();
+    assert!(!second_reused(&server, &client));
+
+    let server = TestServer::start();
+    server.respond_with_fn(|_| http10(&[("Connection", "keep-alive")]));
+    server.respond_with(200, &[], "ok");
+    let client = pooling();
+    assert!(second_reused(&server, &client));
+}
+
+// Answers one request per connection and then closes it: right away, or only when the next
+// request arrives on it, as a server whose idle timeout fires just as a reused connection is picked up
+fn one_request_per_connection(close_on_next: bool) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // The head, and a chunked body if there is one
+    fn read_head<R: BufRead>(reader: &mut R) -> bool {
+        let mut line = String::new();
+        let mut chunked = false;
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return false,
+                Ok(_) if line == "\r\n" => break,
+                Ok(_) => chunked |= line.eq_ignore_ascii_case("Transfer-Encoding: chunked\r\n"),
+            }
+        }
+        // Up to the last chunk, then past any trailers to the blank line
+        let mut last_chunk = false;
+        while chunked {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return false,
+                Ok(_) if line == "0\r\n" => last_chunk = true,
+                Ok(_) => chunked = !(last_chunk && line == "\r\n"),
+            }
+        }
+        true
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let accepted = std::sync::Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { return };
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            if read_head(&mut reader) {
+                let _ = writer.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+            }
+            if close_on_next {
+                read_head(&mut reader);
+            }
+        }
+    });
+    (address, accepted)
+}
+
+fn accepted(counter: &std::sync::atomic::AtomicUsize) -> usize {
+    counter.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+#[test]
+fn a_pooled_connection_closed_while_idle_is_replaced_before_sending() {
+    let (address, counter) = one_request_per_connection(false);
+    let client = pooling();
+    client.request(Method::Get, &address, "/").send().unwrap();
+    // Let the server's close reach the pooled socket
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    let second = client.request(Method::Get, &address, "/").send().unwrap();
+    let connection = second.connection.unwrap();
+    assert!(!connection.reused && !connection.stale_retry);
+    assert_eq!(client.pool_stats().closed_stale, 1);
+    assert_eq!(accepted(&counter), 2);
+}
+
+// A streamed body can't be replayed after a failed send, so it depends on the check
+#[test]
+fn a_streamed_post_after_an_idle_close_goes_out_on_a_new_connection() {
+    let (address, counter) = one_request_per_connection(false);
+    let client = pooling();
+    client.request(Method::Get, &address, "/").send().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    let body = std::io::Cursor::new(b"streamed body".to_vec());
+    let second = client.request(Method::Post, &address, "/upload").body_stream(body).send().unwrap();
+    assert_eq!(second.body, b"ok");
+    assert!(!second.connection.unwrap().reused);
+    assert_eq!(client.pool_stats().closed_stale, 1);
+    assert_eq!(accepted(&counter), 2);
+}
+
+#[test]
+fn a_reused_connection_closed_unanswered_is_retried_once_on_a_fresh_one() {
+    let (address, counter) = one_request_per_connection(true);
+    let client = pooling();
+    let first = client.request(Method::Get, &address, "/").send().unwrap();
+    assert!(!first.connection.unwrap().stale_retry);
+
+    let second = client.request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(second.body, b"ok");
+    let connection = second.connection.unwrap();
+    assert!(connection.stale_retry);
+    assert!(connection.to_string().contains("retried after a stale connection"));
+    assert_eq!(client.pool_stats().closed_stale, 1);
+    assert_eq!(accepted(&counter), 2);
+}
+
+#[test]
+fn a_streamed_body_is_not_replayed_on_a_stale_connection() {
+    let (address, counter) = one_request_per_connection(true);
+    let client = pooling();
+    client.request(Method::Get, &address, "/").send().unwrap();
+
+    let result = client.request(Method::Post, &address, "/").body_stream(&b"only once"[..]).send();
+    assert!(result.is_err(), "{:?}", result);
+    assert_eq!(accepted(&counter), 1);
+}
+
+#[test]
+fn a_keep_alive_timeout_ends_reuse_once_it_has_passed() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Keep-Alive", "timeout=1")], "ok");
+    server.respond_with(200, &[], "ok");
+    let client = pooling();
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    let second = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert!(!second.connection.unwrap().reused);
+    assert_eq!(client.pool_stats().closed_idle, 1);
+}
+
+#[test]
+fn a_keep_alive_max_retires_the_connection_after_that_many_requests() {
+    let server = TestServer::start();
+    for _ in 0..3 {
+        server.respond_with(200, &[("Keep-Alive", "timeout=30, max=2")], "ok");
+    }
+    let client = pooling();
+    let reused: Vec<bool> = (0..3)
+        .map(|_| client.request(Method::Get, &server.address(), "/").send().unwrap().connection.unwrap().reused)
+        .collect();
+    assert_eq!(reused, [false, true, false]);
+    assert_eq!(client.pool_stats().created, 2);
+}
+
+#[test]
+fn a_malformed_keep_alive_falls_back_to_the_pool_idle_timeout() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Keep-Alive", "timeout=never, max=-1")], "ok");
+    server.respond_with(200, &[], "ok");
+    let client = pooling();
+    assert!(second_reused(&server, &client));
+}
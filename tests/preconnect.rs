@@ -0,0 +1,50 @@
+use std::thread;
+use std::time::Duration;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+fn pooling(idle_timeout: Duration) -> Client {
+    Client::with_config(RequestConfig { keep_alive: true, pool_idle_timeout: idle_timeout, ..RequestConfig::default() })
+}
+
+fn port(server: &TestServer) -> u16 {
+    server.address().rsplit(':').next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn the_first_request_after_preconnect_reuses_the_warm_connection() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "warm");
+    let client = pooling(Duration::from_secs(30));
+    let warmed = client.preconnect("127.0.0.1", port(&server)).unwrap();
+    assert!(!warmed.reused);
+    let again = client.preconnect("127.0.0.1", port(&server)).unwrap();
+    assert!(again.reused);
+    assert_eq!(again.local_addr, warmed.local_addr);
+
+    let response = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert!(response.connection.unwrap().reused);
+    assert_eq!(client.pool_stats().created, 1);
+}
+
+#[test]
+fn a_warm_connection_left_too_long_is_pruned_and_replaced() {
+    let server = TestServer::start();
+    let client = pooling(Duration::from_millis(50));
+    client.preconnect("127.0.0.1", port(&server)).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert!(!client.preconnect("127.0.0.1", port(&server)).unwrap().reused);
+    let stats = client.pool_stats();
+    assert_eq!((stats.created, stats.closed_idle, stats.currently_idle), (2, 1, 1));
+}
+
+#[test]
+fn preconnect_needs_keep_alive_and_reports_connect_errors() {
+    let result = Client::new().preconnect("127.0.0.1", 9);
+    assert!(matches!(result, Err(HttpError::InvalidRequest(_))), "{:?}", result);
+
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let result = pooling(Duration::from_secs(30)).preconnect("127.0.0.1", closed);
+    assert!(matches!(result, Err(HttpError::NetworkError(_))), "{:?}", result);
+}
@@ -0,0 +1,123 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::testing::{ReceivedRequest, TestServer};
+use http_client_from_scratch_rust::{Client, HttpError, HttpResponse, Method, Proxy, RequestConfig};
+
+// "alice:wonderland" as Basic credentials
+const GOOD: &str = "Basic YWxpY2U6d29uZGVybGFuZA==";
+const CHALLENGE: &str = "Basic realm=\"test proxy\"";
+
+fn through(proxy: Proxy) -> Client {
+    Client::with_config(RequestConfig { proxy: Some(proxy), ..RequestConfig::default() })
+}
+
+// A forward proxy that answers for the origin itself, once the credentials are right
+fn enforce(request: &ReceivedRequest) -> HttpResponse {
+    if request.header("Proxy-Authorization") == Some(GOOD) {
+        HttpResponse::new(200, &[], "from the origin")
+    } else {
+        HttpResponse::new(407, &[("Proxy-Authenticate", CHALLENGE)], "proxy says no")
+    }
+}
+
+fn proxy_server(answers: usize) -> TestServer {
+    let server = TestServer::start();
+    for _ in 0..answers {
+        server.respond_with_fn(enforce);
+    }
+    server
+}
+
+#[test]
+fn preemptive_credentials_go_with_the_absolute_form_request() {
+    let server = proxy_server(1);
+    let proxy = Proxy::new(&server.address()).with_credentials("alice", "wonderland");
+    let response = through(proxy).request(Method::Get, "origin.test", "/page?x=1").send().unwrap();
+    assert_eq!(response.body, b"from the origin");
+
+    let received = server.requests();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].target, "http://origin.test/page?x=1");
+    assert_eq!(received[0].header("Proxy-Authorization"), Some(GOOD));
+}
+
+#[test]
+fn a_407_is_an_error_carrying_the_challenge_not_a_response() {
+    for proxy in [Proxy::new("placeholder"), Proxy::new("placeholder").with_credentials("alice", "wrong")] {
+        let server = proxy_server(2);
+        let proxy = Proxy { authority: server.address(), ..proxy };
+        let result = through(proxy).request(Method::Get, "origin.test", "/").send();
+        assert!(matches!(&result, Err(HttpError::ProxyAuthRequired { challenge }) if challenge == CHALLENGE), "{:?}", result);
+    }
+}
+
+#[test]
+fn credentials_that_are_not_preemptive_are_sent_after_a_407() {
+    let server = proxy_server(2);
+    let proxy = Proxy { preemptive: false, ..Proxy::new(&server.address()).with_credentials("alice", "wonderland") };
+    let response = through(proxy).request(Method::Get, "origin.test", "/").send().unwrap();
+    assert_eq!(response.body, b"from the origin");
+
+    let received = server.requests();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].header("Proxy-Authorization"), None);
+    assert_eq!(received[1].header("Proxy-Authorization"), Some(GOOD));
+}
+
+// A CONNECT proxy that checks the credentials, then plays the origin inside the tunnel
+fn tunnel_proxy() -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut read_head = || {
+            let mut lines = Vec::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                    return lines;
+                }
+                lines.push(line.trim_end().to_string());
+            }
+        };
+
+        let connect = read_head();
+        if !connect.iter().any(|line| line == &format!("Proxy-Authorization: {}", GOOD)) {
+            let _ = writer.write_all(format!("HTTP/1.1 407 Proxy Authentication Required\r\nProxy-Authenticate: {}\r\nContent-Length: 0\r\n\r\n", CHALLENGE).as_bytes());
+            return connect;
+        }
+        writer.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").unwrap();
+        let mut seen = connect;
+        seen.extend(read_head());
+        writer.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\ntunnel!").unwrap();
+        seen
+    });
+    (address, handle)
+}
+
+#[test]
+fn a_tunnel_sends_the_credentials_on_connect_only() {
+    let (address, proxy) = tunnel_proxy();
+    let proxy_config = Proxy { tunnel: true, ..Proxy::new(&address).with_credentials("alice", "wonderland") };
+    let response = through(proxy_config).request(Method::Get, "origin.test:8080", "/inside").send().unwrap();
+    assert_eq!(response.body, b"tunnel!");
+
+    let seen = proxy.join().unwrap();
+    assert_eq!(seen[0], "CONNECT origin.test:8080 HTTP/1.1");
+    let inner = seen.iter().position(|line| line.starts_with("GET ")).unwrap();
+    assert_eq!(seen[inner], "GET /inside HTTP/1.1");
+    assert!(seen[..inner].iter().any(|line| line.starts_with("Proxy-Authorization:")));
+    assert!(!seen[inner..].iter().any(|line| line.starts_with("Proxy-Authorization:")));
+}
+
+#[test]
+fn a_tunnel_without_credentials_fails_with_the_challenge() {
+    let (address, proxy) = tunnel_proxy();
+    let result = through(Proxy { tunnel: true, ..Proxy::new(&address) }).request(Method::Get, "origin.test", "/").send();
+    assert!(matches!(&result, Err(HttpError::ProxyAuthRequired { challenge }) if challenge == CHALLENGE), "{:?}", result);
+    proxy.join().unwrap();
+}
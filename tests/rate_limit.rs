@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, HttpResponse, Method, RequestConfig};
+
+#[test]
+fn threads_sharing_a_client_share_its_budget() {
+    let server = TestServer::start();
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+    for _ in 0..16 {
+        let arrivals = Arc::clone(&arrivals);
+        server.respond_with_fn(move |_| {
+            arrivals.lock().unwrap().push(Instant::now());
+            HttpResponse::new(200, &[], "ok")
+        });
+    }
+    let client = Client::with_config(RequestConfig { rate_limit: Some(20.0), ..RequestConfig::default() });
+    let start = Instant::now();
+
+    let workers: Vec<_> = (0..8)
+        .map(|_| {
+            let client = client.clone();
+            let address = server.address();
+            thread::spawn(move || {
+                for _ in 0..2 {
+                    client.request(Method::Get, &address, "/").send().unwrap();
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    // 16 requests at 20 a second take at least 15 intervals of 50ms
+    assert!(start.elapsed() >= Duration::from_millis(750), "{:?}", start.elapsed());
+    let mut arrivals = arrivals.lock().unwrap().clone();
+    arrivals.sort();
+    assert_eq!(arrivals.len(), 16);
+    // Half a second holds 10 of them; allow a couple more for scheduling jitter
+    for (i, first) in arrivals.iter().enumerate() {
+        let in_window = arrivals[i..].iter().take_while(|at| at.duration_since(*first) < Duration::from_millis(500)).count();
+        assert!(in_window <= 12, "{} requests within 500ms", in_window);
+    }
+}
+
+#[test]
+fn fail_at_rate_limit_fails_the_request_over_budget() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let client = Client::with_config(RequestConfig { rate_limit: Some(1.0), fail_at_rate_limit: true, ..RequestConfig::default() });
+
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    let start = Instant::now();
+    let err = client.request(Method::Get, &server.address(), "/").send().unwrap_err();
+    assert!(matches!(err, HttpError::NetworkError(_)), "{:?}", err);
+    assert!(start.elapsed() < Duration::from_millis(500));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn a_rate_of_zero_is_rejected() {
+    let server = TestServer::start();
+    let client = Client::with_config(RequestConfig { rate_limit: Some(0.0), ..RequestConfig::default() });
+    let err = client.request(Method::Get, &server.address(), "/").send().unwrap_err();
+    assert!(matches!(err, HttpError::InvalidRequest(_)), "{:?}", err);
+    assert!(server.requests().is_empty());
+}
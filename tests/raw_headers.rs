@@ -0,0 +1,40 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+#[test]
+fn a_captured_block_is_sent_verbatim_after_structured_headers() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let client = Client::new();
+    let block = "User-Agent: curl/8.5.0\r\nAccept: */*\n\nX-Spaced:  two  spaces\r\nX-Latin: caf\u{e9}\r\n";
+    let (sent, _) = client.request(Method::Get, &server.address(), "/replay")
+        .header("X-First", "structured")
+        .raw_headers(block)
+        .send_debug()
+        .unwrap();
+
+    let mut expected = b"X-First: structured\r\nUser-Agent: curl/8.5.0\r\nAccept: */*\r\nX-Spaced:  two  spaces\r\nX-Latin: caf".to_vec();
+    expected.extend_from_slice(b"\xe9\r\n");
+    assert!(sent.bytes.windows(expected.len()).any(|w| w == expected), "{:?}", String::from_utf8_lossy(&sent.bytes));
+    let received = &server.requests()[0];
+    assert_eq!(received.header("X-Spaced"), Some("two  spaces"));
+    assert_eq!(received.header("User-Agent"), Some("curl/8.5.0"));
+}
+
+#[test]
+fn malformed_or_injecting_lines_fail_the_send() {
+    let server = TestServer::start();
+    let client = Client::new();
+    for block in [
+        "no colon here",
+        "X-A: 1\r\n folded continuation",
+        ": empty name",
+        "X-A: one\rX-Evil: injected",
+        "Bad Name: value",
+        "X-Nul: a\0b",
+    ] {
+        let result = client.request(Method::Get, &server.address(), "/").raw_headers(block).send();
+        assert!(matches!(result, Err(HttpError::InvalidHeader(_))), "{:?}: {:?}", block, result);
+    }
+    assert!(server.requests().is_empty());
+}
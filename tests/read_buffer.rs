@@ -0,0 +1,27 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+fn body() -> Vec<u8> {
+    (0..256 * 1024u32).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+fn download(read_buffer_size: usize, headers: &[(&str, &str)]) -> Result<Vec<u8>, HttpError> {
+    let server = TestServer::start();
+    server.respond_with(200, headers, body());
+    let client = Client::with_config(RequestConfig { read_buffer_size, ..RequestConfig::default() });
+    client.request(Method::Get, &server.address(), "/large").send().map(|response| response.body)
+}
+
+#[test]
+fn large_bodies_arrive_whole_with_tiny_and_large_buffers() {
+    for size in [1, 64, 8192, 1024 * 1024] {
+        assert!(download(size, &[]).unwrap() == body(), "Content-Length with {}", size);
+        assert!(download(size, &[("Transfer-Encoding", "chunked")]).unwrap() == body(), "chunked with {}", size);
+    }
+}
+
+#[test]
+fn a_zero_buffer_size_is_refused() {
+    assert!(matches!(download(0, &[]), Err(HttpError::InvalidRequest(_))));
+    assert_eq!(RequestConfig::default().read_buffer_size, 8192);
+}
@@ -0,0 +1,63 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+const BODY: &str = "an HTTP/1.0 body that only the close of the connection ends";
+
+// Answers once with neither Content-Length nor chunked, trickling the body, then closes
+fn http10_server(head: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { return };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let mut out = &stream;
+            out.write_all(head.as_bytes()).unwrap();
+            for piece in BODY.as_bytes().chunks(7) {
+                out.write_all(piece).unwrap();
+                out.flush().unwrap();
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+    });
+    address
+}
+
+#[test]
+fn a_body_without_framing_is_read_until_the_connection_closes() {
+    let address = http10_server("HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\n");
+    for keep_alive in [false, true] {
+        let client = Client::with_config(RequestConfig { keep_alive, ..RequestConfig::default() });
+        let response = client.request(Method::Get, &address, "/").send().unwrap();
+        assert_eq!(response.body, BODY.as_bytes(), "keep_alive={}", keep_alive);
+    }
+}
+
+#[test]
+fn a_close_delimited_connection_is_never_pooled_even_with_keep_alive() {
+    let address = http10_server("HTTP/1.0 200 OK\r\nConnection: keep-alive\r\n\r\n");
+    let client = Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() });
+    for _ in 0..2 {
+        let response = client.request(Method::Get, &address, "/").send().unwrap();
+        assert_eq!(response.body, BODY.as_bytes());
+        assert!(!response.connection.unwrap().reused);
+    }
+    assert_eq!(client.pool_stats().created, 2);
+}
+
+#[test]
+fn a_streamed_close_delimited_body_reads_to_the_end() {
+    let address = http10_server("HTTP/1.0 200 OK\r\n\r\n");
+    let mut reader = Client::new().request(Method::Get, &address, "/").send_streaming().unwrap();
+    let mut body = String::new();
+    reader.read_to_string(&mut body).unwrap();
+    assert_eq!(body, BODY);
+}
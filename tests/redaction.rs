@@ -0,0 +1,44 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{mark_sensitive_header, Client, Method, Proxy, RequestConfig};
+
+const TOKEN: &str = "tok_9f8e7d6c5b4a";
+
+#[test]
+fn tokens_stay_out_of_debug_and_display_but_reach_the_wire() {
+    mark_sensitive_header("X-Api-Key");
+    let server = TestServer::start();
+    server.respond_with(200, &[("Set-Cookie", &format!("session={}", TOKEN)), ("X-Api-Key", TOKEN)], "ok");
+    let client = Client::new().with_default_header("X-Api-Key", TOKEN);
+    let builder = client.request(Method::Get, &server.address(), "/").header("Authorization", &format!("Bearer {}", TOKEN));
+    let builder_debug = format!("{:?}", builder);
+    let (sent, response) = builder.send_debug().unwrap();
+
+    for shown in [
+        format!("{:?}", client),
+        builder_debug,
+        format!("{:?}", sent),
+        format!("{:?}", response),
+        response.to_string(),
+        response.dump(1024),
+        format!("{:?}", server.requests()[0]),
+    ] {
+        assert!(!shown.contains(TOKEN), "{}", shown);
+        assert!(shown.contains("***"), "{}", shown);
+    }
+
+    // Only what is shown changes
+    let received = &server.requests()[0];
+    assert_eq!(received.header("Authorization"), Some(format!("Bearer {}", TOKEN).as_str()));
+    assert_eq!(received.header("X-Api-Key"), Some(TOKEN));
+    assert!(String::from_utf8_lossy(&sent.bytes).contains(TOKEN));
+    assert_eq!(response.header("Set-Cookie"), Some(format!("session={}", TOKEN).as_str()));
+}
+
+#[test]
+fn proxy_passwords_and_config_stay_out_of_debug() {
+    let proxy = Proxy::new("proxy.internal:3128").with_credentials("ci", TOKEN);
+    let config = RequestConfig { proxy: Some(proxy.clone()), ..RequestConfig::default() };
+    for shown in [format!("{:?}", proxy), format!("{:?}", config), format!("{:?}", Client::with_config(config.clone()))] {
+        assert!(!shown.contains(TOKEN), "{}", shown);
+    }
+}
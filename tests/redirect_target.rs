@@ -0,0 +1,41 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+#[test]
+fn relative_location_on_a_302_is_resolved() {
+    let server = TestServer::start();
+    server.respond_with(302, &[("Location", "../c?d=1#frag")], "");
+    server.respond_with(200, &[], "next");
+    let client = Client::new();
+
+    let response = client.request(Method::Get, &server.address(), "/a/b/x?q").send().unwrap();
+    assert_eq!(response.status_code, 302);
+    let target = response.redirect_target().unwrap();
+    assert_eq!(target, format!("http://{}/a/c?d=1#frag", server.address()));
+
+    // Following it by hand gets the next response
+    let path = target.strip_prefix(&format!("http://{}", server.address())).unwrap();
+    let path = path.split('#').next().unwrap();
+    assert_eq!(client.request(Method::Get, &server.address(), path).send().unwrap().body, b"next");
+    assert_eq!(server.requests()[1].target, "/a/c?d=1");
+}
+
+#[test]
+fn url_is_the_last_hop_after_following() {
+    let server = TestServer::start();
+    server.respond_with(301, &[("Location", "/final")], "");
+    server.respond_with(200, &[("Location", "/ignored")], "done");
+    let client = Client::with_config(RequestConfig { max_redirects: 5, ..RequestConfig::default() });
+
+    let response = client.request(Method::Get, &server.address(), "/start").send().unwrap();
+    assert_eq!(response.url, Some(format!("http://{}/final", server.address())));
+    assert_eq!(response.redirect_target(), None);
+}
+
+#[test]
+fn streamed_head_resolves_against_its_own_path() {
+    let server = TestServer::start();
+    server.respond_with(303, &[("Location", "sibling")], "");
+    let reader = Client::new().request(Method::Get, &server.address(), "/dir/page").send_streaming().unwrap();
+    assert_eq!(reader.head().redirect_target(), Some(format!("http://{}/dir/sibling", server.address())));
+}
@@ -0,0 +1,174 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+fn following(max_redirects: usize) -> Client {
+    Client::with_config(RequestConfig { max_redirects, ..Default::default() })
+}
+
+#[test]
+fn credentials_are_not_sent_to_another_origin() {
+    let first = TestServer::start();
+    let second = TestServer::start();
+    first.respond_with(302, &[("Location", &format!("http://{}/next", second.address()))], "");
+    second.respond_with(200, &[], "done");
+
+    let response = following(5)
+        .request(Method::Get, &first.address(), "/")
+        .header("Authorization", "Bearer TOKEN")
+        .header("Cookie", "sid=1")
+        .header("Proxy-Authorization", "Basic cHJveHk6cHc=")
+        .header("X-Trace", "kept")
+        .send()
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+
+    let sent = &first.requests()[0];
+    assert_eq!(sent.header("Authorization"), Some("Bearer TOKEN"));
+    assert_eq!(sent.header("Cookie"), Some("sid=1"));
+    let followed = &second.requests()[0];
+    assert_eq!(followed.target, "/next");
+    assert_eq!(followed.header("Authorization"), None);
+    assert_eq!(followed.header("Cookie"), None);
+    assert_eq!(followed.header("Proxy-Authorization"), None);
+    assert_eq!(followed.header("X-Trace"), Some("kept"));
+}
+
+#[test]
+fn credentials_stay_on_a_same_origin_redirect() {
+    let server = TestServer::start();
+    server.respond_with(301, &[("Location", "/elsewhere")], "");
+    server.respond_with(200, &[], "done");
+
+    following(5)
+        .request(Method::Get, &server.address(), "/")
+        .header("Authorization", "Bearer TOKEN")
+        .send()
+        .unwrap();
+    let requests = server.requests();
+    assert_eq!(requests[1].target, "/elsewhere");
+    assert_eq!(requests[1].header("Authorization"), Some("Bearer TOKEN"));
+}
+
+#[test]
+fn location_path_is_percent_encoded() {
+    let server = TestServer::start();
+    server.respond_with(302, &[("Location", "/a b HTTP/1.0\tX")], "");
+    server.respond_with(200, &[], "done");
+
+    following(5).request(Method::Get, &server.address(), "/").send().unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].target, "/a%20b%20HTTP/1.0%09X");
+}
+
+#[test]
+fn streamed_body_is_not_resent_on_a_body_preserving_redirect() {
+    for status in [307, 308] {
+        let server = TestServer::start();
+        server.respond_with(status, &[("Location", "/next")], "");
+        let response = following(5)
+            .request(Method::Post, &server.address(), "/upload")
+            .body_stream(&b"streamed"[..])
+            .send()
+            .unwrap();
+        assert_eq!(response.status_code, status);
+        assert_eq!(response.header("Location"), Some("/next"));
+        assert_eq!(response.redirect_count, 0);
+        assert_eq!(server.requests().len(), 1);
+    }
+}
+
+#[test]
+fn buffered_body_is_resent_on_a_body_preserving_redirect() {
+    let server = TestServer::start();
+    server.respond_with(308, &[("Location", "/next")], "");
+    server.respond_with(200, &[], "done");
+    let response = following(5).request(Method::Post, &server.address(), "/upload").body("buffered").send().unwrap();
+    assert_eq!(response.status_code, 200);
+
+    let requests = server.requests();
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].target, "/next");
+    assert_eq!(requests[1].body, b"buffered");
+}
+
+#[test]
+fn redirect_without_location_is_returned_as_it_is() {
+    let server = TestServer::start();
+    server.respond_with(302, &[], "no location");
+    let response = following(5).request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.status_code, 302);
+    assert_eq!(response.body, b"no location");
+    assert_eq!(response.redirect_count, 0);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn relative_and_dot_segment_locations_are_joined_to_the_request_url() {
+    let server = TestServer::start();
+    server.respond_with(302, &[("Location", "../up/./here?x=1")], "");
+    server.respond_with(302, &[("Location", "sibling")], "");
+    server.respond_with(302, &[("Location", &format!("//{}/scheme-relative", server.address()))], "");
+    server.respond_with(200, &[], "done");
+
+    let response = following(5).request(Method::Get, &server.address(), "/a/b/start").send().unwrap();
+    assert_eq!(response.body, b"done");
+    let targets: Vec<String> = server.requests().into_iter().map(|r| r.target).collect();
+    assert_eq!(targets, ["/a/b/start", "/a/up/here?x=1", "/a/up/sibling", "/scheme-relative"]);
+}
+
+// /redirect/3 -> /redirect/2 -> /redirect/1 -> /done
+fn redirect_chain(server: &TestServer) {
+    for hops in (1..=3).rev() {
+        let next = if hops == 1 { "/done".to_string() } else { format!("/redirect/{}", hops - 1) };
+        server.respond_with(302, &[("Location", &next)], "");
+    }
+    server.respond_with(200, &[], "done");
+}
+
+#[test]
+fn redirect_count_is_the_number_of_hops_followed() {
+    let server = TestServer::start();
+    redirect_chain(&server);
+    let response = following(5).request(Method::Get, &server.address(), "/redirect/3").send().unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.redirect_count, 3);
+    assert_eq!(response.url, Some(format!("http://{}/done", server.address())));
+
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    assert_eq!(following(5).request(Method::Get, &server.address(), "/").send().unwrap().redirect_count, 0);
+}
+
+// The 3xx that max_redirects stopped at carries the hops followed before it
+#[test]
+fn redirect_count_when_the_limit_stops_the_chain() {
+    let server = TestServer::start();
+    redirect_chain(&server);
+    let response = following(1).request(Method::Get, &server.address(), "/redirect/3").send().unwrap();
+    assert_eq!(response.status_code, 302);
+    assert_eq!(response.redirect_count, 1);
+    assert_eq!(response.header("Location"), Some("/redirect/1"));
+}
+
+#[test]
+fn locations_with_other_schemes_are_not_followed() {
+    for location in ["file:///etc/passwd", "gopher://127.0.0.1:70/1"] {
+        let server = TestServer::start();
+        server.respond_with(302, &[("Location", location)], "");
+        let response = following(5).request(Method::Get, &server.address(), "/").send().unwrap();
+        assert_eq!(response.status_code, 302);
+        assert_eq!(response.redirect_count, 0);
+        assert_eq!(response.header("Location"), Some(location));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
+
+#[test]
+fn error_on_non_http_redirect_names_the_scheme() {
+    let server = TestServer::start();
+    server.respond_with(301, &[("Location", "FILE:///etc/passwd")], "");
+    let client = Client::with_config(RequestConfig { max_redirects: 5, error_on_non_http_redirect: true, ..Default::default() });
+    let err = client.request(Method::Get, &server.address(), "/").send().unwrap_err();
+    assert!(matches!(err, HttpError::InvalidResponse(ref message) if message.contains("FILE")), "{:?}", err);
+}
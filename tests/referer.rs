@@ -0,0 +1,23 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+#[test]
+fn a_valid_referer_is_sent_without_its_fragment() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    Client::new().request(Method::Get, &server.address(), "/")
+        .referer("https://example.com/page?x=1#section")
+        .send()
+        .unwrap();
+    assert_eq!(server.requests()[0].header("Referer"), Some("https://example.com/page?x=1"));
+}
+
+#[test]
+fn invalid_referers_fail_before_anything_is_sent() {
+    let server = TestServer::start();
+    for referer in ["/relative/path", "https://example.com/\r\nX-Injected: 1", "not a url", ""] {
+        let result = Client::new().request(Method::Get, &server.address(), "/").referer(referer).send();
+        assert!(matches!(&result, Err(HttpError::InvalidRequest(message)) if message.contains("Referer")), "{:?}: {:?}", referer, result);
+    }
+    assert!(server.requests().is_empty());
+}
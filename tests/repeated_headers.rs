@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{get, Client, HeaderList, HttpError, Method};
+
+fn values<'a>(headers: &'a [(String, String)], name: &str) -> Vec<&'a str> {
+    headers.iter().filter(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str()).collect()
+}
+
+#[test]
+fn repeated_headers_are_all_sent_in_order() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let mut headers = HeaderList::new();
+    headers.append("X-Trace", "a");
+    headers.append("Cookie", "one=1");
+    headers.append("X-Trace", "b");
+    headers.append("Cookie", "two=2");
+    get(&server.address(), "/", headers).unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!(values(&request.headers, "X-Trace"), ["a", "b"]);
+    assert_eq!(values(&request.headers, "Cookie"), ["one=1", "two=2"]);
+}
+
+#[test]
+fn set_replaces_and_hash_maps_still_convert() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    server.respond_with(200, &[], "ok");
+    let mut headers = HeaderList::from([("X-Trace", "a"), ("X-Trace", "b")]);
+    headers.set("X-Trace", "c");
+    get(&server.address(), "/", headers).unwrap();
+    get(&server.address(), "/", Some(HashMap::from([("X-Map".to_string(), "m".to_string())]))).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(values(&requests[0].headers, "X-Trace"), ["c"]);
+    assert_eq!(requests[1].header("X-Map"), Some("m"));
+}
+
+#[test]
+fn builder_headers_adds_a_vec_in_order() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let headers = vec![("X-One", "1".to_string()), ("X-Two", "2".to_string()), ("X-One", "3".to_string())];
+    Client::new().request(Method::Get, &server.address(), "/").headers(headers).send().unwrap();
+
+    let request = &server.requests()[0];
+    let sent: Vec<(&str, &str)> = request.headers.iter()
+        .filter(|(name, _)| name.starts_with("X-"))
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    assert_eq!(sent, [("X-One", "1"), ("X-Two", "2"), ("X-One", "3")]);
+}
+
+#[test]
+fn builder_headers_checks_each_header() {
+    let server = TestServer::start();
+    let result = Client::new()
+        .request(Method::Get, &server.address(), "/")
+        .headers([("X-Fine", "ok"), ("X-Bad", "a\r\nX-Injected: 1")])
+        .send();
+    assert!(matches!(result, Err(HttpError::InvalidHeader(_))), "{:?}", result);
+    assert!(server.requests().is_empty());
+}
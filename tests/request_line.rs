@@ -0,0 +1,28 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{get, Client, HttpError, Method};
+
+#[test]
+fn injected_method_is_not_sent() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let outcome = Client::new().request("GET\r\nX-Evil: 1\r\n", &server.address(), "/").send();
+    assert!(matches!(outcome, Err(HttpError::InvalidRequest(_))));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn injected_target_is_not_sent() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let outcome = get(&server.address(), "/x HTTP/1.1\r\nX-Injected: 1\r\nY:", None);
+    assert!(matches!(outcome, Err(HttpError::InvalidRequest(_))));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn custom_token_method_goes_out_as_given() {
+    let server = TestServer::start();
+    server.respond_with(207, &[], "");
+    Client::new().request(Method::Custom("PROPFIND".to_string()), &server.address(), "/dav").send().unwrap();
+    assert_eq!(server.requests()[0].method, "PROPFIND");
+}
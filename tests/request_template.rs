@@ -0,0 +1,26 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Credentials, Method, RequestTemplate};
+
+#[test]
+fn derived_requests_all_carry_the_template_auth() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "[]");
+    server.respond_with(201, &[], "{}");
+    let client = Client::new();
+    let api = RequestTemplate::new(Method::Post, &server.address())
+        .base_path("/api/v2")
+        .credentials(&Credentials::basic("Aladdin", "open sesame"))
+        .header("Accept", "application/json");
+
+    client.execute(&api.instantiate("users", None).unwrap()).unwrap();
+    client.request_from(api.instantiate("/items", Some("{\"a\":1}")).unwrap()).header("X-Extra", "1").send().unwrap();
+
+    let requests = server.requests();
+    for request in &requests {
+        assert_eq!(request.header("Authorization"), Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+        assert_eq!(request.header("Accept"), Some("application/json"));
+    }
+    assert_eq!((requests[0].target.as_str(), requests[1].target.as_str()), ("/api/v2/users", "/api/v2/items"));
+    assert_eq!(requests[1].body, b"{\"a\":1}");
+    assert_eq!(requests[1].header("X-Extra"), Some("1"));
+}
@@ -0,0 +1,131 @@
+use std::cell::Cell;
+use std::io::Read;
+use std::rc::Rc;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+// FNV-1a, standing in for a digest that is only known once the whole body has been read
+fn fnv(bytes: &[u8], mut hash: u64) -> u64 {
+    for &b in bytes {
+        hash = (hash ^ u64::from(b)).wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+struct Hashing<R> {
+    inner: R,
+    hash: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for Hashing<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hash.set(fnv(&buf[..n], self.hash.get()));
+        Ok(n)
+    }
+}
+
+#[test]
+fn a_checksum_trailer_follows_the_streamed_body() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let body = "streamed ".repeat(5000);
+    let hash = Rc::new(Cell::new(FNV_OFFSET));
+    let reader = Hashing { inner: body.as_bytes(), hash: hash.clone() };
+
+    Client::new().request(Method::Put, &server.address(), "/upload")
+        .body_stream(reader)
+        .trailers(&["X-Content-Hash"], move || vec![("X-Content-Hash".to_string(), format!("{:016x}", hash.get()))])
+        .send()
+        .unwrap();
+
+    let received = &server.requests()[0];
+    assert_eq!(received.header("Transfer-Encoding"), Some("chunked"));
+    assert_eq!(received.header("Trailer"), Some("X-Content-Hash"));
+    assert_eq!(received.body, body.as_bytes());
+    let expected = format!("{:016x}", fnv(&received.body, FNV_OFFSET));
+    assert_eq!(received.trailers, [("X-Content-Hash".to_string(), expected)]);
+}
+
+#[test]
+fn trailers_need_a_chunked_body() {
+    let server = TestServer::start();
+    let client = Client::new();
+    let buffered = client.request(Method::Post, &server.address(), "/").body("fixed").trailer("X-Sum", "1").send();
+    assert!(matches!(buffered, Err(HttpError::InvalidRequest(_))), "{:?}", buffered);
+
+    let sized = client.request(Method::Post, &server.address(), "/")
+        .header("Content-Length", "5")
+        .body_stream(&b"fixed"[..])
+        .trailer("X-Sum", "1")
+        .send();
+    assert!(sized.is_err(), "{:?}", sized);
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn forbidden_and_unannounced_trailers_are_refused() {
+    let server = TestServer::start();
+    let client = Client::new();
+    let forbidden = client.request(Method::Post, &server.address(), "/").body_stream(&b"x"[..]).trailer("Content-Length", "1").send();
+    assert!(matches!(forbidden, Err(HttpError::InvalidHeader(_))), "{:?}", forbidden);
+
+    let unannounced = client.request(Method::Post, &server.address(), "/")
+        .body_stream(&b"x"[..])
+        .trailers(&["X-A"], || vec![("X-B".to_string(), "1".to_string())])
+        .send();
+    assert!(matches!(&unannounced, Err(HttpError::InvalidRequest(message)) if message.contains("X-B")), "{:?}", unannounced);
+}
+
+#[test]
+fn a_body_that_fails_locally_does_not_wait_for_the_response() {
+    let server = TestServer::start();
+    let start = std::time::Instant::now();
+    let result = Client::new().request(Method::Post, &server.address(), "/")
+        .body_stream(&b"x"[..])
+        .trailers(&["X-A"], || vec![("X-A".to_string(), "bad\r\nvalue".to_string())])
+        .send();
+    assert!(result.is_err(), "{:?}", result);
+    assert!(start.elapsed() < std::time::Duration::from_secs(2), "took {:?}", start.elapsed());
+}
+
+// trailer() and trailers() add to each other, in order
+#[test]
+fn trailer_and_trailers_mix() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    Client::new().request(Method::Put, &server.address(), "/")
+        .body_stream(&b"payload"[..])
+        .trailer("X-Checksum", "abc")
+        .trailers(&["X-Count"], || vec![("X-Count".to_string(), "7".to_string())])
+        .trailer("X-Done", "yes")
+        .send()
+        .unwrap();
+
+    let received = &server.requests()[0];
+    assert_eq!(received.header("Trailer"), Some("X-Checksum, X-Count, X-Done"));
+    let fields = |pairs: &[(&str, &str)]| pairs.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect::<Vec<_>>();
+    assert_eq!(received.trailers, fields(&[("X-Checksum", "abc"), ("X-Count", "7"), ("X-Done", "yes")]));
+    assert_eq!(received.body, b"payload");
+}
+
+#[test]
+fn content_metadata_and_credentials_are_not_trailers() {
+    let server = TestServer::start();
+    let client = Client::new();
+    for name in ["authorization", "Host", "Content-Type", "Cookie"] {
+        let result = client.request(Method::Post, &server.address(), "/").body_stream(&b"x"[..]).trailer(name, "v").send();
+        assert!(matches!(result, Err(HttpError::InvalidHeader(_))), "{}: {:?}", name, result);
+    }
+    let announced = client.request(Method::Post, &server.address(), "/")
+        .body_stream(&b"x"[..])
+        .trailers(&["Content-Type"], || vec![("Content-Type".to_string(), "text/plain".to_string())])
+        .send();
+    assert!(matches!(announced, Err(HttpError::InvalidHeader(_))), "{:?}", announced);
+    let injected = client.request(Method::Post, &server.address(), "/").body_stream(&b"x"[..]).trailer("X-A", "1\r\nX-B: 2").send();
+    assert!(matches!(injected, Err(HttpError::InvalidHeader(_))), "{:?}", injected);
+    assert!(server.requests().is_empty());
+}
@@ -0,0 +1,42 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, Resolver, StaticResolver, SystemResolver};
+
+fn port(server: &TestServer) -> u16 {
+    server.address().rsplit(':').next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn a_static_resolver_points_a_made_up_name_at_localhost() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "internal");
+    let client = Client::new().with_resolver(StaticResolver::default().with_host("api.internal", vec![Ipv4Addr::LOCALHOST.into()]));
+    let response = client.request(Method::Get, &format!("api.internal:{}", port(&server)), "/").send().unwrap();
+    assert_eq!(response.body, b"internal");
+    assert_eq!(server.requests()[0].header("Host"), Some(format!("api.internal:{}", port(&server)).as_str()));
+
+    let result = client.request(Method::Get, &format!("unknown.internal:{}", port(&server)), "/").send();
+    assert!(matches!(&result, Err(HttpError::NetworkError(message)) if message.contains("static resolver")), "{:?}", result);
+}
+
+// Records what it was asked, then answers like the system resolver
+struct Recording(Arc<Mutex<Vec<(String, u16)>>>);
+
+impl Resolver for Recording {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, HttpError> {
+        self.0.lock().unwrap().push((host.to_string(), port));
+        SystemResolver.resolve(if host.ends_with(".example") { "127.0.0.1" } else { host }, port)
+    }
+}
+
+#[test]
+fn a_custom_resolver_gets_the_punycode_name_and_port() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let asked = Arc::new(Mutex::new(Vec::new()));
+    let client = Client::new().with_resolver(Recording(asked.clone()));
+    client.request(Method::Get, &format!("bücher.example:{}", port(&server)), "/").send().unwrap();
+    assert_eq!(*asked.lock().unwrap(), [("xn--bcher-kva.example".to_string(), port(&server))]);
+}
@@ -0,0 +1,30 @@
+use http_client_from_scratch_rust::{HttpResponse, Json};
+
+#[test]
+fn response_round_trips_through_json() {
+    let response = HttpResponse::new(201, &[("Content-Type", "text/plain"), ("X-A", "1"), ("X-A", "2")], "made");
+    let text = response.to_json().to_string();
+    let back = HttpResponse::from_json(&Json::parse(&text).unwrap()).unwrap();
+    assert_eq!(back, response);
+    assert_eq!(back.status_line, "HTTP/1.1 201 Created");
+    assert_eq!(back.header_all("X-A"), ["1", "2"]);
+}
+
+#[test]
+fn binary_body_round_trips_as_base64() {
+    let response = HttpResponse::new(200, &[], vec![0, 159, 146, 150, 255]);
+    let json = response.to_json();
+    assert!(json.get("body_base64").is_some());
+    assert_eq!(HttpResponse::from_json(&json).unwrap().body, response.body);
+}
+
+#[test]
+fn response_can_cross_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<HttpResponse>();
+
+    let response = HttpResponse::new(200, &[], "ok");
+    let copy = response.clone();
+    let handle = std::thread::spawn(move || copy.body);
+    assert_eq!(handle.join().unwrap(), response.body);
+}
@@ -0,0 +1,101 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+fn retrying(config: RequestConfig) -> Client {
+    Client::with_config(RequestConfig { max_retries: 3, retry_delay: Duration::ZERO, ..config })
+}
+
+#[test]
+fn custom_predicate_retries_on_500() {
+    let server = TestServer::start();
+    server.respond_with(500, &[], "");
+    server.respond_with(500, &[], "");
+    server.respond_with(200, &[], "ok");
+    let retried = Arc::new(AtomicU32::new(0));
+    let count = Arc::clone(&retried);
+    let client = retrying(RequestConfig {
+        retry_if: Some(Arc::new(move |ctx| {
+            let retry = ctx.status == Some(500);
+            if retry {
+                count.store(ctx.attempt, Ordering::SeqCst);
+            }
+            retry
+        })),
+        ..RequestConfig::default()
+    });
+
+    let response = client.request(Method::Post, &server.address(), "/").body("x").send().unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(server.requests().len(), 3);
+    assert_eq!(retried.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn default_policy_retries_503_but_not_500() {
+    let server = TestServer::start();
+    server.respond_with(503, &[], "");
+    server.respond_with(200, &[], "ok");
+    server.respond_with(500, &[], "");
+    let client = retrying(RequestConfig::default());
+
+    assert_eq!(client.request(Method::Get, &server.address(), "/").send().unwrap().status_code, 200);
+    let error = client.request(Method::Get, &server.address(), "/").send().unwrap_err();
+    assert!(matches!(error, HttpError::HttpError { code: 500, .. }));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn retries_stop_at_max_retries() {
+    let server = TestServer::start();
+    for _ in 0..5 {
+        server.respond_with(503, &[], "");
+    }
+    let error = retrying(RequestConfig::default()).request(Method::Get, &server.address(), "/").send().unwrap_err();
+    assert!(matches!(error, HttpError::HttpError { code: 503, .. }));
+    assert_eq!(server.requests().len(), 4);
+}
+
+fn keyed() -> Client {
+    retrying(RequestConfig { idempotency_key_header: Some("Idempotency-Key".to_string()), ..RequestConfig::default() })
+}
+
+#[test]
+fn a_retried_post_carries_the_same_idempotency_key_on_both_attempts() {
+    let server = TestServer::start();
+    server.respond_with(503, &[], "");
+    server.respond_with(200, &[], "charged once");
+    let response = keyed().request(Method::Post, &server.address(), "/pay").body("{}").send().unwrap();
+    let keys: Vec<String> = server.requests().iter().map(|r| r.header("Idempotency-Key").unwrap().to_string()).collect();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[0], keys[1]);
+    assert_eq!(response.idempotency_key.as_deref(), Some(keys[0].as_str()));
+}
+
+#[test]
+fn a_caller_key_is_kept_and_shows_in_the_final_error() {
+    let server = TestServer::start();
+    for _ in 0..4 {
+        server.respond_with(503, &[], "");
+    }
+    let result = keyed().request(Method::Post, &server.address(), "/").header("idempotency-key", "mine").send();
+    assert!(matches!(&result, Err(HttpError::HttpError { code: 503, message }) if message.ends_with("[Idempotency-Key: mine]")), "{:?}", result);
+    assert!(server.requests().iter().all(|r| r.header("Idempotency-Key") == Some("mine")));
+}
+
+#[test]
+fn get_requests_and_clients_without_the_option_send_no_key() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "");
+    let response = keyed().request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.idempotency_key, None);
+
+    server.respond_with(503, &[], "");
+    let result = retrying(RequestConfig::default()).request(Method::Post, &server.address(), "/").send();
+    assert!(matches!(result, Err(HttpError::HttpError { code: 503, .. })), "{:?}", result);
+    assert_eq!(server.requests().len(), 2);
+    assert!(server.requests().iter().all(|r| r.header("Idempotency-Key").is_none()));
+}
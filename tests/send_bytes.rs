@@ -0,0 +1,32 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{send_bytes, try_parse_response, RequestConfig};
+
+fn port(server: &TestServer) -> u16 {
+    server.address().rsplit(':').next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn handcrafted_request_gets_a_raw_response() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("X-Reply", "1")], "raw");
+    let request = b"GET /exact?x=1 HTTP/1.1\r\nHost: anything\r\nX-Odd:  spaced \r\nConnection: close\r\n\r\n";
+    let raw = send_bytes("127.0.0.1", port(&server), request, &RequestConfig::default()).unwrap();
+
+    assert!(raw.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    let response = try_parse_response(&raw).unwrap();
+    assert_eq!(response.header("X-Reply"), Some("1"));
+    assert_eq!(response.body, b"raw");
+
+    let received = &server.requests()[0];
+    assert_eq!(received.target, "/exact?x=1");
+    assert_eq!(received.header("Host"), Some("anything"));
+}
+
+#[test]
+fn refused_connection_is_an_error() {
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    assert!(send_bytes("127.0.0.1", port, b"GET / HTTP/1.0\r\n\r\n", &RequestConfig::default()).is_err());
+}
@@ -0,0 +1,65 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Cassette, Client, Method, RequestConfig};
+
+// Captures every byte of the one request it gets, then answers
+fn capturing_server() -> (String, thread::JoinHandle<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0; 4096];
+        // The request below ends with its body, "data"
+        while !received.ends_with(b"\r\n\r\ndata") {
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0, "connection closed early");
+            received.extend_from_slice(&buf[..n]);
+        }
+        stream.write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").unwrap();
+        received
+    });
+    (address, handle)
+}
+
+#[test]
+fn the_sent_request_is_byte_for_byte_what_the_server_received() {
+    let (address, server) = capturing_server();
+    let (sent, response) = Client::new()
+        .request(Method::Post, &address, "/items?id=7")
+        .header("X-Custom", "1")
+        .body("data")
+        .send_debug()
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(sent.bytes, server.join().unwrap());
+    assert!(sent.bytes.starts_with(b"POST /items?id=7 HTTP/1.1\r\n"));
+    assert_eq!(sent.address.unwrap().to_string(), address);
+}
+
+#[test]
+fn send_debug_does_not_retry_or_redirect() {
+    let server = TestServer::start();
+    server.respond_with(302, &[("Location", "/next")], "");
+    let client = Client::with_config(RequestConfig { max_redirects: 5, max_retries: 3, ..RequestConfig::default() });
+    let (_, response) = client.request(Method::Get, &server.address(), "/").send_debug().unwrap();
+    assert_eq!(response.status().as_u16(), 302);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn a_replayed_response_has_no_address() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "recorded");
+    let recording = Cassette::recording();
+    Client::new().with_cassette(recording.clone()).request(Method::Get, &server.address(), "/").send().unwrap();
+
+    let replay = Cassette::from_json(&recording.to_json()).unwrap();
+    let (sent, response) = Client::new().with_cassette(replay).request(Method::Get, &server.address(), "/").send_debug().unwrap();
+    assert_eq!(response.body, b"recorded");
+    assert_eq!(sent.address, None);
+    assert!(sent.bytes.starts_with(b"GET / HTTP/1.1\r\n"));
+}
@@ -0,0 +1,56 @@
+use std::io::{self, Cursor, Read, Write};
+
+use http_client_from_scratch_rust::{send_request_on, HeaderList, HttpError};
+
+// An in-memory connection: reads come from a canned response, writes are kept
+struct Duplex {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Duplex {
+    fn answering(response: &str) -> Duplex {
+        Duplex { input: Cursor::new(response.as_bytes().to_vec()), output: Vec::new() }
+    }
+}
+
+impl Read for Duplex {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for Duplex {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn request_is_written_and_response_parsed() {
+    let mut stream = Duplex::answering("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let response = send_request_on(&mut stream, "POST", "example.com", "/items", Some("a=1"), [("X-Trace", "t")]).unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body, b"hello");
+
+    let sent = String::from_utf8(stream.output).unwrap();
+    assert!(sent.starts_with("POST /items HTTP/1.1\r\nHost: example.com\r\n"), "{}", sent);
+    assert!(sent.contains("\r\nX-Trace: t\r\n"), "{}", sent);
+    assert!(sent.contains("\r\nContent-Length: 3\r\n"), "{}", sent);
+    assert!(sent.ends_with("\r\n\r\na=1"), "{}", sent);
+}
+
+#[test]
+fn error_status_and_empty_stream_are_errors() {
+    let mut stream = Duplex::answering("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    let result = send_request_on(&mut stream, "GET", "example.com", "/", None, HeaderList::new());
+    assert!(matches!(result, Err(HttpError::HttpError { code: 404, .. })));
+
+    let mut stream = Duplex::answering("");
+    let result = send_request_on(&mut stream, "GET", "example.com", "/", None, HeaderList::new());
+    assert!(matches!(result, Err(HttpError::EmptyResponse)));
+}
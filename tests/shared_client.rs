@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::thread;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpResponse, Method, RequestConfig};
+
+#[test]
+fn one_client_shared_across_threads_gets_every_answer_right() {
+    let server = TestServer::start();
+    for _ in 0..200 {
+        server.respond_with_fn(|request| HttpResponse::new(200, &[], request.target.clone()));
+    }
+    let client = Arc::new(Client::with_config(RequestConfig { keep_alive: true, max_connections: Some(4), ..RequestConfig::default() }));
+    let threads: Vec<_> = (0..8)
+        .map(|t| {
+            let (client, address) = (Arc::clone(&client), server.address());
+            thread::spawn(move || {
+                for i in 0..25 {
+                    let path = format!("/{}/{}", t, i);
+                    assert_eq!(client.request(Method::Get, &address, &path).send().unwrap().body, path.as_bytes());
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let stats = client.pool_stats();
+    assert!(stats.created <= 4, "{:?}", stats);
+    assert_eq!(stats.created + stats.reused, 200);
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn client_and_its_shared_parts_are_send_and_sync() {
+    assert_send_sync::<Client>();
+    assert_send_sync::<RequestConfig>();
+    assert_send_sync::<HttpResponse>();
+}
+
+#[test]
+fn sixteen_concurrent_requests_each_get_their_own_connection() {
+    let server = TestServer::start();
+    let barrier = Arc::new(std::sync::Barrier::new(16));
+    for _ in 0..16 {
+        let barrier = Arc::clone(&barrier);
+        // Each answer waits until all 16 requests have arrived, so none can share a connection
+        server.respond_with_fn(move |request| {
+            barrier.wait();
+            HttpResponse::new(200, &[], request.target.clone())
+        });
+    }
+    let client = Arc::new(Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() }));
+    let threads: Vec<_> = (0..16)
+        .map(|t| {
+            let (client, address) = (Arc::clone(&client), server.address());
+            thread::spawn(move || {
+                let path = format!("/{}", t);
+                assert_eq!(client.request(Method::Get, &address, &path).send().unwrap().body, path.as_bytes());
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(client.pool_stats().created, 16);
+    assert_eq!(client.pool_stats().currently_in_use, 0);
+}
@@ -0,0 +1,49 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// A legacy server that reads the whole request up to EOF, then answers with the number of
+// bytes it got
+fn reads_to_eof() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        if stream.read_to_end(&mut request).is_ok() {
+            let body = request.len().to_string();
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        }
+    });
+    address
+}
+
+fn client(shutdown_after_write: bool) -> Client {
+    Client::with_config(RequestConfig {
+        shutdown_after_write,
+        read_timeout: Some(Duration::from_millis(300)),
+        ..RequestConfig::default()
+    })
+}
+
+#[test]
+fn a_server_waiting_for_eof_answers_only_after_a_half_close() {
+    let result = client(false).request(Method::Post, &reads_to_eof(), "/").body("data").send();
+    assert!(matches!(result, Err(HttpError::Timeout { .. })), "{:?}", result);
+
+    let response = client(true).request(Method::Post, &reads_to_eof(), "/").body("data").send().unwrap();
+    assert!(response.body_text().unwrap().parse::<usize>().unwrap() > 4);
+
+    let streamed = client(true).request(Method::Post, &reads_to_eof(), "/").body_stream(&b"streamed"[..]).send().unwrap();
+    assert_eq!(streamed.status().as_u16(), 200);
+}
+
+#[test]
+fn a_half_close_is_refused_with_keep_alive() {
+    let client = Client::with_config(RequestConfig { shutdown_after_write: true, keep_alive: true, ..RequestConfig::default() });
+    let result = client.request(Method::Post, "127.0.0.1:9", "/").body("x").send();
+    assert!(matches!(result, Err(HttpError::InvalidRequest(_))), "{:?}", result);
+}
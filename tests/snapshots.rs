@@ -0,0 +1,18 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method};
+
+#[test]
+fn two_fetches_differing_only_in_volatile_headers_match() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Date", "Sun, 06 Nov 1994 08:49:37 GMT"), ("Set-Cookie", "sid=1"), ("Age", "0")], "{\"id\":7}");
+    server.respond_with(200, &[("Date", "Sun, 06 Nov 1994 08:49:38 GMT"), ("Set-Cookie", "sid=2"), ("Age", "3")], "{\"id\":7}");
+    server.respond_with(200, &[("Date", "Sun, 06 Nov 1994 08:49:39 GMT"), ("ETag", "\"v2\"")], "{\"id\":7}");
+    let client = Client::new();
+    let fetch = || client.request(Method::Get, &server.address(), "/item").send().unwrap();
+    let (first, second, third) = (fetch(), fetch(), fetch());
+
+    assert!(first.semantically_equal(&second, &[]));
+    assert!(!first.semantically_equal(&third, &[]));
+    assert!(first.semantically_equal(&third, &["ETag"]));
+    assert!(first.body_equals(&third));
+}
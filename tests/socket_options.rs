@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, RequestConfig, SocketConfig};
+
+fn send_with(socket: SocketConfig) -> bool {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    let client = Client::with_config(RequestConfig { socket, ..Default::default() });
+    let response = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    response.connection.unwrap().socket_config_applied
+}
+
+#[test]
+fn applied_socket_options_show_in_connection_info() {
+    assert!(send_with(SocketConfig::default()));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_socket_option_is_surfaced_and_the_request_still_succeeds() {
+    assert!(!send_with(SocketConfig { keepalive: Some(Duration::from_secs(40_000)), ..SocketConfig::default() }));
+}
@@ -0,0 +1,47 @@
+use std::io::Read;
+
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpResponse, Method, RequestConfig, SpooledBody};
+
+fn body(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 253) as u8).collect()
+}
+
+fn read_back(spooled: SpooledBody) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    spooled.into_reader().read_to_end(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn a_body_past_the_threshold_lands_in_a_file() {
+    let server = TestServer::start();
+    server.respond_with_fn(|_| {
+        let mut response = HttpResponse::new(200, &[("Transfer-Encoding", "chunked")], body(3 * 1024 * 1024));
+        response.trailers = vec!["X-Checksum: abc".to_string()];
+        response
+    });
+    let reader = Client::new().request(Method::Get, &server.address(), "/dump").send_streaming().unwrap();
+    let (response, spooled) = reader.spool(64 * 1024).unwrap();
+
+    assert!(spooled.is_file());
+    assert!(response.body.is_empty());
+    assert_eq!(response.trailer("X-Checksum"), Some("abc"));
+    assert!(read_back(spooled) == body(3 * 1024 * 1024));
+}
+
+#[test]
+fn the_threshold_itself_stays_in_memory() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], body(1000));
+    server.respond_with(200, &[], body(1001));
+    let client = Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() });
+
+    let (_, at) = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap().spool(1000).unwrap();
+    assert!(matches!(&at, SpooledBody::Memory(bytes) if *bytes == body(1000)));
+    let (_, over) = client.request(Method::Get, &server.address(), "/").send_streaming().unwrap().spool(1000).unwrap();
+    assert!(over.is_file());
+    assert_eq!(read_back(over), body(1001));
+    // Both bodies were read to the end, so the connection went back to the pool
+    assert_eq!(client.pool_stats().created, 1);
+}
@@ -0,0 +1,12 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method, StatusCode};
+
+#[test]
+fn response_status_is_typed() {
+    let server = TestServer::start();
+    server.respond_with(204, &[], "");
+    let response = Client::new().request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.status(), StatusCode(204));
+    assert!(response.status().is_success());
+    assert_eq!(response.status_code, 204);
+}
@@ -0,0 +1,55 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method};
+
+#[test]
+fn chunks_of_any_size_do_not_preallocate_it() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "hello world");
+    let reader = Client::new().request(Method::Get, &server.address(), "/").send_streaming().unwrap();
+    let chunks: Vec<Vec<u8>> = reader.chunks(usize::MAX).map(Result::unwrap).collect();
+    assert_eq!(chunks, [b"hello world".to_vec()]);
+}
+
+#[test]
+fn chunks_split_the_body_at_the_given_size() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "hello world");
+    let reader = Client::new().request(Method::Get, &server.address(), "/").send_streaming().unwrap();
+    let chunks: Vec<Vec<u8>> = reader.chunks(4).map(Result::unwrap).collect();
+    assert_eq!(chunks, [b"hell".to_vec(), b"o wo".to_vec(), b"rld".to_vec()]);
+}
+
+#[test]
+fn lines_arrive_one_at_a_time() {
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (next, wait) = mpsc::channel::<()>();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 1024];
+        let _ = stream.read(&mut buf);
+        stream.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n").unwrap();
+        // Each line goes out only once the client has read the one before; closing the
+        // connection ends the last, unterminated one
+        for line in ["first\n", "second\r\n"] {
+            stream.write_all(line.as_bytes()).unwrap();
+            if wait.recv().is_err() {
+                return;
+            }
+        }
+        stream.write_all(b"partial").unwrap();
+    });
+
+    let reader = Client::new().request(Method::Get, &address, "/logs").send_streaming().unwrap();
+    let mut lines = reader.lines_iter();
+    for expected in ["first", "second"] {
+        assert_eq!(lines.next().unwrap().unwrap(), expected);
+        next.send(()).unwrap();
+    }
+    assert_eq!(lines.next().unwrap().unwrap(), "partial");
+    assert!(lines.next().is_none());
+    server.join().unwrap();
+}
@@ -0,0 +1,17 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, Method};
+
+#[test]
+fn download_names_come_from_content_disposition() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Content-Disposition", "attachment; filename=plain.csv")], "a,b");
+    server.respond_with(200, &[("Content-Disposition", "attachment; filename=\"quoted name.zip\"")], "PK");
+    server.respond_with(200, &[("Content-Disposition", "attachment; filename=\"rates.txt\"; filename*=UTF-8''%E2%82%AC%20rates.txt")], "1");
+    server.respond_with(200, &[], "no header");
+    let client = Client::new();
+
+    let names: Vec<Option<String>> = (0..4)
+        .map(|_| client.request(Method::Get, &server.address(), "/download").send().unwrap().suggested_filename())
+        .collect();
+    assert_eq!(names, [Some("plain.csv".to_string()), Some("quoted name.zip".to_string()), Some("\u{20ac} rates.txt".to_string()), None]);
+}
@@ -0,0 +1,44 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpResponse, Method};
+
+#[test]
+fn chunked_request_body_and_trailers_are_recorded() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "ok");
+    Client::new()
+        .request(Method::Post, &server.address(), "/upload")
+        .body_stream(&b"streamed body"[..])
+        .trailer("X-Checksum", "abc123")
+        .send()
+        .unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!(request.body, b"streamed body");
+    assert_eq!(request.trailers, [("X-Checksum".to_string(), "abc123".to_string())]);
+}
+
+#[test]
+fn responses_are_sent_as_to_bytes_writes_them() {
+    let server = TestServer::start();
+    let mut response = HttpResponse::new(200, &[("Transfer-Encoding", "chunked")], "in one chunk");
+    response.trailers.push("X-Done: yes".to_string());
+    let expected = response.clone();
+    server.respond_with_fn(move |_| response);
+    server.respond_with(204, &[], "");
+
+    let client = Client::new();
+    let chunked = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(chunked.body, expected.body);
+    assert_eq!(chunked.trailers, expected.trailers);
+    let empty = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(empty.status_code, 204);
+    assert_eq!(empty.header("Content-Length"), None);
+}
+
+#[test]
+fn unqueued_requests_get_a_500() {
+    let server = TestServer::start();
+    let response = Client::new().request(Method::Get, &server.address(), "/").send();
+    assert!(response.is_err_and(|e| e.to_string().contains("500")));
+    assert_eq!(server.requests().len(), 1);
+}
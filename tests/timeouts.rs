@@ -0,0 +1,61 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use http_client_from_scratch_rust::{CancelToken, Client, HttpError, Method, RequestConfig, TimeoutPhase};
+
+// Reads the request, then sends `sent` and holds the connection open for two seconds
+fn stalling_server(sent: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        let _ = stream.write_all(sent);
+        thread::sleep(Duration::from_secs(2));
+    });
+    address
+}
+
+fn get(config: RequestConfig, address: &str) -> HttpError {
+    Client::with_config(config).request(Method::Get, address, "/").send().unwrap_err()
+}
+
+#[test]
+fn read_timeout_is_a_timeout_not_a_network_error() {
+    let limit = Duration::from_millis(200);
+    let error = get(RequestConfig { read_timeout: Some(limit), ..RequestConfig::default() }, &stalling_server(b""));
+    assert!(matches!(&error, HttpError::Timeout { phase: TimeoutPhase::Read, limit: l } if *l == limit), "{:?}", error);
+    assert!(error.to_string().contains("no data arrived"), "{}", error);
+}
+
+#[test]
+fn a_stalled_body_runs_into_read_timeout() {
+    let limit = Duration::from_millis(200);
+    let address = stalling_server(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial");
+    let error = get(RequestConfig { read_timeout: Some(limit), ..RequestConfig::default() }, &address);
+    assert!(matches!(&error, HttpError::Timeout { phase: TimeoutPhase::Read, limit: l } if *l == limit), "{:?}", error);
+}
+
+#[test]
+fn the_overall_deadline_is_total() {
+    let limit = Duration::from_millis(300);
+    let config = RequestConfig { read_timeout: Some(Duration::from_secs(5)), timeout: Some(limit), ..RequestConfig::default() };
+    let error = get(config, &stalling_server(b""));
+    assert!(matches!(&error, HttpError::Timeout { phase: TimeoutPhase::Total, limit: l } if *l == limit), "{:?}", error);
+    assert!(error.to_string().contains("longer than"), "{}", error);
+}
+
+// A cancel token that is never used doesn't change which error a timeout gives
+#[test]
+fn read_timeout_with_a_cancel_token() {
+    let limit = Duration::from_millis(200);
+    let config = RequestConfig { read_timeout: Some(limit), cancel: Some(CancelToken::new()), ..RequestConfig::default() };
+    let error = get(config, &stalling_server(b""));
+    assert!(matches!(&error, HttpError::Timeout { phase: TimeoutPhase::Read, limit: l } if *l == limit), "{:?}", error);
+}
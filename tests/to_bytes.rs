@@ -0,0 +1,15 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{try_parse_response, Client, Method};
+
+#[test]
+fn a_fetched_response_survives_a_trip_through_to_bytes() {
+    let server = TestServer::start();
+    server.respond_with(200, &[("Content-Type", "text/plain"), ("X-Trace", "abc")], "payload");
+    let client = Client::new();
+    let response = client.request(Method::Get, &server.address(), "/").send().unwrap();
+
+    let again = try_parse_response(&response.to_bytes()).unwrap();
+    assert_eq!(again.status_line, response.status_line);
+    assert_eq!(again.headers, response.headers);
+    assert_eq!(again.body, b"payload");
+}
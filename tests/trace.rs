@@ -0,0 +1,48 @@
+use http_client_from_scratch_rust::testing::{ReceivedRequest, TestServer};
+use http_client_from_scratch_rust::{Client, HttpError, HttpResponse, Method};
+
+// Answers with the request it received, as a TRACE target would
+fn echo(request: &ReceivedRequest) -> HttpResponse {
+    let mut head = format!("{} {} HTTP/1.1\r\n", request.method, request.target);
+    for (name, value) in &request.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    HttpResponse::new(200, &[("Content-Type", "message/http")], head)
+}
+
+#[test]
+fn a_trace_round_trips_without_a_body_or_default_credentials() {
+    let server = TestServer::start();
+    server.respond_with_fn(echo);
+    let client = Client::new().with_default_header("Authorization", "Bearer s3cret").with_default_header("X-Client", "tests");
+    let response = client.trace(&server.address(), "/debug", [("X-Probe", "1")]).unwrap();
+    let echoed = response.echoed_request().unwrap();
+    assert_eq!((echoed.method.as_str(), echoed.target.as_str()), ("TRACE", "/debug"));
+    assert_eq!(echoed.header("X-Probe"), Some("1"));
+    assert_eq!(echoed.header("X-Client"), Some("tests"));
+    assert_eq!(echoed.header("Max-Forwards"), Some("10"));
+    assert_eq!(echoed.header("Authorization"), None);
+    assert!(server.requests()[0].body.is_empty());
+
+    // Other methods still get the default
+    server.respond_with(200, &[], "");
+    client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(server.requests()[1].header("Authorization"), Some("Bearer s3cret"));
+}
+
+#[test]
+fn a_callers_max_forwards_replaces_the_default() {
+    let server = TestServer::start();
+    server.respond_with_fn(echo);
+    let response = Client::new().trace(&server.address(), "/", [("Max-Forwards", "0")]).unwrap();
+    assert_eq!(response.echoed_request().unwrap().header_all("Max-Forwards"), ["0"]);
+}
+
+#[test]
+fn a_trace_with_a_body_is_refused_before_sending() {
+    let server = TestServer::start();
+    let result = Client::new().request(Method::Trace, &server.address(), "/").body("no").send();
+    assert!(matches!(result, Err(HttpError::InvalidRequest(_))), "{:?}", result);
+    assert!(server.requests().is_empty());
+}
@@ -0,0 +1,32 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpResponse, Method, RequestConfig};
+
+fn chunked_with_trailer(server: &TestServer) {
+    server.respond_with_fn(|_| {
+        let mut response = HttpResponse::new(200, &[("Transfer-Encoding", "chunked"), ("Trailer", "X-Checksum")], "payload");
+        response.trailers = vec!["X-Checksum: abc123".to_string(), "X-Unannounced: 1".to_string()];
+        response
+    });
+}
+
+#[test]
+fn trailers_are_readable_after_a_chunked_body() {
+    let server = TestServer::start();
+    chunked_with_trailer(&server);
+    let response = Client::new().request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.body, b"payload");
+    assert_eq!(response.trailer("X-Checksum"), Some("abc123"));
+    assert_eq!(response.trailer("X-Unannounced"), Some("1"));
+    assert_eq!(response.header("X-Checksum"), None);
+}
+
+#[test]
+fn merge_trailers_copies_the_announced_ones_into_the_headers() {
+    let server = TestServer::start();
+    chunked_with_trailer(&server);
+    let client = Client::with_config(RequestConfig { merge_trailers: true, ..RequestConfig::default() });
+    let response = client.request(Method::Get, &server.address(), "/").send().unwrap();
+    assert_eq!(response.header("X-Checksum"), Some("abc123"));
+    assert_eq!(response.header("X-Unannounced"), None);
+    assert_eq!(response.trailers.len(), 2);
+}
@@ -0,0 +1,47 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::{Client, Method, RequestConfig};
+
+// Answers each request, ending in `body_end`, with the next of `replies` on one keep-alive
+// connection, and returns how many bytes each request took on the wire
+fn counting_server(body_end: &'static [u8], replies: &'static [&'static [u8]]) -> (String, thread::JoinHandle<Vec<usize>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut sizes = Vec::new();
+        for reply in replies {
+            let mut received = Vec::new();
+            let mut byte = [0; 1];
+            while !received.ends_with(body_end) {
+                assert_eq!(stream.read(&mut byte).unwrap(), 1, "connection closed early");
+                received.push(byte[0]);
+            }
+            sizes.push(received.len());
+            stream.write_all(reply).unwrap();
+        }
+        sizes
+    });
+    (address, handle)
+}
+
+const LENGTH: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+const CHUNKED: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: X-Sum\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\nX-Sum: 5\r\n\r\n";
+
+#[test]
+fn bytes_sent_and_received_match_the_wire_per_request() {
+    let (address, server) = counting_server(b"\r\n\r\nsame body", &[LENGTH, CHUNKED]);
+    let client = Client::with_config(RequestConfig { keep_alive: true, ..RequestConfig::default() });
+    let first = client.request(Method::Post, &address, "/a").body("same body").send().unwrap();
+    let second = client.request(Method::Post, &address, "/bb").body("same body").send().unwrap();
+    assert_eq!(second.body, b"abcde");
+    assert!(second.connection.as_ref().unwrap().reused);
+
+    let sent = server.join().unwrap();
+    let timings = [first.timings.unwrap(), second.timings.unwrap()];
+    assert_eq!([timings[0].bytes_sent, timings[1].bytes_sent], [sent[0] as u64, sent[1] as u64]);
+    assert_eq!(sent[1], sent[0] + 1);
+    assert_eq!([timings[0].bytes_received, timings[1].bytes_received], [LENGTH.len() as u64, CHUNKED.len() as u64]);
+}
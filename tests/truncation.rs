@@ -0,0 +1,52 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use http_client_from_scratch_rust::{Client, HttpError, Method, RequestConfig};
+
+// Sends `response` as it is to the first request, then closes the connection
+fn lying_server(response: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+        let _ = (&stream).write_all(response);
+    });
+    address
+}
+
+#[test]
+fn a_connection_closed_before_the_content_length_is_an_error() {
+    let address = lying_server(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n{\"half\": \"a doc");
+    let result = Client::new().request(Method::Get, &address, "/").send();
+    assert!(matches!(result, Err(HttpError::TruncatedBody { expected: 100, received: 15 })), "{:?}", result);
+}
+
+#[test]
+fn truncated_bodies_can_be_allowed() {
+    let address = lying_server(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial");
+    let client = Client::with_config(RequestConfig { allow_truncated_body: true, ..RequestConfig::default() });
+    let response = client.request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(response.body, b"partial");
+}
+
+#[test]
+fn a_streamed_read_reports_the_truncation_at_the_end() {
+    let address = lying_server(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nline one\nline two");
+    let reader = Client::new().request(Method::Get, &address, "/").send_streaming().unwrap();
+    let lines: Vec<_> = reader.lines_iter().collect();
+    assert_eq!(lines[0].as_ref().unwrap(), "line one");
+    assert!(matches!(lines.last(), Some(Err(HttpError::TruncatedBody { expected: 100, received: 17 }))), "{:?}", lines);
+}
+
+#[test]
+fn bytes_beyond_the_content_length_are_not_part_of_the_body() {
+    let address = lying_server(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nokAND MORE GARBAGE");
+    let response = Client::new().request(Method::Get, &address, "/").send().unwrap();
+    assert_eq!(response.body, b"ok");
+}
@@ -0,0 +1,23 @@
+use http_client_from_scratch_rust::testing::TestServer;
+use http_client_from_scratch_rust::{Client, HttpError, Method};
+
+#[test]
+fn a_wrong_accept_value_fails_the_handshake() {
+    let server = TestServer::start();
+    server.respond_with(101, &[("Upgrade", "websocket"), ("Connection", "Upgrade"), ("Sec-WebSocket-Accept", "bm9wZQ==")], "");
+    let result = Client::new().request(Method::Get, &server.address(), "/socket").upgrade_websocket();
+    assert!(matches!(&result, Err(HttpError::InvalidResponse(message)) if message.contains("Sec-WebSocket-Accept")), "{:?}", result);
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("Upgrade"), Some("websocket"));
+    assert_eq!(request.header("Sec-WebSocket-Version"), Some("13"));
+    assert_eq!(request.header("Sec-WebSocket-Key").map(str::len), Some(24));
+}
+
+#[test]
+fn a_plain_200_is_not_an_upgrade() {
+    let server = TestServer::start();
+    server.respond_with(200, &[], "hi");
+    let result = Client::new().request(Method::Get, &server.address(), "/").upgrade_websocket();
+    assert!(matches!(&result, Err(HttpError::InvalidResponse(message)) if message.contains("101")), "{:?}", result);
+}